@@ -37,6 +37,8 @@ pub enum Error {
     HabitatCore(habitat_core::error::Error),
     NonExistentRumor(String, String),
     ProtocolMismatch(&'static str),
+    RingKeyIsPrimary(String),
+    RingKeyNotFound(String),
     ServiceConfigDecode(String, toml::de::Error),
     ServiceConfigNotUtf8(String, str::Utf8Error),
     SocketSetReadTimeout(io::Error),
@@ -76,6 +78,10 @@ impl fmt::Display for Error {
                 "Received an unsupported or bad protocol message. Missing field: {}",
                 field
             ),
+            Error::RingKeyIsPrimary(ref key) => {
+                format!("Cannot retire {}, it is the primary ring key", key)
+            }
+            Error::RingKeyNotFound(ref key) => format!("Ring key {} is not accepted", key),
             Error::ServiceConfigDecode(ref sg, ref err) => {
                 format!("Cannot decode service config: group={}, {:?}", sg, err)
             }
@@ -114,6 +120,8 @@ impl error::Error for Error {
             Error::ProtocolMismatch(_) => {
                 "Received an unprocessable wire message from another Supervisor"
             }
+            Error::RingKeyIsPrimary(_) => "Cannot retire the primary ring key",
+            Error::RingKeyNotFound(_) => "Ring key is not accepted",
             Error::ServiceConfigDecode(_, _) => "Cannot decode service config into TOML",
             Error::ServiceConfigNotUtf8(_, _) => "Cannot read service config bytes to UTF-8",
             Error::SocketSetReadTimeout(_) => "Cannot set UDP socket read timeout",
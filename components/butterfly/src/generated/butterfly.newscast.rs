@@ -40,6 +40,10 @@ pub struct Service {
     pub cfg: ::std::option::Option<Vec<u8>>,
     #[prost(message, optional, tag="12")]
     pub sys: ::std::option::Option<SysInfo>,
+    #[prost(int32, optional, tag="13")]
+    pub health: ::std::option::Option<i32>,
+    #[prost(string, optional, tag="14")]
+    pub rolled_back_from: ::std::option::Option<String>,
 }
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -95,6 +99,30 @@ pub struct Departure {
 }
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
+pub struct Digest {
+    #[prost(message, repeated, tag="1")]
+    pub entries: ::std::vec::Vec<digest::Entry>,
+    #[prost(bool, optional, tag="2")]
+    pub reply: ::std::option::Option<bool>,
+}
+pub mod digest {
+    #[derive(Clone, PartialEq, Message)]
+    #[derive(Serialize, Deserialize)]
+    pub struct Entry {
+        #[prost(enumeration="super::rumor::Type", optional, tag="1")]
+        pub type_: ::std::option::Option<i32>,
+        #[prost(string, optional, tag="2")]
+        pub key: ::std::option::Option<String>,
+        #[prost(string, optional, tag="3")]
+        pub id: ::std::option::Option<String>,
+        #[prost(uint64, optional, tag="4")]
+        pub version: ::std::option::Option<u64>,
+        #[prost(uint64, optional, tag="5")]
+        pub minor_version: ::std::option::Option<u64>,
+    }
+}
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
 pub struct Rumor {
     #[prost(enumeration="rumor::Type", required, tag="1")]
     pub type_: i32,
@@ -102,7 +130,7 @@ pub struct Rumor {
     pub tag: ::std::vec::Vec<String>,
     #[prost(string, optional, tag="3")]
     pub from_id: ::std::option::Option<String>,
-    #[prost(oneof="rumor::Payload", tags="4, 5, 6, 7, 8, 9")]
+    #[prost(oneof="rumor::Payload", tags="4, 5, 6, 7, 8, 9, 10")]
     pub payload: ::std::option::Option<rumor::Payload>,
 }
 pub mod rumor {
@@ -118,6 +146,7 @@ pub mod rumor {
         Fake2 = 7,
         ElectionUpdate = 8,
         Departure = 9,
+        Digest = 10,
     }
     #[derive(Clone, Oneof, PartialEq)]
     #[derive(Serialize, Deserialize)]
@@ -134,5 +163,7 @@ pub mod rumor {
         Election(super::Election),
         #[prost(message, tag="9")]
         Departure(super::Departure),
+        #[prost(message, tag="10")]
+        Digest(super::Digest),
     }
 }
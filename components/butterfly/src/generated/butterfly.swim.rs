@@ -15,6 +15,9 @@ pub struct Member {
     pub persistent: ::std::option::Option<bool>,
     #[prost(bool, optional, tag="7", default="false")]
     pub departed: ::std::option::Option<bool>,
+    /// Operator-defined key/value labels of the Supervisor, such as its zone.
+    #[prost(map="string, string", tag="8")]
+    pub labels: ::std::collections::HashMap<String, String>,
 }
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -47,6 +50,10 @@ pub struct Membership {
     pub member: ::std::option::Option<Member>,
     #[prost(enumeration="membership::Health", optional, tag="2")]
     pub health: ::std::option::Option<i32>,
+    /// Members which suspect the member, each having failed to reach it itself. Only set when its
+    /// health is SUSPECT.
+    #[prost(string, repeated, tag="3")]
+    pub suspected_by: ::std::vec::Vec<String>,
 }
 pub mod membership {
     #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
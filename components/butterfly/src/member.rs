@@ -15,7 +15,7 @@
 //! Tracks membership. Contains both the `Member` struct and the `MemberList`.
 
 use std::cmp;
-use std::collections::{hash_map, HashMap};
+use std::collections::{hash_map, BTreeMap, HashMap, HashSet};
 use std::iter::IntoIterator;
 use std::net::SocketAddr;
 use std::result;
@@ -37,6 +37,9 @@ use rumor::{RumorEnvelope, RumorKey, RumorKind, RumorPayload, RumorType};
 
 /// How many nodes do we target when we need to run PingReq.
 const PINGREQ_TARGETS: usize = 5;
+/// How many of the members suspecting a member are passed on with its membership rumor. By
+/// default, suspicions confirmed by more members than this time out no sooner.
+pub const SUSPECTED_BY_MAX: usize = 4;
 
 // This is a Uuid type turned to a string
 pub type UuidSimple = String;
@@ -52,6 +55,9 @@ pub struct Member {
     pub gossip_port: i32,
     pub persistent: bool,
     pub departed: bool,
+    /// Operator-defined key/value labels, such as the zone the member runs in.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
 }
 
 impl Member {
@@ -87,6 +93,7 @@ impl Default for Member {
             gossip_port: 0,
             persistent: false,
             departed: false,
+            labels: BTreeMap::new(),
         }
     }
 }
@@ -119,6 +126,7 @@ impl From<Member> for proto::Member {
             gossip_port: Some(value.gossip_port),
             persistent: Some(value.persistent),
             departed: Some(value.departed),
+            labels: value.labels.into_iter().collect(),
         }
     }
 }
@@ -127,6 +135,9 @@ impl From<Member> for proto::Member {
 pub struct Membership {
     pub member: Member,
     pub health: Health,
+    /// The members which suspect a `Suspect` member, each having failed to reach it itself.
+    #[serde(default)]
+    pub suspected_by: Vec<String>,
 }
 
 impl Membership {
@@ -151,6 +162,7 @@ impl From<Membership> for proto::Membership {
         proto::Membership {
             member: Some(value.member.into()),
             health: Some(value.health as i32),
+            suspected_by: value.suspected_by,
         }
     }
 }
@@ -206,6 +218,7 @@ impl FromProto<proto::Member> for Member {
                 .ok_or(Error::ProtocolMismatch("gossip-port"))?,
             persistent: proto.persistent.unwrap_or(false),
             departed: proto.departed.unwrap_or(false),
+            labels: proto.labels.into_iter().collect(),
         })
     }
 }
@@ -221,6 +234,7 @@ impl FromProto<proto::Membership> for Membership {
                 .health
                 .and_then(Health::from_i32)
                 .unwrap_or(Health::Alive),
+            suspected_by: proto.suspected_by,
         })
     }
 }
@@ -248,6 +262,14 @@ pub struct MemberList {
     /// `Confirmed`. This supports automatically transitioning them to
     /// `Departed` after an appropriate amount of time.
     aging_confirmed: Arc<RwLock<HashMap<UuidSimple, SteadyTime>>>,
+    /// Records timestamps of when Members were first marked
+    /// `Confirmed` or `Departed`. This supports dropping the rumors
+    /// of members that have been dead for a long time.
+    aging_dead: Arc<RwLock<HashMap<UuidSimple, SteadyTime>>>,
+    /// Records which members we have heard suspect a `Suspect`
+    /// member, ourselves included. The more members suspect it, the
+    /// sooner its suspicion times out.
+    suspected_by: Arc<RwLock<HashMap<UuidSimple, HashSet<String>>>>,
     initial_members: Arc<RwLock<Vec<Member>>>,
     update_counter: Arc<AtomicUsize>,
 }
@@ -284,6 +306,8 @@ impl MemberList {
             health: Arc::new(RwLock::new(HashMap::new())),
             aging_suspects: Arc::new(RwLock::new(HashMap::new())),
             aging_confirmed: Arc::new(RwLock::new(HashMap::new())),
+            aging_dead: Arc::new(RwLock::new(HashMap::new())),
+            suspected_by: Arc::new(RwLock::new(HashMap::new())),
             initial_members: Arc::new(RwLock::new(Vec::new())),
             update_counter: Arc::new(AtomicUsize::new(0)),
         }
@@ -493,6 +517,13 @@ impl MemberList {
                     .expect("aging lock is poisoned")
                     .remove(member_id)
             });
+
+            if *current_health == Health::Suspect {
+                self.suspected_by
+                    .write()
+                    .expect("Suspected by lock is poisoned")
+                    .remove(member_id);
+            }
         }
 
         // Whether we have seen this member before or not, we now need
@@ -508,6 +539,22 @@ impl MemberList {
                 .insert(member_id.to_string(), SteadyTime::now())
         });
 
+        // Moving between Confirmed and Departed doesn't reset how
+        // long a member has been dead; coming back to life does.
+        {
+            let mut aging_dead = self.aging_dead.write().expect("aging lock is poisoned");
+            match health {
+                Health::Confirmed | Health::Departed => {
+                    aging_dead
+                        .entry(member_id.to_string())
+                        .or_insert(SteadyTime::now());
+                }
+                Health::Alive | Health::Suspect => {
+                    aging_dead.remove(member_id);
+                }
+            }
+        }
+
         // Finally, we record the new health.
         self.health
             .write()
@@ -615,11 +662,17 @@ impl MemberList {
             Some(health) => *health,
             None => return None,
         };
+        let suspected_by = if mhealth == Health::Suspect {
+            self.suspected_by(member_id)
+        } else {
+            Vec::new()
+        };
         let ml = self.members.read().expect("Member list lock is poisoned");
         match ml.get(member_id) {
             Some(member) => Some(Membership {
                 health: mhealth,
                 member: member.clone(),
+                suspected_by: suspected_by,
             }),
             None => None,
         }
@@ -633,6 +686,16 @@ impl MemberList {
             .len()
     }
 
+    /// Returns the number of members which have not departed.
+    pub fn len_not_departed(&self) -> usize {
+        self.health
+            .read()
+            .expect("Health lock is poisoned")
+            .values()
+            .filter(|health| **health != Health::Departed)
+            .count()
+    }
+
     /// A randomized list of members to check.
     pub fn check_list(&self, exclude_id: &str) -> Vec<Member> {
         let mut members: Vec<Member> = self
@@ -717,19 +780,90 @@ impl MemberList {
         }
     }
 
+    /// Record that `suspected_by` suspects the member at the given
+    /// incarnation. Does nothing unless the member is currently
+    /// `Suspect` at that incarnation, so a stale rumor never counts
+    /// towards confirming a suspicion. Returns `true` if
+    /// `suspected_by` wasn't already known to suspect the member.
+    pub fn add_suspicion(&self, member_id: &str, incarnation: u64, suspected_by: &str) -> bool {
+        if !self.check_health_of_by_id(member_id, Health::Suspect) {
+            return false;
+        }
+        match self
+            .members
+            .read()
+            .expect("Member list lock is poisoned")
+            .get(member_id)
+        {
+            Some(member) if member.incarnation == incarnation => (),
+            _ => return false,
+        }
+        self.suspected_by
+            .write()
+            .expect("Suspected by lock is poisoned")
+            .entry(member_id.to_string())
+            .or_insert_with(HashSet::new)
+            .insert(suspected_by.to_string())
+    }
+
+    /// The members known to suspect a `Suspect` member, at most
+    /// `SUSPECTED_BY_MAX` of them, in a stable order.
+    pub fn suspected_by(&self, member_id: &str) -> Vec<String> {
+        let mut suspected_by: Vec<String> = self
+            .suspected_by
+            .read()
+            .expect("Suspected by lock is poisoned")
+            .get(member_id)
+            .map_or(Vec::new(), |ids| ids.iter().cloned().collect());
+        suspected_by.sort();
+        suspected_by.truncate(SUSPECTED_BY_MAX);
+        suspected_by
+    }
+
+    /// How many members have confirmed the suspicion of a `Suspect`
+    /// member, besides the first one to suspect it.
+    pub fn suspicion_confirmations(&self, member_id: &str) -> usize {
+        self.suspected_by
+            .read()
+            .expect("Suspected by lock is poisoned")
+            .get(member_id)
+            .map_or(0, |suspected_by| suspected_by.len().saturating_sub(1))
+    }
+
     /// Query the list of aging Suspect members to find those which
     /// have now expired to Confirmed. Health is updated
     /// appropriately, and a list of newly-Confirmed Member IDs is
     /// returned.
     pub fn members_expired_to_confirmed(&self, timeout: Duration) -> Vec<String> {
-        self.members_expired_to(Health::Confirmed, timeout)
+        self.members_expired_to(Health::Confirmed, |_| timeout)
+    }
+
+    /// Like `members_expired_to_confirmed`, except that the timeout
+    /// of each member depends on how many confirmations its
+    /// suspicion has.
+    pub fn members_expired_to_confirmed_by<F>(&self, timeout: F) -> Vec<String>
+    where
+        F: Fn(usize) -> Duration,
+    {
+        let confirmations: HashMap<UuidSimple, usize> = self
+            .suspected_by
+            .read()
+            .expect("Suspected by lock is poisoned")
+            .iter()
+            .map(|(member_id, suspected_by)| {
+                (member_id.clone(), suspected_by.len().saturating_sub(1))
+            })
+            .collect();
+        self.members_expired_to(Health::Confirmed, |member_id| {
+            timeout(confirmations.get(member_id).cloned().unwrap_or(0))
+        })
     }
 
     /// Query the list of aging Confirmed members to find those which
     /// have now expired to Departed. Health is updated appropriately,
     /// and a list of newly-Departed Member IDs is returned.
     pub fn members_expired_to_departed(&self, timeout: Duration) -> Vec<String> {
-        self.members_expired_to(Health::Departed, timeout)
+        self.members_expired_to(Health::Departed, |_| timeout)
     }
 
     /// Return the member IDs of all members that have "timed out" to
@@ -737,14 +871,17 @@ impl MemberList {
     ///
     /// For instance,
     ///
-    ///   members_expired_to(Health::Departed, timeout)
+    ///   members_expired_to(Health::Departed, |_| timeout)
     ///
     /// will return the IDs of those members that have been
     /// `Confirmed` for longer than the given `timeout`.
     ///
     /// The newly-updated health status is recorded properly.
     // TODO (CM): Better return type than Vec<String>
-    fn members_expired_to(&self, expiring_to: Health, timeout: Duration) -> Vec<String> {
+    fn members_expired_to<F>(&self, expiring_to: Health, timeout: F) -> Vec<String>
+    where
+        F: Fn(&str) -> Duration,
+    {
         let now = SteadyTime::now();
         let mut expired = Vec::new();
 
@@ -759,7 +896,7 @@ impl MemberList {
 
         population.write().expect("aging lock is poisoned").retain(
             |ref member_id, ref starting_timestamp| {
-                if now >= **starting_timestamp + timeout {
+                if now >= **starting_timestamp + timeout(member_id) {
                     expired.push(member_id.to_string());
                     false
                 } else {
@@ -774,6 +911,23 @@ impl MemberList {
         expired
     }
 
+    /// Return the member IDs of all members that have been
+    /// `Confirmed` or `Departed` for longer than the given `timeout`.
+    ///
+    /// Unlike `members_expired_to_confirmed` and
+    /// `members_expired_to_departed`, this doesn't change any health;
+    /// members are returned for as long as they stay dead.
+    pub fn members_dead_longer_than(&self, timeout: Duration) -> Vec<String> {
+        let now = SteadyTime::now();
+        self.aging_dead
+            .read()
+            .expect("aging lock is poisoned")
+            .iter()
+            .filter(|&(_, starting_timestamp)| now >= *starting_timestamp + timeout)
+            .map(|(member_id, _)| member_id.to_string())
+            .collect()
+    }
+
     pub fn contains_member(&self, member_id: &str) -> bool {
         self.members
             .read()
@@ -786,6 +940,7 @@ impl MemberList {
 mod tests {
     mod member {
         use member::Member;
+        use protocol::{swim as proto, FromProto};
 
         // Sets the uuid to simple, and the incarnation to zero.
         #[test]
@@ -794,6 +949,17 @@ mod tests {
             assert_eq!(member.id.len(), 32);
             assert_eq!(member.incarnation, 0);
         }
+
+        #[test]
+        fn labels_survive_the_wire() {
+            let mut member = Member::default();
+            member
+                .labels
+                .insert(String::from("zone"), String::from("us-east-1a"));
+            let proto: proto::Member = member.clone().into();
+            let decoded = Member::from_proto(proto).unwrap();
+            assert_eq!(decoded.labels, member.labels);
+        }
     }
 
     mod member_list {
@@ -1622,6 +1788,109 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn confirmed_suspicions_time_out_sooner() {
+                let ml = MemberList::new();
+                let member_1 = Member::default();
+                let member_2 = Member::default();
+
+                assert!(ml.insert(member_1.clone(), Health::Suspect));
+                assert!(ml.insert(member_2.clone(), Health::Suspect));
+                assert!(ml.add_suspicion(&member_1.id, 0, "adam"));
+                assert!(ml.add_suspicion(&member_1.id, 0, "eve"));
+                assert!(!ml.add_suspicion(&member_1.id, 0, "eve"));
+                assert!(ml.add_suspicion(&member_2.id, 0, "adam"));
+                assert_eq!(ml.suspicion_confirmations(&member_1.id), 1);
+                assert_eq!(ml.suspicion_confirmations(&member_2.id), 0);
+                thread::sleep(StdDuration::from_secs(1));
+
+                let newly_confirmed = ml.members_expired_to_confirmed_by(|confirmations| {
+                    if confirmations > 0 {
+                        Duration::zero()
+                    } else {
+                        Duration::hours(1)
+                    }
+                });
+                assert_eq!(newly_confirmed, vec![member_1.id.clone()]);
+                assert_eq!(
+                    ml.suspicion_confirmations(&member_1.id),
+                    0,
+                    "Suspicions should be forgotten once a member is no longer Suspect"
+                );
+            }
+
+            #[test]
+            fn suspicions_are_only_recorded_for_suspect_members() {
+                let ml = MemberList::new();
+                let member = Member::default();
+
+                assert!(ml.insert(member.clone(), Health::Alive));
+                assert!(!ml.add_suspicion(&member.id, 0, "adam"));
+                assert!(!ml.add_suspicion(&member.id, 0, "eve"));
+                assert_eq!(ml.suspicion_confirmations(&member.id), 0);
+            }
+
+            #[test]
+            fn suspicions_of_an_older_incarnation_are_not_recorded() {
+                let ml = MemberList::new();
+                let mut member = Member::default();
+                member.incarnation = 2;
+
+                assert!(ml.insert(member.clone(), Health::Suspect));
+                assert!(ml.add_suspicion(&member.id, 2, "adam"));
+                assert!(!ml.add_suspicion(&member.id, 1, "eve"));
+                assert_eq!(ml.suspicion_confirmations(&member.id), 0);
+            }
+
+            #[test]
+            fn memberships_of_suspect_members_list_who_suspects_them() {
+                let ml = MemberList::new();
+                let member = Member::default();
+
+                assert!(ml.insert(member.clone(), Health::Suspect));
+                ml.add_suspicion(&member.id, 0, "eve");
+                ml.add_suspicion(&member.id, 0, "adam");
+                assert_eq!(
+                    ml.membership_for(&member.id).unwrap().suspected_by,
+                    vec!["adam".to_string(), "eve".to_string()]
+                );
+            }
+
+            #[test]
+            fn dead_members_age_until_they_come_back() {
+                let ml = MemberList::new();
+                let member_1 = Member::default();
+                let member_2 = Member::default();
+                let member_3 = Member::default();
+
+                assert!(ml.insert(member_1.clone(), Health::Confirmed));
+                assert!(ml.insert(member_2.clone(), Health::Confirmed));
+                assert!(ml.insert(member_3.clone(), Health::Suspect));
+                thread::sleep(StdDuration::from_secs(2));
+                assert!(ml.insert_health_by_id(&member_1.id, Health::Departed));
+
+                let timeout = Duration::from_std(StdDuration::from_secs(1)).unwrap();
+
+                let dead = ml.members_dead_longer_than(timeout);
+                assert!(
+                    dead.contains(&member_1.id),
+                    "Member 1 should still be aging after going from Confirmed to Departed"
+                );
+                assert!(
+                    dead.contains(&member_2.id),
+                    "Member 2 should be dead after timing out"
+                );
+                assert!(
+                    !dead.contains(&member_3.id),
+                    "Member 3 should NOT be dead, because it was never Confirmed"
+                );
+
+                assert!(ml.insert_health_by_id(&member_2.id, Health::Alive));
+                assert!(
+                    !ml.members_dead_longer_than(timeout).contains(&member_2.id),
+                    "Member 2 should NOT be dead after coming back to life"
+                );
+            }
         }
     }
 }
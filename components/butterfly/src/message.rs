@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::{Arc, RwLock};
+
 use bytes::BytesMut;
 use habitat_core::crypto::SymKey;
 use prost::Message;
@@ -19,6 +21,115 @@ use prost::Message;
 use error::{Error, Result};
 use protocol::Wire;
 
+/// The ring keys a server uses on the wire. Messages are encrypted with the primary key, and
+/// decrypted with whichever of the primary or accepted keys works. Holding the old and new keys
+/// as accepted while a ring moves from one to the other lets us rotate keys without a restart.
+#[derive(Clone, Debug, Default)]
+pub struct RingKeys(Arc<RwLock<KeySet>>);
+
+#[derive(Debug, Default)]
+struct KeySet {
+    primary: Option<SymKey>,
+    accepted: Vec<SymKey>,
+}
+
+impl KeySet {
+    fn position(&self, name_with_rev: &str) -> Option<usize> {
+        self.accepted
+            .iter()
+            .position(|key| key.name_with_rev() == name_with_rev)
+    }
+
+    fn is_primary(&self, name_with_rev: &str) -> bool {
+        self.primary
+            .as_ref()
+            .map_or(false, |key| key.name_with_rev() == name_with_rev)
+    }
+}
+
+impl RingKeys {
+    pub fn new(primary: Option<SymKey>) -> Self {
+        RingKeys(Arc::new(RwLock::new(KeySet {
+            primary: primary,
+            accepted: Vec::new(),
+        })))
+    }
+
+    /// The key we encrypt with, if the ring is encrypted.
+    pub fn primary(&self) -> Option<SymKey> {
+        self.0
+            .read()
+            .expect("Ring keys lock poisoned")
+            .primary
+            .clone()
+    }
+
+    /// The names, with revisions, of the keys we accept besides the primary.
+    pub fn accepted(&self) -> Vec<String> {
+        self.0
+            .read()
+            .expect("Ring keys lock poisoned")
+            .accepted
+            .iter()
+            .map(|key| key.name_with_rev())
+            .collect()
+    }
+
+    /// Accept messages encrypted with `key`. Returns false if we already knew about it. A ring
+    /// without a primary key keeps accepting plaintext messages until a key is promoted.
+    pub fn add(&self, key: SymKey) -> bool {
+        let mut keys = self.0.write().expect("Ring keys lock poisoned");
+        let name_with_rev = key.name_with_rev();
+        if keys.is_primary(&name_with_rev) || keys.position(&name_with_rev).is_some() {
+            return false;
+        }
+        keys.accepted.push(key);
+        true
+    }
+
+    /// Start encrypting with an accepted key. The previous primary key is still accepted, so
+    /// members which haven't promoted the new key yet can still talk to us.
+    pub fn promote(&self, name_with_rev: &str) -> Result<()> {
+        let mut keys = self.0.write().expect("Ring keys lock poisoned");
+        if keys.is_primary(name_with_rev) {
+            return Ok(());
+        }
+        let idx = keys
+            .position(name_with_rev)
+            .ok_or(Error::RingKeyNotFound(name_with_rev.to_string()))?;
+        let key = keys.accepted.remove(idx);
+        if let Some(old) = keys.primary.take() {
+            keys.accepted.push(old);
+        }
+        keys.primary = Some(key);
+        Ok(())
+    }
+
+    /// Stop accepting messages encrypted with a key. The primary key cannot be retired.
+    pub fn retire(&self, name_with_rev: &str) -> Result<()> {
+        let mut keys = self.0.write().expect("Ring keys lock poisoned");
+        if keys.is_primary(name_with_rev) {
+            return Err(Error::RingKeyIsPrimary(name_with_rev.to_string()));
+        }
+        let idx = keys
+            .position(name_with_rev)
+            .ok_or(Error::RingKeyNotFound(name_with_rev.to_string()))?;
+        keys.accepted.remove(idx);
+        Ok(())
+    }
+
+    pub fn generate_wire(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        let keys = self.0.read().expect("Ring keys lock poisoned");
+        generate_wire(payload, keys.primary.as_ref())
+    }
+
+    pub fn unwrap_wire(&self, payload: &[u8]) -> Result<Vec<u8>> {
+        let keys = self.0.read().expect("Ring keys lock poisoned");
+        let all: Vec<&SymKey> = keys.primary.iter().chain(keys.accepted.iter()).collect();
+        decode_wire(payload, &all, keys.primary.is_none())
+    }
+}
+
 pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&SymKey>) -> Result<Vec<u8>> {
     let mut wire = Wire::default();
     if let Some(ring_key) = ring_key {
@@ -34,15 +145,103 @@ pub fn generate_wire(payload: Vec<u8>, ring_key: Option<&SymKey>) -> Result<Vec<
     Ok(buf.to_vec())
 }
 
-pub fn unwrap_wire(payload: &[u8], ring_key: Option<&SymKey>) -> Result<Vec<u8>> {
+/// Decode a wire message, decrypting the payload with the first of `ring_keys` that works. With
+/// no keys, the payload is returned as is.
+pub fn unwrap_wire(payload: &[u8], ring_keys: &[&SymKey]) -> Result<Vec<u8>> {
+    decode_wire(payload, ring_keys, false)
+}
+
+/// Like `unwrap_wire`, but a payload sent without a nonce is returned as is when
+/// `accept_plaintext` is set, rather than rejected.
+fn decode_wire(payload: &[u8], ring_keys: &[&SymKey], accept_plaintext: bool) -> Result<Vec<u8>> {
     let wire = Wire::decode(payload)?;
     let payload = wire
         .payload
         .ok_or(Error::ProtocolMismatch("missing payload"))?;
-    if let Some(ring_key) = ring_key {
-        let nonce = wire.nonce.ok_or(Error::ProtocolMismatch("missing nonce"))?;
-        Ok(ring_key.decrypt(&nonce, &payload)?)
-    } else {
-        Ok(payload)
+    if ring_keys.is_empty() {
+        return Ok(payload);
+    }
+    let nonce = match wire.nonce {
+        Some(nonce) => nonce,
+        None if accept_plaintext => return Ok(payload),
+        None => return Err(Error::ProtocolMismatch("missing nonce")),
+    };
+    let mut first_err = None;
+    for ring_key in ring_keys {
+        match ring_key.decrypt(&nonce, &payload) {
+            Ok(decrypted) => return Ok(decrypted),
+            Err(err) => {
+                if first_err.is_none() {
+                    first_err = Some(err);
+                }
+            }
+        }
+    }
+    Err(Error::from(first_err.expect("At least one ring key")))
+}
+
+#[cfg(test)]
+mod tests {
+    use habitat_core::crypto::SymKey;
+
+    use super::{unwrap_wire, RingKeys};
+
+    fn ring_key(name: &str) -> SymKey {
+        SymKey::generate_pair_for_ring(name).expect("Cannot generate ring key")
+    }
+
+    #[test]
+    fn unwrap_wire_accepts_any_known_key() {
+        let old = ring_key("beyonce");
+        let new = ring_key("solange");
+        let sender = RingKeys::new(Some(old.clone()));
+        let receiver = RingKeys::new(Some(new.clone()));
+
+        let wire = sender.generate_wire(b"hello".to_vec()).unwrap();
+        assert!(receiver.unwrap_wire(&wire).is_err());
+        assert!(receiver.add(old.clone()));
+        assert_eq!(receiver.unwrap_wire(&wire).unwrap(), b"hello".to_vec());
+        assert_eq!(
+            unwrap_wire(&wire, &[&new, &old]).unwrap(),
+            b"hello".to_vec()
+        );
+    }
+
+    #[test]
+    fn unencrypted_ring_accepts_plaintext_until_a_key_is_promoted() {
+        let key = ring_key("beyonce");
+        let plaintext = RingKeys::new(None)
+            .generate_wire(b"hello".to_vec())
+            .unwrap();
+        let encrypted = RingKeys::new(Some(key.clone()))
+            .generate_wire(b"hello".to_vec())
+            .unwrap();
+        let keys = RingKeys::new(None);
+
+        assert!(keys.add(key.clone()));
+        assert_eq!(keys.unwrap_wire(&plaintext).unwrap(), b"hello".to_vec());
+        assert_eq!(keys.unwrap_wire(&encrypted).unwrap(), b"hello".to_vec());
+
+        keys.promote(&key.name_with_rev()).unwrap();
+        assert!(keys.unwrap_wire(&plaintext).is_err());
+        assert_eq!(keys.unwrap_wire(&encrypted).unwrap(), b"hello".to_vec());
+    }
+
+    #[test]
+    fn promote_keeps_old_primary_accepted() {
+        let old = ring_key("beyonce");
+        let new = ring_key("solange");
+        let keys = RingKeys::new(Some(old.clone()));
+        assert!(keys.add(new.clone()));
+        assert!(!keys.add(new.clone()));
+
+        keys.promote(&new.name_with_rev()).unwrap();
+        assert_eq!(keys.primary().unwrap().name_with_rev(), new.name_with_rev());
+        assert_eq!(keys.accepted(), vec![old.name_with_rev()]);
+
+        assert!(keys.retire(&new.name_with_rev()).is_err());
+        keys.retire(&old.name_with_rev()).unwrap();
+        assert!(keys.accepted().is_empty());
+        assert!(keys.retire(&old.name_with_rev()).is_err());
     }
 }
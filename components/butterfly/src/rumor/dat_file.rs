@@ -12,12 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::mem;
 use std::path::{Path, PathBuf};
 
 use byteorder::{ByteOrder, LittleEndian};
+use habitat_core::crypto::hash;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
@@ -25,11 +27,23 @@ use error::{Error, Result};
 use member::{MemberList, Membership};
 use protocol::{newscast, Message};
 use rumor::{
-    Departure, Election, ElectionUpdate, Rumor, RumorStore, Service, ServiceConfig, ServiceFile,
+    Departure, Election, ElectionUpdate, Rumor, RumorKey, RumorStore, Service, ServiceConfig,
+    ServiceFile,
 };
 use server::Server;
 
-const HEADER_VERSION: u8 = 2;
+const HEADER_VERSION: u8 = 3;
+/// The number of sections in a dat file.
+const SECTION_COUNT: usize = 7;
+/// The size of a section checksum in the header; a hex-encoded BLAKE2b hash.
+const CHECKSUM_SIZE: usize = 64;
+const HEADER_V1_SIZE: usize = 48;
+const HEADER_V2_SIZE: usize = 64;
+/// The size of the current header: its own size, then the length and the checksum of every
+/// section.
+const HEADER_SIZE: usize = HEADER_V2_SIZE + SECTION_COUNT * CHECKSUM_SIZE;
+/// Headers claiming to be larger than this are corrupt.
+const MAX_HEADER_SIZE: u64 = 64 * 1024;
 
 /// A versioned binary file containing rumors exchanged by the butterfly server which have
 /// been periodically persisted to disk.
@@ -45,14 +59,29 @@ pub struct DatFile {
     header: Header,
     header_size: u64,
     path: PathBuf,
+    /// The sections which could not be read the last time the file was read.
+    corrupt: Vec<&'static str>,
+    /// The entries which could not be decoded the last time the file was read.
+    skipped: Vec<String>,
+    /// Whether the file at `path` is known to be good: it was read without any corrupt sections,
+    /// or we wrote it ourselves. Only a good file is kept as the backup when it is replaced.
+    intact: bool,
 }
 
 impl DatFile {
     pub fn new<T: AsRef<Path>>(member_id: &str, data_path: T) -> Self {
+        Self::from_path(data_path.as_ref().join(format!("{}.rst", member_id)))
+    }
+
+    /// A dat file at the given path, rather than the one a server keeps in its data path.
+    pub fn from_path<T: AsRef<Path>>(path: T) -> Self {
         DatFile {
-            path: data_path.as_ref().join(format!("{}.rst", member_id)),
+            path: path.as_ref().to_path_buf(),
             header_size: 0,
             header: Header::default(),
+            corrupt: vec![],
+            skipped: vec![],
+            intact: false,
         }
     }
 
@@ -60,15 +89,162 @@ impl DatFile {
         &self.path
     }
 
+    /// The path of the last-known-good copy of the dat file, kept next to it.
+    pub fn backup_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".bak");
+        PathBuf::from(path)
+    }
+
+    /// The sections which were skipped the last time the file was read, because they were
+    /// truncated or didn't match their checksum.
+    pub fn corrupt_sections(&self) -> &[&'static str] {
+        &self.corrupt
+    }
+
+    /// The entries which were skipped the last time the file was read, because they could not be
+    /// decoded. Each is described by its section, position and error.
+    pub fn skipped_rumors(&self) -> &[String] {
+        &self.skipped
+    }
+
+    /// Read the dat file into the server. Sections which are corrupt are replaced by the same
+    /// section of the last-known-good copy, if there is one; otherwise they are skipped and
+    /// re-learned from gossip. If the file can't be read at all, the last-known-good copy is read
+    /// in its place.
     pub fn read_into(&mut self, server: &Server) -> Result<()> {
+        let contents = match self.read_contents() {
+            Ok(mut contents) => {
+                self.intact = self.corrupt.is_empty() && self.skipped.is_empty();
+                if !self.intact {
+                    if let Some(mut backup) = self.read_backup() {
+                        for section in self.corrupt.iter() {
+                            contents.take_section(section, &mut backup);
+                        }
+                    }
+                }
+                contents
+            }
+            Err(err) => match self.read_backup() {
+                Some(contents) => {
+                    warn!("{}", err);
+                    contents
+                }
+                None => return Err(err),
+            },
+        };
+        for membership in contents.member {
+            server.insert_member(membership.member, membership.health);
+        }
+        for rumor in contents.service {
+            server.insert_service(rumor);
+        }
+        for rumor in contents.service_config {
+            server.insert_service_config(rumor);
+        }
+        for rumor in contents.service_file {
+            server.insert_service_file(rumor);
+        }
+        for rumor in contents.election {
+            server.insert_election(rumor);
+        }
+        for rumor in contents.election_update {
+            server.insert_update_election(rumor);
+        }
+        for rumor in contents.departure {
+            server.insert_departure(rumor);
+        }
+        Ok(())
+    }
+
+    /// Read and decode everything in the dat file, without merging it into a server. Sections
+    /// which are truncated or don't match their checksum are skipped with a warning, and listed
+    /// in `corrupt_sections`. Entries which can't be decoded are skipped with a warning, and
+    /// listed in `skipped_rumors`. Errors are only returned if the header itself can't be read.
+    pub fn read_contents(&mut self) -> Result<Contents> {
+        let (mut reader, version) = self.open()?;
+        let file_len = self.file_len(&reader)?;
+        let mut contents = Contents::default();
+        let mut skipped = vec![];
+        self.corrupt.clear();
+        let mut offset = self.member_offset();
+        for (idx, &(section, len)) in self.header.sections(version).iter().enumerate() {
+            debug!("Reading {} section from {}", section, self.path().display());
+            let result = self
+                .read_section(&mut reader, idx, offset, len, file_len)
+                .and_then(|bytes| {
+                    let entries = split_entries(&bytes)
+                        .map_err(|err| Error::BadDatFile(self.path.clone(), err))?;
+                    contents.decode_section(section, &entries, &mut skipped);
+                    Ok(())
+                });
+            offset = offset.saturating_add(len);
+            if let Err(err) = result {
+                warn!(
+                    "Skipping the {} section of {}, it will be re-learned from gossip: {}",
+                    section,
+                    self.path().display(),
+                    err
+                );
+                self.corrupt.push(section);
+            }
+        }
+        for entry in skipped.iter() {
+            warn!("Skipping {} of {}", entry, self.path().display());
+        }
+        self.skipped = skipped;
+        Ok(contents)
+    }
+
+    /// Check that the sections of the dat file add up to its size, that they match their
+    /// checksums, and that every entry in them can be decoded. Returns a description of each
+    /// problem found; an empty list means the file is sound. Errors are only returned if the
+    /// header itself can't be read.
+    pub fn validate(&mut self) -> Result<Vec<String>> {
+        let (mut reader, version) = self.open()?;
+        let file_len = self.file_len(&reader)?;
+        let mut problems = vec![];
+        let mut offset = self.member_offset();
+        for (idx, &(section, len)) in self.header.sections(version).iter().enumerate() {
+            let bytes = match self.read_section(&mut reader, idx, offset, len, file_len) {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    problems.push(format!("{}: {}", section, err));
+                    offset = offset.saturating_add(len);
+                    continue;
+                }
+            };
+            offset += len;
+            let entries = match split_entries(&bytes) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    problems.push(format!("{}: {}", section, err));
+                    continue;
+                }
+            };
+            for (entry, bytes) in entries.iter().enumerate() {
+                if let Err(err) = decode_entry(section, bytes) {
+                    problems.push(format!(
+                        "{}: entry {} cannot be decoded, {}",
+                        section, entry, err
+                    ));
+                }
+            }
+        }
+        if file_len != offset {
+            problems.push(format!(
+                "file is {} bytes, but its header describes {} bytes",
+                file_len, offset
+            ));
+        }
+        Ok(problems)
+    }
+
+    /// Open the dat file and read its header. Returns the reader and the header version.
+    fn open(&mut self) -> Result<(BufReader<File>, u8)> {
         let mut version = [0; 1];
-        let mut size_buf = [0; 8];
-        // JW: Resizing this buffer is terrible for performance, but it's the easiest way to
-        // read exactly N bytes from a file. I'm not sure what the right approach is but this
-        // won't be a performance issue for a long time anyway, if ever.
-        let mut rumor_buf: Vec<u8> = vec![];
-        let mut bytes_read = 0;
-        let file = File::open(&self.path).map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
+        let file =
+            File::open(&self.path).map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
         let mut reader = BufReader::new(file);
         reader
             .read_exact(&mut version)
@@ -80,160 +256,119 @@ impl DatFile {
         self.header_size = header_size;
         debug!("Header Size: {:?}", self.header_size);
         debug!("Header: {:?}", self.header);
+        Ok((reader, version[0]))
+    }
 
+    fn file_len(&self, reader: &BufReader<File>) -> Result<u64> {
+        reader
+            .get_ref()
+            .metadata()
+            .map(|metadata| metadata.len())
+            .map_err(|err| Error::DatFileIO(self.path.clone(), err))
+    }
+
+    /// Read the section at `offset`, and verify it against its checksum in the header, if the
+    /// file has checksums.
+    fn read_section(
+        &self,
+        reader: &mut BufReader<File>,
+        idx: usize,
+        offset: u64,
+        len: u64,
+        file_len: u64,
+    ) -> Result<Vec<u8>> {
+        // Guard against allocating whatever a corrupt length says
+        if offset.checked_add(len).map_or(true, |end| end > file_len) {
+            return Err(Error::DatFileIO(
+                self.path.clone(),
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "section of {} bytes at offset {} runs past the end of the file",
+                        len, offset
+                    ),
+                ),
+            ));
+        }
         reader
-            .seek(SeekFrom::Start(self.member_offset()))
+            .seek(SeekFrom::Start(offset))
             .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-        debug!("Reading membership list from {}", self.path().display());
-        loop {
-            if bytes_read >= self.header.member_len {
-                break;
-            }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            bytes_read += size_buf.len() as u64 + rumor_size;
-            match Membership::from_bytes(&rumor_buf) {
-                Ok(membership) => server.insert_member(membership.member, membership.health),
-                Err(err) => warn!("Error reading membership rumor from dat file, {}", err),
+        let mut bytes = vec![0; len as usize];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
+        if let Some(expected) = self.header.checksums.get(idx) {
+            let actual = hash::hash_bytes(&bytes);
+            if actual != *expected {
+                return Err(Error::BadDatFile(
+                    self.path.clone(),
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("checksum {} does not match {}", actual, expected),
+                    ),
+                ));
             }
         }
+        Ok(bytes)
+    }
 
-        debug!("Reading service rumors from {}", self.path().display());
-        bytes_read = 0;
-        loop {
-            if bytes_read >= self.header.service_len {
-                break;
-            }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor = Service::from_bytes(&rumor_buf)?;
-            server.insert_service(rumor);
-            bytes_read += size_buf.len() as u64 + rumor_size;
+    /// Read the last-known-good copy of the dat file, if there is one.
+    fn read_backup(&self) -> Option<Contents> {
+        let mut backup = DatFile::from_path(self.backup_path());
+        if !backup.path().exists() {
+            return None;
         }
-
-        debug!(
-            "Reading service-config rumors from {}",
-            self.path().display()
-        );
-        bytes_read = 0;
-        loop {
-            if bytes_read >= self.header.service_config_len {
-                break;
+        match backup.read_contents() {
+            Ok(contents) => {
+                warn!(
+                    "Recovering rumors from the last known good dat file, {}",
+                    backup.path().display()
+                );
+                Some(contents)
             }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor = ServiceConfig::from_bytes(&rumor_buf)?;
-            server.insert_service_config(rumor);
-            bytes_read += size_buf.len() as u64 + rumor_size;
-        }
-
-        debug!("Reading service-file rumors from {}", self.path().display());
-        bytes_read = 0;
-        loop {
-            if bytes_read >= self.header.service_file_len {
-                break;
+            Err(err) => {
+                warn!("Unable to read the last known good dat file, {}", err);
+                None
             }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor = ServiceFile::from_bytes(&rumor_buf)?;
-            server.insert_service_file(rumor);
-            bytes_read += size_buf.len() as u64 + rumor_size;
         }
+    }
 
-        debug!("Reading election rumors from {}", self.path().display());
-        bytes_read = 0;
-        loop {
-            if bytes_read >= self.header.election_len {
-                break;
-            }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor = Election::from_bytes(&rumor_buf)?;
-            server.insert_election(rumor);
-            bytes_read += size_buf.len() as u64 + rumor_size;
-        }
+    /// Write the server's members and rumors to the dat file. If the file it replaces is known
+    /// to be good, it is kept as the last-known-good copy.
+    pub fn write(&mut self, server: &Server) -> Result<usize> {
+        let mut sections = vec![vec![]; SECTION_COUNT];
+        self.write_member_list(&mut sections[0], &server.member_list)?;
+        self.write_rumor_store(&mut sections[1], &server.service_store)?;
+        self.write_rumor_store(&mut sections[2], &server.service_config_store)?;
+        self.write_rumor_store(&mut sections[3], &server.service_file_store)?;
+        self.write_rumor_store(&mut sections[4], &server.election_store)?;
+        self.write_rumor_store(&mut sections[5], &server.update_store)?;
+        self.write_rumor_store(&mut sections[6], &server.departure_store)?;
+        let keep_backup = self.intact;
+        self.write_sections(&sections, keep_backup)?;
+        self.intact = true;
+        Ok(0)
+    }
 
-        debug!(
-            "Reading update election rumors list from {}",
-            self.path().display()
-        );
-        bytes_read = 0;
-        loop {
-            if bytes_read >= self.header.update_len {
-                break;
-            }
-            reader
-                .read_exact(&mut size_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor_size = LittleEndian::read_u64(&size_buf);
-            rumor_buf.resize(rumor_size as usize, 0);
-            reader
-                .read_exact(&mut rumor_buf)
-                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            let rumor = ElectionUpdate::from_bytes(&rumor_buf)?;
-            server.insert_update_election(rumor);
-            bytes_read += size_buf.len() as u64 + rumor_size;
-        }
-
-        if version[0] >= 2 {
-            debug!(
-                "Reading departure rumors list from {}",
-                self.path().display()
-            );
-            bytes_read = 0;
-            loop {
-                if bytes_read >= self.header.departure_len {
-                    break;
-                }
-                reader
-                    .read_exact(&mut size_buf)
-                    .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-                let rumor_size = LittleEndian::read_u64(&size_buf);
-                rumor_buf.resize(rumor_size as usize, 0);
-                reader
-                    .read_exact(&mut rumor_buf)
-                    .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-                let rumor = Departure::from_bytes(&rumor_buf)?;
-                server.insert_departure(rumor);
-                bytes_read += size_buf.len() as u64 + rumor_size;
-            }
+    /// Write the given contents to the dat file, replacing whatever it held.
+    pub fn write_contents(&self, contents: &Contents) -> Result<()> {
+        let mut sections = vec![vec![]; SECTION_COUNT];
+        for membership in contents.member.iter() {
+            self.write_member(&mut sections[0], membership)?;
         }
-
-        Ok(())
+        self.write_rumors(&mut sections[1], &contents.service)?;
+        self.write_rumors(&mut sections[2], &contents.service_config)?;
+        self.write_rumors(&mut sections[3], &contents.service_file)?;
+        self.write_rumors(&mut sections[4], &contents.election)?;
+        self.write_rumors(&mut sections[5], &contents.election_update)?;
+        self.write_rumors(&mut sections[6], &contents.departure)?;
+        self.write_sections(&sections, false)
     }
 
-    pub fn write(&self, server: &Server) -> Result<usize> {
-        let mut header = Header::default();
+    /// Write a new dat file holding the given sections next to the current one, and move it into
+    /// place. With `keep_backup`, the current file is moved to the backup path first.
+    fn write_sections(&self, sections: &[Vec<u8>], keep_backup: bool) -> Result<()> {
+        let header = Header::for_sections(sections);
         let tmp_path = self.path.with_extension(
             thread_rng()
                 .sample_iter(&Alphanumeric)
@@ -248,20 +383,17 @@ impl DatFile {
                 .open(&tmp_path)
                 .map_err(|err| Error::DatFileIO(tmp_path.clone(), err))?;
             let mut writer = BufWriter::new(&file);
-            self.init(&mut writer)?;
-            header.member_len = self.write_member_list(&mut writer, &server.member_list)?;
-            header.service_len = self.write_rumor_store(&mut writer, &server.service_store)?;
-            header.service_config_len =
-                self.write_rumor_store(&mut writer, &server.service_config_store)?;
-            header.service_file_len =
-                self.write_rumor_store(&mut writer, &server.service_file_store)?;
-            header.election_len = self.write_rumor_store(&mut writer, &server.election_store)?;
-            header.update_len = self.write_rumor_store(&mut writer, &server.update_store)?;
-            header.departure_len = self.write_rumor_store(&mut writer, &server.departure_store)?;
             writer
-                .seek(SeekFrom::Start(1))
+                .write_all(&[HEADER_VERSION])
                 .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-            self.write_header(&mut writer, &header)?;
+            writer
+                .write_all(&header.write_to_bytes()?)
+                .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
+            for section in sections {
+                writer
+                    .write_all(section)
+                    .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
+            }
             writer
                 .flush()
                 .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
@@ -269,9 +401,15 @@ impl DatFile {
             file.sync_all()
                 .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
         }
-        fs::rename(&tmp_path, &self.path).map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
+        if keep_backup && self.path.exists() {
+            let backup_path = self.backup_path();
+            fs::rename(&self.path, &backup_path)
+                .map_err(|err| Error::DatFileIO(backup_path, err))?;
+        }
+        fs::rename(&tmp_path, &self.path)
+            .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
         self.sync_parent_dir()?;
-        Ok(0)
+        Ok(())
     }
 
     /// sync_parent_dir calls sync_all (fsync) on the parent directory
@@ -296,21 +434,6 @@ impl DatFile {
         Ok(())
     }
 
-    fn init<W>(&self, writer: &mut W) -> Result<usize>
-    where
-        W: Write,
-    {
-        let mut total = 0;
-        let header_reserve = vec![0; mem::size_of::<Header>() + 8];
-        total += writer
-            .write(&[HEADER_VERSION])
-            .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-        total += writer
-            .write(&header_reserve)
-            .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-        Ok(total)
-    }
-
     fn member_offset(&self) -> u64 {
         1 + self.header_size
     }
@@ -345,17 +468,6 @@ impl DatFile {
         self.update_offset() + self.header.update_len
     }
 
-    fn write_header<W>(&self, writer: &mut W, header: &Header) -> Result<usize>
-    where
-        W: Write,
-    {
-        let bytes = header.write_to_bytes().unwrap();
-        let total = writer
-            .write(&bytes)
-            .map_err(|err| Error::DatFileIO(self.path.clone(), err))?;
-        Ok(total)
-    }
-
     fn write_member_list<W>(&self, writer: &mut W, member_list: &MemberList) -> Result<u64>
     where
         W: Write,
@@ -409,6 +521,18 @@ impl DatFile {
         Ok(total)
     }
 
+    fn write_rumors<T, W>(&self, writer: &mut W, rumors: &[T]) -> Result<u64>
+    where
+        T: Message<newscast::Rumor>,
+        W: Write,
+    {
+        let mut total = 0;
+        for rumor in rumors {
+            total += self.write_rumor(writer, rumor)?;
+        }
+        Ok(total)
+    }
+
     fn write_rumor<T, W>(&self, writer: &mut W, rumor: &T) -> Result<u64>
     where
         T: Message<newscast::Rumor>,
@@ -428,10 +552,280 @@ impl DatFile {
     }
 }
 
+/// The decoded contents of a dat file, grouped by the store each entry belongs to.
+#[derive(Debug, Default, Serialize)]
+pub struct Contents {
+    pub member: Vec<Membership>,
+    pub service: Vec<Service>,
+    pub service_config: Vec<ServiceConfig>,
+    pub service_file: Vec<ServiceFile>,
+    pub election: Vec<Election>,
+    pub election_update: Vec<ElectionUpdate>,
+    pub departure: Vec<Departure>,
+}
+
+/// The entries of one section which differ between two dat files, by key.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SectionDiff {
+    /// Entries only present in the other file.
+    pub added: Vec<String>,
+    /// Entries only present in this file.
+    pub removed: Vec<String>,
+    /// Entries present in both files, with different contents.
+    pub changed: Vec<String>,
+}
+
+impl SectionDiff {
+    fn new(ours: BTreeMap<String, Vec<u8>>, theirs: BTreeMap<String, Vec<u8>>) -> Self {
+        let mut diff = SectionDiff::default();
+        for (key, bytes) in ours.iter() {
+            match theirs.get(key) {
+                Some(other) if other != bytes => diff.changed.push(key.clone()),
+                Some(_) => (),
+                None => diff.removed.push(key.clone()),
+            }
+        }
+        for key in theirs.keys() {
+            if !ours.contains_key(key) {
+                diff.added.push(key.clone());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Contents {
+    /// Compare with the contents of another dat file. Members are keyed by their id, and rumors
+    /// by their rumor key. Sections without differences are left out.
+    pub fn diff(&self, other: &Contents) -> BTreeMap<&'static str, SectionDiff> {
+        let sections = vec![
+            (
+                "member",
+                SectionDiff::new(members_by_key(&self.member), members_by_key(&other.member)),
+            ),
+            (
+                "service",
+                SectionDiff::new(rumors_by_key(&self.service), rumors_by_key(&other.service)),
+            ),
+            (
+                "service_config",
+                SectionDiff::new(
+                    rumors_by_key(&self.service_config),
+                    rumors_by_key(&other.service_config),
+                ),
+            ),
+            (
+                "service_file",
+                SectionDiff::new(
+                    rumors_by_key(&self.service_file),
+                    rumors_by_key(&other.service_file),
+                ),
+            ),
+            (
+                "election",
+                SectionDiff::new(
+                    rumors_by_key(&self.election),
+                    rumors_by_key(&other.election),
+                ),
+            ),
+            (
+                "election_update",
+                SectionDiff::new(
+                    rumors_by_key(&self.election_update),
+                    rumors_by_key(&other.election_update),
+                ),
+            ),
+            (
+                "departure",
+                SectionDiff::new(
+                    rumors_by_key(&self.departure),
+                    rumors_by_key(&other.departure),
+                ),
+            ),
+        ];
+        sections
+            .into_iter()
+            .filter(|&(_, ref diff)| !diff.is_empty())
+            .collect()
+    }
+
+    /// Remove the entries matching every given filter: the section they are in, the key of the
+    /// rumor (usually a service group), and the id of the rumor or member. Filters left as `None`
+    /// match anything. Returns the number of entries removed.
+    pub fn drop_rumors(
+        &mut self,
+        section: Option<&str>,
+        key: Option<&str>,
+        id: Option<&str>,
+    ) -> usize {
+        let in_section = |name: &str| section.map_or(true, |section| section == name);
+        let mut dropped = 0;
+        if in_section("member") && key.is_none() {
+            let before = self.member.len();
+            self.member
+                .retain(|membership| id.map_or(false, |id| membership.member.id != id));
+            dropped += before - self.member.len();
+        }
+        if in_section("service") {
+            dropped += drop_matching(&mut self.service, key, id);
+        }
+        if in_section("service_config") {
+            dropped += drop_matching(&mut self.service_config, key, id);
+        }
+        if in_section("service_file") {
+            dropped += drop_matching(&mut self.service_file, key, id);
+        }
+        if in_section("election") {
+            dropped += drop_matching(&mut self.election, key, id);
+        }
+        if in_section("election_update") {
+            dropped += drop_matching(&mut self.election_update, key, id);
+        }
+        if in_section("departure") {
+            dropped += drop_matching(&mut self.departure, key, id);
+        }
+        dropped
+    }
+
+    /// Decode the entries of the named section, describing any which can't be decoded in
+    /// `skipped`.
+    fn decode_section(&mut self, section: &str, entries: &[&[u8]], skipped: &mut Vec<String>) {
+        match section {
+            "member" => {
+                self.member = decode_entries(section, entries, skipped, Membership::from_bytes)
+            }
+            "service" => {
+                self.service = decode_entries(section, entries, skipped, Service::from_bytes)
+            }
+            "service_config" => {
+                self.service_config =
+                    decode_entries(section, entries, skipped, ServiceConfig::from_bytes)
+            }
+            "service_file" => {
+                self.service_file =
+                    decode_entries(section, entries, skipped, ServiceFile::from_bytes)
+            }
+            "election" => {
+                self.election = decode_entries(section, entries, skipped, Election::from_bytes)
+            }
+            "election_update" => {
+                self.election_update =
+                    decode_entries(section, entries, skipped, ElectionUpdate::from_bytes)
+            }
+            _ => self.departure = decode_entries(section, entries, skipped, Departure::from_bytes),
+        }
+    }
+
+    /// Replace the named section with the same section of `other`.
+    fn take_section(&mut self, section: &str, other: &mut Contents) {
+        match section {
+            "member" => mem::swap(&mut self.member, &mut other.member),
+            "service" => mem::swap(&mut self.service, &mut other.service),
+            "service_config" => mem::swap(&mut self.service_config, &mut other.service_config),
+            "service_file" => mem::swap(&mut self.service_file, &mut other.service_file),
+            "election" => mem::swap(&mut self.election, &mut other.election),
+            "election_update" => mem::swap(&mut self.election_update, &mut other.election_update),
+            _ => mem::swap(&mut self.departure, &mut other.departure),
+        }
+    }
+}
+
+/// Split a section into its length-prefixed entries.
+fn split_entries(bytes: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let mut entries = vec![];
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        if rest.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "entry length overruns its section",
+            ));
+        }
+        let entry_len = LittleEndian::read_u64(&rest[0..8]);
+        rest = &rest[8..];
+        if entry_len > rest.len() as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("entry of {} bytes overruns its section", entry_len),
+            ));
+        }
+        let (entry, remainder) = rest.split_at(entry_len as usize);
+        entries.push(entry);
+        rest = remainder;
+    }
+    Ok(entries)
+}
+
+/// Decode the entries of a section, leaving out any which can't be decoded and describing them in
+/// `skipped`.
+fn decode_entries<T, F>(
+    section: &str,
+    entries: &[&[u8]],
+    skipped: &mut Vec<String>,
+    decode: F,
+) -> Vec<T>
+where
+    F: Fn(&[u8]) -> Result<T>,
+{
+    let mut decoded = Vec::with_capacity(entries.len());
+    for (entry, bytes) in entries.iter().enumerate() {
+        match decode(bytes) {
+            Ok(value) => decoded.push(value),
+            Err(err) => skipped.push(format!("{} entry {}, {}", section, entry, err)),
+        }
+    }
+    decoded
+}
+
+fn decode_entry(section: &str, bytes: &[u8]) -> Result<()> {
+    match section {
+        "member" => Membership::from_bytes(bytes).map(|_| ()),
+        "service" => Service::from_bytes(bytes).map(|_| ()),
+        "service_config" => ServiceConfig::from_bytes(bytes).map(|_| ()),
+        "service_file" => ServiceFile::from_bytes(bytes).map(|_| ()),
+        "election" => Election::from_bytes(bytes).map(|_| ()),
+        "election_update" => ElectionUpdate::from_bytes(bytes).map(|_| ()),
+        _ => Departure::from_bytes(bytes).map(|_| ()),
+    }
+}
+
+fn members_by_key(members: &[Membership]) -> BTreeMap<String, Vec<u8>> {
+    members
+        .iter()
+        .map(|membership| {
+            let bytes = membership.clone().write_to_bytes().unwrap_or_default();
+            (membership.member.id.clone(), bytes)
+        })
+        .collect()
+}
+
+fn rumors_by_key<T: Rumor>(rumors: &[T]) -> BTreeMap<String, Vec<u8>> {
+    rumors
+        .iter()
+        .map(|rumor| {
+            let bytes = rumor.write_to_bytes().unwrap_or_default();
+            (RumorKey::from(rumor).key(), bytes)
+        })
+        .collect()
+}
+
+fn drop_matching<T: Rumor>(rumors: &mut Vec<T>, key: Option<&str>, id: Option<&str>) -> usize {
+    let before = rumors.len();
+    rumors.retain(|rumor| {
+        !(key.map_or(true, |key| rumor.key() == key) && id.map_or(true, |id| rumor.id() == id))
+    });
+    before - rumors.len()
+}
+
 /// Describes contents and structure of dat file.
 ///
 /// The information in this header is used to enable IO seeking operations on a binary dat
-/// file containing rumors exchanged by the butterfly server.
+/// file containing rumors exchanged by the butterfly server. From version 3 on it also holds a
+/// checksum of every section, so a damaged section can be told apart from the rest.
 #[derive(Debug, Default, PartialEq)]
 pub struct Header {
     pub member_len: u64,
@@ -441,16 +835,53 @@ pub struct Header {
     pub election_len: u64,
     pub update_len: u64,
     pub departure_len: u64,
+    /// The hex-encoded BLAKE2b hash of every section, in file order. Empty for files written
+    /// before version 3.
+    pub checksums: Vec<String>,
 }
 
 impl Header {
+    /// The header describing the given sections, in file order.
+    pub fn for_sections(sections: &[Vec<u8>]) -> Self {
+        let len = |idx: usize| sections.get(idx).map_or(0, |section| section.len() as u64);
+        Header {
+            member_len: len(0),
+            service_len: len(1),
+            service_config_len: len(2),
+            service_file_len: len(3),
+            election_len: len(4),
+            update_len: len(5),
+            departure_len: len(6),
+            checksums: sections
+                .iter()
+                .map(|section| hash::hash_bytes(section))
+                .collect(),
+        }
+    }
+
     pub fn from_file<R>(reader: &mut R, version: u8) -> io::Result<(u64, Self)>
     where
         R: Read,
     {
         let mut bytes = match version {
-            1 => vec![0; mem::size_of::<Self>()],
-            _ => vec![0; mem::size_of::<Self>() + 8],
+            1 => vec![0; HEADER_V1_SIZE],
+            2 => vec![0; HEADER_V2_SIZE],
+            // From version 3 on, read the size of the header before the rest of it
+            _ => {
+                let mut size_buf = [0; 8];
+                reader.read_exact(&mut size_buf)?;
+                let size = LittleEndian::read_u64(&size_buf);
+                if size < HEADER_SIZE as u64 || size > MAX_HEADER_SIZE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("dat file header of {} bytes is invalid", size),
+                    ));
+                }
+                let mut bytes = vec![0; size as usize];
+                bytes[0..8].copy_from_slice(&size_buf);
+                reader.read_exact(&mut bytes[8..])?;
+                return Ok(Self::from_bytes(&bytes, version));
+            }
         };
         reader.read_exact(&mut bytes)?;
         Ok(Self::from_bytes(&bytes, version))
@@ -473,17 +904,31 @@ impl Header {
                         election_len: LittleEndian::read_u64(&bytes[32..40]),
                         update_len: LittleEndian::read_u64(&bytes[40..48]),
                         departure_len: 0,
+                        checksums: vec![],
                     },
                 )
             }
+            2 => (
+                LittleEndian::read_u64(&bytes[0..8]),
+                Header {
+                    member_len: LittleEndian::read_u64(&bytes[8..16]),
+                    service_len: LittleEndian::read_u64(&bytes[16..24]),
+                    service_config_len: LittleEndian::read_u64(&bytes[24..32]),
+                    service_file_len: LittleEndian::read_u64(&bytes[32..40]),
+                    election_len: LittleEndian::read_u64(&bytes[40..48]),
+                    update_len: LittleEndian::read_u64(&bytes[48..56]),
+                    departure_len: LittleEndian::read_u64(&bytes[56..64]),
+                    checksums: vec![],
+                },
+            ),
             // This should be the latest version of the header. As we deprecate
             // header versions, just roll this code up, and match it, then add
             // your new structure.
             //
-            // So copy this struct to the last version number. Then add 8 to the previous struct's
-            // (the size of a 64 bit integer) size. Then start the empty fields at 0. The result
-            // will be that you read the back-compat version of the data format, and then write the
-            // new.
+            // So copy this struct to the last version number. Then add the size of the new
+            // fields to the previous struct's size. Then start the empty fields at their
+            // defaults. The result will be that you read the back-compat version of the data
+            // format, and then write the new.
             _ => (
                 LittleEndian::read_u64(&bytes[0..8]),
                 Header {
@@ -494,16 +939,38 @@ impl Header {
                     election_len: LittleEndian::read_u64(&bytes[40..48]),
                     update_len: LittleEndian::read_u64(&bytes[48..56]),
                     departure_len: LittleEndian::read_u64(&bytes[56..64]),
+                    checksums: bytes[HEADER_V2_SIZE..HEADER_SIZE]
+                        .chunks(CHECKSUM_SIZE)
+                        .map(|checksum| {
+                            String::from_utf8_lossy(checksum)
+                                .trim_right_matches('\0')
+                                .to_string()
+                        })
+                        .collect(),
                 },
             ),
         }
     }
 
+    /// The name and length of each section a file of the given version holds, in file order.
+    pub fn sections(&self, version: u8) -> Vec<(&'static str, u64)> {
+        let mut sections = vec![
+            ("member", self.member_len),
+            ("service", self.service_len),
+            ("service_config", self.service_config_len),
+            ("service_file", self.service_file_len),
+            ("election", self.election_len),
+            ("election_update", self.update_len),
+        ];
+        if version >= 2 {
+            sections.push(("departure", self.departure_len));
+        }
+        sections
+    }
+
     pub fn write_to_bytes(&self) -> Result<Vec<u8>> {
-        // The header is the size of the struct plus 8 bytes for the length of the header itself.
-        let header_size = mem::size_of::<Self>() + 8;
-        let mut bytes = vec![0; header_size];
-        LittleEndian::write_u64(&mut bytes[0..8], header_size as u64);
+        let mut bytes = vec![0; HEADER_SIZE];
+        LittleEndian::write_u64(&mut bytes[0..8], HEADER_SIZE as u64);
         LittleEndian::write_u64(&mut bytes[8..16], self.member_len);
         LittleEndian::write_u64(&mut bytes[16..24], self.service_len);
         LittleEndian::write_u64(&mut bytes[24..32], self.service_config_len);
@@ -511,16 +978,161 @@ impl Header {
         LittleEndian::write_u64(&mut bytes[40..48], self.election_len);
         LittleEndian::write_u64(&mut bytes[48..56], self.update_len);
         LittleEndian::write_u64(&mut bytes[56..64], self.departure_len);
+        for (slot, checksum) in bytes[HEADER_V2_SIZE..HEADER_SIZE]
+            .chunks_mut(CHECKSUM_SIZE)
+            .zip(self.checksums.iter())
+        {
+            let checksum = checksum.as_bytes();
+            let len = checksum.len().min(CHECKSUM_SIZE);
+            slot[..len].copy_from_slice(&checksum[..len]);
+        }
         Ok(bytes)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::mem;
+    use std::str::FromStr;
 
-    use super::*;
+    use habitat_core::service::ServiceGroup;
     use rand;
+    use tempdir::TempDir;
+
+    use super::*;
+    use member::{Health, Member};
+
+    fn sample_contents() -> Contents {
+        let mut contents = Contents::default();
+        for id in ["alpha", "beta"].iter() {
+            let mut member = Member::default();
+            member.id = id.to_string();
+            contents.member.push(Membership {
+                member: member,
+                health: Health::Alive,
+                suspected_by: Vec::new(),
+            });
+        }
+        for group in ["redis.default", "nginx.default"].iter() {
+            contents.service_config.push(ServiceConfig::new(
+                "alpha",
+                ServiceGroup::from_str(group).unwrap(),
+                b"port = 1234".to_vec(),
+            ));
+        }
+        contents.departure.push(Departure::new("gamma"));
+        contents
+    }
+
+    #[test]
+    fn contents_survive_a_roundtrip() {
+        let tmpdir = TempDir::new("dat_file").unwrap();
+        let path = tmpdir.path().join("test.rst");
+        DatFile::from_path(&path)
+            .write_contents(&sample_contents())
+            .unwrap();
+
+        let mut dat_file = DatFile::from_path(&path);
+        let contents = dat_file.read_contents().unwrap();
+        assert_eq!(contents.member.len(), 2);
+        assert_eq!(contents.service_config.len(), 2);
+        assert_eq!(contents.departure.len(), 1);
+        assert!(dat_file.validate().unwrap().is_empty());
+        assert!(sample_contents().diff(&contents).is_empty());
+    }
+
+    #[test]
+    fn validate_reports_a_truncated_file() {
+        let tmpdir = TempDir::new("dat_file").unwrap();
+        let path = tmpdir.path().join("test.rst");
+        DatFile::from_path(&path)
+            .write_contents(&sample_contents())
+            .unwrap();
+        let len = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 4)
+            .unwrap();
+
+        let problems = DatFile::from_path(&path).validate().unwrap();
+        assert!(problems.iter().any(|p| p.starts_with("departure:")));
+        assert!(problems.iter().any(|p| p.starts_with("file is")));
+    }
+
+    #[test]
+    fn corrupt_sections_are_skipped() {
+        let tmpdir = TempDir::new("dat_file").unwrap();
+        let path = tmpdir.path().join("test.rst");
+        DatFile::from_path(&path)
+            .write_contents(&sample_contents())
+            .unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&path, &bytes).unwrap();
+
+        let mut dat_file = DatFile::from_path(&path);
+        let contents = dat_file.read_contents().unwrap();
+        assert_eq!(dat_file.corrupt_sections(), &["departure"]);
+        assert_eq!(contents.member.len(), 2);
+        assert_eq!(contents.service_config.len(), 2);
+        assert!(contents.departure.is_empty());
+
+        let problems = dat_file.validate().unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("departure:"));
+        assert!(problems[0].contains("checksum"));
+    }
+
+    #[test]
+    fn undecodable_rumors_are_skipped() {
+        let tmpdir = TempDir::new("dat_file").unwrap();
+        let path = tmpdir.path().join("test.rst");
+        let dat_file = DatFile::from_path(&path);
+        let contents = sample_contents();
+        let mut sections = vec![vec![]; SECTION_COUNT];
+        for membership in contents.member.iter() {
+            dat_file.write_member(&mut sections[0], membership).unwrap();
+        }
+        dat_file
+            .write_rumors(&mut sections[2], &contents.service_config[..1])
+            .unwrap();
+        let mut len_buf = [0; 8];
+        LittleEndian::write_u64(&mut len_buf, 4);
+        sections[2].extend_from_slice(&len_buf);
+        sections[2].extend_from_slice(&[0xff; 4]);
+        dat_file
+            .write_rumors(&mut sections[2], &contents.service_config[1..])
+            .unwrap();
+        dat_file.write_sections(&sections, false).unwrap();
+
+        let mut dat_file = DatFile::from_path(&path);
+        let contents = dat_file.read_contents().unwrap();
+        assert!(dat_file.corrupt_sections().is_empty());
+        assert_eq!(dat_file.skipped_rumors().len(), 1);
+        assert!(dat_file.skipped_rumors()[0].starts_with("service_config entry 1,"));
+        assert_eq!(contents.member.len(), 2);
+        assert_eq!(contents.service_config.len(), 2);
+    }
+
+    #[test]
+    fn diff_and_drop_rumors() {
+        let mut contents = sample_contents();
+        assert_eq!(
+            contents.drop_rumors(Some("service_config"), Some("redis.default"), None),
+            1
+        );
+        assert_eq!(contents.drop_rumors(None, None, Some("beta")), 1);
+
+        let diff = sample_contents().diff(&contents);
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff["member"].removed, vec!["beta".to_string()]);
+        assert_eq!(
+            diff["service_config"].removed,
+            vec!["service_config-redis.default".to_string()]
+        );
+    }
 
     #[test]
     fn read_write_header() {
@@ -530,9 +1142,11 @@ mod tests {
         original.service_file_len = rand::random::<u64>();
         original.election_len = rand::random::<u64>();
         original.update_len = rand::random::<u64>();
+        original.checksums = vec![hash::hash_bytes(b"section"); SECTION_COUNT];
         let bytes = original.write_to_bytes().unwrap();
-        let (_size_of_header, restored) = Header::from_bytes(&bytes, HEADER_VERSION);
-        assert_eq!(bytes.len(), mem::size_of::<Header>() + 8);
+        let (size_of_header, restored) = Header::from_bytes(&bytes, HEADER_VERSION);
+        assert_eq!(bytes.len(), HEADER_SIZE);
+        assert_eq!(size_of_header, HEADER_SIZE as u64);
         assert_eq!(original, restored);
     }
 }
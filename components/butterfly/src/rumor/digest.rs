@@ -0,0 +1,161 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Digest message.
+//!
+//! A digest lists the key and version of every rumor a member knows about, without the rumors
+//! themselves. Members swap digests to find out which rumors the other side is missing, or has an
+//! older version of, and then send only those.
+
+use std::collections::HashMap;
+
+use error::{Error, Result};
+use protocol::{newscast, newscast::Rumor as ProtoRumor, FromProto};
+use rumor::{RumorKey, RumorPayload, RumorType};
+
+/// The version of a rumor, such as a member's incarnation and health. Versions compare the first
+/// part, and only then the second.
+pub type Version = (u64, u64);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Digest {
+    pub entries: Vec<(RumorKey, Version)>,
+    /// True if this digest answers another one. Answers are never answered in turn.
+    pub reply: bool,
+}
+
+impl Digest {
+    pub fn new(entries: Vec<(RumorKey, Version)>, reply: bool) -> Self {
+        Digest {
+            entries: entries,
+            reply: reply,
+        }
+    }
+
+    /// Returns the keys of the rumors in this digest which `other` is missing, or has an older
+    /// version of.
+    pub fn newer_than(&self, other: &Digest) -> Vec<RumorKey> {
+        let theirs: HashMap<&RumorKey, Version> =
+            other.entries.iter().map(|&(ref rk, v)| (rk, v)).collect();
+        self.entries
+            .iter()
+            .filter(|&&(ref rk, version)| theirs.get(rk).map_or(true, |v| *v < version))
+            .map(|&(ref rk, _)| rk.clone())
+            .collect()
+    }
+}
+
+impl FromProto<ProtoRumor> for Digest {
+    fn from_proto(rumor: ProtoRumor) -> Result<Self> {
+        let payload = match rumor.payload.ok_or(Error::ProtocolMismatch("payload"))? {
+            RumorPayload::Digest(payload) => payload,
+            _ => panic!("from-bytes digest"),
+        };
+        let mut entries = Vec::with_capacity(payload.entries.len());
+        for entry in payload.entries {
+            let kind = entry
+                .type_
+                .and_then(RumorType::from_i32)
+                .ok_or(Error::ProtocolMismatch("type"))?;
+            entries.push((
+                RumorKey::new(
+                    kind,
+                    entry.id.ok_or(Error::ProtocolMismatch("id"))?,
+                    entry.key.unwrap_or_default(),
+                ),
+                (entry.version.unwrap_or(0), entry.minor_version.unwrap_or(0)),
+            ));
+        }
+        Ok(Digest {
+            entries: entries,
+            reply: payload.reply.unwrap_or(false),
+        })
+    }
+}
+
+impl From<Digest> for newscast::Digest {
+    fn from(value: Digest) -> Self {
+        newscast::Digest {
+            entries: value
+                .entries
+                .into_iter()
+                .map(|(rk, version)| newscast::digest::Entry {
+                    type_: Some(rk.kind as i32),
+                    key: Some(rk.key),
+                    id: Some(rk.id),
+                    version: Some(version.0),
+                    minor_version: Some(version.1),
+                })
+                .collect(),
+            reply: Some(value.reply),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Digest;
+    use rumor::{RumorKey, RumorType};
+
+    fn service_key(id: &str) -> RumorKey {
+        RumorKey::new(RumorType::Service, id, "neurosis.production")
+    }
+
+    #[test]
+    fn newer_than_finds_missing_and_older_rumors() {
+        let ours = Digest::new(
+            vec![
+                (service_key("adam"), (2, 0)),
+                (service_key("eve"), (1, 0)),
+                (service_key("cain"), (1, 0)),
+            ],
+            false,
+        );
+        let theirs = Digest::new(
+            vec![
+                (service_key("adam"), (1, 0)),
+                (service_key("eve"), (1, 0)),
+                (service_key("abel"), (4, 0)),
+            ],
+            false,
+        );
+        let newer = ours.newer_than(&theirs);
+        assert_eq!(newer.len(), 2);
+        assert!(newer.contains(&service_key("adam")));
+        assert!(newer.contains(&service_key("cain")));
+        assert_eq!(theirs.newer_than(&ours), vec![service_key("abel")]);
+    }
+
+    #[test]
+    fn newer_than_matches_on_kind() {
+        let ours = Digest::new(vec![(service_key("adam"), (1, 0))], false);
+        let theirs = Digest::new(
+            vec![(
+                RumorKey::new(RumorType::ServiceConfig, "adam", "neurosis.production"),
+                (1, 0),
+            )],
+            false,
+        );
+        assert_eq!(ours.newer_than(&theirs), vec![service_key("adam")]);
+    }
+
+    #[test]
+    fn newer_than_compares_incarnation_before_health() {
+        let member = RumorKey::new(RumorType::Member, "adam", "");
+        let ours = Digest::new(vec![(member.clone(), (1 << 62, 0))], false);
+        let theirs = Digest::new(vec![(member.clone(), (1, 2))], false);
+        assert_eq!(ours.newer_than(&theirs), vec![member]);
+        assert!(theirs.newer_than(&ours).is_empty());
+    }
+}
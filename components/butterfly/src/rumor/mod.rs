@@ -22,6 +22,7 @@
 
 pub mod dat_file;
 pub mod departure;
+pub mod digest;
 pub mod election;
 pub mod heat;
 pub mod service;
@@ -42,6 +43,7 @@ use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
 
 pub use self::departure::Departure;
+pub use self::digest::{Digest, Version};
 pub use self::election::{Election, ElectionUpdate};
 pub use self::service::Service;
 pub use self::service_config::ServiceConfig;
@@ -54,6 +56,7 @@ use protocol::{FromProto, Message};
 #[derive(Debug, Clone, Serialize)]
 pub enum RumorKind {
     Departure(Departure),
+    Digest(Digest),
     Election(Election),
     ElectionUpdate(ElectionUpdate),
     Membership(Membership),
@@ -66,6 +69,7 @@ impl From<RumorKind> for RumorPayload {
     fn from(value: RumorKind) -> Self {
         match value {
             RumorKind::Departure(departure) => RumorPayload::Departure(departure.into()),
+            RumorKind::Digest(digest) => RumorPayload::Digest(digest.into()),
             RumorKind::Election(election) => RumorPayload::Election(election.into()),
             RumorKind::ElectionUpdate(election) => RumorPayload::Election(election.into()),
             RumorKind::Membership(membership) => RumorPayload::Member(membership.into()),
@@ -79,7 +83,7 @@ impl From<RumorKind> for RumorPayload {
 }
 
 /// The description of a `RumorKey`.
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize)]
 pub struct RumorKey {
     pub kind: RumorType,
     pub id: String,
@@ -248,6 +252,45 @@ where
         list.get_mut(key).and_then(|r| r.remove(id));
     }
 
+    /// Remove every rumor matching the predicate, returning the keys of the rumors removed.
+    pub fn remove_where<F>(&self, mut predicate: F) -> Vec<RumorKey>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut removed = Vec::new();
+        {
+            let mut list = self.list.write().expect("Rumor store lock poisoned");
+            for rumors in list.values_mut() {
+                rumors.retain(|_, rumor| {
+                    if predicate(&*rumor) {
+                        removed.push(RumorKey::from(&*rumor));
+                        false
+                    } else {
+                        true
+                    }
+                });
+            }
+            list.retain(|_, rumors| !rumors.is_empty());
+        }
+        if !removed.is_empty() {
+            self.increment_update_counter();
+        }
+        removed
+    }
+
+    /// Returns the key of every rumor in this store, along with its version as given by the
+    /// `version` function. Used to build anti-entropy digests.
+    pub fn versions<F>(&self, version: F) -> Vec<(RumorKey, Version)>
+    where
+        F: Fn(&T) -> Version,
+    {
+        let list = self.list.read().expect("Rumor store lock poisoned");
+        list.values()
+            .flat_map(|rumors| rumors.values())
+            .map(|rumor| (RumorKey::from(rumor), version(rumor)))
+            .collect()
+    }
+
     pub fn with_keys<F>(&self, mut with_closure: F)
     where
         F: FnMut((&String, &HashMap<String, T>)),
@@ -312,6 +355,7 @@ impl RumorEnvelope {
             .ok_or(Error::ProtocolMismatch("from-id"))?;
         let kind = match type_ {
             RumorType::Departure => RumorKind::Departure(Departure::from_proto(proto)?),
+            RumorType::Digest => RumorKind::Digest(Digest::from_proto(proto)?),
             RumorType::Election => RumorKind::Election(Election::from_proto(proto)?),
             RumorType::ElectionUpdate => {
                 RumorKind::ElectionUpdate(ElectionUpdate::from_proto(proto)?)
@@ -569,5 +613,35 @@ mod tests {
             let rs = create_rumor_store();
             rs.with_rumor("bar", "foo", |o| assert!(o.is_none()));
         }
+
+        #[test]
+        fn remove_where_removes_matching_rumors() {
+            let rs = create_rumor_store();
+            let f1 = FakeRumor::default();
+            let f1_id = f1.id.clone();
+            let key = f1.key.clone();
+            let f2 = FakeRumor::default();
+            let f2_id = f2.id.clone();
+            rs.insert(f1);
+            rs.insert(f2);
+
+            let removed = rs.remove_where(|rumor| rumor.id == f1_id);
+            assert_eq!(removed.len(), 1);
+            assert_eq!(removed[0].id, f1_id);
+            assert_eq!(rs.get_update_counter(), 3);
+            assert!(!rs.contains_rumor(&key, &f1_id));
+            assert!(rs.contains_rumor(&key, &f2_id));
+
+            rs.remove_where(|rumor| rumor.id == f2_id);
+            assert!(rs.list.read().unwrap().is_empty());
+        }
+
+        #[test]
+        fn remove_where_leaves_update_counter_when_nothing_matches() {
+            let rs = create_rumor_store();
+            rs.insert(FakeRumor::default());
+            assert!(rs.remove_where(|_| false).is_empty());
+            assert_eq!(rs.get_update_counter(), 1);
+        }
     }
 }
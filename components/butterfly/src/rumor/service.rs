@@ -41,6 +41,12 @@ pub struct Service {
     pub pkg: String,
     pub cfg: Vec<u8>,
     pub sys: SysInfo,
+    /// The most recent health check result of the service, as reported by the Supervisor
+    /// running it. Absent when the member has not run a health check.
+    pub health: Option<i32>,
+    /// A release the member rolled back from after it failed to become healthy, which the rest of
+    /// the service group should not update to.
+    pub rolled_back_from: Option<String>,
 }
 
 // Ensures that `cfg` is rendered as a map, and not an array of bytes
@@ -49,7 +55,7 @@ impl Serialize for Service {
     where
         S: Serializer,
     {
-        let mut strukt = serializer.serialize_struct("service", 7)?;
+        let mut strukt = serializer.serialize_struct("service", 9)?;
         let cfg = toml::from_slice(&self.cfg).unwrap_or(toml::value::Table::default());
         strukt.serialize_field("member_id", &self.member_id)?;
         strukt.serialize_field("service_group", &self.service_group)?;
@@ -58,6 +64,8 @@ impl Serialize for Service {
         strukt.serialize_field("cfg", &cfg)?;
         strukt.serialize_field("sys", &self.sys)?;
         strukt.serialize_field("initialized", &self.initialized)?;
+        strukt.serialize_field("health", &self.health)?;
+        strukt.serialize_field("rolled_back_from", &self.rolled_back_from)?;
         strukt.end()
     }
 }
@@ -115,6 +123,8 @@ impl Service {
             cfg: cfg
                 .map(|v| toml::ser::to_vec(v).expect("Struct should serialize to bytes"))
                 .unwrap_or_default(),
+            health: None,
+            rolled_back_from: None,
         }
     }
 }
@@ -143,6 +153,8 @@ impl FromProto<newscast::Rumor> for Service {
                 .sys
                 .ok_or(Error::ProtocolMismatch("sys"))
                 .and_then(SysInfo::from_proto)?,
+            health: payload.health,
+            rolled_back_from: payload.rolled_back_from,
         })
     }
 }
@@ -157,6 +169,8 @@ impl From<Service> for newscast::Service {
             pkg: Some(value.pkg),
             cfg: Some(value.cfg),
             sys: Some(value.sys.into()),
+            health: value.health,
+            rolled_back_from: value.rolled_back_from,
         }
     }
 }
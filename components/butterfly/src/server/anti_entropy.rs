@@ -0,0 +1,118 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The anti-entropy thread.
+//!
+//! Hot rumors are pushed to a handful of members at a time, and stop being pushed once they have
+//! cooled off. A member that was partitioned away, or that just joined, would otherwise only hear
+//! about a rumor once it changes again. To make sure every member converges, we periodically send
+//! a digest of everything we know to one random member. It answers with the rumors we are missing
+//! or have an older version of and, if we know about rumors it is missing, with a digest of its
+//! own, which we answer in turn.
+
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use time::SteadyTime;
+
+use member::{Health, Member};
+use rumor::Digest;
+use server::push::PushWorker;
+use server::timing::Timing;
+use server::Server;
+
+pub struct AntiEntropy {
+    pub server: Server,
+    pub timing: Timing,
+}
+
+impl AntiEntropy {
+    pub fn new(server: Server, timing: Timing) -> AntiEntropy {
+        AntiEntropy {
+            server: server,
+            timing: timing,
+        }
+    }
+
+    pub fn run(&self) {
+        loop {
+            if self.server.pause.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+
+            let next_digest = self.timing.anti_entropy_timeout();
+            let target = self
+                .server
+                .member_list
+                .check_list(self.server.member_id())
+                .into_iter()
+                .find(|member| can_sync_with(&self.server, member));
+            if let Some(member) = target {
+                let digest = self.server.digest(false);
+                PushWorker::new(self.server.clone()).send_digest(member, digest);
+            }
+
+            let wait_time = (next_digest - SteadyTime::now()).num_milliseconds();
+            if wait_time > 0 {
+                thread::sleep(Duration::from_millis(wait_time as u64));
+            }
+        }
+    }
+}
+
+/// Whether we should swap digests with the member; the same members we push rumors to.
+fn can_sync_with(server: &Server, member: &Member) -> bool {
+    !server.is_member_blocked(&member.id)
+        && server.member_list.pingable(member)
+        && !server.member_list.persistent_and_confirmed(member)
+        && !server.member_list.check_health_of(member, Health::Departed)
+}
+
+/// Answer a digest from another member. We send it every rumor it is missing or has an older
+/// version of and, unless the digest is itself an answer, our own digest if it knows about rumors
+/// we don't. Sending never waits for gossip bandwidth, so the digest is answered on the pull
+/// thread itself.
+pub fn answer(server: &Server, from_id: &str, digest: Digest) {
+    let mut from = None;
+    server.member_list.with_member(from_id, |member| {
+        from = member.cloned();
+    });
+    let member = match from {
+        Some(member) => member,
+        None => {
+            debug!("Not answering digest from unknown member {}", from_id);
+            return;
+        }
+    };
+    if !can_sync_with(server, &member) {
+        debug!("Not answering digest from member {}", from_id);
+        return;
+    }
+    let ours = server.digest(true);
+    let rumors = ours.newer_than(&digest);
+    let worker = PushWorker::new(server.clone());
+    if !rumors.is_empty() {
+        debug!(
+            "Sending {} rumors to {} after digest",
+            rumors.len(),
+            member.id
+        );
+        worker.send_rumors(member.clone(), rumors);
+    }
+    if !digest.reply && !digest.newer_than(&ours).is_empty() {
+        worker.send_digest(member, ours);
+    }
+}
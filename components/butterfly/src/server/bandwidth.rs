@@ -0,0 +1,265 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bandwidth limiting for gossip traffic.
+//!
+//! Without a limit, messages are sent as fast as the protocol periods allow. With one, every
+//! message draws its size from a bucket which refills at the limit and holds one second worth of
+//! traffic. SWIM messages and membership rumors are never held back, since the failure detector
+//! depends on them arriving on time; they draw the bucket down, possibly below empty, and the
+//! other messages are deferred until it refills. Each lower priority has to leave part of the
+//! bucket to the priorities above it, so large service configs and files can't crowd out
+//! elections and service rumors. Nothing waits for bandwidth, so a full bucket never holds up the
+//! gossip threads; deferred rumors stay hot, and are sent again on a later round.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use time::SteadyTime;
+
+use rumor::RumorType;
+
+/// The priority of a kind of message, from highest to lowest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Priority {
+    /// SWIM probes, and membership and departure rumors.
+    Membership,
+    /// Service, election and digest messages.
+    Rumor,
+    /// Service configs and service files.
+    Bulk,
+}
+
+impl Priority {
+    fn of(kind: RumorType) -> Priority {
+        match kind {
+            RumorType::Member | RumorType::Departure => Priority::Membership,
+            RumorType::ServiceConfig | RumorType::ServiceFile => Priority::Bulk,
+            _ => Priority::Rumor,
+        }
+    }
+
+    /// The fraction of the bucket messages of this priority leave to the priorities above them.
+    fn reserve(&self) -> f64 {
+        match *self {
+            Priority::Membership => 0.0,
+            Priority::Rumor => 0.25,
+            Priority::Bulk => 0.5,
+        }
+    }
+}
+
+/// The name a kind of message is counted under.
+fn name_of(kind: RumorType) -> &'static str {
+    match kind {
+        RumorType::Member => "membership",
+        RumorType::Departure => "departure",
+        RumorType::Service => "service",
+        RumorType::ServiceConfig => "service_config",
+        RumorType::ServiceFile => "service_file",
+        RumorType::Election => "election",
+        RumorType::ElectionUpdate => "election_update",
+        RumorType::Digest => "digest",
+        RumorType::Fake | RumorType::Fake2 => "fake",
+    }
+}
+
+/// What happened to the messages of one kind since the server started.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Counters {
+    pub sent_bytes: u64,
+    /// Messages which were not sent, because the bucket was too low for them.
+    pub deferred: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes per second; `None` when there is no limit.
+    limit: Option<u64>,
+    tokens: f64,
+    refilled_at: SteadyTime,
+    counters: BTreeMap<&'static str, Counters>,
+}
+
+impl Bucket {
+    fn counter(&mut self, name: &'static str) -> &mut Counters {
+        self.counters.entry(name).or_insert_with(Counters::default)
+    }
+
+    /// Take `bytes` from the bucket if a message of the given priority may be sent now.
+    fn admit(&mut self, priority: Priority, bytes: usize) -> bool {
+        let limit = match self.limit {
+            Some(limit) => limit as f64,
+            None => return true,
+        };
+        let now = SteadyTime::now();
+        let elapsed = (now - self.refilled_at).num_microseconds().unwrap_or(0) as f64;
+        self.tokens = (self.tokens + limit * elapsed / 1_000_000.0).min(limit);
+        self.refilled_at = now;
+
+        let bytes = bytes as f64;
+        let reserve = limit * priority.reserve();
+        // A message larger than this priority's share of the bucket is sent once the share is
+        // full, and leaves the bucket in debt.
+        if priority != Priority::Membership && self.tokens - reserve < bytes.min(limit - reserve) {
+            return false;
+        }
+        self.tokens = (self.tokens - bytes).max(-limit);
+        true
+    }
+}
+
+/// The gossip bandwidth limit of a server, and the counters of what it held back. Clones share
+/// the same bucket.
+#[derive(Debug, Clone)]
+pub struct Bandwidth {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+impl Default for Bandwidth {
+    fn default() -> Bandwidth {
+        Bandwidth {
+            bucket: Arc::new(Mutex::new(Bucket {
+                limit: None,
+                tokens: 0.0,
+                refilled_at: SteadyTime::now(),
+                counters: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl Bandwidth {
+    /// The limit in bytes per second, if there is one.
+    pub fn limit(&self) -> Option<u64> {
+        self.bucket.lock().expect("Bandwidth lock poisoned").limit
+    }
+
+    /// Set the limit in bytes per second, or remove it with `None`.
+    pub fn set_limit(&self, limit: Option<u64>) {
+        let mut bucket = self.bucket.lock().expect("Bandwidth lock poisoned");
+        bucket.limit = limit;
+        bucket.tokens = limit.unwrap_or(0) as f64;
+        bucket.refilled_at = SteadyTime::now();
+    }
+
+    /// Account for a SWIM message we are about to send. SWIM messages are never held back.
+    pub fn consume_swim(&self, bytes: usize) {
+        let mut bucket = self.bucket.lock().expect("Bandwidth lock poisoned");
+        bucket.admit(Priority::Membership, bytes);
+        bucket.counter("swim").sent_bytes += bytes as u64;
+    }
+
+    /// Whether a gossip message of the given type and size may be sent now. Returns `false`
+    /// right away if it has to be deferred; this never waits for the bucket to refill.
+    pub fn acquire(&self, kind: RumorType, bytes: usize) -> bool {
+        let mut bucket = self.bucket.lock().expect("Bandwidth lock poisoned");
+        let admitted = bucket.admit(Priority::of(kind), bytes);
+        let counter = bucket.counter(name_of(kind));
+        if admitted {
+            counter.sent_bytes += bytes as u64;
+        } else {
+            counter.deferred += 1;
+        }
+        admitted
+    }
+
+    /// The counters of every kind of message sent so far.
+    pub fn counters(&self) -> BTreeMap<String, Counters> {
+        self.bucket
+            .lock()
+            .expect("Bandwidth lock poisoned")
+            .counters
+            .iter()
+            .map(|(name, counters)| (name.to_string(), *counters))
+            .collect()
+    }
+}
+
+impl Serialize for Bandwidth {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut strukt = serializer.serialize_struct("bandwidth", 2)?;
+        strukt.serialize_field("limit", &self.limit())?;
+        strukt.serialize_field("messages", &self.counters())?;
+        strukt.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use time::SteadyTime;
+
+    use super::Bandwidth;
+    use rumor::RumorType;
+
+    fn limited(limit: u64) -> Bandwidth {
+        let bandwidth = Bandwidth::default();
+        bandwidth.set_limit(Some(limit));
+        bandwidth
+    }
+
+    #[test]
+    fn unlimited_sends_everything() {
+        let bandwidth = Bandwidth::default();
+        assert!(bandwidth.acquire(RumorType::ServiceFile, 10 * 1024 * 1024));
+        let counters = bandwidth.counters();
+        assert_eq!(counters["service_file"].sent_bytes, 10 * 1024 * 1024);
+        assert_eq!(counters["service_file"].deferred, 0);
+    }
+
+    #[test]
+    fn swim_is_never_held_back() {
+        let bandwidth = limited(1000);
+        bandwidth.consume_swim(5000);
+        assert!(bandwidth.acquire(RumorType::Member, 100));
+        assert!(!bandwidth.acquire(RumorType::Service, 100));
+        let counters = bandwidth.counters();
+        assert_eq!(counters["swim"].sent_bytes, 5000);
+        assert_eq!(counters["service"].deferred, 1);
+    }
+
+    #[test]
+    fn bulk_leaves_room_for_higher_priorities() {
+        let bandwidth = limited(1000);
+        assert!(bandwidth.acquire(RumorType::ServiceFile, 500));
+        assert!(!bandwidth.acquire(RumorType::ServiceConfig, 100));
+        assert!(bandwidth.acquire(RumorType::Election, 100));
+        let counters = bandwidth.counters();
+        assert_eq!(counters["service_file"].sent_bytes, 500);
+        assert_eq!(counters["service_config"].deferred, 1);
+        assert_eq!(counters["election"].sent_bytes, 100);
+    }
+
+    #[test]
+    fn over_budget_messages_are_deferred_without_waiting() {
+        let bandwidth = limited(100_000);
+        bandwidth.consume_swim(100_000);
+        let started = SteadyTime::now();
+        assert!(!bandwidth.acquire(RumorType::Service, 100));
+        assert!((SteadyTime::now() - started).num_milliseconds() < 100);
+        thread::sleep(Duration::from_millis(300));
+        assert!(bandwidth.acquire(RumorType::Service, 100));
+        let counters = bandwidth.counters();
+        assert_eq!(counters["service"].deferred, 1);
+        assert_eq!(counters["service"].sent_bytes, 100);
+    }
+}
@@ -14,7 +14,9 @@
 
 //! Periodically check membership rumors to automatically "time out"
 //! `Suspect` rumors to `Confirmed`, and `Confirmed` rumors to
-//! `Departed`.
+//! `Departed`. Suspicions confirmed by other members time out sooner
+//! than those nobody else shares. Members being confirmed are also
+//! watched for signs of a network partition.
 
 use std::thread;
 use std::time::Duration;
@@ -40,10 +42,25 @@ impl Expire {
 
     pub fn run(&self) {
         loop {
-            let newly_confirmed_members = self
-                .server
-                .member_list
-                .members_expired_to_confirmed(self.timing.suspicion_timeout_duration());
+            let members = self.server.member_list.len();
+            let newly_confirmed_members =
+                self.server
+                    .member_list
+                    .members_expired_to_confirmed_by(|confirmations| {
+                        self.timing
+                            .dynamic_suspicion_timeout_duration(confirmations, members)
+                    });
+
+            if !newly_confirmed_members.is_empty() {
+                self.server.partition.record_confirmed(
+                    &newly_confirmed_members,
+                    self.server.member_list.len_not_departed(),
+                );
+            }
+            let member_list = &self.server.member_list;
+            self.server
+                .partition
+                .update(|member_id| member_list.health_of_by_id(member_id));
 
             for id in newly_confirmed_members {
                 self.server
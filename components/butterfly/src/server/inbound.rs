@@ -16,6 +16,7 @@
 //!
 //! This module handles all the inbound SWIM messages.
 
+use std::mem;
 use std::net::{SocketAddr, UdpSocket};
 use std::sync::atomic::Ordering;
 use std::thread;
@@ -24,7 +25,7 @@ use std::time::Duration;
 use super::AckSender;
 use member::Health;
 use server::{outbound, Server};
-use swim::{Ack, Ping, PingReq, Swim, SwimKind};
+use swim::{Ack, Ping, PingReq, Swim, SwimKind, SWIM_MAX_BYTES};
 use trace::TraceKind;
 
 /// Takes the Server and a channel to send received Acks to the outbound thread.
@@ -44,9 +45,10 @@ impl Inbound {
         }
     }
 
-    /// Run the thread. Listens for messages up to 1k in size, and then processes them accordingly.
+    /// Run the thread. Listens for messages up to `SWIM_MAX_BYTES` in size, and then processes
+    /// them accordingly.
     pub fn run(&self) {
-        let mut recv_buffer: Vec<u8> = vec![0; 1024];
+        let mut recv_buffer: Vec<u8> = vec![0; SWIM_MAX_BYTES];
         loop {
             if self.server.pause.load(Ordering::Relaxed) {
                 thread::sleep(Duration::from_millis(100));
@@ -142,13 +144,21 @@ impl Inbound {
             .get(&msg.target.id)
         {
             Some(target) => {
+                // Pass on what the requester knows about the target's health, so that a target
+                // it suspects can refute the suspicion in its ack.
+                let hints = msg
+                    .membership
+                    .into_iter()
+                    .filter(|membership| membership.member.id == target.id)
+                    .collect();
                 // Set the route-back address to the one we received the pingreq from
-                outbound::ping(
+                outbound::forward_ping(
                     &self.server,
                     &self.socket,
                     target,
                     target.swim_socket_address(),
-                    Some(msg.from),
+                    msg.from,
+                    hints,
                 );
             }
             None => {
@@ -196,8 +206,7 @@ impl Inbound {
         match self.tx_outbound.send((addr, msg)) {
             Ok(()) => {
                 for membership in memberships {
-                    self.server
-                        .insert_member_from_rumor(membership.member, membership.health);
+                    self.server.insert_member_from_rumor(membership);
                 }
             }
             Err(e) => panic!("Outbound thread has died - this shouldn't happen: #{:?}", e),
@@ -205,8 +214,14 @@ impl Inbound {
     }
 
     /// Process ping messages.
+    ///
+    /// The rumors in the ping are processed before we ack it, so that if the pinger suspects us
+    /// our refutation goes out with the ack, rather than whenever the rumor reaches us again.
     fn process_ping(&self, addr: SocketAddr, mut msg: Ping) {
         trace_it!(SWIM: &self.server, TraceKind::RecvPing, &msg.from.id, addr, &msg);
+        for membership in mem::replace(&mut msg.membership, Vec::new()) {
+            self.server.insert_member_from_rumor(membership);
+        }
         outbound::ack(&self.server, &self.socket, &msg.from, addr, msg.forward_to);
         // Populate the member for this sender with its remote address
         msg.from.address = addr.ip().to_string();
@@ -216,9 +231,5 @@ impl Inbound {
         } else {
             self.server.insert_member(msg.from, Health::Alive);
         }
-        for membership in msg.membership {
-            self.server
-                .insert_member_from_rumor(membership.member, membership.health);
-        }
     }
 }
@@ -19,11 +19,15 @@
 //! protocol), expire (turning Suspect members into Confirmed members), push (the fan-out rumors),
 //! and pull (the inbound receipt of rumors.).
 
+mod anti_entropy;
+pub mod bandwidth;
 mod expire;
 mod inbound;
 mod outbound;
+pub mod partition;
 mod pull;
 mod push;
+mod rumor_expire;
 pub mod timing;
 
 use std::collections::HashSet;
@@ -35,7 +39,7 @@ use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
 use std::path::PathBuf;
 use std::result;
 use std::str::FromStr;
-use std::sync::atomic::{AtomicBool, AtomicIsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, channel};
 use std::sync::{Arc, RwLock};
 use std::thread;
@@ -45,18 +49,23 @@ use habitat_core::crypto::SymKey;
 use habitat_core::service::ServiceGroup;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
+use time::Duration as TimeDuration;
 
 use error::{Error, Result};
-use member::{Health, Member, MemberList};
-use message;
+use member::{Health, Member, MemberList, Membership, SUSPECTED_BY_MAX};
+use message::RingKeys;
 use rumor::dat_file::DatFile;
 use rumor::departure::Departure;
+use rumor::digest::Digest;
 use rumor::election::{Election, ElectionUpdate};
 use rumor::heat::RumorHeat;
 use rumor::service::Service;
 use rumor::service_config::ServiceConfig;
 use rumor::service_file::ServiceFile;
 use rumor::{Rumor, RumorKey, RumorStore, RumorType};
+use server::bandwidth::Bandwidth;
+use server::partition::{Partition, PartitionDetector};
+use server::timing::LocalHealth;
 use swim::Ack;
 use trace::{Trace, TraceKind};
 
@@ -78,7 +87,7 @@ pub struct Server {
     member_id: Arc<String>,
     pub member: Arc<RwLock<Member>>,
     pub member_list: MemberList,
-    ring_key: Arc<Option<SymKey>>,
+    ring_keys: RingKeys,
     rumor_heat: RumorHeat,
     pub service_store: RumorStore<Service>,
     pub service_config_store: RumorStore<ServiceConfig>,
@@ -93,6 +102,10 @@ pub struct Server {
     dat_file: Arc<RwLock<Option<DatFile>>>,
     socket: Option<UdpSocket>,
     departed: Arc<AtomicBool>,
+    rumors_reaped: Arc<AtomicUsize>,
+    local_health: LocalHealth,
+    bandwidth: Bandwidth,
+    partition: PartitionDetector,
     // These are all here for testing support
     pause: Arc<AtomicBool>,
     pub trace: Arc<RwLock<Trace>>,
@@ -108,7 +121,7 @@ impl Clone for Server {
             member_id: self.member_id.clone(),
             member: self.member.clone(),
             member_list: self.member_list.clone(),
-            ring_key: self.ring_key.clone(),
+            ring_keys: self.ring_keys.clone(),
             rumor_heat: self.rumor_heat.clone(),
             service_store: self.service_store.clone(),
             service_config_store: self.service_config_store.clone(),
@@ -122,6 +135,10 @@ impl Clone for Server {
             data_path: self.data_path.clone(),
             dat_file: self.dat_file.clone(),
             departed: self.departed.clone(),
+            rumors_reaped: self.rumors_reaped.clone(),
+            local_health: self.local_health.clone(),
+            bandwidth: self.bandwidth.clone(),
+            partition: self.partition.clone(),
             pause: self.pause.clone(),
             trace: self.trace.clone(),
             swim_rounds: self.swim_rounds.clone(),
@@ -162,7 +179,7 @@ impl Server {
                     member_id: Arc::new(member.id.clone()),
                     member: Arc::new(RwLock::new(member)),
                     member_list: MemberList::new(),
-                    ring_key: Arc::new(ring_key),
+                    ring_keys: RingKeys::new(ring_key),
                     rumor_heat: RumorHeat::default(),
                     service_store: RumorStore::default(),
                     service_config_store: RumorStore::default(),
@@ -176,6 +193,10 @@ impl Server {
                     data_path: Arc::new(data_path.as_ref().map(|p| p.into())),
                     dat_file: Arc::new(RwLock::new(None)),
                     departed: Arc::new(AtomicBool::new(false)),
+                    rumors_reaped: Arc::new(AtomicUsize::new(0)),
+                    local_health: LocalHealth::default(),
+                    bandwidth: Bandwidth::default(),
+                    partition: PartitionDetector::default(),
                     pause: Arc::new(AtomicBool::new(false)),
                     trace: Arc::new(RwLock::new(trace)),
                     swim_rounds: Arc::new(AtomicIsize::new(0)),
@@ -260,7 +281,7 @@ impl Server {
                 return Err(Error::BadDataPath(path.to_path_buf(), err));
             }
             let mut file = DatFile::new(&self.member_id, path);
-            if file.path().exists() {
+            if file.path().exists() || file.backup_path().exists() {
                 match file.read_into(self) {
                     Ok(_) => debug!(
                         "Successfully ingested rumors from {}",
@@ -330,6 +351,24 @@ impl Server {
                 panic!("You should never, ever get here, frank");
             });
 
+        let server_g = self.clone();
+        let timing_g = timing.clone();
+        let _ = thread::Builder::new()
+            .name(format!("rumor-expire-{}", self.name()))
+            .spawn(move || {
+                rumor_expire::RumorExpire::new(server_g, timing_g).run();
+                panic!("You should never, ever get here, gus");
+            });
+
+        let server_h = self.clone();
+        let timing_h = timing.clone();
+        let _ = thread::Builder::new()
+            .name(format!("anti-entropy-{}", self.name()))
+            .spawn(move || {
+                anti_entropy::AntiEntropy::new(server_h, timing_h).run();
+                panic!("You should never, ever get here, hana");
+            });
+
         let server_d = self.clone();
         let _ = thread::Builder::new()
             .name(format!("pull-{}", self.name()))
@@ -524,12 +563,23 @@ impl Server {
         }
     }
 
-    /// Given a membership record and some health, insert it into the Member List.
-    fn insert_member_from_rumor(&self, member: Member, mut health: Health) {
+    /// Given a membership record, insert it into the Member List. If it says the member is
+    /// suspect, each member it lists as suspecting it counts once towards confirming the
+    /// suspicion, however many members relay it to us, and only if the record is about the
+    /// member's current incarnation. New confirmations are passed on like any other update.
+    fn insert_member_from_rumor(&self, membership: Membership) {
+        let Membership {
+            member,
+            mut health,
+            suspected_by,
+        } = membership;
         let mut incremented_incarnation = false;
         let rk: RumorKey = RumorKey::from(&member);
         if member.id == self.member_id() {
             if health != Health::Alive {
+                // Having to refute rumors of our own demise suggests we are the ones who are
+                // slow to answer.
+                self.local_health.degrade();
                 let mut me = self.member.write().expect("Member lock is poisoned");
                 let mut incarnation = me.incarnation;
                 incarnation += 1;
@@ -545,7 +595,17 @@ impl Server {
         let trace_incarnation = member.incarnation;
         let trace_health = health.clone();
 
-        if self.member_list.insert(member, health) || incremented_incarnation {
+        let mut updated = self.member_list.insert(member, health) || incremented_incarnation;
+        if trace_health == Health::Suspect {
+            for suspected_by in suspected_by.iter().take(SUSPECTED_BY_MAX) {
+                updated |= self.member_list.add_suspicion(
+                    &trace_member_id,
+                    trace_incarnation,
+                    suspected_by,
+                );
+            }
+        }
+        if updated {
             trace_it!(
                 MEMBERSHIP: self,
                 TraceKind::MemberUpdate,
@@ -633,6 +693,105 @@ impl Server {
         }
     }
 
+    /// Returns a digest of every member and rumor we know about, for anti-entropy.
+    ///
+    /// Versions order the same way the rumors merge: members by incarnation and then health,
+    /// service, config and file rumors by incarnation, and elections by term and then status.
+    /// Departures never change, so they all have the same version.
+    pub fn digest(&self, reply: bool) -> Digest {
+        let mut entries = Vec::new();
+        self.member_list.with_members(|member| {
+            if let Some(health) = self.member_list.health_of(member) {
+                entries.push((
+                    RumorKey::new(RumorType::Member, &member.id, ""),
+                    (member.incarnation, health as u64),
+                ));
+            }
+        });
+        entries.extend(self.service_store.versions(|s| (s.incarnation, 0)));
+        entries.extend(self.service_config_store.versions(|s| (s.incarnation, 0)));
+        entries.extend(self.service_file_store.versions(|s| (s.incarnation, 0)));
+        entries.extend(self.election_store.versions(|e| (e.term, e.status as u64)));
+        entries.extend(self.update_store.versions(|e| (e.term, e.status as u64)));
+        entries.extend(self.departure_store.versions(|_| (0, 0)));
+        Digest::new(entries, reply)
+    }
+
+    /// Drop the service and election rumors of members that have been `Confirmed` or `Departed`
+    /// for longer than `ttl`. Returns how many rumors were dropped.
+    ///
+    /// Dropped rumors may still be gossiped back to us by members that haven't dropped them yet;
+    /// since the members stay dead, those rumors are dropped again the next time around.
+    pub fn reap_rumors(&self, ttl: TimeDuration) -> usize {
+        let dead: HashSet<String> = self
+            .member_list
+            .members_dead_longer_than(ttl)
+            .into_iter()
+            .filter(|id| id != self.member_id())
+            .collect();
+        if dead.is_empty() {
+            return 0;
+        }
+        let mut reaped = self
+            .service_store
+            .remove_where(|service| dead.contains(&service.member_id));
+        reaped.extend(
+            self.election_store
+                .remove_where(|election| dead.contains(&election.member_id)),
+        );
+        reaped.extend(
+            self.update_store
+                .remove_where(|election| dead.contains(&election.member_id)),
+        );
+        for rk in reaped.iter() {
+            self.rumor_heat.purge(rk);
+        }
+        self.rumors_reaped
+            .fetch_add(reaped.len(), Ordering::Relaxed);
+        reaped.len()
+    }
+
+    /// The number of rumors dropped by `reap_rumors` since this server started.
+    pub fn rumors_reaped(&self) -> usize {
+        self.rumors_reaped.load(Ordering::Relaxed)
+    }
+
+    /// Our local health score; zero when we are keeping up with the protocol.
+    pub fn local_health(&self) -> usize {
+        self.local_health.score()
+    }
+
+    /// The network partition we suspect we are on one side of, if any, along with the service
+    /// groups it affects.
+    pub fn partition(&self) -> Option<Partition> {
+        self.partition.partition().map(|mut partition| {
+            self.service_store.with_keys(|(service_group, rumors)| {
+                if rumors
+                    .values()
+                    .any(|rumor| partition.lost.contains(&rumor.member_id))
+                {
+                    partition.service_groups.insert(service_group.clone());
+                }
+            });
+            partition
+        })
+    }
+
+    /// Whether a suspected network partition affects the given service group. Elections in such
+    /// a group are not restarted until the partition clears.
+    pub fn is_partitioned(&self, service_group: &str) -> bool {
+        match self.partition.partition() {
+            Some(partition) => {
+                let mut partitioned = false;
+                self.service_store.with_rumors(service_group, |rumor| {
+                    partitioned |= partition.lost.contains(&rumor.member_id);
+                });
+                partitioned
+            }
+            None => false,
+        }
+    }
+
     /// Get all the Member ID's who are present in a given service group, and eligible to vote
     /// (alive)
     fn get_electorate(&self, key: &str) -> Vec<String> {
@@ -708,15 +867,40 @@ impl Server {
         self.rumor_heat.start_hot_rumor(ek);
     }
 
+    /// Start a new term of the update election for the given service group, in which this member
+    /// stands with the given suitability. Returns the new term.
+    pub fn restart_update_election(&self, sg: ServiceGroup, suitability: u64) -> u64 {
+        let mut term = 0;
+        self.update_store.with_rumor(&sg, "election", |election| {
+            if let Some(election) = election {
+                term = election.term + 1;
+            }
+        });
+        warn!("Starting a new election for {} {}", sg, term);
+        self.update_store.remove(&sg, "election");
+        self.start_update_election(sg, suitability, term);
+        term
+    }
+
     /// Check to see if this server needs to restart a given election. This happens when:
     ///
     /// a) We are the leader, and we have lost quorum with the rest of the group.
     /// b) We are not the leader, and we have detected that the leader is confirmed dead.
+    ///
+    /// Elections in service groups affected by a suspected network partition are left alone until
+    /// it clears.
     pub fn restart_elections(&self) {
         let mut elections_to_restart: Vec<(String, u64)> = vec![];
         let mut update_elections_to_restart: Vec<(String, u64)> = vec![];
 
         self.election_store.with_keys(|(service_group, rumors)| {
+            if self.is_partitioned(service_group) {
+                debug!(
+                    "Not restarting elections in {} during a suspected network partition",
+                    service_group
+                );
+                return;
+            }
             if self
                 .service_store
                 .contains_rumor(&service_group, self.member_id())
@@ -755,6 +939,13 @@ impl Server {
         });
 
         self.update_store.with_keys(|(service_group, rumors)| {
+            if self.is_partitioned(service_group) {
+                debug!(
+                    "Not restarting elections in {} during a suspected network partition",
+                    service_group
+                );
+                return;
+            }
             if self
                 .service_store
                 .contains_rumor(&service_group, self.member_id())
@@ -985,16 +1176,26 @@ impl Server {
         }
     }
 
+    /// A handle on the gossip bandwidth limit of this server, and its counters.
+    pub fn bandwidth(&self) -> Bandwidth {
+        self.bandwidth.clone()
+    }
+
+    /// A handle on the ring keys this server uses, for rotating them while it runs.
+    pub fn ring_keys(&self) -> RingKeys {
+        self.ring_keys.clone()
+    }
+
     fn generate_wire(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
-        message::generate_wire(payload, (*self.ring_key).as_ref())
+        self.ring_keys.generate_wire(payload)
     }
 
     fn unwrap_wire(&self, payload: &[u8]) -> Result<Vec<u8>> {
-        message::unwrap_wire(payload, (*self.ring_key).as_ref())
+        self.ring_keys.unwrap_wire(payload)
     }
 
     fn persist_data(&self) {
-        if let Some(ref dat_file) = *self.dat_file.write().expect("DatFile lock poisoned") {
+        if let Some(ref mut dat_file) = *self.dat_file.write().expect("DatFile lock poisoned") {
             if let Some(err) = dat_file.write(self).err() {
                 error!("Error persisting rumors to disk, {}", err);
             } else {
@@ -1017,7 +1218,7 @@ impl Serialize for Server {
     where
         S: Serializer,
     {
-        let mut strukt = serializer.serialize_struct("butterfly", 6)?;
+        let mut strukt = serializer.serialize_struct("butterfly", 13)?;
         strukt.serialize_field("member", &self.member_list)?;
         strukt.serialize_field("service", &self.service_store)?;
         strukt.serialize_field("service_config", &self.service_config_store)?;
@@ -1025,6 +1226,12 @@ impl Serialize for Server {
         strukt.serialize_field("election", &self.election_store)?;
         strukt.serialize_field("election_update", &self.update_store)?;
         strukt.serialize_field("departure", &self.departure_store)?;
+        strukt.serialize_field("swim_rounds", &self.swim_rounds())?;
+        strukt.serialize_field("gossip_rounds", &self.gossip_rounds())?;
+        strukt.serialize_field("rumors_reaped", &self.rumors_reaped())?;
+        strukt.serialize_field("local_health", &self.local_health())?;
+        strukt.serialize_field("bandwidth", &self.bandwidth)?;
+        strukt.serialize_field("partition", &self.partition())?;
         strukt.end()
     }
 }
@@ -1059,15 +1266,21 @@ fn persist_loop(server: Server) {
 #[cfg(test)]
 mod tests {
     mod server {
+        use habitat_core::package::{Identifiable, PackageIdent};
         use habitat_core::service::ServiceGroup;
-        use member::Member;
+        use member::{Health, Member, Membership};
+        use rumor::election::Election;
+        use rumor::service::{Service, SysInfo};
+        use rumor::{RumorKey, RumorType};
         use server::timing::Timing;
         use server::{Server, Suitability};
         use std::fs::File;
         use std::io::prelude::*;
         use std::path::PathBuf;
+        use std::str::FromStr;
         use std::sync::atomic::{AtomicUsize, Ordering, ATOMIC_USIZE_INIT};
         use tempdir::TempDir;
+        use time::Duration;
         use trace::Trace;
 
         static SWIM_PORT: AtomicUsize = ATOMIC_USIZE_INIT;
@@ -1169,5 +1382,123 @@ mod tests {
                 .start(Timing::default())
                 .expect("Server failed to start");
         }
+
+        #[test]
+        fn suspicion_relayed_by_many_members_counts_once() {
+            let server = start_server();
+            let mut member = Member::default();
+            member.incarnation = 1;
+            for _ in 0..5 {
+                server.insert_member_from_rumor(Membership {
+                    member: member.clone(),
+                    health: Health::Suspect,
+                    suspected_by: vec!["adam".to_string()],
+                });
+            }
+            assert_eq!(
+                server.member_list.suspected_by(&member.id),
+                vec!["adam".to_string()]
+            );
+            assert_eq!(server.member_list.suspicion_confirmations(&member.id), 0);
+
+            server.insert_member_from_rumor(Membership {
+                member: member.clone(),
+                health: Health::Suspect,
+                suspected_by: vec!["adam".to_string(), "eve".to_string()],
+            });
+            assert_eq!(server.member_list.suspicion_confirmations(&member.id), 1);
+        }
+
+        #[test]
+        fn stale_suspicions_are_not_counted() {
+            let server = start_server();
+            let mut member = Member::default();
+            member.incarnation = 2;
+            server.insert_member_from_rumor(Membership {
+                member: member.clone(),
+                health: Health::Suspect,
+                suspected_by: vec!["adam".to_string()],
+            });
+
+            let mut stale = member.clone();
+            stale.incarnation = 1;
+            server.insert_member_from_rumor(Membership {
+                member: stale,
+                health: Health::Suspect,
+                suspected_by: vec!["eve".to_string()],
+            });
+            assert_eq!(
+                server.member_list.suspected_by(&member.id),
+                vec!["adam".to_string()]
+            );
+        }
+
+        #[test]
+        fn digest_lists_members_and_rumors_with_versions() {
+            let server = start_server();
+            let pkg = PackageIdent::from_str("core/neurosis/1.2.3/20161208121212").unwrap();
+            let sg = ServiceGroup::new(None, pkg.name(), "production", None).unwrap();
+            let mut member = Member::default();
+            member.incarnation = 2;
+            server.insert_member(member.clone(), Health::Suspect);
+            let mut service = Service::new(
+                member.id.clone(),
+                &pkg,
+                sg.clone(),
+                SysInfo::default(),
+                None,
+            );
+            service.incarnation = 5;
+            server.service_store.insert(service);
+
+            let digest = server.digest(false);
+            assert!(!digest.reply);
+            assert!(digest.entries.contains(&(
+                RumorKey::new(RumorType::Member, &member.id, ""),
+                (2, Health::Suspect as u64)
+            )));
+            assert!(digest.entries.contains(&(
+                RumorKey::new(RumorType::Service, &member.id, sg.to_string()),
+                (5, 0)
+            )));
+        }
+
+        #[test]
+        fn reap_rumors_drops_rumors_of_dead_members() {
+            let server = start_server();
+            let pkg = PackageIdent::from_str("core/neurosis/1.2.3/20161208121212").unwrap();
+            let sg = ServiceGroup::new(None, pkg.name(), "production", None).unwrap();
+            let alive = Member::default();
+            let dead = Member::default();
+            server.insert_member(alive.clone(), Health::Alive);
+            server.insert_member(dead.clone(), Health::Confirmed);
+            for member in &[&alive, &dead] {
+                server.service_store.insert(Service::new(
+                    member.id.clone(),
+                    &pkg,
+                    sg.clone(),
+                    SysInfo::default(),
+                    None,
+                ));
+            }
+            server
+                .election_store
+                .insert(Election::new(dead.id.clone(), sg.clone(), 0));
+
+            assert_eq!(server.reap_rumors(Duration::days(1)), 0);
+            assert_eq!(server.reap_rumors(Duration::zero()), 2);
+            assert_eq!(server.rumors_reaped(), 2);
+            assert!(
+                server
+                    .service_store
+                    .contains_rumor(&sg.to_string(), &alive.id)
+            );
+            assert!(
+                !server
+                    .service_store
+                    .contains_rumor(&sg.to_string(), &dead.id)
+            );
+            assert_eq!(server.election_store.len(), 0);
+        }
     }
 }
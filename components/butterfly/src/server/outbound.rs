@@ -26,7 +26,7 @@ use std::time::Duration;
 use time::SteadyTime;
 
 use super::AckReceiver;
-use member::{Health, Member};
+use member::{Health, Member, Membership};
 use rumor::{RumorKey, RumorType};
 use server::timing::Timing;
 use server::Server;
@@ -113,7 +113,7 @@ impl Outbound {
 
             self.server.update_swim_round();
 
-            let long_wait = self.timing.next_protocol_period();
+            let long_wait = self.probe_timing().next_protocol_period();
 
             let check_list = self
                 .server
@@ -125,7 +125,7 @@ impl Outbound {
                     // This is the timeout for the next protocol period - if we
                     // complete faster than this, we want to wait in the end
                     // until this timer expires.
-                    let next_protocol_period = self.timing.next_protocol_period();
+                    let next_protocol_period = self.probe_timing().next_protocol_period();
 
                     self.probe(member);
 
@@ -149,6 +149,12 @@ impl Outbound {
         }
     }
 
+    /// The timing to probe with right now; stretched while our local health is poor.
+    fn probe_timing(&self) -> Timing {
+        self.timing
+            .with_local_health(self.server.local_health.score())
+    }
+
     /// Probe Loop
     ///
     /// First, we send the ping to the remote address. This operation never blocks - we just
@@ -172,6 +178,7 @@ impl Outbound {
         // Ping the member, and wait for the ack.
         ping(&self.server, &self.socket, &member, addr, None);
         if self.recv_ack(&member, addr, AckFrom::Ping) {
+            self.server.local_health.improve();
             trace_it!(PROBE: &self.server, TraceKind::ProbeAckReceived, &member.id, addr);
             trace_it!(PROBE: &self.server, TraceKind::ProbeComplete, &member.id, addr);
             return;
@@ -195,7 +202,14 @@ impl Outbound {
             warn!("Marking {} as Suspect", &member.id);
             trace_it!(PROBE: &self.server, TraceKind::ProbeSuspect, &member.id, addr);
             trace_it!(PROBE: &self.server, TraceKind::ProbeComplete, &member.id, addr);
+            // Nobody answering at all is as likely to be our fault as theirs.
+            self.server.local_health.degrade();
+            let member_id = member.id.clone();
+            let incarnation = member.incarnation;
             self.server.insert_member(member, Health::Suspect);
+            self.server
+                .member_list
+                .add_suspicion(&member_id, incarnation, self.server.member_id());
         } else {
             trace_it!(PROBE: &self.server, TraceKind::ProbeComplete, &member.id, addr);
         }
@@ -203,9 +217,10 @@ impl Outbound {
 
     /// Listen for an ack from the `Inbound` thread.
     fn recv_ack(&mut self, member: &Member, addr: SocketAddr, ack_from: AckFrom) -> bool {
+        let timing = self.probe_timing();
         let timeout = match ack_from {
-            AckFrom::Ping => self.timing.ping_timeout(),
-            AckFrom::PingReq => self.timing.pingreq_timeout(),
+            AckFrom::Ping => timing.ping_timeout(),
+            AckFrom::PingReq => timing.pingreq_timeout(),
         };
         loop {
             match self.rx_inbound.try_recv() {
@@ -248,31 +263,41 @@ impl Outbound {
     }
 }
 
-/// Populate a SWIM message with rumors.
+/// Populate a SWIM message with rumors, adding only as many as fit in `SWIM_MAX_BYTES`.
 pub fn populate_membership_rumors(server: &Server, target: &Member, swim: &mut Swim) {
+    // Rumors which were already placed in the message, such as the hints of a forwarded ping, are
+    // dropped from the end until it fits.
+    while !swim.fits_on_wire() && swim.membership.pop().is_some() {}
+
     // If this isn't the first time we are communicating with this target, we want to include this
     // targets current status. This ensures that members always get a "Confirmed" rumor, before we
     // have the chance to flip it to "Alive", which helps make sure we heal from a partition.
     if server.member_list.contains_member(&target.id) {
         if let Some(always_target) = server.member_list.membership_for(&target.id) {
-            swim.membership.push(always_target);
+            swim.push_membership(always_target);
         }
     }
 
     // NOTE: the way this is currently implemented, this is grabbing
     // the 5 coolest (but still warm!) Member rumors.
-    let rumors: Vec<RumorKey> = server.rumor_heat
+    let mut rumors: Vec<RumorKey> = server.rumor_heat
         .currently_hot_rumors(&target.id)
         .into_iter()
         .filter(|ref r| r.kind == RumorType::Member)
         .take(5) // TODO (CM): magic number!
         .collect();
 
-    for ref rkey in rumors.iter() {
+    // Only the rumors which fit in the message are cooled; the rest go out in a later one.
+    let mut sent = 0;
+    for rkey in rumors.iter() {
         if let Some(member) = server.member_list.membership_for(&rkey.key()) {
-            swim.membership.push(member);
+            if !swim.push_membership(member) {
+                break;
+            }
         }
+        sent += 1;
     }
+    rumors.truncate(sent);
     // We don't want to update the heat for rumors that we know we are sending to a target that is
     // confirmed dead; the odds are, they won't receive them. Lets spam them a little harder with
     // rumors.
@@ -305,6 +330,7 @@ pub fn pingreq(server: &Server, socket: &UdpSocket, pingreq_target: &Member, tar
             return;
         }
     };
+    server.bandwidth.consume_swim(payload.len());
     match socket.send_to(&payload, addr) {
         Ok(_s) => trace!(
             "Sent PingReq to {}@{} for {}@{}",
@@ -338,6 +364,31 @@ pub fn ping(
     target: &Member,
     addr: SocketAddr,
     forward_to: Option<Member>,
+) {
+    send_ping(server, socket, target, addr, forward_to, Vec::new())
+}
+
+/// Send a Ping on behalf of the member that sent us a PingReq. The `hints` are its membership
+/// rumors about the target, which go first so the target learns it is suspected in time to
+/// refute it.
+pub fn forward_ping(
+    server: &Server,
+    socket: &UdpSocket,
+    target: &Member,
+    addr: SocketAddr,
+    forward_to: Member,
+    hints: Vec<Membership>,
+) {
+    send_ping(server, socket, target, addr, Some(forward_to), hints)
+}
+
+fn send_ping(
+    server: &Server,
+    socket: &UdpSocket,
+    target: &Member,
+    addr: SocketAddr,
+    forward_to: Option<Member>,
+    hints: Vec<Membership>,
 ) {
     let forward_addr = if let Some(ref forward_to) = forward_to {
         Some(format!("{}@{}", forward_to.id, forward_to.address))
@@ -345,7 +396,7 @@ pub fn ping(
         None
     };
     let ping = Ping {
-        membership: vec![],
+        membership: hints,
         from: server.member.read().unwrap().clone(),
         forward_to: forward_to,
     };
@@ -365,6 +416,7 @@ pub fn ping(
             return;
         }
     };
+    server.bandwidth.consume_swim(payload.len());
     match socket.send_to(&payload, addr) {
         Ok(_s) => {
             if let Some(forward_addr) = forward_addr {
@@ -403,6 +455,7 @@ pub fn forward_ack(server: &Server, socket: &UdpSocket, addr: SocketAddr, msg: A
             return;
         }
     };
+    server.bandwidth.consume_swim(payload.len());
     match socket.send_to(&payload, addr) {
         Ok(_s) => trace!("Forwarded ack to {}@{}", member_id, addr),
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
@@ -439,6 +492,7 @@ pub fn ack(
             return;
         }
     };
+    server.bandwidth.consume_swim(payload.len());
     match socket.send_to(&payload, addr) {
         Ok(_s) => trace!("Sent ack to {}@{}", member_id, addr),
         Err(e) => error!("Failed ack to {}@{}: {}", member_id, addr, e),
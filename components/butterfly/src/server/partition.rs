@@ -0,0 +1,216 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Detection of likely network partitions.
+//!
+//! One member being confirmed dead is routine. When a large share of the ring is confirmed dead
+//! within a short window, it is far more likely that the network between us and them has split
+//! than that they all failed at once. Such a partition lasts until more than half of the members
+//! lost in it are alive or suspect again. While it lasts, elections are not restarted and updates
+//! are not rolled out in the service groups those members belong to, since the other side of the
+//! split is most likely still running them.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use time::{self, Duration, SteadyTime};
+
+use member::Health;
+
+/// How far back confirmations count towards a partition.
+const WINDOW_SECS: i64 = 60;
+/// The fewest members which must be confirmed within the window to raise a partition.
+const MIN_LOST: usize = 3;
+/// The share of the ring which must be confirmed within the window to raise a partition.
+const LOST_FRACTION: f64 = 0.3;
+
+/// A partition we believe we are on one side of.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Partition {
+    /// When the partition was detected, in seconds since the epoch.
+    pub detected_at: i64,
+    /// The members confirmed dead around the partition, which have not come back yet.
+    pub lost: BTreeSet<String>,
+    /// The service groups of the lost members. Filled in by the server when it is asked for the
+    /// partition, so it reflects the current service rumors.
+    pub service_groups: BTreeSet<String>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Members confirmed dead within the window, and when.
+    recent: VecDeque<(String, SteadyTime)>,
+    partition: Option<Partition>,
+}
+
+/// Watches members being confirmed dead for signs of a partition. Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct PartitionDetector {
+    state: Arc<Mutex<State>>,
+    window: Duration,
+}
+
+impl Default for PartitionDetector {
+    fn default() -> PartitionDetector {
+        PartitionDetector {
+            state: Arc::new(Mutex::new(State {
+                recent: VecDeque::new(),
+                partition: None,
+            })),
+            window: Duration::seconds(WINDOW_SECS),
+        }
+    }
+}
+
+impl PartitionDetector {
+    /// Record members which were just confirmed dead, out of a ring of `ring_size` members which
+    /// have not departed. Returns `true` if this raised a new partition.
+    pub fn record_confirmed(&self, member_ids: &[String], ring_size: usize) -> bool {
+        let mut state = self.state.lock().expect("Partition lock poisoned");
+        let now = SteadyTime::now();
+        for member_id in member_ids {
+            state.recent.push_back((member_id.clone(), now));
+        }
+        while state
+            .recent
+            .front()
+            .map_or(false, |&(_, at)| now - at > self.window)
+        {
+            state.recent.pop_front();
+        }
+
+        if let Some(ref mut partition) = state.partition {
+            partition.lost.extend(member_ids.iter().cloned());
+            return false;
+        }
+        let lost = state.recent.len();
+        if lost < MIN_LOST || (lost as f64) < (ring_size as f64) * LOST_FRACTION {
+            return false;
+        }
+        let partition = Partition {
+            detected_at: time::get_time().sec,
+            lost: state.recent.iter().map(|&(ref id, _)| id.clone()).collect(),
+            service_groups: BTreeSet::new(),
+        };
+        warn!(
+            "Suspected network partition: {} of {} members confirmed dead within {}s",
+            lost,
+            ring_size,
+            self.window.num_seconds()
+        );
+        state.partition = Some(partition);
+        true
+    }
+
+    /// Check the members lost in the partition against their current health, and clear the
+    /// partition once more than half of them are back. Members which have departed, or are gone
+    /// from the member list, are no longer waited for, so the partition also clears once there
+    /// are none left to wait for. Returns `true` if the partition cleared.
+    pub fn update<F>(&self, health_of: F) -> bool
+    where
+        F: Fn(&str) -> Option<Health>,
+    {
+        let mut state = self.state.lock().expect("Partition lock poisoned");
+        let cleared = match state.partition {
+            Some(ref mut partition) => {
+                let mut back = 0;
+                let mut lost = BTreeSet::new();
+                for member_id in partition.lost.iter() {
+                    match health_of(member_id) {
+                        Some(Health::Alive) | Some(Health::Suspect) => back += 1,
+                        Some(Health::Confirmed) => (),
+                        Some(Health::Departed) | None => continue,
+                    }
+                    lost.insert(member_id.clone());
+                }
+                partition.lost = lost;
+                partition.lost.is_empty() || back * 2 > partition.lost.len()
+            }
+            None => return false,
+        };
+        if cleared {
+            info!("Network partition cleared; lost members are reachable again");
+            state.partition = None;
+            state.recent.clear();
+        }
+        cleared
+    }
+
+    /// The partition we are in, if any.
+    pub fn partition(&self) -> Option<Partition> {
+        self.state
+            .lock()
+            .expect("Partition lock poisoned")
+            .partition
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PartitionDetector;
+    use member::Health;
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn a_few_deaths_are_not_a_partition() {
+        let detector = PartitionDetector::default();
+        assert!(!detector.record_confirmed(&ids(&["a"]), 10));
+        assert!(!detector.record_confirmed(&ids(&["b"]), 10));
+        assert!(detector.partition().is_none());
+    }
+
+    #[test]
+    fn many_deaths_at_once_are_a_partition() {
+        let detector = PartitionDetector::default();
+        assert!(!detector.record_confirmed(&ids(&["a", "b"]), 9));
+        assert!(detector.record_confirmed(&ids(&["c"]), 9));
+        assert!(!detector.record_confirmed(&ids(&["d"]), 9));
+        let partition = detector.partition().expect("partition was not raised");
+        assert_eq!(partition.lost.len(), 4);
+    }
+
+    #[test]
+    fn partition_clears_when_most_lost_members_are_back() {
+        let detector = PartitionDetector::default();
+        assert!(detector.record_confirmed(&ids(&["a", "b", "c", "d"]), 8));
+        assert!(!detector.update(|id| if id == "a" {
+            Some(Health::Alive)
+        } else {
+            Some(Health::Confirmed)
+        }));
+        assert!(detector.partition().is_some());
+        assert!(detector.update(|id| if id == "d" {
+            Some(Health::Departed)
+        } else {
+            Some(Health::Suspect)
+        }));
+        assert!(detector.partition().is_none());
+    }
+
+    #[test]
+    fn partition_clears_when_every_lost_member_departs() {
+        let detector = PartitionDetector::default();
+        assert!(detector.record_confirmed(&ids(&["a", "b", "c"]), 6));
+        assert!(detector.update(|id| if id == "a" {
+            None
+        } else {
+            Some(Health::Departed)
+        }));
+        assert!(detector.partition().is_none());
+    }
+}
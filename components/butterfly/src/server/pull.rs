@@ -23,6 +23,7 @@ use std::time::Duration;
 use zmq;
 
 use rumor::{RumorEnvelope, RumorKind};
+use server::anti_entropy;
 use server::Server;
 use trace::TraceKind;
 use ZMQ_CONTEXT;
@@ -92,8 +93,7 @@ impl Pull {
             trace_it!(GOSSIP: &self.server, TraceKind::RecvRumor, &proto.from_id, &proto);
             match proto.kind {
                 RumorKind::Membership(membership) => {
-                    self.server
-                        .insert_member_from_rumor(membership.member, membership.health);
+                    self.server.insert_member_from_rumor(membership);
                 }
                 RumorKind::Service(service) => self.server.insert_service(service),
                 RumorKind::ServiceConfig(service_config) => {
@@ -111,6 +111,9 @@ impl Pull {
                 RumorKind::Departure(departure) => {
                     self.server.insert_departure(departure);
                 }
+                RumorKind::Digest(digest) => {
+                    anti_entropy::answer(&self.server, &proto.from_id, digest);
+                }
             }
         }
     }
@@ -24,8 +24,8 @@ use std::time::Duration;
 use time::SteadyTime;
 use zmq;
 
-use member::{Member, Membership};
-use rumor::{RumorEnvelope, RumorKey, RumorKind, RumorType};
+use member::{Health, Member, Membership};
+use rumor::{Digest, RumorEnvelope, RumorKey, RumorKind, RumorType};
 use server::timing::Timing;
 use server::Server;
 use trace::TraceKind;
@@ -131,7 +131,7 @@ impl Push {
 }
 
 /// A worker thread for pushing messages to a target
-struct PushWorker {
+pub struct PushWorker {
     pub server: Server,
 }
 
@@ -141,11 +141,8 @@ impl PushWorker {
         PushWorker { server: server }
     }
 
-    /// Send the list of rumors to a given member. This method creates an outbound socket and then
-    /// closes the connection as soon as we are done sending rumors. ZeroMQ may choose to keep the
-    /// connection and socket open for 1 second longer - so it is possible, but unlikely, that this
-    /// method can loose messages.
-    fn send_rumors(&self, member: Member, rumors: Vec<RumorKey>) {
+    /// Creates an outbound socket connected to the given member.
+    fn connect(&self, member: &Member) -> Option<zmq::Socket> {
         let socket = (**ZMQ_CONTEXT)
             .as_mut()
             .socket(zmq::PUSH)
@@ -167,12 +164,31 @@ impl PushWorker {
             .expect("Failure to set the ZMQ send timeout");
         let to_addr = format!("{}:{}", member.address, member.gossip_port);
         match socket.connect(&format!("tcp://{}", to_addr)) {
-            Ok(()) => debug!("Connected push socket to {:?}", member),
+            Ok(()) => {
+                debug!("Connected push socket to {:?}", member);
+                Some(socket)
+            }
             Err(e) => {
                 error!("Cannot connect push socket to {:?}: {:?}", member, e);
-                return;
+                None
             }
         }
+    }
+
+    /// Send the list of rumors to a given member. This method creates an outbound socket and then
+    /// closes the connection as soon as we are done sending rumors. ZeroMQ may choose to keep the
+    /// connection and socket open for 1 second longer - so it is possible, but unlikely, that this
+    /// method can loose messages.
+    ///
+    /// Rumors deferred for lack of gossip bandwidth are not cooled, so they are sent again on a
+    /// later round.
+    pub fn send_rumors(&self, member: Member, rumors: Vec<RumorKey>) {
+        let socket = match self.connect(&member) {
+            Some(socket) => socket,
+            None => return,
+        };
+        let to_addr = format!("{}:{}", member.address, member.gossip_port);
+        let mut sent = Vec::with_capacity(rumors.len());
         'rumorlist: for ref rumor_key in rumors.iter() {
             let rumor_as_bytes = match rumor_key.kind {
                 RumorType::Member => {
@@ -310,6 +326,10 @@ impl PushWorker {
                         continue 'rumorlist;
                     }
                 },
+                RumorType::Digest => {
+                    debug!("Digests aren't kept as rumors; not sending {:?}", rumor_key);
+                    continue 'rumorlist;
+                }
                 RumorType::Fake | RumorType::Fake2 => {
                     debug!("You have fake rumors; how odd!");
                     continue 'rumorlist;
@@ -322,6 +342,14 @@ impl PushWorker {
                     continue 'rumorlist;
                 }
             };
+            if !self.server.bandwidth.acquire(rumor_key.kind, payload.len()) {
+                debug!(
+                    "Deferred rumor {:?} to {:?}; no gossip bandwidth left",
+                    rumor_key, member.id
+                );
+                continue 'rumorlist;
+            }
+            sent.push((*rumor_key).clone());
             match socket.send(&payload, 0) {
                 Ok(()) => debug!("Sent rumor {:?} to {:?}", rumor_key, member),
                 Err(e) => warn!(
@@ -330,7 +358,49 @@ impl PushWorker {
                 ),
             }
         }
-        self.server.rumor_heat.cool_rumors(&member.id, &rumors);
+        self.server.rumor_heat.cool_rumors(&member.id, &sent);
+    }
+
+    /// Send an anti-entropy digest to a given member, the same way `send_rumors` sends rumors.
+    pub fn send_digest(&self, member: Member, digest: Digest) {
+        let socket = match self.connect(&member) {
+            Some(socket) => socket,
+            None => return,
+        };
+        let rumor = RumorEnvelope {
+            type_: RumorType::Digest,
+            from_id: self.server.member_id().to_string(),
+            kind: RumorKind::Digest(digest),
+        };
+        trace_it!(GOSSIP: &self.server, TraceKind::SendRumor, &member.id, &rumor);
+        let payload = match rumor
+            .encode()
+            .and_then(|bytes| self.server.generate_wire(bytes))
+        {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Could not write our digest to bytes: {}", e);
+                return;
+            }
+        };
+        if !self
+            .server
+            .bandwidth
+            .acquire(RumorType::Digest, payload.len())
+        {
+            debug!(
+                "Deferred digest to {:?}; no gossip bandwidth left",
+                member.id
+            );
+            return;
+        }
+        match socket.send(&payload, 0) {
+            Ok(()) => debug!("Sent digest to {:?}", member),
+            Err(e) => warn!(
+                "Could not send digest to {:?} @ {}:{}; ZMQ said: {:?}",
+                member.id, member.address, member.gossip_port, e
+            ),
+        }
     }
 
     /// Given a rumorkey, creates a protobuf rumor for sharing.
@@ -344,13 +414,20 @@ impl PushWorker {
         if member.is_none() {
             return None;
         }
+        let health = self
+            .server
+            .member_list
+            .health_of_by_id(&rumor_key.key())
+            .unwrap();
+        let suspected_by = if health == Health::Suspect {
+            self.server.member_list.suspected_by(&rumor_key.key())
+        } else {
+            Vec::new()
+        };
         let payload = Membership {
             member: member.unwrap(),
-            health: self
-                .server
-                .member_list
-                .health_of_by_id(&rumor_key.key())
-                .unwrap(),
+            health: health,
+            suspected_by: suspected_by,
         };
         let rumor = RumorEnvelope {
             type_: RumorType::Member,
@@ -0,0 +1,57 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Periodically drop the service and election rumors of members that
+//! have been `Confirmed` or `Departed` for longer than the rumor TTL,
+//! so they don't live on forever in memory, in the rumor file, and in
+//! the census.
+
+use std::thread;
+use std::time::Duration;
+
+use server::timing::Timing;
+use server::Server;
+
+const LOOP_DELAY_MS: u64 = 60_000;
+
+pub struct RumorExpire {
+    pub server: Server,
+    pub timing: Timing,
+}
+
+impl RumorExpire {
+    pub fn new(server: Server, timing: Timing) -> RumorExpire {
+        RumorExpire {
+            server: server,
+            timing: timing,
+        }
+    }
+
+    pub fn run(&self) {
+        loop {
+            let reaped = self.server.reap_rumors(self.timing.rumor_ttl_duration());
+            if reaped > 0 {
+                debug!(
+                    "Dropped {} rumors of long-dead members ({} total)",
+                    reaped,
+                    self.server.rumors_reaped()
+                );
+                // Rewrite the rumor file right away, rather than waiting for the next persist.
+                self.server.persist_data();
+            }
+
+            thread::sleep(Duration::from_millis(LOOP_DELAY_MS));
+        }
+    }
+}
@@ -12,19 +12,36 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::cmp;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use time::{Duration as TimeDuration, SteadyTime};
 
 /// How long to wait for an Ack after we ping
 const PING_TIMING_DEFAULT_MS: i64 = 1000;
 /// How long to wait for an Ack after we PingReq - should be at least 2x the PING_TIMING_DEFAULT_MS
 const PINGREQ_TIMING_DEFAULT_MS: i64 = 2100;
-/// How many protocol periods before a suspect member is marked as confirmed.
+/// How many protocol periods before a suspect member is marked as confirmed, once enough other
+///   members have confirmed the suspicion.
 const SUSPICION_TIMEOUT_DEFAULT_PROTOCOL_PERIODS: i64 = 3;
+/// How many times longer than the above a suspect member has to refute a suspicion nobody else
+///   has confirmed.
+const SUSPICION_MAX_MULTIPLIER_DEFAULT: i64 = 6;
+/// How many other members suspecting a member bring its suspicion timeout down to the minimum.
+const SUSPICION_CONFIRMATIONS_DEFAULT: i64 = 3;
+/// The highest our local health score goes; probe timeouts are stretched by up to 1 + this.
+const LOCAL_HEALTH_MAX_SCORE: usize = 8;
 /// How long is the gossip period
 const GOSSIP_PERIOD_DEFAULT_MS: i64 = 1000;
 /// How long before we set a confirmed member to a departed member, removing them from quorums
 ///   just for your own sanity - this is 3 days.
 const DEPARTURE_TIMEOUT_DEFAULT_MS: i64 = 259200000;
+/// How long a member can be confirmed or departed before its service and election rumors are
+///   dropped - this is 7 days.
+const RUMOR_TTL_DEFAULT_MS: i64 = 604800000;
+/// How long between sending anti-entropy digests to a random member
+const ANTI_ENTROPY_PERIOD_DEFAULT_MS: i64 = 30000;
 
 /// The timing of the outbound threads.
 #[derive(Debug, Clone)]
@@ -33,7 +50,11 @@ pub struct Timing {
     pub pingreq_ms: i64,
     pub gossip_period_ms: i64,
     pub suspicion_timeout_protocol_periods: i64,
+    pub suspicion_max_multiplier: i64,
+    pub suspicion_confirmations: i64,
     pub departure_timeout_ms: i64,
+    pub rumor_ttl_ms: i64,
+    pub anti_entropy_period_ms: i64,
 }
 
 impl Default for Timing {
@@ -43,7 +64,11 @@ impl Default for Timing {
             pingreq_ms: PINGREQ_TIMING_DEFAULT_MS,
             gossip_period_ms: GOSSIP_PERIOD_DEFAULT_MS,
             suspicion_timeout_protocol_periods: SUSPICION_TIMEOUT_DEFAULT_PROTOCOL_PERIODS,
+            suspicion_max_multiplier: SUSPICION_MAX_MULTIPLIER_DEFAULT,
+            suspicion_confirmations: SUSPICION_CONFIRMATIONS_DEFAULT,
             departure_timeout_ms: DEPARTURE_TIMEOUT_DEFAULT_MS,
+            rumor_ttl_ms: RUMOR_TTL_DEFAULT_MS,
+            anti_entropy_period_ms: ANTI_ENTROPY_PERIOD_DEFAULT_MS,
         }
     }
 }
@@ -55,22 +80,46 @@ impl Timing {
         pingreq_ms: i64,
         gossip_period_ms: i64,
         suspicion_timeout_protocol_periods: i64,
+        suspicion_max_multiplier: i64,
+        suspicion_confirmations: i64,
         departure_timeout_ms: i64,
+        rumor_ttl_ms: i64,
+        anti_entropy_period_ms: i64,
     ) -> Timing {
         Timing {
             ping_ms: ping_ms,
             pingreq_ms: pingreq_ms,
             gossip_period_ms: gossip_period_ms,
             suspicion_timeout_protocol_periods: suspicion_timeout_protocol_periods,
+            suspicion_max_multiplier: suspicion_max_multiplier,
+            suspicion_confirmations: suspicion_confirmations,
             departure_timeout_ms: departure_timeout_ms,
+            rumor_ttl_ms: rumor_ttl_ms,
+            anti_entropy_period_ms: anti_entropy_period_ms,
         }
     }
 
+    /// The timing to probe with, given our local health score. The worse our own health, the
+    /// longer we wait for acks and between probes, so a member that is slow to process messages
+    /// doesn't mistake its own lag for other members failing.
+    pub fn with_local_health(&self, score: usize) -> Timing {
+        let multiplier = cmp::min(score, LOCAL_HEALTH_MAX_SCORE) as i64 + 1;
+        let mut timing = self.clone();
+        timing.ping_ms = self.ping_ms * multiplier;
+        timing.pingreq_ms = self.pingreq_ms * multiplier;
+        timing
+    }
+
     /// When should this gossip period expire
     pub fn gossip_timeout(&self) -> SteadyTime {
         SteadyTime::now() + TimeDuration::milliseconds(self.gossip_period_ms)
     }
 
+    /// When should we send our next anti-entropy digest
+    pub fn anti_entropy_timeout(&self) -> SteadyTime {
+        SteadyTime::now() + TimeDuration::milliseconds(self.anti_entropy_period_ms)
+    }
+
     /// How long is a protocol period, in millis.
     pub fn protocol_period_ms(&self) -> i64 {
         self.ping_ms + self.pingreq_ms
@@ -98,7 +147,122 @@ impl Timing {
         )
     }
 
+    /// How long before a suspect entry times out, given how many other members have confirmed
+    /// the suspicion and how many members there are. An unconfirmed suspicion lasts
+    /// `suspicion_max_multiplier` times the suspicion timeout, and shrinks logarithmically
+    /// towards the suspicion timeout as confirmations arrive.
+    pub fn dynamic_suspicion_timeout_duration(
+        &self,
+        confirmations: usize,
+        members: usize,
+    ) -> TimeDuration {
+        let min = self.suspicion_timeout_duration().num_milliseconds();
+        let max = min * cmp::max(self.suspicion_max_multiplier, 1);
+        // Besides the suspect and the member that suspected it first, there is nobody left to
+        // confirm a suspicion in a small ring.
+        let expected = cmp::min(
+            cmp::max(self.suspicion_confirmations, 0) as usize,
+            members.saturating_sub(2),
+        );
+        if expected == 0 || confirmations >= expected {
+            return TimeDuration::milliseconds(min);
+        }
+        let fraction = (confirmations as f64 + 1.0).ln() / (expected as f64 + 1.0).ln();
+        let timeout = max as f64 - (max - min) as f64 * fraction;
+        TimeDuration::milliseconds(cmp::max(min, timeout.floor() as i64))
+    }
+
     pub fn departure_timeout_duration(&self) -> TimeDuration {
         TimeDuration::milliseconds(self.departure_timeout_ms)
     }
+
+    /// How long a dead member's rumors are kept before they are dropped
+    pub fn rumor_ttl_duration(&self) -> TimeDuration {
+        TimeDuration::milliseconds(self.rumor_ttl_ms)
+    }
+}
+
+/// Our local health score, after Lifeguard's local health multiplier. It goes up when our probes
+/// fail or when we have to refute a suspicion about ourselves, both of which happen more often
+/// when we are the ones struggling, and down when our probes succeed.
+#[derive(Debug, Clone, Default)]
+pub struct LocalHealth(Arc<AtomicUsize>);
+
+impl LocalHealth {
+    pub fn score(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Something suggests we are not keeping up.
+    pub fn degrade(&self) {
+        let mut current = self.score();
+        while current < LOCAL_HEALTH_MAX_SCORE {
+            let previous = self
+                .0
+                .compare_and_swap(current, current + 1, Ordering::Relaxed);
+            if previous == current {
+                break;
+            }
+            current = previous;
+        }
+    }
+
+    /// Something suggests we are keeping up.
+    pub fn improve(&self) {
+        let mut current = self.score();
+        while current > 0 {
+            let previous = self
+                .0
+                .compare_and_swap(current, current - 1, Ordering::Relaxed);
+            if previous == current {
+                break;
+            }
+            current = previous;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalHealth, Timing, LOCAL_HEALTH_MAX_SCORE};
+
+    #[test]
+    fn suspicion_timeout_shrinks_with_confirmations() {
+        let timing = Timing::default();
+        let min = timing.suspicion_timeout_duration();
+        let unconfirmed = timing.dynamic_suspicion_timeout_duration(0, 10);
+        let confirmed_once = timing.dynamic_suspicion_timeout_duration(1, 10);
+        assert_eq!(unconfirmed, min * timing.suspicion_max_multiplier as i32);
+        assert!(confirmed_once < unconfirmed);
+        assert!(confirmed_once > min);
+        assert_eq!(timing.dynamic_suspicion_timeout_duration(3, 10), min);
+    }
+
+    #[test]
+    fn suspicion_timeout_is_minimal_when_nobody_can_confirm() {
+        let timing = Timing::default();
+        assert_eq!(
+            timing.dynamic_suspicion_timeout_duration(0, 2),
+            timing.suspicion_timeout_duration()
+        );
+    }
+
+    #[test]
+    fn local_health_stretches_probe_timing_within_bounds() {
+        let health = LocalHealth::default();
+        health.improve();
+        assert_eq!(health.score(), 0);
+        for _ in 0..(LOCAL_HEALTH_MAX_SCORE + 2) {
+            health.degrade();
+        }
+        assert_eq!(health.score(), LOCAL_HEALTH_MAX_SCORE);
+        health.improve();
+        assert_eq!(health.score(), LOCAL_HEALTH_MAX_SCORE - 1);
+
+        let timing = Timing::default();
+        let stretched = timing.with_local_health(1);
+        assert_eq!(stretched.ping_ms, timing.ping_ms * 2);
+        assert_eq!(stretched.pingreq_ms, timing.pingreq_ms * 2);
+        assert_eq!(stretched.gossip_period_ms, timing.gossip_period_ms);
+    }
 }
@@ -23,6 +23,11 @@ use member::{Health, Member, Membership};
 pub use protocol::swim::{SwimPayload, SwimType};
 use protocol::{self, swim as proto, FromProto};
 
+/// The largest SWIM message, once wrapped for the wire, that members send or receive.
+pub const SWIM_MAX_BYTES: usize = 1024;
+/// Room left around an encoded SWIM message for its wire framing and encryption.
+const WIRE_OVERHEAD_BYTES: usize = 64;
+
 #[derive(Debug, Clone, Serialize)]
 pub struct Ack {
     pub membership: Vec<Membership>,
@@ -304,6 +309,24 @@ impl Swim {
         proto.encode(&mut buf)?;
         Ok(buf.to_vec())
     }
+
+    /// Returns `true` if the message will still fit in `SWIM_MAX_BYTES` once wrapped for the
+    /// wire.
+    pub fn fits_on_wire(&self) -> bool {
+        let proto: proto::Swim = self.clone().into();
+        proto.encoded_len() + WIRE_OVERHEAD_BYTES <= SWIM_MAX_BYTES
+    }
+
+    /// Add a membership rumor to the message if it fits, returning `false` if it does not.
+    pub fn push_membership(&mut self, membership: Membership) -> bool {
+        self.membership.push(membership);
+        if self.fits_on_wire() {
+            true
+        } else {
+            self.membership.pop();
+            false
+        }
+    }
 }
 
 impl From<Swim> for proto::Swim {
@@ -316,3 +339,45 @@ impl From<Swim> for proto::Swim {
     }
 }
 //
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use member::SUSPECTED_BY_MAX;
+
+    // A member carrying as many labels, of the longest length, as a Supervisor may gossip.
+    fn labelled_member() -> Member {
+        let mut member = Member::default();
+        member.address = String::from("fe80:0000:0000:0000:0202:b3ff:fe1e:8329");
+        for i in 0..4 {
+            member
+                .labels
+                .insert(format!("label{}", i), "v".repeat(32 - 7));
+        }
+        member
+    }
+
+    fn suspected_membership() -> Membership {
+        Membership {
+            member: labelled_member(),
+            health: Health::Suspect,
+            suspected_by: (0..SUSPECTED_BY_MAX)
+                .map(|_| Member::default().id)
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn memberships_are_only_added_while_they_fit_on_the_wire() {
+        let ping = Ping {
+            membership: vec![],
+            from: labelled_member(),
+            forward_to: Some(labelled_member()),
+        };
+        let mut swim: Swim = ping.into();
+        assert!(swim.push_membership(suspected_membership()));
+        while swim.push_membership(suspected_membership()) {}
+        assert!(swim.fits_on_wire());
+        assert!(swim.clone().encode().unwrap().len() <= SWIM_MAX_BYTES);
+    }
+}
@@ -388,6 +388,9 @@ macro_rules! trace_it {
                     election.votes
                 ),
                 rumor::RumorKind::Departure(ref departure) => format!("{}", departure.member_id),
+                rumor::RumorKind::Digest(ref digest) => {
+                    format!("{}-{}", digest.entries.len(), digest.reply)
+                }
             };
 
             let mut tw = TraceWrite::new($msg_type, module_path!(), line!(), thread_name);
@@ -18,7 +18,7 @@ use std::result;
 use std::str::FromStr;
 
 use clap::{App, AppSettings, Arg};
-use hcore::{crypto::keys::PairType, service::ServiceGroup};
+use hcore::{crypto::keys::PairType, package::PackageIdent, service::ServiceGroup};
 use protocol;
 use url::Url;
 
@@ -410,6 +410,7 @@ pub fn get() -> App<'static, 'static> {
                     (ex: core/busybox-static/1.24.2/20160708162350)")
                 (@arg FULL_PATHS: -p "Show full path to file")
             )
+            (subcommand: sub_pkg_render())
             (@subcommand search =>
                 (about: "Search for a package in Builder")
                 (@arg SEARCH_TERM: +required +takes_value "Search term")
@@ -547,6 +548,31 @@ pub fn get() -> App<'static, 'static> {
                     (aliases: &["g", "ge", "gen", "gene", "gener", "genera", "generat"])
                     (@arg RING: +required +takes_value "Ring key name")
                 )
+                (@subcommand add =>
+                    (about: "Makes a running Supervisor accept gossip encrypted with a ring key \
+                    from its own key cache, while it keeps encrypting with its current key")
+                    (@arg KEY: +required +takes_value
+                        "Ring key name, with an optional revision; the latest revision is used \
+                        if none is given (ex: beyonce or beyonce-20160504220722)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+                (@subcommand promote =>
+                    (about: "Makes a running Supervisor encrypt gossip with an accepted ring key. \
+                    Its previous ring key is still accepted")
+                    (@arg KEY: +required +takes_value
+                        "Ring key name with revision (ex: beyonce-20160504220722)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+                (@subcommand retire =>
+                    (about: "Makes a running Supervisor stop accepting gossip encrypted with a \
+                    ring key")
+                    (@arg KEY: +required +takes_value
+                        "Ring key name with revision (ex: beyonce-20160504220722)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
             )
         )
         (@subcommand sup =>
@@ -575,10 +601,62 @@ pub fn get() -> App<'static, 'static> {
                     (@arg ORG: "The service organization")
                 )
             )
+            (@subcommand config =>
+                (about: "Commands relating to a loaded service's configuration")
+                (aliases: &["co", "con", "conf", "confi"])
+                (@setting ArgRequiredElseHelp)
+                (@subcommand diff =>
+                    (about: "Displays the effective configuration of a loaded service, with the \
+                        layer each value came from. Given a pending configuration, also displays \
+                        how it would change the service's rendered configuration files.")
+                    (aliases: &["d", "di", "dif"])
+                    (@arg PKG_IDENT: +required +takes_value
+                        "A Habitat package identifier (ex: core/redis)")
+                    (@arg FILE: {file_exists_or_stdin}
+                        "Path to a pending configuration on disk, or - for <stdin> \
+                        (ex: /tmp/config.toml)")
+                    (@arg USER_TOML: --("user-toml")
+                        "Treat the pending configuration as a new user.toml instead of one \
+                        applied with 'hab config apply'")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+            )
             (subcommand: sub_svc_load().aliases(&["l", "lo", "loa"]))
             (subcommand: sub_svc_start().aliases(&["star"]))
             (subcommand: sub_svc_status().aliases(&["stat", "statu"]))
             (subcommand: sub_svc_stop().aliases(&["sto"]))
+            (@subcommand update =>
+                (about: "Commands relating to a loaded service's updates")
+                (@setting ArgRequiredElseHelp)
+                (@subcommand now =>
+                    (about: "Check for an update to a loaded service now, or install a given \
+                        release now, following the service's update strategy")
+                    (@arg PKG_IDENT: +required +takes_value
+                        "A Habitat package identifier (ex: core/redis)")
+                    (@arg TO: --to +takes_value {valid_fully_qualified_ident}
+                        "A fully qualified release to install instead of the latest one \
+                        (ex: core/redis/3.2.4/20180514213321)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+                (@subcommand hold =>
+                    (about: "Hold a loaded service at its current release by pausing its \
+                        updates. The service keeps running and stays held across Supervisor \
+                        restarts.")
+                    (@arg PKG_IDENT: +required +takes_value
+                        "A Habitat package identifier (ex: core/redis)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+                (@subcommand release =>
+                    (about: "Release a held service, resuming its updates")
+                    (@arg PKG_IDENT: +required +takes_value
+                        "A Habitat package identifier (ex: core/redis)")
+                    (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
+                        "Address to a remote Supervisor's Control Gateway [default: 127.0.0.1:9632]")
+                )
+            )
             (@subcommand unload =>
                 (about: "Unload a service loaded by the Habitat Supervisor. If the service is \
                     running it will additionally be stopped.")
@@ -718,6 +796,25 @@ fn sub_pkg_build() -> App<'static, 'static> {
     }
 }
 
+// public, as `hab pkg render` is delegated to `hab-sup`, which does the rendering
+pub fn sub_pkg_render() -> App<'static, 'static> {
+    clap_app!(@subcommand render =>
+        (about: "Renders the configuration and hook templates of a package without a running \
+            Supervisor")
+        (usage: "hab pkg render [OPTIONS] <PKG_IDENT_OR_PATH>")
+        (@arg PKG_IDENT_OR_PATH: +required +takes_value
+            "An installed package identifier, or the path to an installed package or a plan \
+            directory (ex: core/redis, /hab/pkgs/core/redis/3.2.4/20170514150022, ./habitat)")
+        (@arg CENSUS: -c --census +takes_value {file_exists}
+            "Path to a TOML or JSON file describing the members, leader and binds of the \
+            service group [default: a group of just this member]")
+        (@arg USER_TOML: --("user-toml") +takes_value {file_exists}
+            "Path to a user.toml to render with, in place of the service's own")
+        (@arg OUTPUT: -o --output +takes_value
+            "Directory to write the rendered files to [default: stdout]")
+    )
+}
+
 fn sub_pkg_install() -> App<'static, 'static> {
     let mut sub = clap_app!(@subcommand install =>
         (about: "Installs a Habitat package from Builder or locally from a Habitat Artifact")
@@ -823,18 +920,44 @@ pub fn sub_sup_run() -> App<'static, 'static> {
         (@arg PEER_WATCH_FILE: --("peer-watch-file") +takes_value conflicts_with[peer]
             "Watch this file for connecting to the ring"
         )
+        (@arg PEER_DNS: --("peer-dns") +takes_value +multiple
+            "Discover peers from the A records of this DNS name; they are expected to listen on \
+             the default gossip port")
+        (@arg PEER_SRV: --("peer-srv") +takes_value +multiple
+            "Discover peers, and their gossip ports, from the SRV records of this DNS name \
+             (ex: _hab-gossip._udp.example.com)")
+        (@arg PEER_DIR: --("peer-dir") +takes_value {dir_exists}
+            "Discover peers from every file in this directory, one IP[:PORT] per line")
+        (@arg PEER_EXEC: --("peer-exec") +takes_value {file_exists}
+            "Discover peers by running this executable, which prints one IP[:PORT] per line")
+        (@arg PEER_DISCOVERY_INTERVAL: --("peer-discovery-interval") +takes_value {valid_peer_discovery_interval}
+            "How many seconds pass between rounds of peer discovery, at least 1 [default: 30]")
         (@arg RING: --ring -r +takes_value "Ring key name")
+        (@arg LABEL: --label +takes_value +multiple {valid_label}
+            "A label to gossip along with this Supervisor's membership (KEY=VALUE); at most 4 \
+             labels of up to 32 bytes each")
+        (@arg GOSSIP_BANDWIDTH_LIMIT: --("gossip-bandwidth-limit") +takes_value {valid_gossip_bandwidth_limit}
+            "Cap the gossip traffic this Supervisor sends at this many kilobytes per second, at \
+             least 1; membership is sent first, and service configs and files last \
+             [default: no limit]")
+        (@arg RUMOR_TTL: --("rumor-ttl") +takes_value {valid_rumor_ttl}
+            "Drop the service and election rumors of members that have been confirmed dead or \
+             departed for this many seconds, at most 315360000 (ten years) [default: 604800]")
         (@arg CHANNEL: --channel +takes_value
             "Receive Supervisor updates from the specified release channel [default: stable]")
         (@arg BLDR_URL: -u --url +takes_value {valid_url}
             "Specify an alternate Builder endpoint. If not specified, the value will \
              be taken from the HAB_BLDR_URL environment variable if defined. (default: \
-             https://bldr.habitat.sh)")
+             https://bldr.habitat.sh). A file:// URL names a local directory of .hart \
+             files and origin public keys to install and update from.")
 
         (@arg CONFIG_DIR: --("config-from") +takes_value {dir_exists}
             "Use package config from this path, rather than the package itself")
         (@arg AUTO_UPDATE: --("auto-update") -A "Enable automatic updates for the Supervisor \
             itself")
+        (@arg AUTO_UPDATE_WINDOW: --("auto-update-window") +takes_value requires[AUTO_UPDATE]
+            "Only apply automatic updates to the Supervisor during the given windows, in the same \
+             format as --update-window [default: not set]")
         (@arg EVENTS: --events -n +takes_value {valid_service_group} "Name of the service \
             group running a Habitat EventSrv to forward Supervisor and service event data to")
         // === Optional arguments to additionally load an initial service for the Supervisor
@@ -851,12 +974,59 @@ pub fn sub_sup_run() -> App<'static, 'static> {
         (@arg TOPOLOGY: --topology -t +takes_value {valid_topology}
             "Service topology; [default: none]")
         (@arg STRATEGY: --strategy -s +takes_value {valid_update_strategy}
-            "The update strategy; [default: none] [values: none, at-once, rolling]")
+            "The update strategy; [default: none] [values: none, at-once, rolling, canary]")
         (@arg BIND: --bind +takes_value +multiple
-            "One or more service groups to bind to a configuration")
+            "One or more service groups to bind to a configuration, optionally restricted to \
+             members with the given labels (ex: 'db:postgres.default/zone=us-east-1a')")
         (@arg BINDING_MODE: --("binding-mode") +takes_value {valid_binding_mode}
             "Governs how the presence or absence of binds affects service startup. `strict` blocks \
              startup until all binds are present. [default: strict] [values: relaxed, strict]")
+        (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") +takes_value {valid_numeric::<u32>}
+            "The interval, in seconds, between health checks [default: 30]")
+        (@arg HEALTH_CHECK_TIMEOUT: --("health-check-timeout") +takes_value {valid_numeric::<u32>}
+            "The number of seconds a health check hook may run before it is killed; \
+             0 disables the timeout [default: 0]")
+        (@arg HEALTH_CHECK_THRESHOLD: --("health-check-threshold") +takes_value {valid_numeric::<u32>}
+            "The number of consecutive critical health checks required before a service \
+             is reported as critical [default: 1]")
+        (@arg RESTART_ON_CRITICAL: --("restart-on-critical") +takes_value {valid_numeric::<u32>}
+            "Restart the service after this many consecutive critical health checks; \
+             0 disables restarts [default: 0]")
+        (@arg RESTART_BACKOFF: --("restart-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting an unhealthy service, doubled \
+             after each consecutive restart [default: 10]")
+        (@arg RESTART_LIMIT: --("restart-limit") +takes_value {valid_numeric::<u32>}
+            "The maximum number of consecutive restarts of an unhealthy service; \
+             0 allows unlimited restarts [default: 0]")
+        (@arg CRASH_WINDOW: --("crash-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds within which unexpected process exits are counted towards \
+             the crash limit [default: 300]")
+        (@arg CRASH_LIMIT: --("crash-limit") +takes_value {valid_numeric::<u32>}
+            "The number of unexpected process exits within the crash window after which the \
+             service is marked as failed; 0 restarts it indefinitely [default: 5]")
+        (@arg CRASH_BACKOFF: --("crash-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting a process which exited \
+             unexpectedly, doubled for each further exit in the crash window [default: 2]")
+        (@arg UPDATE_CANARY: --("update-canary") +takes_value {valid_update_canary}
+            "The number of members, or percentage of the service group, which update first \
+             when using the canary update strategy [default: 1]")
+        (@arg UPDATE_BATCH_SIZE: --("update-batch-size") +takes_value {valid_numeric::<u32>}
+            "The number of members updated together in each batch once the canaries are \
+             healthy [default: 1]")
+        (@arg UPDATE_SOAK: --("update-soak") +takes_value {valid_numeric::<u32>}
+            "The number of seconds the members ahead of a batch must report a healthy status \
+             on the new release before the batch updates [default: 60]")
+        (@arg UPDATE_SPREAD_BY: --("update-spread-by") +takes_value
+            "Spread rolling and canary updates across the values of this member label, so that \
+             consecutive members updating run in different zones (ex: 'zone')")
+        (@arg UPDATE_ROLLBACK_WINDOW: --("update-rollback-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds an updated service has to report a healthy status before it \
+             is rolled back to its previous release; 0 disables rollbacks [default: 0]")
+        (@arg UPDATE_WINDOW: --("update-window") +takes_value
+            "Only apply updates during the given windows, as day and time ranges \
+             (ex: 'Sat-Sun 02:00-04:00 Europe/Berlin') or cron-like expressions \
+             (ex: '* 1-3 * * Mon-Fri'), separated by ';'. Updates found outside of a window are \
+             installed and held until it opens [default: not set]")
         (@arg VERBOSE: -v "Verbose output; shows file and line/column numbers")
         (@arg NO_COLOR: --("no-color") "Turn ANSI color off")
         (@arg JSON: --("json-logging") "Use structured JSON logging for the Supervisor. \
@@ -886,6 +1056,46 @@ pub fn sub_sup_term() -> App<'static, 'static> {
     )
 }
 
+pub fn sub_sup_dat() -> App<'static, 'static> {
+    clap_app!(@subcommand dat =>
+        (about: "Inspect, compare and repair the gossip data file of a Habitat Supervisor")
+        (usage: "hab sup dat <SUBCOMMAND>")
+        (@setting ArgRequiredElseHelp)
+        (@subcommand dump =>
+            (about: "Print the contents of a gossip data file as JSON, grouped by store")
+            (@arg FILE: +required {file_exists}
+                "Path to the data file (ex: /hab/sup/default/data/<member-id>.rst)")
+        )
+        (@subcommand diff =>
+            (about: "List the members and rumors which differ between two gossip data files")
+            (@arg FILE_A: +required {file_exists} "Path to the first data file")
+            (@arg FILE_B: +required {file_exists} "Path to the second data file")
+        )
+        (@subcommand validate =>
+            (about: "Check the section lengths of a gossip data file and that every entry decodes")
+            (@arg FILE: +required {file_exists} "Path to the data file")
+        )
+        (@subcommand drop =>
+            (about: "Write a copy of a gossip data file without the members and rumors matching \
+                every given filter")
+            (@setting ArgRequiredElseHelp)
+            (@arg FILE: +required {file_exists} "Path to the data file")
+            (@arg OUTPUT: -o --output +required +takes_value
+                "Path to write the repaired data file to")
+            (@group filter =>
+                (@attributes +required +multiple)
+                (@arg STORE: --store +takes_value
+                    possible_value[member service service_config service_file election election_update departure]
+                    "Only drop entries from this store")
+                (@arg KEY: --key +takes_value
+                    "Only drop rumors with this key, usually a service group (ex: redis.default)")
+                (@arg MEMBER_ID: --("member-id") +takes_value
+                    "Only drop the member, or rumors, with this id")
+            )
+        )
+    )
+}
+
 fn sub_svc_start() -> App<'static, 'static> {
     clap_app!(@subcommand start =>
         (about: "Start a loaded, but stopped, Habitat service.")
@@ -936,16 +1146,64 @@ fn sub_svc_load() -> App<'static, 'static> {
         (@arg BLDR_URL: -u --url +takes_value {valid_url}
             "Specify an alternate Builder endpoint. If not specified, the value will \
              be taken from the HAB_BLDR_URL environment variable if defined. (default: \
-             https://bldr.habitat.sh)")
+             https://bldr.habitat.sh). A file:// URL names a local directory of .hart \
+             files and origin public keys to install and update from.")
         (@arg TOPOLOGY: --topology -t +takes_value {valid_topology}
             "Service topology; [default: none]")
         (@arg STRATEGY: --strategy -s +takes_value {valid_update_strategy}
-            "The update strategy; [default: none] [values: none, at-once, rolling]")
+            "The update strategy; [default: none] [values: none, at-once, rolling, canary]")
         (@arg BIND: --bind +takes_value +multiple
-            "One or more service groups to bind to a configuration")
+            "One or more service groups to bind to a configuration, optionally restricted to \
+             members with the given labels (ex: 'db:postgres.default/zone=us-east-1a')")
         (@arg BINDING_MODE: --("binding-mode") +takes_value {valid_binding_mode}
              "Governs how the presence or absence of binds affects service startup. `strict` blocks \
               startup until all binds are present. [default: strict] [values: relaxed, strict]")
+        (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") +takes_value {valid_numeric::<u32>}
+            "The interval, in seconds, between health checks [default: 30]")
+        (@arg HEALTH_CHECK_TIMEOUT: --("health-check-timeout") +takes_value {valid_numeric::<u32>}
+            "The number of seconds a health check hook may run before it is killed; \
+             0 disables the timeout [default: 0]")
+        (@arg HEALTH_CHECK_THRESHOLD: --("health-check-threshold") +takes_value {valid_numeric::<u32>}
+            "The number of consecutive critical health checks required before a service \
+             is reported as critical [default: 1]")
+        (@arg RESTART_ON_CRITICAL: --("restart-on-critical") +takes_value {valid_numeric::<u32>}
+            "Restart the service after this many consecutive critical health checks; \
+             0 disables restarts [default: 0]")
+        (@arg RESTART_BACKOFF: --("restart-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting an unhealthy service, doubled \
+             after each consecutive restart [default: 10]")
+        (@arg RESTART_LIMIT: --("restart-limit") +takes_value {valid_numeric::<u32>}
+            "The maximum number of consecutive restarts of an unhealthy service; \
+             0 allows unlimited restarts [default: 0]")
+        (@arg CRASH_WINDOW: --("crash-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds within which unexpected process exits are counted towards \
+             the crash limit [default: 300]")
+        (@arg CRASH_LIMIT: --("crash-limit") +takes_value {valid_numeric::<u32>}
+            "The number of unexpected process exits within the crash window after which the \
+             service is marked as failed; 0 restarts it indefinitely [default: 5]")
+        (@arg CRASH_BACKOFF: --("crash-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting a process which exited \
+             unexpectedly, doubled for each further exit in the crash window [default: 2]")
+        (@arg UPDATE_CANARY: --("update-canary") +takes_value {valid_update_canary}
+            "The number of members, or percentage of the service group, which update first \
+             when using the canary update strategy [default: 1]")
+        (@arg UPDATE_BATCH_SIZE: --("update-batch-size") +takes_value {valid_numeric::<u32>}
+            "The number of members updated together in each batch once the canaries are \
+             healthy [default: 1]")
+        (@arg UPDATE_SOAK: --("update-soak") +takes_value {valid_numeric::<u32>}
+            "The number of seconds the members ahead of a batch must report a healthy status \
+             on the new release before the batch updates [default: 60]")
+        (@arg UPDATE_SPREAD_BY: --("update-spread-by") +takes_value
+            "Spread rolling and canary updates across the values of this member label, so that \
+             consecutive members updating run in different zones (ex: 'zone')")
+        (@arg UPDATE_ROLLBACK_WINDOW: --("update-rollback-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds an updated service has to report a healthy status before it \
+             is rolled back to its previous release; 0 disables rollbacks [default: 0]")
+        (@arg UPDATE_WINDOW: --("update-window") +takes_value
+            "Only apply updates during the given windows, as day and time ranges \
+             (ex: 'Sat-Sun 02:00-04:00 Europe/Berlin') or cron-like expressions \
+             (ex: '* 1-3 * * Mon-Fri'), separated by ';'. Updates found outside of a window are \
+             installed and held until it opens [default: not set]")
         (@arg FORCE: --force -f "Load or reload an already loaded service. If the service \
             was previously loaded and running this operation will also restart the service")
         (@arg REMOTE_SUP: --("remote-sup") -r +takes_value
@@ -972,16 +1230,64 @@ fn sub_svc_load() -> App<'static, 'static> {
         (@arg BLDR_URL: -u --url +takes_value {valid_url}
             "Specify an alternate Builder endpoint. If not specified, the value will \
              be taken from the HAB_BLDR_URL environment variable if defined. (default: \
-             https://bldr.habitat.sh)")
+             https://bldr.habitat.sh). A file:// URL names a local directory of .hart \
+             files and origin public keys to install and update from.")
         (@arg TOPOLOGY: --topology -t +takes_value {valid_topology}
             "Service topology; [default: none]")
         (@arg STRATEGY: --strategy -s +takes_value {valid_update_strategy}
-            "The update strategy; [default: none] [values: none, at-once, rolling]")
+            "The update strategy; [default: none] [values: none, at-once, rolling, canary]")
         (@arg BIND: --bind +takes_value +multiple
-            "One or more service groups to bind to a configuration")
+            "One or more service groups to bind to a configuration, optionally restricted to \
+             members with the given labels (ex: 'db:postgres.default/zone=us-east-1a')")
         (@arg BINDING_MODE: --("binding-mode") +takes_value {valid_binding_mode}
              "Governs how the presence or absence of binds affects service startup. `strict` blocks \
               startup until all binds are present. [default: strict] [values: relaxed, strict]")
+        (@arg HEALTH_CHECK_INTERVAL: --("health-check-interval") +takes_value {valid_numeric::<u32>}
+            "The interval, in seconds, between health checks [default: 30]")
+        (@arg HEALTH_CHECK_TIMEOUT: --("health-check-timeout") +takes_value {valid_numeric::<u32>}
+            "The number of seconds a health check hook may run before it is killed; \
+             0 disables the timeout [default: 0]")
+        (@arg HEALTH_CHECK_THRESHOLD: --("health-check-threshold") +takes_value {valid_numeric::<u32>}
+            "The number of consecutive critical health checks required before a service \
+             is reported as critical [default: 1]")
+        (@arg RESTART_ON_CRITICAL: --("restart-on-critical") +takes_value {valid_numeric::<u32>}
+            "Restart the service after this many consecutive critical health checks; \
+             0 disables restarts [default: 0]")
+        (@arg RESTART_BACKOFF: --("restart-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting an unhealthy service, doubled \
+             after each consecutive restart [default: 10]")
+        (@arg RESTART_LIMIT: --("restart-limit") +takes_value {valid_numeric::<u32>}
+            "The maximum number of consecutive restarts of an unhealthy service; \
+             0 allows unlimited restarts [default: 0]")
+        (@arg CRASH_WINDOW: --("crash-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds within which unexpected process exits are counted towards \
+             the crash limit [default: 300]")
+        (@arg CRASH_LIMIT: --("crash-limit") +takes_value {valid_numeric::<u32>}
+            "The number of unexpected process exits within the crash window after which the \
+             service is marked as failed; 0 restarts it indefinitely [default: 5]")
+        (@arg CRASH_BACKOFF: --("crash-backoff") +takes_value {valid_numeric::<u32>}
+            "The number of seconds to wait before restarting a process which exited \
+             unexpectedly, doubled for each further exit in the crash window [default: 2]")
+        (@arg UPDATE_CANARY: --("update-canary") +takes_value {valid_update_canary}
+            "The number of members, or percentage of the service group, which update first \
+             when using the canary update strategy [default: 1]")
+        (@arg UPDATE_BATCH_SIZE: --("update-batch-size") +takes_value {valid_numeric::<u32>}
+            "The number of members updated together in each batch once the canaries are \
+             healthy [default: 1]")
+        (@arg UPDATE_SOAK: --("update-soak") +takes_value {valid_numeric::<u32>}
+            "The number of seconds the members ahead of a batch must report a healthy status \
+             on the new release before the batch updates [default: 60]")
+        (@arg UPDATE_SPREAD_BY: --("update-spread-by") +takes_value
+            "Spread rolling and canary updates across the values of this member label, so that \
+             consecutive members updating run in different zones (ex: 'zone')")
+        (@arg UPDATE_ROLLBACK_WINDOW: --("update-rollback-window") +takes_value {valid_numeric::<u32>}
+            "The number of seconds an updated service has to report a healthy status before it \
+             is rolled back to its previous release; 0 disables rollbacks [default: 0]")
+        (@arg UPDATE_WINDOW: --("update-window") +takes_value
+            "Only apply updates during the given windows, as day and time ranges \
+             (ex: 'Sat-Sun 02:00-04:00 Europe/Berlin') or cron-like expressions \
+             (ex: '* 1-3 * * Mon-Fri'), separated by ';'. Updates found outside of a window are \
+             installed and held until it opens [default: not set]")
         (@arg FORCE: --force -f "Load or reload an already loaded service. If the service \
             was previously loaded and running this operation will also restart the service")
         (@arg PASSWORD: --password +takes_value "Password of the service user")
@@ -1037,6 +1343,16 @@ fn dir_exists(val: String) -> result::Result<(), String> {
     }
 }
 
+fn valid_fully_qualified_ident(val: String) -> result::Result<(), String> {
+    match PackageIdent::from_str(&val) {
+        Ok(ref ident) if ident.fully_qualified() => Ok(()),
+        _ => Err(format!(
+            "'{}' is not a fully qualified package identifier (ex: core/redis/3.2.4/20180514213321)",
+            &val
+        )),
+    }
+}
+
 fn valid_socket_addr(val: String) -> result::Result<(), String> {
     match SocketAddr::from_str(&val) {
         Ok(_) => Ok(()),
@@ -1060,6 +1376,55 @@ fn valid_numeric<T: FromStr>(val: String) -> result::Result<(), String> {
     }
 }
 
+fn valid_peer_discovery_interval(val: String) -> result::Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(()),
+        _ => Err(format!(
+            "Peer discovery interval: '{}' must be at least 1 second",
+            &val
+        )),
+    }
+}
+
+fn valid_gossip_bandwidth_limit(val: String) -> result::Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(kb) if kb > 0 && kb.checked_mul(1024).is_some() => Ok(()),
+        _ => Err(format!(
+            "Gossip bandwidth limit: '{}' must be between 1 and {} kilobytes per second",
+            &val,
+            u64::max_value() / 1024
+        )),
+    }
+}
+
+fn valid_rumor_ttl(val: String) -> result::Result<(), String> {
+    match val.parse::<u64>() {
+        Ok(ttl) if ttl <= protocol::types::RUMOR_TTL_MAX_SECS => Ok(()),
+        _ => Err(format!(
+            "Rumor TTL: '{}' must be at most {} seconds",
+            &val,
+            protocol::types::RUMOR_TTL_MAX_SECS
+        )),
+    }
+}
+
+fn valid_label(val: String) -> result::Result<(), String> {
+    match val.find('=') {
+        Some(idx) if idx > 0 => {
+            if val.len() > protocol::types::MEMBER_LABEL_MAX_BYTES {
+                Err(format!(
+                    "Label: '{}' is longer than {} bytes",
+                    &val,
+                    protocol::types::MEMBER_LABEL_MAX_BYTES
+                ))
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err(format!("Label: '{}' is not of the form KEY=VALUE", &val)),
+    }
+}
+
 fn valid_topology(val: String) -> result::Result<(), String> {
     match protocol::types::Topology::from_str(&val) {
         Ok(_) => Ok(()),
@@ -1073,4 +1438,23 @@ fn valid_update_strategy(val: String) -> result::Result<(), String> {
         Err(_) => Err(format!("Update strategy: '{}' is not valid", &val)),
     }
 }
+
+fn valid_update_canary(val: String) -> result::Result<(), String> {
+    let valid = if val.ends_with('%') {
+        val[..val.len() - 1]
+            .parse::<u32>()
+            .map(|p| p <= 100)
+            .unwrap_or(false)
+    } else {
+        val.parse::<u32>().is_ok()
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Update canary: '{}' is not a member count or percentage",
+            &val
+        ))
+    }
+}
 ////////////////////////////////////////////////////////////////////////
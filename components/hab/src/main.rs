@@ -94,6 +94,8 @@ lazy_static! {
             "elapsed (s)",
             "pid",
             "group",
+            "held update",
+            "update hold",
         ]
     };
 
@@ -240,6 +242,9 @@ fn start(ui: &mut UI) -> Result<()> {
                 ("export", Some(sc)) => sub_ring_key_export(sc)?,
                 ("import", Some(_)) => sub_ring_key_import(ui)?,
                 ("generate", Some(sc)) => sub_ring_key_generate(ui, sc)?,
+                ("add", Some(sc)) => sub_ring_key_add(sc)?,
+                ("promote", Some(sc)) => sub_ring_key_promote(sc)?,
+                ("retire", Some(sc)) => sub_ring_key_retire(sc)?,
                 _ => unreachable!(),
             },
             _ => unreachable!(),
@@ -249,11 +254,21 @@ fn start(ui: &mut UI) -> Result<()> {
                 ("generate", Some(sc)) => sub_service_key_generate(ui, sc)?,
                 _ => unreachable!(),
             },
+            ("config", Some(m)) => match m.subcommand() {
+                ("diff", Some(sc)) => sub_svc_config_diff(sc)?,
+                _ => unreachable!(),
+            },
             ("load", Some(m)) => sub_svc_load(m)?,
             ("unload", Some(m)) => sub_svc_unload(m)?,
             ("start", Some(m)) => sub_svc_start(m)?,
             ("stop", Some(m)) => sub_svc_stop(m)?,
             ("status", Some(m)) => sub_svc_status(m)?,
+            ("update", Some(m)) => match m.subcommand() {
+                ("now", Some(sc)) => sub_svc_update_now(sc)?,
+                ("hold", Some(sc)) => sub_svc_update_hold(sc)?,
+                ("release", Some(sc)) => sub_svc_update_release(sc)?,
+                _ => unreachable!(),
+            },
             _ => unreachable!(),
         },
         ("sup", Some(m)) => match m.subcommand() {
@@ -810,7 +825,7 @@ fn sub_svc_set(m: &ArgMatches) -> Result<()> {
         }
         _ => set.cfg = Some(buf.to_vec()),
     }
-    set.service_group = Some(service_group.into());
+    set.service_group = Some(service_group.clone().into());
     set.version = Some(value_t!(m, "VERSION_NUMBER", u64).unwrap());
     ui.begin(format!(
         "Setting new configuration version {} for {}",
@@ -824,6 +839,7 @@ fn sub_svc_set(m: &ArgMatches) -> Result<()> {
             .unwrap_or("UNKNOWN".to_string()),
     ))?;
     ui.status(Status::Creating, format!("service configuration"))?;
+    let mut invalid = false;
     SrvClient::connect(&sup_addr, &secret_key)
         .and_then(|conn| {
             conn.call(validate)
@@ -834,6 +850,16 @@ fn sub_svc_set(m: &ArgMatches) -> Result<()> {
                         match ErrCode::from_i32(m.code) {
                             Some(ErrCode::InvalidPayload) => {
                                 ui.warn(m)?;
+                                invalid = true;
+                                Ok(())
+                            }
+                            // The configuration is still gossiped to the Supervisors running
+                            // the service, which ignore it if it does not match their schema.
+                            Some(ErrCode::NotFound) => {
+                                ui.warn(format!(
+                                    "Configuration not validated; {} is not loaded by {}",
+                                    service_group, sup_addr
+                                ))?;
                                 Ok(())
                             }
                             _ => Err(SrvClientError::from(m)),
@@ -845,6 +871,10 @@ fn sub_svc_set(m: &ArgMatches) -> Result<()> {
                 })
         })
         .wait()?;
+    if invalid {
+        ui.fatal("Configuration not applied; it does not match the service's config schema.")?;
+        process::exit(1);
+    }
     ui.status(Status::Applying, format!("via peer {}", sup_addr))?;
     // JW: We should not need to make two connections here. I need a way to return the
     // SrvClient from a for_each iterator so we can chain upon a successful stream but I don't
@@ -895,6 +925,78 @@ fn sub_svc_config(m: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn sub_svc_config_diff(m: &ArgMatches) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut ui = ui();
+    let mut msg = protocol::ctl::SvcCfgDiff::default();
+    msg.ident = Some(ident.into());
+    if let Some(f) = m.value_of("FILE") {
+        let mut buf = Vec::with_capacity(protocol::butterfly::MAX_SVC_CFG_SIZE);
+        let cfg_len = match f {
+            "-" => io::stdin().read_to_end(&mut buf)?,
+            f => File::open(f)?.read_to_end(&mut buf)?,
+        };
+        if cfg_len > protocol::butterfly::MAX_SVC_CFG_SIZE {
+            ui.fatal(format!(
+                "Configuration too large. Maximum size allowed is {} bytes.",
+                protocol::butterfly::MAX_SVC_CFG_SIZE
+            ))?;
+            process::exit(1);
+        }
+        msg.cfg = Some(buf);
+        msg.user = Some(m.is_present("USER_TOML"));
+    }
+    let pending = msg.cfg.is_some();
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| {
+            conn.call(msg).for_each(|reply| match reply.message_id() {
+                "ServiceCfgDiff" => {
+                    let m = reply.parse::<protocol::types::ServiceCfgDiff>().unwrap();
+                    for key in m.keys {
+                        println!(
+                            "{} = {}  # {}",
+                            key.key.unwrap_or_default(),
+                            key.value.unwrap_or_default(),
+                            key.layer.unwrap_or_default()
+                        );
+                    }
+                    if !pending {
+                        return Ok(());
+                    }
+                    println!("");
+                    if m.files.is_empty() {
+                        println!("No rendered configuration files would change.");
+                    }
+                    for file in m.files {
+                        let name = file.name.unwrap_or_default();
+                        println!("--- {}", name);
+                        println!("+++ {} (pending)", name);
+                        print!("{}", file.diff.unwrap_or_default());
+                    }
+                    Ok(())
+                }
+                "NetErr" => {
+                    let m = reply.parse::<protocol::net::NetErr>().unwrap();
+                    match ErrCode::from_i32(m.code) {
+                        Some(ErrCode::InvalidPayload) => {
+                            ui.warn(m)?;
+                            Ok(())
+                        }
+                        _ => Err(SrvClientError::from(m)),
+                    }
+                }
+                _ => Err(SrvClientError::from(io::Error::from(
+                    io::ErrorKind::UnexpectedEof,
+                ))),
+            })
+        })
+        .wait()?;
+    Ok(())
+}
+
 fn sub_svc_load(m: &ArgMatches) -> Result<()> {
     let cfg = config::load()?;
     let sup_addr = sup_addr_from_input(m)?;
@@ -989,6 +1091,48 @@ fn sub_svc_stop(m: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn sub_svc_update_now(m: &ArgMatches) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::SvcUpdate::default();
+    msg.ident = Some(ident.into());
+    if let Some(to) = m.value_of("TO") {
+        msg.to = Some(PackageIdent::from_str(to)?.into());
+    }
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
+fn sub_svc_update_hold(m: &ArgMatches) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::SvcUpdateHold::default();
+    msg.ident = Some(ident.into());
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
+fn sub_svc_update_release(m: &ArgMatches) -> Result<()> {
+    let ident = PackageIdent::from_str(m.value_of("PKG_IDENT").unwrap())?;
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::SvcUpdateRelease::default();
+    msg.ident = Some(ident.into());
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
 fn sub_file_put(m: &ArgMatches) -> Result<()> {
     let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
     let cfg = config::load()?;
@@ -1137,6 +1281,42 @@ fn sub_ring_key_import(ui: &mut UI) -> Result<()> {
     command::ring::key::import::start(ui, content.trim(), &default_cache_key_path(Some(&*FS_ROOT)))
 }
 
+fn sub_ring_key_add(m: &ArgMatches) -> Result<()> {
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::RingKeyAdd::default();
+    msg.name = Some(m.value_of("KEY").unwrap().to_string()); // Required via clap
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
+fn sub_ring_key_promote(m: &ArgMatches) -> Result<()> {
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::RingKeyPromote::default();
+    msg.name = Some(m.value_of("KEY").unwrap().to_string()); // Required via clap
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
+fn sub_ring_key_retire(m: &ArgMatches) -> Result<()> {
+    let cfg = config::load()?;
+    let sup_addr = sup_addr_from_input(m)?;
+    let secret_key = ctl_secret_key(&cfg)?;
+    let mut msg = protocol::ctl::RingKeyRetire::default();
+    msg.name = Some(m.value_of("KEY").unwrap().to_string()); // Required via clap
+    SrvClient::connect(&sup_addr, secret_key)
+        .and_then(|conn| conn.call(msg).for_each(handle_ctl_reply))
+        .wait()?;
+    Ok(())
+}
+
 fn sub_service_key_generate(ui: &mut UI, m: &ArgMatches) -> Result<()> {
     let org = org_param_or_env(&m)?;
     let service_group = ServiceGroup::from_str(m.value_of("SERVICE_GROUP").unwrap())?;
@@ -1188,6 +1368,7 @@ fn exec_subcommand_if_called(ui: &mut UI) -> Result<()> {
         | ("sup", "run", "-h")
         | ("sup", "run", "--help")
         | ("sup", "term", _)
+        | ("sup", "dat", _)
         | ("sup", "bash", _)
         | ("sup", "sh", _)
         | ("sup", "-h", _)
@@ -1196,6 +1377,8 @@ fn exec_subcommand_if_called(ui: &mut UI) -> Result<()> {
         | ("sup", "-V", _)
         | ("sup", "--version", _) => command::sup::start(ui, env::args_os().skip(2).collect()),
         ("term", _, _) => command::sup::start(ui, env::args_os().skip(1).collect()),
+        // Delegate `hab pkg render *` to `hab-sup render *`, which has the templating
+        ("pkg", "render", _) => command::sup::start(ui, env::args_os().skip(2).collect()),
         // Delegate `hab help sup *` to `hab-sup`, passing only "help"
         ("help", "sup", _) => command::sup::start(ui, env::args_os().skip(1).take(1).collect()),
         // Delegate `hab sup run *` to the Launcher
@@ -1415,6 +1598,9 @@ where
         }
     };
     let svc_type = status.composite.unwrap_or("standalone".to_string());
+    let svc_held_update = status
+        .held_update
+        .map_or("<none>".to_string(), |ident| ident.to_string());
     let svc_desired_state = status
         .desired_state
         .map_or("<none>".to_string(), |s| s.to_string());
@@ -1439,7 +1625,7 @@ where
     }
     write!(
         out,
-        "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
         status.ident,
         svc_type,
         DesiredState::from_str(&svc_desired_state)?,
@@ -1447,6 +1633,8 @@ where
         svc_elapsed,
         svc_pid,
         status.service_group,
+        svc_held_update,
+        status.update_hold.unwrap_or(false),
     )?;
     return Ok(());
 }
@@ -1525,6 +1713,11 @@ fn get_strategy_from_input(m: &ArgMatches) -> Option<UpdateStrategy> {
         .and_then(|f| UpdateStrategy::from_str(f).ok())
 }
 
+fn get_health_check_value_from_input(m: &ArgMatches, name: &str) -> Option<u32> {
+    // There won't be errors, because we validate with `valid_numeric`
+    m.value_of(name).and_then(|v| v.parse::<u32>().ok())
+}
+
 fn sup_addr_from_input(m: &ArgMatches) -> Result<SocketAddr> {
     match m.value_of("REMOTE_SUP") {
         Some(rs) => {
@@ -1596,5 +1789,23 @@ fn update_svc_load_from_input(m: &ArgMatches, msg: &mut protocol::ctl::SvcLoad)
     msg.binding_mode = get_binding_mode_from_input(m).map(|v| v as i32);
     msg.topology = get_topology_from_input(m).map(|v| v as i32);
     msg.update_strategy = get_strategy_from_input(m).map(|v| v as i32);
+    msg.health_check_interval = get_health_check_value_from_input(m, "HEALTH_CHECK_INTERVAL");
+    msg.health_check_timeout = get_health_check_value_from_input(m, "HEALTH_CHECK_TIMEOUT");
+    msg.health_check_threshold = get_health_check_value_from_input(m, "HEALTH_CHECK_THRESHOLD");
+    msg.restart_on_critical = get_health_check_value_from_input(m, "RESTART_ON_CRITICAL");
+    msg.restart_backoff = get_health_check_value_from_input(m, "RESTART_BACKOFF");
+    msg.restart_limit = get_health_check_value_from_input(m, "RESTART_LIMIT");
+    msg.crash_window = get_health_check_value_from_input(m, "CRASH_WINDOW");
+    msg.crash_limit = get_health_check_value_from_input(m, "CRASH_LIMIT");
+    msg.crash_backoff = get_health_check_value_from_input(m, "CRASH_BACKOFF");
+    msg.update_canary = m.value_of("UPDATE_CANARY").map(str::to_string);
+    // There won't be errors, because we validate with `valid_numeric`
+    msg.update_batch_size = m
+        .value_of("UPDATE_BATCH_SIZE")
+        .and_then(|v| v.parse::<u32>().ok());
+    msg.update_soak = get_health_check_value_from_input(m, "UPDATE_SOAK");
+    msg.update_rollback_window = get_health_check_value_from_input(m, "UPDATE_ROLLBACK_WINDOW");
+    msg.update_window = m.value_of("UPDATE_WINDOW").map(str::to_string);
+    msg.update_spread_by = m.value_of("UPDATE_SPREAD_BY").map(str::to_string);
     Ok(())
 }
@@ -12,6 +12,15 @@ impl message::MessageStatic for ServiceBindList {
 impl message::MessageStatic for SupDepart {
     const MESSAGE_ID: &'static str = "SupDepart";
 }
+impl message::MessageStatic for RingKeyAdd {
+    const MESSAGE_ID: &'static str = "RingKeyAdd";
+}
+impl message::MessageStatic for RingKeyPromote {
+    const MESSAGE_ID: &'static str = "RingKeyPromote";
+}
+impl message::MessageStatic for RingKeyRetire {
+    const MESSAGE_ID: &'static str = "RingKeyRetire";
+}
 impl message::MessageStatic for SvcFilePut {
     const MESSAGE_ID: &'static str = "SvcFilePut";
 }
@@ -24,6 +33,9 @@ impl message::MessageStatic for SvcValidateCfg {
 impl message::MessageStatic for SvcSetCfg {
     const MESSAGE_ID: &'static str = "SvcSetCfg";
 }
+impl message::MessageStatic for SvcCfgDiff {
+    const MESSAGE_ID: &'static str = "SvcCfgDiff";
+}
 impl message::MessageStatic for SvcLoad {
     const MESSAGE_ID: &'static str = "SvcLoad";
 }
@@ -36,6 +48,15 @@ impl message::MessageStatic for SvcStart {
 impl message::MessageStatic for SvcStop {
     const MESSAGE_ID: &'static str = "SvcStop";
 }
+impl message::MessageStatic for SvcUpdate {
+    const MESSAGE_ID: &'static str = "SvcUpdate";
+}
+impl message::MessageStatic for SvcUpdateHold {
+    const MESSAGE_ID: &'static str = "SvcUpdateHold";
+}
+impl message::MessageStatic for SvcUpdateRelease {
+    const MESSAGE_ID: &'static str = "SvcUpdateRelease";
+}
 impl message::MessageStatic for SvcStatus {
     const MESSAGE_ID: &'static str = "SvcStatus";
 }
@@ -38,6 +38,35 @@ pub struct SupDepart {
     #[prost(string, optional, tag="1")]
     pub member_id: ::std::option::Option<String>,
 }
+/// Request to accept gossip encrypted with a ring key, in addition to the keys already accepted.
+/// The key is never sent over the wire; the Supervisor loads it from its own key cache.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RingKeyAdd {
+    /// Name of the ring key, with an optional revision. The latest revision in the Supervisor's key
+    /// cache is used if none is given.
+    #[prost(string, optional, tag="1")]
+    pub name: ::std::option::Option<String>,
+}
+/// Request to encrypt gossip with an accepted ring key. The previous primary key stays accepted.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RingKeyPromote {
+    /// Name, with revision, of the ring key.
+    #[prost(string, optional, tag="1")]
+    pub name: ::std::option::Option<String>,
+}
+/// Request to stop accepting gossip encrypted with a ring key which isn't the primary key.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RingKeyRetire {
+    /// Name, with revision, of the ring key.
+    #[prost(string, optional, tag="1")]
+    pub name: ::std::option::Option<String>,
+}
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -94,6 +123,27 @@ pub struct SvcSetCfg {
     #[prost(bool, optional, tag="4", default="false")]
     pub is_encrypted: ::std::option::Option<bool>,
 }
+/// Request to show the effective configuration of a loaded service, annotated with the layer each
+/// value came from. Given a pending configuration, also shows how it would change the service's
+/// rendered configuration files.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcCfgDiff {
+    /// Package identifier to target running service.
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// Structured and self-describing string format contained in the configuration string.
+    #[prost(enumeration="super::types::service_cfg::Format", optional, tag="2", default="Toml")]
+    pub format: ::std::option::Option<i32>,
+    /// Unencrypted pending configuration to compare against.
+    #[prost(bytes, optional, tag="3")]
+    pub cfg: ::std::option::Option<Vec<u8>>,
+    /// If the pending configuration replaces the service's user.toml, rather than being applied to
+    /// its service group.
+    #[prost(bool, optional, tag="4", default="false")]
+    pub user: ::std::option::Option<bool>,
+}
 /// Request to load a new service.
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -142,6 +192,64 @@ pub struct SvcLoad {
     /// Update strategy for the service.
     #[prost(enumeration="super::types::UpdateStrategy", optional, tag="13")]
     pub update_strategy: ::std::option::Option<i32>,
+    /// Number of seconds between runs of the service's health check.
+    #[prost(uint32, optional, tag="15")]
+    pub health_check_interval: ::std::option::Option<u32>,
+    /// Number of seconds a health check hook may run before it is killed. A value of 0 disables the
+    /// timeout.
+    #[prost(uint32, optional, tag="16")]
+    pub health_check_timeout: ::std::option::Option<u32>,
+    /// Number of consecutive critical health check results required before the service's health
+    /// changes to critical.
+    #[prost(uint32, optional, tag="17")]
+    pub health_check_threshold: ::std::option::Option<u32>,
+    /// Restart the service after this many consecutive critical health checks. A value of 0 disables
+    /// restarting on health check failures.
+    #[prost(uint32, optional, tag="18")]
+    pub restart_on_critical: ::std::option::Option<u32>,
+    /// Number of seconds to wait before restarting an unhealthy service, doubled after each
+    /// consecutive restart.
+    #[prost(uint32, optional, tag="19")]
+    pub restart_backoff: ::std::option::Option<u32>,
+    /// Maximum number of consecutive restarts of an unhealthy service. A value of 0 allows
+    /// unlimited restarts.
+    #[prost(uint32, optional, tag="20")]
+    pub restart_limit: ::std::option::Option<u32>,
+    /// Number of members which update first when using the canary update strategy, given either as a
+    /// count, like "2", or a percentage of the service group, like "25%".
+    #[prost(string, optional, tag="21")]
+    pub update_canary: ::std::option::Option<String>,
+    /// Number of members updated together in each batch after the canaries report healthy.
+    #[prost(uint32, optional, tag="22")]
+    pub update_batch_size: ::std::option::Option<u32>,
+    /// Number of seconds an updated service has to report a healthy status before it is rolled back
+    /// to the previous release. A value of 0 disables rollbacks.
+    #[prost(uint32, optional, tag="23")]
+    pub update_rollback_window: ::std::option::Option<u32>,
+    /// Schedule of the windows in which updates may be applied to the service, either as day and
+    /// time ranges or as a cron-like expression, with an optional timezone. An empty value removes
+    /// the schedule.
+    #[prost(string, optional, tag="24")]
+    pub update_window: ::std::option::Option<String>,
+    /// Member label whose values the update is spread across, so that consecutive members updating
+    /// run in different zones, racks, etc. An empty value removes the setting.
+    #[prost(string, optional, tag="25")]
+    pub update_spread_by: ::std::option::Option<String>,
+    /// Number of seconds every member ahead of a batch has to report a healthy status on the new
+    /// release before the batch updates when using the canary update strategy.
+    #[prost(uint32, optional, tag="26")]
+    pub update_soak: ::std::option::Option<u32>,
+    /// Number of seconds within which unexpected process exits are counted towards the crash limit.
+    #[prost(uint32, optional, tag="27")]
+    pub crash_window: ::std::option::Option<u32>,
+    /// Number of unexpected process exits within the crash window after which the service is marked
+    /// as failed and no longer restarted. A value of 0 restarts the service indefinitely.
+    #[prost(uint32, optional, tag="28")]
+    pub crash_limit: ::std::option::Option<u32>,
+    /// Number of seconds to wait before restarting a service whose process exited unexpectedly,
+    /// doubled for each further exit within the crash window.
+    #[prost(uint32, optional, tag="29")]
+    pub crash_backoff: ::std::option::Option<u32>,
 }
 /// Request to unload a loaded service.
 #[derive(Clone, PartialEq, Message)]
@@ -167,6 +275,34 @@ pub struct SvcStop {
     #[prost(message, optional, tag="1")]
     pub ident: ::std::option::Option<super::types::PackageIdent>,
 }
+/// Request to check for an update to a loaded service now, or to install the given release now,
+/// following the service's update strategy.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcUpdate {
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+    /// A fully qualified release to install instead of the latest one.
+    #[prost(message, optional, tag="2")]
+    pub to: ::std::option::Option<super::types::PackageIdent>,
+}
+/// Request to hold a loaded service at its current release by pausing its updates.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcUpdateHold {
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+}
+/// Request to resume the updates of a loaded service which is held at its current release.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SvcUpdateRelease {
+    #[prost(message, optional, tag="1")]
+    pub ident: ::std::option::Option<super::types::PackageIdent>,
+}
 /// Request to retrieve the service status of one or all services.
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
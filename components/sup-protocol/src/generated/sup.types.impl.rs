@@ -15,6 +15,15 @@ impl message::MessageStatic for ServiceBind {
 impl message::MessageStatic for ServiceCfg {
     const MESSAGE_ID: &'static str = "ServiceCfg";
 }
+impl message::MessageStatic for ServiceCfgDiff {
+    const MESSAGE_ID: &'static str = "ServiceCfgDiff";
+}
+impl message::MessageStatic for ServiceCfgFileDiff {
+    const MESSAGE_ID: &'static str = "ServiceCfgFileDiff";
+}
+impl message::MessageStatic for ServiceCfgKey {
+    const MESSAGE_ID: &'static str = "ServiceCfgKey";
+}
 impl message::MessageStatic for ServiceGroup {
     const MESSAGE_ID: &'static str = "ServiceGroup";
 }
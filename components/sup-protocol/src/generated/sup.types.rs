@@ -41,6 +41,9 @@ pub struct ServiceBind {
     pub service_group: ServiceGroup,
     #[prost(string, optional, tag="3")]
     pub service_name: ::std::option::Option<String>,
+    /// Only members carrying every one of these labels satisfy the bind.
+    #[prost(map="string, string", tag="4")]
+    pub labels: ::std::collections::HashMap<String, String>,
 }
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
@@ -62,6 +65,43 @@ pub mod service_cfg {
         Toml = 0,
     }
 }
+/// The effective configuration of a service, with the layer each value came from, and the changes
+/// a pending configuration would make to the service's rendered configuration files.
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServiceCfgDiff {
+    #[prost(message, repeated, tag="1")]
+    pub keys: ::std::vec::Vec<ServiceCfgKey>,
+    #[prost(message, repeated, tag="2")]
+    pub files: ::std::vec::Vec<ServiceCfgFileDiff>,
+}
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServiceCfgFileDiff {
+    /// Name of the rendered configuration file.
+    #[prost(string, optional, tag="1")]
+    pub name: ::std::option::Option<String>,
+    /// Unified diff of the file as rendered now against the file as the pending configuration would
+    /// render it.
+    #[prost(string, optional, tag="2")]
+    pub diff: ::std::option::Option<String>,
+}
+#[derive(Clone, PartialEq, Message)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ServiceCfgKey {
+    /// Dotted path of the key (ex: repl.port).
+    #[prost(string, optional, tag="1")]
+    pub key: ::std::option::Option<String>,
+    /// The value of the key, formatted as TOML.
+    #[prost(string, optional, tag="2")]
+    pub value: ::std::option::Option<String>,
+    /// The configuration layer the value came from: default, environment, user or gossip.
+    #[prost(string, optional, tag="3")]
+    pub layer: ::std::option::Option<String>,
+}
 #[derive(Clone, PartialEq, Message)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -89,6 +129,12 @@ pub struct ServiceStatus {
     pub composite: ::std::option::Option<String>,
     #[prost(enumeration="DesiredState", optional, tag="5")]
     pub desired_state: ::std::option::Option<i32>,
+    /// A newer release which was installed but is held until the service's update window opens.
+    #[prost(message, optional, tag="6")]
+    pub held_update: ::std::option::Option<PackageIdent>,
+    /// Whether the service is held at its current release, with its updates paused.
+    #[prost(bool, optional, tag="7")]
+    pub update_hold: ::std::option::Option<bool>,
 }
 /// Encapsulate all possible sources we can install packages from.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
@@ -106,6 +152,9 @@ pub enum InstallSource {
 pub enum ProcessState {
     Down = 0,
     Up = 1,
+    /// The process exited too many times in a short period and will not be restarted until the
+    /// service is started again.
+    Failed = 2,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
 #[derive(Serialize, Deserialize)]
@@ -132,6 +181,7 @@ pub enum UpdateStrategy {
     None = 0,
     AtOnce = 1,
     Rolling = 2,
+    Canary = 3,
 }
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
 #[derive(Serialize, Deserialize)]
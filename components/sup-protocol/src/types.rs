@@ -22,7 +22,9 @@
 include!("generated/sup.types.rs");
 include!("generated/sup.types.impl.rs");
 
+use std::collections::HashMap;
 use std::fmt;
+use std::iter::FromIterator;
 use std::str::FromStr;
 
 use core;
@@ -83,6 +85,7 @@ impl fmt::Display for ProcessState {
         let state = match *self {
             ProcessState::Down => "down",
             ProcessState::Up => "up",
+            ProcessState::Failed => "failed",
         };
         write!(f, "{}", state)
     }
@@ -124,10 +127,11 @@ impl FromStr for ProcessState {
         match value.to_lowercase().as_ref() {
             "0" => Ok(ProcessState::Down),
             "1" => Ok(ProcessState::Up),
+            "2" => Ok(ProcessState::Failed),
             _ => Err(net::err(
                 ErrCode::InvalidPayload,
                 format!(
-                    "Invalid process state \"{:?}\", must be `up` or `down`.",
+                    "Invalid process state \"{:?}\", must be `up`, `down` or `failed`.",
                     value
                 ),
             )),
@@ -160,20 +164,28 @@ impl FromStr for ServiceBind {
     type Err = NetErr;
 
     fn from_str(bind_str: &str) -> Result<Self, Self::Err> {
-        let values: Vec<&str> = bind_str.split(':').collect();
-        if !(values.len() == 3 || values.len() == 2) {
+        let mut parts = bind_str.splitn(2, '/');
+        let values: Vec<&str> = parts.next().unwrap_or("").split(':').collect();
+        let labels = match parts.next() {
+            Some(selector) => parse_label_selector(selector),
+            None => Some(HashMap::new()),
+        };
+        if !(values.len() == 3 || values.len() == 2) || labels.is_none() {
             return Err(net::err(
                 ErrCode::InvalidPayload,
                 format!(
                     "Invalid binding \"{}\", must be of the form <NAME>:<SERVICE_GROUP> or \
                     <SERVICE_NAME>:<NAME>:<SERVICE_GROUP> where <NAME> is a service name,
                     <SERVICE_GROUP> is a valid service group, and <SERVICE_NAME> is the name of
-                    a service within a composite if the given bind is for a composite service.",
+                    a service within a composite if the given bind is for a composite service.
+                    Either form may end in /<LABEL>=<VALUE>[,<LABEL>=<VALUE>...] to only bind to
+                    members carrying those labels.",
                     bind_str
                 ),
             ));
         }
         let mut bind = ServiceBind::default();
+        bind.labels = labels.unwrap();
         if values.len() == 3 {
             bind.name = values[1].to_string();
             bind.service_group = ServiceGroup::from_str(values[2])?;
@@ -186,6 +198,37 @@ impl FromStr for ServiceBind {
     }
 }
 
+/// Most labels a Supervisor may gossip along with its membership.
+pub const MEMBER_LABELS_MAX: usize = 4;
+/// Longest label, in bytes of its `<LABEL>=<VALUE>` form, a Supervisor may gossip along with its
+/// membership. Labels ride on every SWIM message, which must fit in 1024 bytes.
+pub const MEMBER_LABEL_MAX_BYTES: usize = 32;
+
+/// Longest, in seconds, a Supervisor may keep the rumors of members that have been confirmed dead
+/// or departed. Ten years keeps the TTL clear of overflowing once it is counted in milliseconds
+/// and added to the time a member died.
+pub const RUMOR_TTL_MAX_SECS: u64 = 10 * 365 * 24 * 60 * 60;
+
+/// Parse a label selector of the form `<LABEL>=<VALUE>[,<LABEL>=<VALUE>...]`. Returns `None` if
+/// any of the labels is malformed.
+pub fn parse_label_selector<T>(selector: &str) -> Option<T>
+where
+    T: FromIterator<(String, String)>,
+{
+    selector
+        .split(',')
+        .map(|label| {
+            let mut parts = label.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if !key.is_empty() => {
+                    Some((key.to_string(), value.to_string()))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
 impl FromStr for ServiceGroup {
     type Err = NetErr;
 
@@ -360,6 +403,7 @@ impl UpdateStrategy {
             UpdateStrategy::None => "none",
             UpdateStrategy::AtOnce => "at-once",
             UpdateStrategy::Rolling => "rolling",
+            UpdateStrategy::Canary => "canary",
         }
     }
 }
@@ -372,6 +416,7 @@ impl FromStr for UpdateStrategy {
             "none" => Ok(UpdateStrategy::None),
             "at-once" => Ok(UpdateStrategy::AtOnce),
             "rolling" => Ok(UpdateStrategy::Rolling),
+            "canary" => Ok(UpdateStrategy::Canary),
             _ => Err(net::err(
                 ErrCode::InvalidPayload,
                 "Invalid update strategy.",
@@ -394,6 +439,18 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn service_bind_from_str_with_labels() {
+        let bind = ServiceBind::from_str("db:postgres.default/zone=us-east-1a,tier=gold").unwrap();
+        assert_eq!(bind.name, "db");
+        assert_eq!(bind.labels.len(), 2);
+        assert_eq!(bind.labels["zone"], "us-east-1a");
+        assert_eq!(bind.labels["tier"], "gold");
+
+        assert!(ServiceBind::from_str("db:postgres.default/zone").is_err());
+        assert!(ServiceBind::from_str("db:postgres.default/=gold").is_err());
+    }
+
     #[test]
     fn topology_default() {
         // This should always be the default topology, if this default gets changed, we have
@@ -466,6 +523,14 @@ mod test {
         assert_eq!(strategy, UpdateStrategy::AtOnce);
     }
 
+    #[test]
+    fn update_strategy_canary_from_str() {
+        let strategy = UpdateStrategy::from_str("canary").unwrap();
+
+        assert_eq!(strategy, UpdateStrategy::Canary);
+        assert_eq!("canary", strategy.to_string());
+    }
+
     #[test]
     fn update_strategy_from_str_invalid() {
         let strategy_str = "dope";
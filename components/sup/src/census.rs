@@ -25,12 +25,14 @@ use butterfly::rumor::service::SysInfo;
 use butterfly::rumor::service_config::ServiceConfig as ServiceConfigRumor;
 use butterfly::rumor::service_file::ServiceFile as ServiceFileRumor;
 use butterfly::rumor::RumorStore;
+use butterfly::server::partition::Partition;
 use hcore;
 use hcore::package::PackageIdent;
 use hcore::service::ServiceGroup;
 use toml;
 
 use error::{Error, SupError};
+use manager::service::HealthCheck;
 
 static LOGKEY: &'static str = "CE";
 
@@ -48,6 +50,7 @@ pub struct CensusRing {
     last_membership_counter: usize,
     last_service_config_counter: usize,
     last_service_file_counter: usize,
+    partition: Option<Partition>,
 }
 
 impl CensusRing {
@@ -71,6 +74,7 @@ impl CensusRing {
             last_membership_counter: 0,
             last_service_config_counter: 0,
             last_service_file_counter: 0,
+            partition: None,
         }
     }
 
@@ -112,6 +116,27 @@ impl CensusRing {
         }
     }
 
+    /// Record the network partition butterfly currently suspects, if any. The census counts as
+    /// changed when it differs from the last one recorded.
+    pub fn update_partition(&mut self, partition: Option<Partition>) {
+        if self.partition != partition {
+            self.changed = true;
+            self.partition = partition;
+        }
+    }
+
+    /// The network partition butterfly suspects, if any.
+    pub fn partition(&self) -> Option<&Partition> {
+        self.partition.as_ref()
+    }
+
+    /// Whether a suspected network partition affects the given service group.
+    pub fn is_partitioned(&self, sg: &ServiceGroup) -> bool {
+        self.partition.as_ref().map_or(false, |partition| {
+            partition.service_groups.contains(&sg.to_string())
+        })
+    }
+
     pub fn census_group_for(&self, sg: &ServiceGroup) -> Option<&CensusGroup> {
         self.census_groups.get(sg)
     }
@@ -282,6 +307,7 @@ pub struct CensusGroup {
     pub service_group: ServiceGroup,
     pub election_status: ElectionStatus,
     pub update_election_status: ElectionStatus,
+    pub update_election_term: u64,
     pub leader_id: Option<MemberId>,
     pub service_config: Option<ServiceConfig>,
 
@@ -298,6 +324,7 @@ impl CensusGroup {
             service_group: sg,
             election_status: ElectionStatus::None,
             update_election_status: ElectionStatus::None,
+            update_election_term: 0,
             local_member_id: local_member_id.clone(),
             population: BTreeMap::new(),
             leader_id: None,
@@ -373,6 +400,50 @@ impl CensusGroup {
         }
     }
 
+    /// Returns the alive members in the order they take part in an update: the update leader
+    /// first, followed by the remaining members ordered by member ID. Returns an empty list if
+    /// there is no update leader.
+    ///
+    /// With `spread_by`, the remaining members are instead taken in turn from each value of that
+    /// label, so that consecutive members run in different zones, racks, etc. Members without
+    /// the label are grouped together as if they shared a value.
+    pub fn update_order(&self, spread_by: Option<&str>) -> Vec<&CensusMember> {
+        let leader = match self.update_leader() {
+            Some(leader) => leader,
+            None => return vec![],
+        };
+        let rest = self
+            .population
+            .values()
+            .filter(|cm| cm.alive() && cm.member_id != leader.member_id);
+        let mut members = vec![leader];
+        match spread_by {
+            Some(label) => {
+                let mut spreads: BTreeMap<Option<&String>, Vec<&CensusMember>> = BTreeMap::new();
+                for member in rest {
+                    spreads
+                        .entry(member.labels.get(label))
+                        .or_insert_with(Vec::new)
+                        .push(member);
+                }
+                let mut spreads: Vec<_> = spreads.into_iter().map(|(_, v)| v.into_iter()).collect();
+                loop {
+                    let before = members.len();
+                    for spread in spreads.iter_mut() {
+                        if let Some(member) = spread.next() {
+                            members.push(member);
+                        }
+                    }
+                    if members.len() == before {
+                        break;
+                    }
+                }
+            }
+            None => members.extend(rest),
+        }
+        members
+    }
+
     fn update_from_service_rumors(&mut self, rumors: &HashMap<String, ServiceRumor>) {
         for (member_id, service_rumor) in rumors.iter() {
             // Yeah - we are ourself - we're alive.
@@ -412,6 +483,7 @@ impl CensusGroup {
 
     fn update_from_election_update_rumor(&mut self, election: &ElectionUpdateRumor) {
         self.update_leader_id = None;
+        self.update_election_term = election.term;
         for census_member in self.population.values_mut() {
             if census_member.update_from_election_update_rumor(election) {
                 self.update_leader_id = Some(census_member.member_id.clone());
@@ -528,12 +600,18 @@ pub struct CensusMember {
     pub update_election_is_running: bool,
     pub update_election_is_no_quorum: bool,
     pub update_election_is_finished: bool,
+    /// The last health check result gossiped by the member, if any.
+    pub health_check: Option<HealthCheck>,
+    /// A release the member rolled back from after it failed to become healthy.
+    pub rolled_back_from: Option<PackageIdent>,
     pub sys: SysInfo,
 
     alive: bool,
     suspect: bool,
     confirmed: bool,
     departed: bool,
+    /// The labels the member's Supervisor gossips along with its membership.
+    pub labels: BTreeMap<String, String>,
     // Maps must be represented last in a serializable struct for the current version of the toml
     // crate. Additionally, this deserialization method is required to correct any ordering issues
     // with the table being serialized - https://docs.rs/toml/0.4.0/toml/ser/fn.tables_last.html
@@ -557,6 +635,11 @@ impl CensusMember {
             Ok(ident) => self.pkg = Some(ident),
             Err(err) => warn!("Received a bad package ident from gossip data, err={}", err),
         };
+        self.health_check = rumor.health.map(|h| HealthCheck::from(h as i8));
+        self.rolled_back_from = rumor
+            .rolled_back_from
+            .as_ref()
+            .and_then(|ident| PackageIdent::from_str(ident).ok());
         self.sys = rumor.sys.clone().into();
         self.cfg = toml::from_slice(&rumor.cfg).unwrap_or(toml::value::Table::default());
     }
@@ -597,6 +680,14 @@ impl CensusMember {
         self.sys.gossip_ip = member.address.to_string();
         self.sys.gossip_port = member.gossip_port as u32;
         self.persistent = true;
+        self.labels = member.labels.clone();
+    }
+
+    /// Whether the member carries every one of the given labels.
+    pub fn matches_labels(&self, selector: &BTreeMap<String, String>) -> bool {
+        selector
+            .iter()
+            .all(|(key, value)| self.labels.get(key) == Some(value))
     }
 
     fn update_from_health(&mut self, health: Health) {
@@ -643,6 +734,8 @@ fn service_group_from_str(sg: &str) -> Result<ServiceGroup, hcore::Error> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
+
     use super::*;
     use butterfly::member::{Health, MemberList};
     use butterfly::rumor::election::Election as ElectionRumor;
@@ -652,6 +745,7 @@ mod tests {
     use butterfly::rumor::service_config::ServiceConfig as ServiceConfigRumor;
     use butterfly::rumor::service_file::ServiceFile as ServiceFileRumor;
     use butterfly::rumor::RumorStore;
+    use butterfly::server::partition::Partition;
     use hcore::package::ident::PackageIdent;
     use hcore::service::ServiceGroup;
 
@@ -764,11 +858,14 @@ mod tests {
             update_election_is_running: false,
             update_election_is_no_quorum: false,
             update_election_is_finished: false,
+            health_check: None,
+            rolled_back_from: None,
             sys: SysInfo::default(),
             alive: health == Health::Alive,
             suspect: health == Health::Suspect,
             confirmed: health == Health::Confirmed,
             departed: health == Health::Departed,
+            labels: BTreeMap::new(),
             cfg: BTreeMap::new(),
         }
     }
@@ -799,4 +896,105 @@ mod tests {
         assert_eq!(active_members[1].member_id, "suspect-one");
     }
 
+    #[test]
+    fn update_order_starts_with_update_leader() {
+        let population = vec![
+            test_census_member("member-a".to_string(), Health::Alive),
+            test_census_member("member-b".to_string(), Health::Confirmed),
+            test_census_member("member-c".to_string(), Health::Alive),
+            test_census_member("member-d".to_string(), Health::Alive),
+        ];
+
+        let sg: ServiceGroup = "test-service.default"
+            .parse()
+            .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"member-a".to_string());
+        for member in population {
+            census_group
+                .population
+                .insert(member.member_id.clone(), member);
+        }
+        assert!(census_group.update_order(None).is_empty());
+
+        census_group.update_leader_id = Some("member-c".to_string());
+        let order: Vec<&str> = census_group
+            .update_order(None)
+            .iter()
+            .map(|cm| cm.member_id.as_str())
+            .collect();
+        assert_eq!(order, vec!["member-c", "member-a", "member-d"]);
+    }
+
+    #[test]
+    fn update_order_spreads_members_across_label_values() {
+        let zones = vec![
+            ("member-a", "zone-1"),
+            ("member-b", "zone-1"),
+            ("member-c", "zone-1"),
+            ("member-d", "zone-2"),
+            ("member-e", "zone-2"),
+            ("member-f", "zone-3"),
+        ];
+
+        let sg: ServiceGroup = "test-service.default"
+            .parse()
+            .expect("This should be a valid service group");
+
+        let mut census_group = CensusGroup::new(sg, &"member-a".to_string());
+        for (id, zone) in zones {
+            let mut member = test_census_member(id.to_string(), Health::Alive);
+            member.labels.insert("zone".to_string(), zone.to_string());
+            census_group.population.insert(id.to_string(), member);
+        }
+        census_group.update_leader_id = Some("member-a".to_string());
+
+        let order: Vec<&str> = census_group
+            .update_order(Some("zone"))
+            .iter()
+            .map(|cm| cm.member_id.as_str())
+            .collect();
+        assert_eq!(
+            order,
+            vec!["member-a", "member-b", "member-d", "member-f", "member-c", "member-e"]
+        );
+    }
+
+    #[test]
+    fn matches_labels_requires_every_label() {
+        let mut member = test_census_member("member-a".to_string(), Health::Alive);
+        member
+            .labels
+            .insert("zone".to_string(), "zone-1".to_string());
+        member.labels.insert("rack".to_string(), "r12".to_string());
+
+        let mut selector = BTreeMap::new();
+        assert!(member.matches_labels(&selector));
+        selector.insert("zone".to_string(), "zone-1".to_string());
+        assert!(member.matches_labels(&selector));
+        selector.insert("rack".to_string(), "r13".to_string());
+        assert!(!member.matches_labels(&selector));
+    }
+
+    #[test]
+    fn partition_affects_only_its_service_groups() {
+        let sg_one = ServiceGroup::new(None, "shield", "one", None).unwrap();
+        let sg_two = ServiceGroup::new(None, "shield", "two", None).unwrap();
+        let mut ring = CensusRing::new("member-a".to_string());
+        let mut partition = Partition {
+            detected_at: 0,
+            lost: BTreeSet::new(),
+            service_groups: BTreeSet::new(),
+        };
+        partition.lost.insert("member-b".to_string());
+        partition.service_groups.insert(sg_one.to_string());
+
+        ring.update_partition(Some(partition));
+        assert!(ring.changed());
+        assert!(ring.is_partitioned(&sg_one));
+        assert!(!ring.is_partitioned(&sg_two));
+
+        ring.update_partition(None);
+        assert!(!ring.is_partitioned(&sg_one));
+    }
 }
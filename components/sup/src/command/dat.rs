@@ -0,0 +1,109 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inspect, compare and repair the dat files the gossip server persists its rumors to.
+
+use std::path::Path;
+
+use butterfly::rumor::dat_file::{Contents, DatFile};
+use serde::Serialize;
+use serde_json;
+
+use error::{Error, Result};
+
+/// Our output key
+static LOGKEY: &'static str = "DAT";
+
+/// Print the contents of a dat file as JSON, grouped by store.
+pub fn dump<P: AsRef<Path>>(path: P) -> Result<()> {
+    let contents = read_contents(path)?;
+    print_json(&contents)
+}
+
+/// Print the members and rumors which differ between two dat files as JSON.
+pub fn diff<P, Q>(path_a: P, path_b: Q) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let contents_a = read_contents(path_a)?;
+    let contents_b = read_contents(path_b)?;
+    print_json(&contents_a.diff(&contents_b))
+}
+
+/// Report every problem found in a dat file. Fails if there were any.
+pub fn validate<P: AsRef<Path>>(path: P) -> Result<()> {
+    let mut dat_file = DatFile::from_path(path);
+    let problems = dat_file.validate()?;
+    if problems.is_empty() {
+        outputln!("{} is valid", dat_file.path().display());
+        return Ok(());
+    }
+    for problem in problems.iter() {
+        outputln!("{}", problem);
+    }
+    Err(sup_error!(Error::DatFileInvalid(
+        dat_file.path().to_path_buf(),
+        problems.len()
+    )))
+}
+
+/// Write a copy of a dat file to `output`, without the entries matching every given filter.
+pub fn drop_rumors<P, Q>(
+    path: P,
+    output: Q,
+    store: Option<&str>,
+    key: Option<&str>,
+    member_id: Option<&str>,
+) -> Result<()>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let mut contents = read_contents(path)?;
+    let dropped = contents.drop_rumors(store, key, member_id);
+    let dat_file = DatFile::from_path(output);
+    dat_file.write_contents(&contents)?;
+    outputln!(
+        "Dropped {} entries, wrote {}",
+        dropped,
+        dat_file.path().display()
+    );
+    Ok(())
+}
+
+/// Read the contents of a dat file, reporting any sections and rumors which had to be skipped on
+/// stderr so they don't mix with the JSON we print.
+fn read_contents<P: AsRef<Path>>(path: P) -> Result<Contents> {
+    let mut dat_file = DatFile::from_path(path);
+    let contents = dat_file.read_contents()?;
+    for section in dat_file.corrupt_sections() {
+        eprintln!(
+            "Skipped the corrupt {} section of {}",
+            section,
+            dat_file.path().display()
+        );
+    }
+    for entry in dat_file.skipped_rumors() {
+        eprintln!("Skipped {} of {}", entry, dat_file.path().display());
+    }
+    Ok(contents)
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|err| sup_error!(Error::DatFileSerialization(err)))?;
+    println!("{}", json);
+    Ok(())
+}
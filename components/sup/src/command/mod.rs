@@ -14,4 +14,6 @@
 
 //! The CLI commands.
 
+pub mod dat;
+pub mod render;
 pub mod shell;
@@ -0,0 +1,572 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Render the configuration and hook templates of a package without a running Supervisor.
+//!
+//! The render context is built the same way a Supervisor builds it, except that the census comes
+//! from a file describing the members of the service's group, the groups it binds to, and their
+//! leaders. Without one, the census holds just the local member.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::result;
+use std::str::FromStr;
+
+use butterfly::member::{Health, Member, MemberList};
+use butterfly::rumor::election::Election as ElectionRumor;
+use butterfly::rumor::election::ElectionUpdate as ElectionUpdateRumor;
+use butterfly::rumor::service::Service as ServiceRumor;
+use butterfly::rumor::service::SysInfo;
+use butterfly::rumor::service_config::ServiceConfig as ServiceConfigRumor;
+use butterfly::rumor::service_file::ServiceFile as ServiceFileRumor;
+use butterfly::rumor::RumorStore;
+use hcore::fs::FS_ROOT_PATH;
+use hcore::package::{Identifiable, PackageIdent, PackageInstall};
+use hcore::service::ServiceGroup;
+use hcore::util::perm;
+use protocol;
+use serde_json;
+use toml;
+
+use census::CensusRing;
+use config::GossipListenAddr;
+use error::{Error, Result, SupError};
+use http_gateway;
+use manager::service::hooks::HOOK_PERMISSIONS;
+use manager::service::{Cfg, Pkg, ServiceBind};
+use manager::Sys;
+use templating::{RenderContext, TemplateRenderer};
+
+/// Our output key
+static LOGKEY: &'static str = "RN";
+
+/// The member the service runs on when the census doesn't list any.
+const LOCAL_MEMBER_ID: &'static str = "local";
+/// The version and release of packages which only have a plan, or are only named by a bind.
+const PLACEHOLDER_VERSION: &'static str = "0.0.0";
+const PLACEHOLDER_RELEASE: &'static str = "00000000000000";
+/// The template directories rendered, relative to the package.
+const TEMPLATE_DIRS: &'static [&'static str] = &["config", "hooks"];
+
+/// The census to render with, as read from a TOML or JSON file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CensusSpec {
+    /// The group of the service being rendered; `default` if not given.
+    group: Option<String>,
+    /// The member the service runs on; the first member if not given.
+    me: Option<String>,
+    leader: Option<String>,
+    update_leader: Option<String>,
+    members: Vec<MemberSpec>,
+    binds: Vec<BindSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberSpec {
+    member_id: String,
+    /// A fully qualified package identifier.
+    #[serde(default)]
+    pkg: Option<String>,
+    #[serde(default)]
+    ip: Option<String>,
+    #[serde(default)]
+    hostname: Option<String>,
+    /// One of `alive`, `suspect`, `confirmed` or `departed`; `alive` if not given.
+    #[serde(default)]
+    health: Option<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    cfg: toml::value::Table,
+}
+
+#[derive(Debug, Deserialize)]
+struct BindSpec {
+    name: String,
+    service_group: String,
+    #[serde(default)]
+    leader: Option<String>,
+    #[serde(default)]
+    members: Vec<MemberSpec>,
+}
+
+/// Render every template under the `config` and `hooks` directories of a package, named by an
+/// identifier or the path to its installed directory or plan. Files are written under `output`
+/// if given, and printed otherwise.
+pub fn start(
+    target: &str,
+    census_file: Option<&Path>,
+    user_toml: Option<&Path>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let pkg = Pkg::from_install(load_package(target)?)?;
+    let mut cfg = Cfg::new(&pkg, None)?;
+    if let Some(path) = user_toml {
+        cfg.user = Some(toml::from_str(&read_file(path)?)?);
+        if let Some(errors) = cfg.validate() {
+            return Err(sup_error!(Error::InvalidConfig(errors)));
+        }
+    }
+    let spec = match census_file {
+        Some(path) => parse_census(path, &read_file(path)?)?,
+        None => CensusSpec::default(),
+    };
+    let origin = census_file.unwrap_or(Path::new("<synthesized census>"));
+    let (service_group, me, ring, binds) = build_census(spec, &pkg)
+        .map_err(|reason| sup_error!(Error::CensusFile(origin.to_path_buf(), reason)))?;
+    let mut sys = Sys::new(
+        true,
+        GossipListenAddr::default(),
+        protocol::ctl::default_addr(),
+        http_gateway::ListenAddr::default(),
+    );
+    sys.member_id = me;
+    let ctx = RenderContext::new(&service_group, &sys, &pkg, &cfg, &ring, binds.iter());
+
+    let mut renderer = TemplateRenderer::new();
+    let mut templates = vec![];
+    for dir in TEMPLATE_DIRS {
+        let entries = match fs::read_dir(pkg.path.join(dir)) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut files = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+        for file in files {
+            let name = format!("{}/{}", dir, file.file_name().unwrap().to_string_lossy());
+            renderer
+                .register_template_string(&name, read_file(&file)?)
+                .map_err(|e| template_error(&file, e.line_no, e.column_no, e.reason.to_string()))?;
+            templates.push((name, file));
+        }
+    }
+
+    for &(ref name, ref file) in templates.iter() {
+        let rendered = render(&renderer, name, file, &ctx)?;
+        match output {
+            Some(dir) => {
+                let dest = dir.join(name);
+                if let Some(parent) = dest.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                File::create(&dest)?.write_all(rendered.as_bytes())?;
+                if name.starts_with("hooks/") {
+                    perm::set_permissions(&dest, HOOK_PERMISSIONS)?;
+                }
+                outputln!("Rendered {}", dest.display());
+            }
+            None => {
+                println!("==> {} <==", name);
+                print!("{}", rendered);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Load an installed package by identifier, or by the path to its installed directory or plan.
+/// Packages which only have a plan take their identifier from it.
+fn load_package(target: &str) -> Result<PackageInstall> {
+    let path = Path::new(target);
+    if !path.is_dir() {
+        let ident = PackageIdent::from_str(target)?;
+        return Ok(PackageInstall::load(&ident, Some(&*FS_ROOT_PATH))?);
+    }
+    let ident_file = path.join("IDENT");
+    let ident = if ident_file.is_file() {
+        PackageIdent::from_str(read_file(&ident_file)?.trim())?
+    } else {
+        plan_ident(path)?
+    };
+    Ok(PackageInstall::new_from_parts(
+        ident,
+        FS_ROOT_PATH.to_path_buf(),
+        FS_ROOT_PATH.to_path_buf(),
+        path.to_path_buf(),
+    ))
+}
+
+/// The identifier of the package a plan builds, from the `pkg_origin`, `pkg_name` and
+/// `pkg_version` it sets. Values computed at build time aren't known, so each falls back to a
+/// placeholder: `local`, the name of the plan's directory, and `0.0.0`.
+fn plan_ident(path: &Path) -> Result<PackageIdent> {
+    let plan = read_file(&path.join("plan.sh")).unwrap_or_default();
+    let value_of = |var: &str| {
+        let prefix = format!("{}=", var);
+        plan.lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(&prefix))
+            .map(|line| line[prefix.len()..].trim_matches(|c| c == '"' || c == '\''))
+            .and_then(|value| {
+                if value.is_empty() || value.contains('$') {
+                    None
+                } else {
+                    Some(value.to_string())
+                }
+            })
+    };
+    let dir_name = path
+        .canonicalize()?
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok(PackageIdent::new(
+        value_of("pkg_origin").unwrap_or("local".to_string()),
+        value_of("pkg_name").unwrap_or(dir_name),
+        Some(value_of("pkg_version").unwrap_or(PLACEHOLDER_VERSION.to_string())),
+        Some(PLACEHOLDER_RELEASE.to_string()),
+    ))
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Census files may be written in TOML or JSON.
+fn parse_census(path: &Path, contents: &str) -> Result<CensusSpec> {
+    match toml::from_str(contents) {
+        Ok(spec) => Ok(spec),
+        Err(toml_err) => serde_json::from_str(contents).map_err(|json_err| {
+            sup_error!(Error::CensusFile(
+                path.to_path_buf(),
+                format!("not valid TOML ({}) or JSON ({})", toml_err, json_err)
+            ))
+        }),
+    }
+}
+
+/// Build the census described by `spec` for the service of `pkg`, by gossiping it the rumors a
+/// real ring would. Returns the service's group, the member it runs on, the census and the
+/// service's binds.
+fn build_census(
+    spec: CensusSpec,
+    pkg: &Pkg,
+) -> result::Result<(ServiceGroup, String, CensusRing, Vec<ServiceBind>), String> {
+    let group = spec.group.unwrap_or("default".to_string());
+    let service_group =
+        ServiceGroup::new(None, &pkg.name, group, None).map_err(|e| e.to_string())?;
+    let mut members = spec.members;
+    if members.is_empty() {
+        members.push(MemberSpec {
+            member_id: LOCAL_MEMBER_ID.to_string(),
+            pkg: None,
+            ip: None,
+            hostname: None,
+            health: None,
+            labels: BTreeMap::new(),
+            cfg: toml::value::Table::new(),
+        });
+    }
+    let me = spec.me.unwrap_or(members[0].member_id.clone());
+    match members.iter().find(|member| member.member_id == me) {
+        Some(member) => match member.health.as_ref().map(String::as_str) {
+            Some("confirmed") | Some("departed") => {
+                return Err(format!("me, {}, must be alive or suspect", me))
+            }
+            _ => (),
+        },
+        None => return Err(format!("me, {}, is not one of the members", me)),
+    }
+
+    let rumors = Rumors::new();
+    rumors.add_group(
+        &service_group,
+        &pkg.ident,
+        &members,
+        spec.leader.as_ref(),
+        spec.update_leader.as_ref(),
+    )?;
+    let mut binds = vec![];
+    for bind in spec.binds {
+        let bind_group = ServiceGroup::from_str(&bind.service_group)
+            .map_err(|e| format!("bind {}: {}", bind.name, e))?;
+        let ident = PackageIdent::new(
+            pkg.origin.as_str(),
+            bind_group.service(),
+            Some(PLACEHOLDER_VERSION),
+            Some(PLACEHOLDER_RELEASE),
+        );
+        rumors.add_group(
+            &bind_group,
+            &ident,
+            &bind.members,
+            bind.leader.as_ref(),
+            None,
+        )?;
+        binds.push(ServiceBind {
+            name: bind.name,
+            service_group: bind_group,
+            service_name: None,
+            labels: BTreeMap::new(),
+        });
+    }
+
+    let mut ring = CensusRing::new(me.clone());
+    ring.update_from_rumors(
+        &rumors.services,
+        &rumors.elections,
+        &rumors.election_updates,
+        &rumors.members,
+        &RumorStore::<ServiceConfigRumor>::default(),
+        &RumorStore::<ServiceFileRumor>::default(),
+    );
+    Ok((service_group, me, ring, binds))
+}
+
+/// The rumors a census is built from.
+struct Rumors {
+    services: RumorStore<ServiceRumor>,
+    elections: RumorStore<ElectionRumor>,
+    election_updates: RumorStore<ElectionUpdateRumor>,
+    members: MemberList,
+}
+
+impl Rumors {
+    fn new() -> Self {
+        Rumors {
+            services: RumorStore::default(),
+            elections: RumorStore::default(),
+            election_updates: RumorStore::default(),
+            members: MemberList::new(),
+        }
+    }
+
+    /// Add the rumors of one service group, whose members run `ident` unless they say otherwise.
+    fn add_group(
+        &self,
+        service_group: &ServiceGroup,
+        ident: &PackageIdent,
+        members: &[MemberSpec],
+        leader: Option<&String>,
+        update_leader: Option<&String>,
+    ) -> result::Result<(), String> {
+        for spec in members {
+            let pkg = match spec.pkg {
+                Some(ref pkg) => match PackageIdent::from_str(pkg) {
+                    Ok(ref pkg) if pkg.fully_qualified() => pkg.clone(),
+                    _ => {
+                        return Err(format!(
+                            "member {}: {} is not a fully qualified package identifier",
+                            spec.member_id, pkg
+                        ))
+                    }
+                },
+                None => ident.clone(),
+            };
+            let health = match spec.health.as_ref().map(String::as_str) {
+                Some("alive") | None => Health::Alive,
+                Some("suspect") => Health::Suspect,
+                Some("confirmed") => Health::Confirmed,
+                Some("departed") => Health::Departed,
+                Some(health) => {
+                    return Err(format!(
+                        "member {}: unknown health {}",
+                        spec.member_id, health
+                    ))
+                }
+            };
+            let ip = spec.ip.clone().unwrap_or("127.0.0.1".to_string());
+            let mut sys_info = SysInfo::default();
+            sys_info.ip = ip.clone();
+            sys_info.hostname = spec.hostname.clone().unwrap_or(spec.member_id.clone());
+            sys_info.gossip_ip = ip.clone();
+            self.services.insert(ServiceRumor::new(
+                spec.member_id.as_str(),
+                &pkg,
+                service_group.clone(),
+                sys_info,
+                Some(&spec.cfg),
+            ));
+            let mut member = Member::default();
+            member.id = spec.member_id.clone();
+            member.address = ip;
+            member.labels = spec.labels.clone();
+            self.members.insert(member, health);
+        }
+        if let Some(id) = leader {
+            let mut election = ElectionRumor::new(id.as_str(), service_group.clone(), 1);
+            election.finish();
+            self.elections.insert(election);
+        }
+        if let Some(id) = update_leader {
+            let mut election = ElectionUpdateRumor::new(id.as_str(), service_group.clone(), 1);
+            election.finish();
+            self.election_updates.insert(election);
+        }
+        Ok(())
+    }
+}
+
+/// Render a template, pointing any error at the line and column of the file it came from.
+fn render(
+    renderer: &TemplateRenderer,
+    name: &str,
+    file: &Path,
+    ctx: &RenderContext,
+) -> Result<String> {
+    renderer.render(name, ctx).map_err(|err| {
+        let position = match err.err {
+            Error::TemplateRenderError(ref e) => Some((e.line_no, e.column_no, e.desc.clone())),
+            _ => None,
+        };
+        match position {
+            Some((line, column, reason)) => template_error(file, line, column, reason),
+            None => err,
+        }
+    })
+}
+
+fn template_error(
+    file: &Path,
+    line: Option<usize>,
+    column: Option<usize>,
+    reason: String,
+) -> SupError {
+    let position = line.and_then(|line| column.map(|column| (line, column)));
+    sup_error!(Error::TemplateError(file.to_path_buf(), position, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use hcore::package::{PackageIdent, PackageInstall};
+
+    use super::*;
+    use manager::service::Pkg;
+
+    const CENSUS_TOML: &'static str = r#"
+group = "prod"
+leader = "member-b"
+
+[[members]]
+member_id = "member-a"
+ip = "10.0.0.1"
+
+[[members]]
+member_id = "member-b"
+ip = "10.0.0.2"
+
+[[binds]]
+name = "database"
+service_group = "postgresql.prod"
+leader = "pg-a"
+
+[[binds.members]]
+member_id = "pg-a"
+[binds.members.cfg]
+port = 5432
+"#;
+
+    fn pkg() -> Pkg {
+        let ident = PackageIdent::new("testing", "web", Some("1.0.0"), Some("20180701000000"));
+        let install = PackageInstall::new_from_parts(
+            ident,
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+            PathBuf::from("/tmp"),
+        );
+        Pkg::from_install(install).expect("Could not create package!")
+    }
+
+    #[test]
+    fn census_file_may_be_toml_or_json() {
+        let path = Path::new("census");
+        let toml = parse_census(path, CENSUS_TOML).expect("parse TOML census");
+        let json = parse_census(
+            path,
+            r#"{"group": "prod", "members": [{"member_id": "member-a", "cfg": {"port": 80}}]}"#,
+        )
+        .expect("parse JSON census");
+        assert_eq!(toml.group, json.group);
+        assert_eq!(toml.members[0].member_id, json.members[0].member_id);
+        assert!(parse_census(path, "members = [").is_err());
+    }
+
+    #[test]
+    fn census_has_members_leaders_and_binds() {
+        let spec = parse_census(Path::new("census"), CENSUS_TOML).expect("parse census");
+        let (service_group, me, ring, binds) = build_census(spec, &pkg()).expect("build census");
+        assert_eq!(service_group.to_string(), "web.prod");
+        assert_eq!(me, "member-a");
+
+        let group = ring
+            .census_group_for(&service_group)
+            .expect("service group");
+        assert_eq!(group.me().expect("me").member_id, "member-a");
+        assert_eq!(group.leader().expect("leader").member_id, "member-b");
+        assert_eq!(group.active_members().len(), 2);
+
+        assert_eq!(binds.len(), 1);
+        assert_eq!(binds[0].name, "database");
+        let bound = ring
+            .census_group_for(&binds[0].service_group)
+            .expect("bound group");
+        let pg = bound.leader().expect("bound leader");
+        assert_eq!(pg.member_id, "pg-a");
+        assert_eq!(pg.cfg["port"].as_integer(), Some(5432));
+    }
+
+    #[test]
+    fn census_is_synthesized_without_a_file() {
+        let (_, me, ring, binds) =
+            build_census(CensusSpec::default(), &pkg()).expect("build census");
+        assert_eq!(me, LOCAL_MEMBER_ID);
+        assert!(binds.is_empty());
+        let group = ring.groups()[0];
+        assert_eq!(group.service_group.to_string(), "web.default");
+        assert!(group.me().expect("me").alive());
+        assert!(group.leader().is_none());
+    }
+
+    #[test]
+    fn me_must_be_a_live_member() {
+        let mut spec = parse_census(Path::new("census"), CENSUS_TOML).expect("parse census");
+        spec.me = Some("member-z".to_string());
+        assert!(build_census(spec, &pkg()).is_err());
+
+        let mut spec = parse_census(Path::new("census"), CENSUS_TOML).expect("parse census");
+        spec.members[0].health = Some("confirmed".to_string());
+        assert!(build_census(spec, &pkg()).is_err());
+    }
+
+    #[test]
+    fn template_errors_point_at_the_file() {
+        let mut renderer = TemplateRenderer::new();
+        let file = Path::new("/src/web/config/web.conf");
+        let err = renderer
+            .register_template_string("config/web.conf", "port = {{cfg.port}}\nhost = {{cfg.host")
+            .map_err(|e| template_error(file, e.line_no, e.column_no, e.reason.to_string()))
+            .unwrap_err();
+        match err.err {
+            Error::TemplateError(ref path, position, _) => {
+                assert_eq!(path, file);
+                assert_eq!(position.map(|(line, _)| line), Some(2));
+            }
+            _ => panic!("Should fail with Error::TemplateError"),
+        }
+        assert!(err.to_string().contains("/src/web/config/web.conf:2:"));
+    }
+}
@@ -327,6 +327,18 @@ impl Future for SrvHandler {
                                     },
                                 )
                             }
+                            "SvcCfgDiff" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcCfgDiff>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::service_cfg_diff(state, req, m.clone())
+                                    },
+                                )
+                            }
                             "SvcLoad" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SvcLoad>()
@@ -369,6 +381,42 @@ impl Future for SrvHandler {
                                     move |state, req| Manager::service_stop(state, req, m.clone()),
                                 )
                             }
+                            "SvcUpdate" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcUpdate>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::service_update(state, req, m.clone())
+                                    },
+                                )
+                            }
+                            "SvcUpdateHold" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcUpdateHold>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::service_update_hold(state, req, m.clone())
+                                    },
+                                )
+                            }
+                            "SvcUpdateRelease" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::SvcUpdateRelease>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::service_update_release(state, req, m.clone())
+                                    },
+                                )
+                            }
                             "SvcStatus" => {
                                 let m = msg
                                     .parse::<protocol::ctl::SvcStatus>()
@@ -393,6 +441,40 @@ impl Future for SrvHandler {
                                     },
                                 )
                             }
+                            "RingKeyAdd" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::RingKeyAdd>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| Manager::ring_key_add(state, req, m.clone()),
+                                )
+                            }
+                            "RingKeyPromote" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::RingKeyPromote>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::ring_key_promote(state, req, m.clone())
+                                    },
+                                )
+                            }
+                            "RingKeyRetire" => {
+                                let m = msg
+                                    .parse::<protocol::ctl::RingKeyRetire>()
+                                    .map_err(HandlerError::from)?;
+                                CtlCommand::new(
+                                    Some(self.tx.clone()),
+                                    msg.transaction(),
+                                    move |state, req| {
+                                        Manager::ring_key_retire(state, req, m.clone())
+                                    },
+                                )
+                            }
                             _ => {
                                 warn!("Unhandled message, {}", msg.message_id());
                                 break;
@@ -116,7 +116,11 @@ pub enum Error {
     BadEnvConfig(String),
     TestBootFail,
     ButterflyError(butterfly::error::Error),
+    CensusFile(PathBuf, String),
+    ConfigSchema(PathBuf, String),
     CtlSecretIo(PathBuf, io::Error),
+    DatFileInvalid(PathBuf, usize),
+    DatFileSerialization(serde_json::Error),
     APIClient(api_client::Error),
     EnvJoinPathsError(env::JoinPathsError),
     ExecCommandNotFound(String),
@@ -127,12 +131,19 @@ pub enum Error {
     HabitatCore(hcore::Error),
     TemplateFileError(handlebars::TemplateFileError),
     TemplateRenderError(handlebars::RenderError),
+    TemplateError(PathBuf, Option<(usize, usize)>, String),
     InvalidBinding(String),
     InvalidBinds(Vec<String>),
+    InvalidConfig(Vec<String>),
+    InvalidGossipBandwidthLimit(u64),
     InvalidKeyParameter(String),
+    InvalidPeerDiscoveryInterval,
     InvalidPidFile,
+    InvalidRumorTtl(u64),
     InvalidTopology(String),
+    InvalidUpdateCanary(String),
     InvalidUpdateStrategy(String),
+    InvalidUpdateWindow(String, String),
     Io(io::Error),
     IPFailed,
     Launcher(launcher_client::Error),
@@ -148,6 +159,8 @@ pub enum Error {
     NotifyError(notify::Error),
     NulError(ffi::NulError),
     PackageNotFound(package::PackageIdent),
+    PeerDiscoveryExec(PathBuf, Option<i32>),
+    PeerDiscoveryTimeout(PathBuf, u64),
     Permissions(String),
     PidFileCorrupt(PathBuf),
     PidFileIO(PathBuf, io::Error),
@@ -156,6 +169,8 @@ pub enum Error {
     ProcessLockIO(PathBuf, io::Error),
     RecvError(mpsc::RecvError),
     RenderContextSerialization(serde_json::Error),
+    RingKeysFile(PathBuf, String),
+    Secret(String, String),
     ServiceDeserializationError(serde_json::Error),
     ServiceNotLoaded(package::PackageIdent),
     ServiceSerializationError(serde_json::Error),
@@ -170,6 +185,7 @@ pub enum Error {
     TomlEncode(toml::ser::Error),
     TomlMergeError(String),
     TomlParser(toml::de::Error),
+    TooManyLabels(usize),
     TryRecvError(mpsc::TryRecvError),
     UnpackFailed,
     UserNotFound(String),
@@ -222,19 +238,53 @@ impl fmt::Display for SupError {
             }
             Error::TestBootFail => format!("Simulated boot failure"),
             Error::ButterflyError(ref err) => format!("Butterfly error: {}", err),
+            Error::CensusFile(ref path, ref reason) => {
+                format!("Invalid census {}, {}", path.display(), reason)
+            }
+            Error::ConfigSchema(ref path, ref err) => {
+                format!("Invalid config schema {}, {}", path.display(), err)
+            }
             Error::CtlSecretIo(ref path, ref err) => format!(
                 "IoError while reading or writing ctl secret, {}, {}",
                 path.display(),
                 err
             ),
+            Error::DatFileInvalid(ref path, count) => format!(
+                "Found {} problem(s) in gossip data file {}",
+                count,
+                path.display()
+            ),
+            Error::DatFileSerialization(ref e) => {
+                format!("Unable to serialize gossip data file contents, {}", e)
+            }
             Error::ExecCommandNotFound(ref c) => {
                 format!("`{}' was not found on the filesystem or in PATH", c)
             }
+            Error::PeerDiscoveryExec(ref path, Some(code)) => format!(
+                "Peer discovery command {} exited with status {}",
+                path.display(),
+                code
+            ),
+            Error::PeerDiscoveryExec(ref path, None) => format!(
+                "Peer discovery command {} was terminated by a signal",
+                path.display()
+            ),
+            Error::PeerDiscoveryTimeout(ref path, secs) => format!(
+                "Peer discovery command {} was killed after running for {}s",
+                path.display(),
+                secs
+            ),
             Error::Permissions(ref err) => format!("{}", err),
             Error::HabitatCommon(ref err) => format!("{}", err),
             Error::HabitatCore(ref err) => format!("{}", err),
             Error::TemplateFileError(ref err) => format!("{:?}", err),
             Error::TemplateRenderError(ref err) => format!("{}", err),
+            Error::TemplateError(ref path, Some((line, column)), ref reason) => {
+                format!("{}:{}:{}: {}", path.display(), line, column, reason)
+            }
+            Error::TemplateError(ref path, None, ref reason) => {
+                format!("{}: {}", path.display(), reason)
+            }
             Error::EnvJoinPathsError(ref err) => format!("{}", err),
             Error::FileNotFound(ref e) => format!("File not found at: {}", e),
             Error::FileWatcherFileIsRoot => format!("Watched file is root"),
@@ -247,12 +297,38 @@ impl fmt::Display for SupError {
                 binding
             ),
             Error::InvalidBinds(ref e) => format!("Invalid bind(s), {}", e.join(", ")),
+            Error::InvalidConfig(ref e) => format!(
+                "Configuration does not match the config schema, {}",
+                e.join(", ")
+            ),
+            Error::InvalidGossipBandwidthLimit(kb) => format!(
+                "Invalid gossip bandwidth limit of {} kilobytes per second, it must be between 1 \
+                 and {}",
+                kb,
+                u64::max_value() / 1024
+            ),
             Error::InvalidKeyParameter(ref e) => {
                 format!("Invalid parameter for key generation: {:?}", e)
             }
+            Error::InvalidPeerDiscoveryInterval => {
+                format!("Invalid peer discovery interval, it must be at least 1 second")
+            }
             Error::InvalidPidFile => format!("Invalid child process PID file"),
+            Error::InvalidRumorTtl(ttl) => format!(
+                "Invalid rumor TTL of {} seconds, it must be at most {}",
+                ttl,
+                protocol::types::RUMOR_TTL_MAX_SECS
+            ),
             Error::InvalidTopology(ref t) => format!("Invalid topology: {}", t),
+            Error::InvalidUpdateCanary(ref c) => format!(
+                "Invalid update canary count '{}'. It must be a number of members, like '2', or a \
+                 percentage of members, like '25%'",
+                c
+            ),
             Error::InvalidUpdateStrategy(ref s) => format!("Invalid update strategy: {}", s),
+            Error::InvalidUpdateWindow(ref w, ref e) => {
+                format!("Invalid update window '{}': {}", w, e)
+            }
             Error::Io(ref err) => format!("{}", err),
             Error::IPFailed => format!("Failed to discover this hosts outbound IP address"),
             Error::Launcher(ref err) => format!("{}", err),
@@ -302,6 +378,11 @@ impl fmt::Display for SupError {
             Error::RenderContextSerialization(ref e) => {
                 format!("Unable to serialize rendering context, {}", e)
             }
+            Error::RingKeysFile(ref path, ref e) => format!(
+                "Unable to read or write ring keys file, {}, {}",
+                path.display(),
+                e
+            ),
             Error::ServiceDeserializationError(ref e) => {
                 format!("Can't deserialize service status: {}", e)
             }
@@ -320,6 +401,9 @@ impl fmt::Display for SupError {
             Error::ServiceSpecRender(ref err) => {
                 format!("Service spec could not be rendered successfully: {}", err)
             }
+            Error::Secret(ref name, ref reason) => {
+                format!("Unable to resolve secret '{}', {}", name, reason)
+            }
             Error::SignalFailed => format!("Failed to send a signal to the child process"),
             Error::SpecWatcherDirNotFound(ref path) => format!(
                 "Spec directory '{}' not created or is not a directory",
@@ -331,6 +415,11 @@ impl fmt::Display for SupError {
             Error::TomlEncode(ref e) => format!("Failed to encode TOML: {}", e),
             Error::TomlMergeError(ref e) => format!("Failed to merge TOML: {}", e),
             Error::TomlParser(ref err) => format!("Failed to parse TOML: {}", err),
+            Error::TooManyLabels(count) => format!(
+                "{} labels were given, but a Supervisor may gossip at most {}",
+                count,
+                protocol::types::MEMBER_LABELS_MAX
+            ),
             Error::TryRecvError(ref err) => format!("{}", err),
             Error::UnpackFailed => format!("Failed to unpack a package"),
             Error::UserNotFound(ref e) => format!("No UID for user '{}' could be found", e),
@@ -365,11 +454,16 @@ impl error::Error for SupError {
             Error::BadEnvConfig(_) => "Unknown syntax in Env Configuration",
             Error::TestBootFail => "Simulated boot failure",
             Error::ButterflyError(ref err) => err.description(),
+            Error::CensusFile(_, _) => "Census file could not be loaded",
+            Error::ConfigSchema(_, _) => "Package config schema could not be loaded",
             Error::CtlSecretIo(_, _) => "IoError while reading ctl secret",
+            Error::DatFileInvalid(_, _) => "Gossip data file failed validation",
+            Error::DatFileSerialization(_) => "Unable to serialize gossip data file contents",
             Error::ExecCommandNotFound(_) => "Exec command was not found on filesystem or in PATH",
             Error::GroupNotFound(_) => "No matching GID for group found",
             Error::TemplateFileError(ref err) => err.description(),
             Error::TemplateRenderError(ref err) => err.description(),
+            Error::TemplateError(_, _, _) => "Unable to compile or render a template",
             Error::HabitatCommon(ref err) => err.description(),
             Error::HabitatCore(ref err) => err.description(),
             Error::EnvJoinPathsError(ref err) => err.description(),
@@ -379,10 +473,16 @@ impl error::Error for SupError {
             Error::InvalidBinds(_) => {
                 "Service binds detected that are neither required nor optional package binds"
             }
+            Error::InvalidConfig(_) => "Configuration does not match the config schema",
+            Error::InvalidGossipBandwidthLimit(_) => "Invalid gossip bandwidth limit",
             Error::InvalidKeyParameter(_) => "Key parameter error",
+            Error::InvalidPeerDiscoveryInterval => "Invalid peer discovery interval",
             Error::InvalidPidFile => "Invalid child process PID file",
+            Error::InvalidRumorTtl(_) => "Invalid rumor TTL",
             Error::InvalidTopology(_) => "Invalid topology",
+            Error::InvalidUpdateCanary(_) => "Invalid update canary count",
             Error::InvalidUpdateStrategy(_) => "Invalid update strategy",
+            Error::InvalidUpdateWindow(_, _) => "Invalid update window",
             Error::Io(ref err) => err.description(),
             Error::IPFailed => "Failed to discover the outbound IP address",
             Error::Launcher(ref err) => err.description(),
@@ -404,6 +504,8 @@ impl error::Error for SupError {
                 "An attempt was made to build a CString with a null byte inside it"
             }
             Error::PackageNotFound(_) => "Cannot find a package",
+            Error::PeerDiscoveryExec(_, _) => "Peer discovery command failed",
+            Error::PeerDiscoveryTimeout(_, _) => "Peer discovery command timed out",
             Error::Permissions(_) => "File system permissions error",
             Error::PidFileCorrupt(_) => "Unable to decode contents of PID file",
             Error::PidFileIO(_, _) => "Unable to read or write to PID file",
@@ -414,12 +516,14 @@ impl error::Error for SupError {
             Error::ProcessLockIO(_, _) => "Unable to read or write to a process lock",
             Error::RecvError(_) => "A channel failed to receive a response",
             Error::RenderContextSerialization(_) => "Unable to serialize rendering context",
+            Error::RingKeysFile(_, _) => "Unable to read or write ring keys file",
             Error::ServiceDeserializationError(_) => "Can't deserialize service status",
             Error::ServiceNotLoaded(_) => "Service status called when service not loaded",
             Error::ServiceSerializationError(_) => "Can't serialize service to file",
             Error::ServiceSpecFileIO(_, _) => "Unable to write or read to a service spec file",
             Error::ServiceSpecParse(_) => "Service spec could not be parsed successfully",
             Error::ServiceSpecRender(_) => "Service spec TOML could not be rendered successfully",
+            Error::Secret(_, _) => "A secret referred to by configuration could not be resolved",
             Error::SignalFailed => "Failed to send a signal to the child process",
             Error::SpecWatcherDirNotFound(_) => "Spec directory not created or is not a directory",
             Error::SpecWatcherGlob(_) => "Spec watcher file globbing error",
@@ -428,6 +532,7 @@ impl error::Error for SupError {
             Error::TomlEncode(_) => "Failed to encode toml!",
             Error::TomlMergeError(_) => "Failed to merge TOML!",
             Error::TomlParser(_) => "Failed to parse TOML!",
+            Error::TooManyLabels(_) => "Too many member labels",
             Error::TryRecvError(_) => "A channel failed to receive a response",
             Error::UnpackFailed => "Failed to unpack a package",
             Error::UserNotFound(_) => "No matching UID for user found",
@@ -82,3 +82,9 @@ pub fn user_path<T: AsRef<Path>>(service_name: T) -> PathBuf {
 pub fn user_config_path<T: AsRef<Path>>(service_name: T) -> PathBuf {
     user_path(service_name).join("config")
 }
+
+/// Returns the path to a given service's secrets directory, which
+/// secret references in its configuration are resolved from.
+pub fn user_secrets_path<T: AsRef<Path>>(service_name: T) -> PathBuf {
+    user_path(service_name).join("secrets")
+}
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::fmt;
+use std::fmt::{self, Write};
 use std::fs::File;
 use std::io::{self, Read};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs};
@@ -118,6 +118,7 @@ impl Server {
             doc: get "/" => doc,
             butterfly: get "/butterfly" => butterfly,
             census: get "/census" => census,
+            metrics: get "/metrics" => metrics,
             services: get "/services" => services,
             service: get "/services/:svc/:group" => service,
             service_org: get "/services/:svc/:group/:org" => service,
@@ -245,6 +246,21 @@ fn service(req: &mut Request) -> IronResult<Response> {
     }
 }
 
+fn metrics(req: &mut Request) -> IronResult<Response> {
+    let state = req.get::<persistent::Read<ManagerFs>>().unwrap();
+    match (
+        json_from_file(&state.butterfly_data_path),
+        json_from_file(&state.services_data_path),
+    ) {
+        (Some(butterfly), Some(services)) => Ok(Response::with((
+            status::Ok,
+            Header(headers::ContentType::plaintext()),
+            render_metrics(&butterfly, &services),
+        ))),
+        _ => Ok(Response::with(status::ServiceUnavailable)),
+    }
+}
+
 fn services(req: &mut Request) -> IronResult<Response> {
     let state = req.get::<persistent::Read<ManagerFs>>().unwrap();
     match File::open(&state.services_data_path) {
@@ -312,6 +328,172 @@ fn build_service_group(req: &mut Request) -> Result<ServiceGroup> {
     Ok(sg)
 }
 
+fn json_from_file<T>(path: T) -> Option<Json>
+where
+    T: AsRef<Path>,
+{
+    File::open(path)
+        .ok()
+        .and_then(|file| serde_json::from_reader(file).ok())
+}
+
+/// Renders the Prometheus text exposition format for the given butterfly and services state, as
+/// persisted to disk by the Manager.
+fn render_metrics(butterfly: &Json, services: &Json) -> String {
+    let mut out = String::new();
+    let services = services.as_array().map(|s| s.as_slice()).unwrap_or(&[]);
+
+    metric_header(
+        &mut out,
+        "hab_sup_service_health",
+        "gauge",
+        "Result of the last health check (0 = ok, 1 = warning, 2 = critical, 3 = unknown)",
+    );
+    for service in services {
+        let health = match service["health_check"].as_str() {
+            Some("Ok") => HealthCheck::Ok,
+            Some("Warning") => HealthCheck::Warning,
+            Some("Critical") => HealthCheck::Critical,
+            _ => HealthCheck::Unknown,
+        };
+        metric_line(
+            &mut out,
+            "hab_sup_service_health",
+            &service_labels(service),
+            health as i8,
+        );
+    }
+
+    metric_header(
+        &mut out,
+        "hab_sup_service_up",
+        "gauge",
+        "Whether the service's process is currently running",
+    );
+    for service in services {
+        let up = (service["process"]["state"] == "up") as u8;
+        metric_line(&mut out, "hab_sup_service_up", &service_labels(service), up);
+    }
+
+    metric_header(
+        &mut out,
+        "hab_sup_service_restarts_total",
+        "counter",
+        "Number of times the service's process has been restarted",
+    );
+    for service in services {
+        metric_line(
+            &mut out,
+            "hab_sup_service_restarts_total",
+            &service_labels(service),
+            service["process"]["restarts"].as_u64().unwrap_or(0),
+        );
+    }
+
+    metric_header(
+        &mut out,
+        "hab_butterfly_swim_rounds_total",
+        "counter",
+        "Number of completed SWIM protocol rounds",
+    );
+    metric_line(
+        &mut out,
+        "hab_butterfly_swim_rounds_total",
+        "",
+        butterfly["swim_rounds"].as_i64().unwrap_or(0),
+    );
+    metric_header(
+        &mut out,
+        "hab_butterfly_gossip_rounds_total",
+        "counter",
+        "Number of completed gossip protocol rounds",
+    );
+    metric_line(
+        &mut out,
+        "hab_butterfly_gossip_rounds_total",
+        "",
+        butterfly["gossip_rounds"].as_i64().unwrap_or(0),
+    );
+    metric_header(
+        &mut out,
+        "hab_butterfly_rumors_reaped_total",
+        "counter",
+        "Number of rumors of long-dead members that have been dropped",
+    );
+    metric_line(
+        &mut out,
+        "hab_butterfly_rumors_reaped_total",
+        "",
+        butterfly["rumors_reaped"].as_u64().unwrap_or(0),
+    );
+
+    metric_header(
+        &mut out,
+        "hab_butterfly_members",
+        "gauge",
+        "Number of members in the ring, by health",
+    );
+    for health in &["Alive", "Suspect", "Confirmed", "Departed"] {
+        let count = butterfly["member"]["health"]
+            .as_object()
+            .map_or(0, |h| h.values().filter(|v| v == health).count());
+        metric_line(
+            &mut out,
+            "hab_butterfly_members",
+            &format!("health=\"{}\"", health.to_lowercase()),
+            count,
+        );
+    }
+
+    metric_header(
+        &mut out,
+        "hab_butterfly_rumors",
+        "gauge",
+        "Number of rumors held in each rumor store",
+    );
+    for store in &[
+        "service",
+        "service_config",
+        "service_file",
+        "election",
+        "election_update",
+        "departure",
+    ] {
+        let count: usize = butterfly[*store]["list"].as_object().map_or(0, |list| {
+            list.values()
+                .map(|rumors| rumors.as_object().map_or(0, |r| r.len()))
+                .sum()
+        });
+        metric_line(
+            &mut out,
+            "hab_butterfly_rumors",
+            &format!("type=\"{}\"", store),
+            count,
+        );
+    }
+    out
+}
+
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+fn metric_line<T: fmt::Display>(out: &mut String, name: &str, labels: &str, value: T) {
+    if labels.is_empty() {
+        writeln!(out, "{} {}", name, value).unwrap();
+    } else {
+        writeln!(out, "{}{{{}}} {}", name, labels, value).unwrap();
+    }
+}
+
+fn service_labels(service: &Json) -> String {
+    format!(
+        "service_group=\"{}\"",
+        service["service_group"].as_str().unwrap_or("")
+    )
+}
+
 fn service_from_file<T>(
     service_group: &ServiceGroup,
     services_data_path: T,
@@ -329,3 +511,41 @@ where
         Err(err) => Err(err),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_metrics_from_state() {
+        let butterfly = json!({
+            "member": {
+                "health": { "a": "Alive", "b": "Alive", "c": "Confirmed" }
+            },
+            "service": { "list": { "redis.default": { "a": {}, "b": {} } } },
+            "election": { "list": {} },
+            "swim_rounds": 12,
+            "gossip_rounds": 7,
+            "rumors_reaped": 4
+        });
+        let services = json!([{
+            "service_group": "redis.default",
+            "health_check": "Critical",
+            "process": { "state": "up", "restarts": 3 }
+        }]);
+        let out = render_metrics(&butterfly, &services);
+        assert!(out.contains("# TYPE hab_sup_service_health gauge\n"));
+        assert!(out.contains("hab_sup_service_health{service_group=\"redis.default\"} 2\n"));
+        assert!(out.contains("hab_sup_service_up{service_group=\"redis.default\"} 1\n"));
+        assert!(
+            out.contains("hab_sup_service_restarts_total{service_group=\"redis.default\"} 3\n")
+        );
+        assert!(out.contains("hab_butterfly_swim_rounds_total 12\n"));
+        assert!(out.contains("hab_butterfly_gossip_rounds_total 7\n"));
+        assert!(out.contains("hab_butterfly_rumors_reaped_total 4\n"));
+        assert!(out.contains("hab_butterfly_members{health=\"alive\"} 2\n"));
+        assert!(out.contains("hab_butterfly_members{health=\"confirmed\"} 1\n"));
+        assert!(out.contains("hab_butterfly_rumors{type=\"service\"} 2\n"));
+        assert!(out.contains("hab_butterfly_rumors{type=\"departure\"} 0\n"));
+    }
+}
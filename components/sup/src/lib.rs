@@ -40,11 +40,14 @@
 //! * [The Habitat Supervisor Sidecar; http interface to promises](sidecar)
 
 extern crate ansi_term;
+extern crate base64;
 #[macro_use]
 extern crate bitflags;
 extern crate byteorder;
 #[cfg(target_os = "linux")]
 extern crate caps;
+extern crate chrono;
+extern crate chrono_tz;
 extern crate crypto;
 #[cfg(windows)]
 extern crate ctrlc;
@@ -91,6 +94,7 @@ extern crate tokio;
 extern crate tokio_core;
 extern crate tokio_io;
 extern crate toml;
+extern crate trust_dns_resolver;
 extern crate url;
 extern crate valico;
 
@@ -35,10 +35,11 @@ extern crate url;
 use std::env;
 use std::io::{self, Write};
 use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::Path;
 use std::process;
 use std::str::{self, FromStr};
 
-use clap::{App, ArgMatches};
+use clap::{App, AppSettings, ArgMatches};
 use common::command::package::install::InstallSource;
 use common::ui::{Coloring, NONINTERACTIVE_ENVVAR, UI};
 use hcore::channel;
@@ -52,19 +53,20 @@ use protocol::{
     ctl::ServiceBindList,
     types::{
         ApplicationEnvironment, BindingMode, ServiceBind, ServiceGroup, Topology, UpdateStrategy,
+        MEMBER_LABELS_MAX,
     },
 };
 
 use hab::cli::{
-    sub_sup_bash, sub_sup_depart, sub_sup_run, sub_sup_secret, sub_sup_sh, sub_sup_term,
-    sub_svc_status,
+    sub_pkg_render, sub_sup_bash, sub_sup_dat, sub_sup_depart, sub_sup_run, sub_sup_secret,
+    sub_sup_sh, sub_sup_term, sub_svc_status,
 };
 use sup::command;
 use sup::config::{GossipListenAddr, GOSSIP_DEFAULT_PORT};
 use sup::error::{Error, Result, SupError};
 use sup::feat;
 use sup::http_gateway;
-use sup::manager::{Manager, ManagerConfig};
+use sup::manager::{Manager, ManagerConfig, PeerSource, UpdateWindow};
 use sup::util;
 use sup::VERSION;
 
@@ -131,6 +133,8 @@ fn start() -> Result<()> {
     };
     match app_matches.subcommand() {
         ("bash", Some(_)) => sub_bash(),
+        ("dat", Some(m)) => sub_dat(m),
+        ("render", Some(m)) => sub_render(m),
         ("run", Some(m)) => {
             let launcher = launcher.ok_or(sup_error!(Error::NoLauncher))?;
             sub_run(m, launcher)
@@ -156,12 +160,15 @@ fn cli<'a, 'b>() -> App<'a, 'b> {
         // they are all enumerated here so that the entire help menu
         // can be displayed from `hab sup --help`
         (subcommand: sub_sup_bash().aliases(&["b", "ba", "bas"]))
+        (subcommand: sub_sup_dat())
         (subcommand: sub_sup_depart().aliases(&["d", "de", "dep", "depa", "depart"]))
         (subcommand: sub_sup_run().aliases(&["r", "ru"]))
         (subcommand: sub_sup_secret().aliases(&["sec", "secr"]))
         (subcommand: sub_sup_sh().aliases(&[]))
         (subcommand: sub_svc_status().aliases(&["stat", "statu"]))
         (subcommand: sub_sup_term().aliases(&["ter"]))
+        // `hab pkg render` is delegated here, since it needs the Supervisor's templating
+        (subcommand: sub_pkg_render().setting(AppSettings::Hidden))
     )
 }
 
@@ -169,6 +176,34 @@ fn sub_bash() -> Result<()> {
     command::shell::bash()
 }
 
+fn sub_dat(m: &ArgMatches) -> Result<()> {
+    match m.subcommand() {
+        ("dump", Some(sc)) => command::dat::dump(sc.value_of("FILE").unwrap()),
+        ("diff", Some(sc)) => command::dat::diff(
+            sc.value_of("FILE_A").unwrap(),
+            sc.value_of("FILE_B").unwrap(),
+        ),
+        ("validate", Some(sc)) => command::dat::validate(sc.value_of("FILE").unwrap()),
+        ("drop", Some(sc)) => command::dat::drop_rumors(
+            sc.value_of("FILE").unwrap(),
+            sc.value_of("OUTPUT").unwrap(),
+            sc.value_of("STORE"),
+            sc.value_of("KEY"),
+            sc.value_of("MEMBER_ID"),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+fn sub_render(m: &ArgMatches) -> Result<()> {
+    command::render::start(
+        m.value_of("PKG_IDENT_OR_PATH").unwrap(),
+        m.value_of("CENSUS").map(Path::new),
+        m.value_of("USER_TOML").map(Path::new),
+        m.value_of("OUTPUT").map(Path::new),
+    )
+}
+
 fn sub_run(m: &ArgMatches, launcher: LauncherCli) -> Result<()> {
     set_supervisor_logging_options(m);
 
@@ -234,6 +269,9 @@ fn sub_term(m: &ArgMatches) -> Result<()> {
 fn mgrcfg_from_matches(m: &ArgMatches) -> Result<ManagerConfig> {
     let mut cfg = ManagerConfig::default();
     cfg.auto_update = m.is_present("AUTO_UPDATE");
+    if let Some(window) = m.value_of("AUTO_UPDATE_WINDOW") {
+        cfg.update_window = Some(UpdateWindow::from_str(window)?);
+    }
     cfg.update_url = bldr_url(m);
     cfg.update_channel = channel(m);
     if let Some(addr_str) = m.value_of("LISTEN_GOSSIP") {
@@ -258,6 +296,21 @@ fn mgrcfg_from_matches(m: &ArgMatches) -> Result<ManagerConfig> {
     }
     cfg.organization = m.value_of("ORGANIZATION").map(|org| org.to_string());
     cfg.gossip_permanent = m.is_present("PERMANENT_PEER");
+    cfg.rumor_ttl = m.value_of("RUMOR_TTL").and_then(|v| v.parse::<u64>().ok());
+    cfg.gossip_bandwidth_limit = m
+        .value_of("GOSSIP_BANDWIDTH_LIMIT")
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(labels) = m.values_of("LABEL") {
+        for label in labels {
+            let mut parts = label.splitn(2, '=');
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                cfg.labels.insert(key.to_string(), value.to_string());
+            }
+        }
+        if cfg.labels.len() > MEMBER_LABELS_MAX {
+            return Err(sup_error!(Error::TooManyLabels(cfg.labels.len())));
+        }
+    }
     // TODO fn: Clean this up--using a for loop doesn't feel good however an iterator was
     // causing a lot of developer/compiler type confusion
     let mut gossip_peers: Vec<SocketAddr> = Vec::new();
@@ -283,6 +336,26 @@ fn mgrcfg_from_matches(m: &ArgMatches) -> Result<ManagerConfig> {
     if let Some(watch_peer_file) = m.value_of("PEER_WATCH_FILE") {
         cfg.watch_peer_file = Some(String::from(watch_peer_file));
     }
+    if let Some(names) = m.values_of("PEER_DNS") {
+        cfg.peer_sources
+            .extend(names.map(|name| PeerSource::DnsA(name.to_string())));
+    }
+    if let Some(names) = m.values_of("PEER_SRV") {
+        cfg.peer_sources
+            .extend(names.map(|name| PeerSource::DnsSrv(name.to_string())));
+    }
+    if let Some(path) = m.value_of("PEER_DIR") {
+        cfg.peer_sources.push(PeerSource::Directory(path.into()));
+    }
+    if let Some(path) = m.value_of("PEER_EXEC") {
+        cfg.peer_sources.push(PeerSource::Exec(path.into()));
+    }
+    if let Some(interval) = m
+        .value_of("PEER_DISCOVERY_INTERVAL")
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        cfg.peer_discovery_interval = interval;
+    }
     cfg.ring_key = match m.value_of("RING") {
         Some(val) => Some(SymKey::get_latest_pair_for(
             &val,
@@ -369,6 +442,11 @@ fn get_strategy_from_input(m: &ArgMatches) -> Option<UpdateStrategy> {
         .and_then(|f| UpdateStrategy::from_str(f).ok())
 }
 
+fn get_health_check_value_from_input(m: &ArgMatches, name: &str) -> Option<u32> {
+    // There won't be errors, because we validate with `valid_numeric`
+    m.value_of(name).and_then(|v| v.parse::<u32>().ok())
+}
+
 fn get_binds_from_input(m: &ArgMatches) -> Result<Option<ServiceBindList>> {
     match m.values_of("BIND") {
         Some(bind_strs) => {
@@ -499,5 +577,23 @@ fn update_svc_load_from_input(m: &ArgMatches, msg: &mut protocol::ctl::SvcLoad)
     msg.binding_mode = get_binding_mode_from_input(m).map(|v| v as i32);
     msg.topology = get_topology_from_input(m).map(|v| v as i32);
     msg.update_strategy = get_strategy_from_input(m).map(|v| v as i32);
+    msg.health_check_interval = get_health_check_value_from_input(m, "HEALTH_CHECK_INTERVAL");
+    msg.health_check_timeout = get_health_check_value_from_input(m, "HEALTH_CHECK_TIMEOUT");
+    msg.health_check_threshold = get_health_check_value_from_input(m, "HEALTH_CHECK_THRESHOLD");
+    msg.restart_on_critical = get_health_check_value_from_input(m, "RESTART_ON_CRITICAL");
+    msg.restart_backoff = get_health_check_value_from_input(m, "RESTART_BACKOFF");
+    msg.restart_limit = get_health_check_value_from_input(m, "RESTART_LIMIT");
+    msg.crash_window = get_health_check_value_from_input(m, "CRASH_WINDOW");
+    msg.crash_limit = get_health_check_value_from_input(m, "CRASH_LIMIT");
+    msg.crash_backoff = get_health_check_value_from_input(m, "CRASH_BACKOFF");
+    msg.update_canary = m.value_of("UPDATE_CANARY").map(str::to_string);
+    // There won't be errors, because we validate with `valid_numeric`
+    msg.update_batch_size = m
+        .value_of("UPDATE_BATCH_SIZE")
+        .and_then(|v| v.parse::<u32>().ok());
+    msg.update_soak = get_health_check_value_from_input(m, "UPDATE_SOAK");
+    msg.update_rollback_window = get_health_check_value_from_input(m, "UPDATE_ROLLBACK_WINDOW");
+    msg.update_window = m.value_of("UPDATE_WINDOW").map(str::to_string);
+    msg.update_spread_by = m.value_of("UPDATE_SPREAD_BY").map(str::to_string);
     Ok(())
 }
@@ -17,16 +17,19 @@ pub mod service;
 mod debug;
 mod events;
 mod file_watcher;
+mod peer_discovery;
 mod peer_watcher;
 mod periodic;
 mod self_updater;
 mod service_updater;
 mod spec_watcher;
 mod sys;
+mod update_window;
 mod user_config_watcher;
 
 use std;
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
@@ -42,6 +45,7 @@ use std::time::Duration;
 
 use butterfly;
 use butterfly::member::Member;
+use butterfly::message::RingKeys;
 use butterfly::server::timing::Timing;
 use butterfly::server::Suitability;
 use butterfly::trace::Trace;
@@ -49,7 +53,7 @@ use common::command::package::install::InstallSource;
 use common::ui::UIWriter;
 use futures::prelude::*;
 use futures::sync::mpsc;
-use hcore::crypto::SymKey;
+use hcore::crypto::{default_cache_key_path, SymKey};
 use hcore::env;
 use hcore::fs::FS_ROOT_PATH;
 use hcore::os::process::{self, Pid, Signal};
@@ -66,15 +70,18 @@ use time::{self, Duration as TimeDuration, Timespec};
 use tokio_core::reactor;
 use toml;
 
+use self::peer_discovery::PeerDiscoverer;
+pub use self::peer_discovery::PeerSource;
 use self::peer_watcher::PeerWatcher;
 use self::self_updater::{SelfUpdater, SUP_PKG_IDENT};
 pub use self::service::{
     CompositeSpec, Service, ServiceBind, ServiceSpec, Spec, Topology, UpdateStrategy,
 };
 use self::service::{DesiredState, IntoServiceSpec, Pkg, ProcessState};
-use self::service_updater::ServiceUpdater;
+use self::service_updater::{ServiceUpdater, UpdateRequest};
 use self::spec_watcher::{SpecWatcher, SpecWatcherEvent};
 pub use self::sys::Sys;
+pub use self::update_window::UpdateWindow;
 use self::user_config_watcher::UserConfigWatcher;
 use super::feat;
 use census::CensusRing;
@@ -101,6 +108,7 @@ pub struct FsCfg {
     pub butterfly_data_path: PathBuf,
     pub census_data_path: PathBuf,
     pub services_data_path: PathBuf,
+    pub ring_keys_data_path: PathBuf,
     pub sup_root: PathBuf,
 
     data_path: PathBuf,
@@ -121,6 +129,7 @@ impl FsCfg {
             butterfly_data_path: data_path.join("butterfly.dat"),
             census_data_path: data_path.join("census.dat"),
             services_data_path: data_path.join("services.dat"),
+            ring_keys_data_path: data_path.join("ring_keys.dat"),
             specs_path: sup_root.join("specs"),
             composites_path: sup_root.join("composites"),
             data_path: data_path,
@@ -143,6 +152,8 @@ pub struct ManagerConfig {
     pub eventsrv_group: Option<ServiceGroup>,
     pub update_url: String,
     pub update_channel: String,
+    /// Schedule of the windows in which automatic updates to the Supervisor may be applied.
+    pub update_window: Option<UpdateWindow>,
     pub gossip_listen: GossipListenAddr,
     pub ctl_listen: SocketAddr,
     pub http_listen: http_gateway::ListenAddr,
@@ -152,6 +163,18 @@ pub struct ManagerConfig {
     pub name: Option<String>,
     pub organization: Option<String>,
     pub watch_peer_file: Option<String>,
+    /// Where to discover peers from, when the ring needs seeding.
+    pub peer_sources: Vec<PeerSource>,
+    /// How many seconds pass between polls of the peer sources, at least 1.
+    pub peer_discovery_interval: u64,
+    /// How many seconds the rumors of dead members are kept before they are dropped, at most
+    /// `RUMOR_TTL_MAX_SECS`.
+    pub rumor_ttl: Option<u64>,
+    /// How many kilobytes per second of gossip traffic we send, at most. It must be at least 1;
+    /// leave it unset for no limit.
+    pub gossip_bandwidth_limit: Option<u64>,
+    /// Labels gossiped along with this Supervisor's membership, such as the zone it runs in.
+    pub labels: BTreeMap<String, String>,
 }
 
 impl ManagerConfig {
@@ -168,6 +191,7 @@ impl Default for ManagerConfig {
             eventsrv_group: None,
             update_url: "".to_string(),
             update_channel: "".to_string(),
+            update_window: None,
             gossip_listen: GossipListenAddr::default(),
             ctl_listen: protocol::ctl::default_addr(),
             http_listen: http_gateway::ListenAddr::default(),
@@ -177,6 +201,11 @@ impl Default for ManagerConfig {
             name: None,
             organization: None,
             watch_peer_file: None,
+            peer_sources: vec![],
+            peer_discovery_interval: peer_discovery::DEFAULT_INTERVAL_SECS,
+            rumor_ttl: None,
+            gossip_bandwidth_limit: None,
+            labels: BTreeMap::new(),
         }
     }
 }
@@ -185,18 +214,23 @@ pub struct ManagerState {
     /// The configuration used to instantiate this Manager instance
     pub cfg: ManagerConfig,
     pub services: Arc<RwLock<Vec<Service>>>,
+    /// The ring keys of our gossip server, shared with it so they can be rotated at runtime
+    pub ring_keys: RingKeys,
+    /// The census of the ring, shared so that control gateway requests can render a service's
+    /// templates against it
+    pub census_ring: RefCell<CensusRing>,
 }
 
 pub struct Manager {
     pub state: Rc<ManagerState>,
 
     butterfly: butterfly::Server,
-    census_ring: CensusRing,
     events_group: Option<ServiceGroup>,
     fs_cfg: Arc<FsCfg>,
     launcher: LauncherCli,
     updater: ServiceUpdater,
     peer_watcher: Option<PeerWatcher>,
+    peer_discoverer: Option<PeerDiscoverer>,
     spec_watcher: SpecWatcher,
     user_config_watcher: UserConfigWatcher,
     organization: Option<String>,
@@ -341,6 +375,7 @@ impl Manager {
                     current,
                     cfg.update_url,
                     cfg.update_channel,
+                    cfg.update_window,
                 ))
             } else {
                 warn!("Supervisor version not fully qualified, unable to start self-updater");
@@ -355,7 +390,8 @@ impl Manager {
             cfg.ctl_listen,
             cfg.http_listen,
         );
-        let member = Self::load_member(&mut sys, &fs_cfg)?;
+        let mut member = Self::load_member(&mut sys, &fs_cfg)?;
+        member.labels = cfg.labels.clone();
         let services = Arc::new(RwLock::new(Vec::new()));
         let server = butterfly::Server::new(
             sys.gossip_listen(),
@@ -367,6 +403,26 @@ impl Manager {
             Some(&fs_cfg.data_path),
             Box::new(SuitabilityLookup(services.clone())),
         )?;
+        if let Err(err) = restore_ring_keys(
+            &fs_cfg.ring_keys_data_path,
+            &server.ring_keys(),
+            &default_cache_key_path(None),
+        ) {
+            outputln!("Unable to restore ring keys, {}", err);
+        }
+        let gossip_bandwidth_limit = match cfg.gossip_bandwidth_limit {
+            Some(kb) => match kb.checked_mul(1024) {
+                Some(bytes) if bytes > 0 => Some(bytes),
+                _ => return Err(sup_error!(Error::InvalidGossipBandwidthLimit(kb))),
+            },
+            None => None,
+        };
+        server.bandwidth().set_limit(gossip_bandwidth_limit);
+        if let Some(ttl) = cfg.rumor_ttl {
+            if ttl > protocol::types::RUMOR_TTL_MAX_SECS {
+                return Err(sup_error!(Error::InvalidRumorTtl(ttl)));
+            }
+        }
         outputln!("Supervisor Member-ID {}", sys.member_id);
         for peer_addr in &cfg.gossip_peers {
             let mut peer = Member::default();
@@ -381,18 +437,30 @@ impl Manager {
         } else {
             None
         };
+        let peer_discoverer = if cfg.peer_sources.is_empty() {
+            None
+        } else if cfg.peer_discovery_interval == 0 {
+            return Err(sup_error!(Error::InvalidPeerDiscoveryInterval));
+        } else {
+            Some(PeerDiscoverer::run(
+                &cfg.peer_sources,
+                Duration::from_secs(cfg.peer_discovery_interval),
+            )?)
+        };
         Ok(Manager {
             state: Rc::new(ManagerState {
                 cfg: cfg_static,
                 services: services,
+                ring_keys: server.ring_keys(),
+                census_ring: RefCell::new(CensusRing::new(sys.member_id.clone())),
             }),
             self_updater: self_updater,
             updater: ServiceUpdater::new(server.clone()),
-            census_ring: CensusRing::new(sys.member_id.clone()),
             butterfly: server,
             events_group: cfg.eventsrv_group,
             launcher: launcher,
             peer_watcher: peer_watcher,
+            peer_discoverer: peer_discoverer,
             spec_watcher: SpecWatcher::run(&fs_cfg.specs_path)?,
             user_config_watcher: UserConfigWatcher::new(),
             fs_cfg: Arc::new(fs_cfg),
@@ -653,7 +721,12 @@ impl Manager {
             "Starting gossip-listener on {}",
             self.butterfly.gossip_addr()
         );
-        self.butterfly.start(Timing::default())?;
+        let mut timing = Timing::default();
+        if let Some(ttl) = self.state.cfg.rumor_ttl {
+            // The TTL was checked against `RUMOR_TTL_MAX_SECS` when we were loaded.
+            timing.rumor_ttl_ms = ttl as i64 * 1000;
+        }
+        self.butterfly.start(timing)?;
         debug!("gossip-listener started");
         self.persist_state();
         let http_listen_addr = self.sys.http_listen();
@@ -711,10 +784,11 @@ impl Manager {
             }
             self.update_running_services_from_spec_watcher()?;
             self.update_peers_from_watch_file()?;
+            self.update_peers_from_discovery();
             self.update_running_services_from_user_config_watcher();
             self.check_for_updated_packages();
             self.restart_elections();
-            self.census_ring.update_from_rumors(
+            self.state.census_ring.borrow_mut().update_from_rumors(
                 &self.butterfly.service_store,
                 &self.butterfly.election_store,
                 &self.butterfly.update_store,
@@ -722,16 +796,18 @@ impl Manager {
                 &self.butterfly.service_config_store,
                 &self.butterfly.service_file_store,
             );
+            self.update_partition();
 
             if self.check_for_changed_services() {
                 self.persist_state();
             }
 
-            if self.census_ring.changed() {
+            if self.state.census_ring.borrow().changed() {
                 self.persist_state();
+                let census_ring = self.state.census_ring.borrow();
                 events
                     .as_ref()
-                    .map(|events| events.try_connect(&self.census_ring));
+                    .map(|events| events.try_connect(&census_ring));
 
                 for service in self
                     .state
@@ -740,8 +816,7 @@ impl Manager {
                     .expect("Services lock is poisoned!")
                     .iter()
                 {
-                    if let Some(census_group) =
-                        self.census_ring.census_group_for(&service.service_group)
+                    if let Some(census_group) = census_ring.census_group_for(&service.service_group)
                     {
                         if let Some(member) = census_group.me() {
                             events
@@ -752,15 +827,18 @@ impl Manager {
                 }
             }
 
-            for service in self
-                .state
-                .services
-                .write()
-                .expect("Services lock is poisoned!")
-                .iter_mut()
             {
-                if service.tick(&self.census_ring, &self.launcher) {
-                    self.gossip_latest_service_rumor(&service);
+                let census_ring = self.state.census_ring.borrow();
+                for service in self
+                    .state
+                    .services
+                    .write()
+                    .expect("Services lock is poisoned!")
+                    .iter_mut()
+                {
+                    if service.tick(&census_ring, &self.launcher) {
+                        self.gossip_latest_service_rumor(&service);
+                    }
                 }
             }
             let time_to_wait = ((next_check - time::get_time()).num_milliseconds()).max(100);
@@ -796,11 +874,12 @@ impl Manager {
     }
 
     pub fn service_cfg_validate(
-        _mgr: &ManagerState,
+        mgr: &ManagerState,
         req: &mut CtlRequest,
         opts: protocol::ctl::SvcValidateCfg,
     ) -> NetResult<()> {
         let cfg = opts.cfg.ok_or(err_update_client())?;
+        let service_group: ServiceGroup = opts.service_group.ok_or(err_update_client())?.into();
         let format = opts
             .format
             .and_then(protocol::types::service_cfg::Format::from_i32)
@@ -817,44 +896,121 @@ impl Manager {
                 format!("Configuration format {} not available.", format),
             ));
         }
-        let _new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
+        let new_cfg: toml::value::Table = toml::from_slice(&cfg).map_err(|e| {
             net::err(
                 ErrCode::BadPayload,
                 format!("Unable to decode configuration as {}, {}", format, e),
             )
         })?;
+        // Services which aren't loaded in this Supervisor, but are known through rumor
+        // propagation, can't be validated; their packages may not even be installed here.
+        let services = mgr.services.read().expect("Services lock is poisoned!");
+        let service = match services
+            .iter()
+            .find(|service| service.service_group == service_group)
+        {
+            Some(service) => service,
+            None => {
+                return Err(net::err(
+                    ErrCode::NotFound,
+                    format!("Service not loaded, {}", service_group),
+                ))
+            }
+        };
+        // Applied configuration is gossiped, and takes precedence over every other layer.
+        let mut candidate = service.cfg.clone();
+        candidate.gossip = Some(new_cfg);
+        if let Some(errors) = candidate.validate() {
+            for error in errors {
+                req.reply_partial(net::err(ErrCode::InvalidPayload, error));
+            }
+        }
         req.reply_complete(net::ok());
         Ok(())
-        // JW TODO: Hold off on validation until we can validate services which aren't currently
-        // loaded in the Supervisor but are known through rumor propagation.
-        // let service_group: ServiceGroup = opts.service_group.into();
-        // for service in mgr.services.read().unwrap().iter() {
-        //     if service.service_group != service_group {
-        //         continue;
-        //     }
-        //     if let Some(interface) = service.cfg.interface() {
-        //         match Cfg::validate(interface, &new_cfg) {
-        //             None => req.reply_complete(net::ok()),
-        //             Some(errors) => {
-        //                 for error in errors {
-        //                     req.reply_partial(net::err(ErrCode::InvalidPayload, error));
-        //                 }
-        //                 req.reply_complete(net::ok());
-        //             }
-        //         }
-        //         return Ok(());
-        //     } else {
-        //         // No interface, this service can't be configured.
-        //         return Err(net::err(
-        //             ErrCode::NotFound,
-        //             "Service has no configurable attributes.",
-        //         ));
-        //     }
-        // }
-        // Err(net::err(
-        //     ErrCode::NotFound,
-        //     format!("Service not loaded, {}", service_group),
-        // ))
+    }
+
+    pub fn service_cfg_diff(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::SvcCfgDiff,
+    ) -> NetResult<()> {
+        let ident: PackageIdent = opts.ident.ok_or(err_update_client())?.into();
+        let format = opts
+            .format
+            .and_then(protocol::types::service_cfg::Format::from_i32)
+            .unwrap_or_default();
+        let pending: Option<toml::value::Table> = match opts.cfg {
+            Some(cfg) => {
+                if cfg.len() > protocol::butterfly::MAX_SVC_CFG_SIZE {
+                    return Err(net::err(
+                        ErrCode::EntityTooLarge,
+                        "Configuration too large.",
+                    ));
+                }
+                if format != protocol::types::service_cfg::Format::Toml {
+                    return Err(net::err(
+                        ErrCode::NotSupported,
+                        format!("Configuration format {} not available.", format),
+                    ));
+                }
+                Some(toml::from_slice(&cfg).map_err(|e| {
+                    net::err(
+                        ErrCode::BadPayload,
+                        format!("Unable to decode configuration as {}, {}", format, e),
+                    )
+                })?)
+            }
+            None => None,
+        };
+        let services = mgr.services.read().expect("Services lock is poisoned!");
+        let service = match services
+            .iter()
+            .find(|service| service.pkg.ident.satisfies(&ident))
+        {
+            Some(service) => service,
+            None => {
+                return Err(net::err(
+                    ErrCode::NotFound,
+                    format!("Service not loaded, {}", ident),
+                ))
+            }
+        };
+        let census_ring = mgr.census_ring.borrow();
+        let mut msg = protocol::types::ServiceCfgDiff::default();
+        msg.keys = service
+            .cfg
+            .layered()
+            .into_iter()
+            .map(|(key, (value, layer))| protocol::types::ServiceCfgKey {
+                key: Some(key),
+                value: Some(value.to_string()),
+                layer: Some(layer.to_string()),
+            })
+            .collect();
+        if let Some(pending) = pending {
+            let mut cfg = service.cfg.clone();
+            if opts.user.unwrap_or(false) {
+                cfg.user = Some(pending);
+            } else {
+                cfg.gossip = Some(pending);
+            }
+            if let Some(errors) = cfg.validate() {
+                for error in errors {
+                    req.reply_partial(net::err(ErrCode::InvalidPayload, error));
+                }
+            }
+            msg.files = service
+                .config_diff(&cfg, &census_ring)
+                .map_err(|e| net::err(ErrCode::Internal, e.to_string()))?
+                .into_iter()
+                .map(|(name, diff)| protocol::types::ServiceCfgFileDiff {
+                    name: Some(name),
+                    diff: Some(diff),
+                })
+                .collect();
+        }
+        req.reply_complete(msg);
+        Ok(())
     }
 
     pub fn service_cfg_set(
@@ -879,7 +1035,7 @@ impl Manager {
         );
         let mut client = match butterfly::client::Client::new(
             mgr.cfg.gossip_listen.local_addr(),
-            mgr.cfg.ring_key.clone(),
+            mgr.ring_keys.primary(),
         ) {
             Ok(client) => client,
             Err(err) => {
@@ -917,7 +1073,7 @@ impl Manager {
         );
         let mut client = match butterfly::client::Client::new(
             mgr.cfg.gossip_listen.local_addr(),
-            mgr.cfg.ring_key.clone(),
+            mgr.ring_keys.primary(),
         ) {
             Ok(client) => client,
             Err(err) => {
@@ -940,6 +1096,10 @@ impl Manager {
         opts: protocol::ctl::SvcLoad,
     ) -> NetResult<()> {
         let ident: PackageIdent = opts.ident.clone().ok_or(err_update_client())?.into();
+        if let Some(ref window) = opts.update_window {
+            update_window::parse_optional(window)
+                .map_err(|err| net::err(ErrCode::InvalidPayload, err.to_string()))?;
+        }
         let bldr_url = opts
             .bldr_url
             .clone()
@@ -1174,9 +1334,11 @@ impl Manager {
         opts: protocol::ctl::SvcStart,
     ) -> NetResult<()> {
         let ident = opts.ident.ok_or(err_update_client())?.into();
+        let mut loaded_idents = vec![];
         let updated_specs = match Self::existing_specs_for_ident(&mgr.cfg, &ident)? {
             Some(Spec::Service(mut spec)) => {
                 let mut updated_specs = vec![];
+                loaded_idents.push(spec.ident.clone());
                 if spec.desired_state == DesiredState::Down {
                     spec.desired_state = DesiredState::Up;
                     updated_specs.push(spec);
@@ -1186,6 +1348,7 @@ impl Manager {
             Some(Spec::Composite(_, service_specs)) => {
                 let mut updated_specs = vec![];
                 for mut spec in service_specs {
+                    loaded_idents.push(spec.ident.clone());
                     if spec.desired_state == DesiredState::Down {
                         spec.desired_state = DesiredState::Up;
                         updated_specs.push(spec);
@@ -1204,7 +1367,19 @@ impl Manager {
         for spec in updated_specs.iter() {
             Self::save_spec_for(&mgr.cfg, spec)?;
         }
-        if specs_changed {
+        // Services which were marked as failed after crash looping are already desired up, so
+        // starting them means clearing the failed state of their process.
+        let mut failed_cleared = false;
+        for service in mgr
+            .services
+            .write()
+            .expect("Services lock is poisoned!")
+            .iter_mut()
+            .filter(|s| loaded_idents.contains(&s.spec_ident))
+        {
+            failed_cleared = service.clear_failed() || failed_cleared;
+        }
+        if specs_changed || failed_cleared {
             // JW TODO: Change the language of the message below to "started" when we actually
             // synchronously control services from the ctl gateway.
             req.info(format!(
@@ -1264,6 +1439,128 @@ impl Manager {
         Ok(())
     }
 
+    pub fn service_update(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::SvcUpdate,
+    ) -> NetResult<()> {
+        let ident: PackageIdent = opts.ident.ok_or(err_update_client())?.into();
+        let request = match opts.to {
+            Some(to) => {
+                let to: PackageIdent = to.into();
+                if !to.fully_qualified() || !to.satisfies(&ident) {
+                    return Err(net::err(
+                        ErrCode::InvalidPayload,
+                        format!("{} is not a fully qualified release of {}", to, ident),
+                    ));
+                }
+                UpdateRequest::Release(to)
+            }
+            None => UpdateRequest::Latest,
+        };
+        let mut services = mgr.services.write().expect("Services lock is poisoned!");
+        let service = match services.iter_mut().find(|s| s.spec_ident == ident) {
+            Some(service) => service,
+            None => {
+                return Err(net::err(
+                    ErrCode::NotFound,
+                    format!("Service not loaded, {}", &ident),
+                ));
+            }
+        };
+        if service.update_strategy == UpdateStrategy::None {
+            return Err(net::err(
+                ErrCode::InvalidPayload,
+                format!("{} has no update strategy", &ident),
+            ));
+        }
+        if service.update_hold {
+            return Err(net::err(
+                ErrCode::Conflict,
+                format!(
+                    "{} is held at its current release, release it and try again",
+                    &ident
+                ),
+            ));
+        }
+        match request {
+            UpdateRequest::Latest => req.info(format!("Checking for an update to {}", &ident))?,
+            UpdateRequest::Release(ref to) => req.info(format!("Updating {} to {}", &ident, to))?,
+        }
+        service.update_request = Some(request);
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+
+    pub fn service_update_hold(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::SvcUpdateHold,
+    ) -> NetResult<()> {
+        let ident: PackageIdent = opts.ident.ok_or(err_update_client())?.into();
+        Self::set_update_hold(mgr, req, &ident, true)
+    }
+
+    pub fn service_update_release(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::SvcUpdateRelease,
+    ) -> NetResult<()> {
+        let ident: PackageIdent = opts.ident.ok_or(err_update_client())?.into();
+        Self::set_update_hold(mgr, req, &ident, false)
+    }
+
+    /// Hold or release the updates of a loaded service, or of every service in a loaded
+    /// composite, and persist the change to their spec files.
+    fn set_update_hold(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        ident: &PackageIdent,
+        hold: bool,
+    ) -> NetResult<()> {
+        let specs = match Self::existing_specs_for_ident(&mgr.cfg, ident)? {
+            Some(Spec::Service(spec)) => vec![spec],
+            Some(Spec::Composite(_, service_specs)) => service_specs,
+            None => {
+                return Err(net::err(
+                    ErrCode::NotFound,
+                    format!("Service not loaded, {}", ident),
+                ));
+            }
+        };
+        let updated_specs: Vec<ServiceSpec> = specs
+            .into_iter()
+            .filter(|spec| spec.update_hold != hold)
+            .map(|mut spec| {
+                spec.update_hold = hold;
+                spec
+            })
+            .collect();
+        // Running services are changed before their spec files are saved so the spec watcher
+        // finds them up to date and leaves their processes alone.
+        for service in mgr
+            .services
+            .write()
+            .expect("Services lock is poisoned!")
+            .iter_mut()
+            .filter(|s| updated_specs.iter().any(|spec| spec.ident == s.spec_ident))
+        {
+            service.update_hold = hold;
+        }
+        for spec in updated_specs.iter() {
+            Self::save_spec_for(&mgr.cfg, spec)?;
+        }
+        let message = match (hold, updated_specs.is_empty()) {
+            (true, false) => format!("Holding {} at its current release", ident),
+            (true, true) => format!("{} is already held at its current release", ident),
+            (false, false) => format!("Released {}; its updates will resume", ident),
+            (false, true) => format!("{} is not held", ident),
+        };
+        req.info(message)?;
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+
     pub fn supervisor_depart(
         mgr: &ManagerState,
         req: &mut CtlRequest,
@@ -1272,7 +1569,7 @@ impl Manager {
         let member_id = opts.member_id.ok_or(err_update_client())?;
         let mut client = match butterfly::client::Client::new(
             mgr.cfg.gossip_listen.local_addr(),
-            mgr.cfg.ring_key.clone(),
+            mgr.ring_keys.primary(),
         ) {
             Ok(client) => client,
             Err(err) => {
@@ -1290,6 +1587,70 @@ impl Manager {
         }
     }
 
+    pub fn ring_key_add(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::RingKeyAdd,
+    ) -> NetResult<()> {
+        let name = opts.name.ok_or(err_update_client())?;
+        let key = match load_ring_key(&name, &default_cache_key_path(None)) {
+            Ok(key) => key,
+            Err(err) => return Err(net::err(ErrCode::NotFound, err.to_string())),
+        };
+        let name_with_rev = key.name_with_rev();
+        if mgr.ring_keys.add(key) {
+            Self::persist_ring_keys(mgr)?;
+            outputln!("Accepting ring key {}", name_with_rev);
+            req.info(format!("Accepting ring key {}", name_with_rev))?;
+        } else {
+            req.info(format!("Ring key {} is already accepted", name_with_rev))?;
+        }
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+
+    pub fn ring_key_promote(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::RingKeyPromote,
+    ) -> NetResult<()> {
+        let name = opts.name.ok_or(err_update_client())?;
+        if let Err(err) = mgr.ring_keys.promote(&name) {
+            return Err(net::err(ErrCode::NotFound, err.to_string()));
+        }
+        Self::persist_ring_keys(mgr)?;
+        outputln!("Encrypting gossip with ring key {}", name);
+        req.info(format!("Encrypting gossip with ring key {}", name))?;
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+
+    pub fn ring_key_retire(
+        mgr: &ManagerState,
+        req: &mut CtlRequest,
+        opts: protocol::ctl::RingKeyRetire,
+    ) -> NetResult<()> {
+        let name = opts.name.ok_or(err_update_client())?;
+        match mgr.ring_keys.retire(&name) {
+            Ok(()) => {}
+            Err(err @ butterfly::error::Error::RingKeyIsPrimary(_)) => {
+                return Err(net::err(ErrCode::Conflict, err.to_string()));
+            }
+            Err(err) => return Err(net::err(ErrCode::NotFound, err.to_string())),
+        }
+        Self::persist_ring_keys(mgr)?;
+        outputln!("Retired ring key {}", name);
+        req.info(format!("Retired ring key {}", name))?;
+        req.reply_complete(net::ok());
+        Ok(())
+    }
+
+    fn persist_ring_keys(mgr: &ManagerState) -> NetResult<()> {
+        let fs_cfg = FsCfg::new(mgr.cfg.sup_root());
+        write_ring_keys(&fs_cfg.ring_keys_data_path, &mgr.ring_keys)
+            .map_err(|err| net::err(ErrCode::Internal, err.to_string()))
+    }
+
     fn check_for_updated_supervisor(&mut self) -> Option<PackageInstall> {
         if let Some(ref mut updater) = self.self_updater {
             return updater.updated();
@@ -1304,6 +1665,7 @@ impl Manager {
     /// The run loop's last updated census is a required parameter on this function to inform the
     /// main loop that we, ourselves, updated the service counter when we updated ourselves.
     fn check_for_updated_packages(&mut self) {
+        let census_ring = self.state.census_ring.borrow();
         for service in self
             .state
             .services
@@ -1313,7 +1675,7 @@ impl Manager {
         {
             if self
                 .updater
-                .check_for_updated_package(service, &self.census_ring, &self.launcher)
+                .check_for_updated_package(service, &census_ring, &self.launcher)
             {
                 self.gossip_latest_service_rumor(&service);
             }
@@ -1396,7 +1758,11 @@ impl Manager {
         };
         let mut writer = BufWriter::new(file);
         if let Some(err) = writer
-            .write(serde_json::to_string(&self.census_ring).unwrap().as_bytes())
+            .write(
+                serde_json::to_string(&*self.state.census_ring.borrow())
+                    .unwrap()
+                    .as_bytes(),
+            )
             .err()
         {
             warn!("Couldn't write to census state file, {}", err);
@@ -1558,6 +1924,38 @@ impl Manager {
         self.butterfly.restart_elections();
     }
 
+    /// Pass the network partition butterfly suspects on to the census, which pauses updates in
+    /// the service groups it affects. Butterfly itself holds off on restarting their elections.
+    fn update_partition(&mut self) {
+        let partition = self.butterfly.partition();
+        match (
+            self.state.census_ring.borrow().partition(),
+            partition.as_ref(),
+        ) {
+            (None, Some(partition)) => {
+                let groups: Vec<&str> = partition
+                    .service_groups
+                    .iter()
+                    .map(|sg| sg.as_str())
+                    .collect();
+                outputln!(
+                    "Suspected network partition; {} members lost. Pausing elections and updates \
+                     in: {}",
+                    partition.lost.len(),
+                    groups.join(", ")
+                );
+            }
+            (Some(_), None) => {
+                outputln!("Network partition cleared; resuming elections and updates");
+            }
+            _ => (),
+        }
+        self.state
+            .census_ring
+            .borrow_mut()
+            .update_partition(partition);
+    }
+
     fn shutdown(&mut self, cause: ShutdownReason) {
         outputln!("Gracefully departing from butterfly network.");
         self.butterfly.set_departed();
@@ -1642,6 +2040,18 @@ impl Manager {
         }
     }
 
+    fn update_peers_from_discovery(&mut self) {
+        if !self.butterfly.need_peer_seeding() {
+            return;
+        }
+        if let Some(ref discoverer) = self.peer_discoverer {
+            let members = discoverer.members();
+            if !members.is_empty() {
+                self.butterfly.member_list.set_initial_members(members);
+            }
+        }
+    }
+
     fn update_running_services_from_user_config_watcher(&mut self) {
         let mut services = self
             .state
@@ -1715,6 +2125,10 @@ pub struct ServiceStatus {
     pub service_group: ServiceGroup,
     pub composite: Option<String>,
     pub desired_state: DesiredState,
+    #[serde(default)]
+    pub held_update: Option<PackageIdent>,
+    #[serde(default)]
+    pub update_hold: bool,
 }
 
 impl fmt::Display for ServiceStatus {
@@ -1777,6 +2191,69 @@ where
     d.deserialize_u64(FromTimespec)
 }
 
+/// The names, with revisions, of the ring keys a Supervisor encrypts with and accepts. They are
+/// written to disk whenever they are changed through the control gateway, so a restarted
+/// Supervisor keeps using them.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct RingKeyNames {
+    primary: Option<String>,
+    accepted: Vec<String>,
+}
+
+/// Load a ring key from the key cache, by its name with revision, or by its name alone for the
+/// latest revision.
+fn load_ring_key(name: &str, cache: &Path) -> Result<SymKey> {
+    match SymKey::get_pair_for(name, cache) {
+        Ok(key) => Ok(key),
+        Err(_) => Ok(SymKey::get_latest_pair_for(name, cache)?),
+    }
+}
+
+fn write_ring_keys(path: &Path, keys: &RingKeys) -> Result<()> {
+    let names = RingKeyNames {
+        primary: keys.primary().map(|key| key.name_with_rev()),
+        accepted: keys.accepted(),
+    };
+    let tmp_file = path.with_extension("dat.tmp");
+    let mut file =
+        File::create(&tmp_file).map_err(|e| sup_error!(Error::BadDataFile(tmp_file.clone(), e)))?;
+    serde_json::to_writer(&mut file, &names)
+        .map_err(|e| sup_error!(Error::RingKeysFile(path.to_path_buf(), e.to_string())))?;
+    fs::rename(&tmp_file, path).map_err(|e| sup_error!(Error::BadDataFile(path.to_path_buf(), e)))
+}
+
+/// Restore the ring keys written by a previous run of the Supervisor, loading each one from the
+/// key cache. Keys of another ring than the one the Supervisor was started with are ignored, and
+/// the key it was started with stays accepted even if it is no longer the primary key. A
+/// Supervisor started without a ring key restores every key, since keys may be added to an
+/// unencrypted ring as well.
+fn restore_ring_keys(path: &Path, keys: &RingKeys, cache: &Path) -> Result<()> {
+    let ring = keys.primary().map(|key| key.name);
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(sup_error!(Error::BadDataFile(path.to_path_buf(), e))),
+    };
+    let names: RingKeyNames = serde_json::from_reader(file)
+        .map_err(|e| sup_error!(Error::RingKeysFile(path.to_path_buf(), e.to_string())))?;
+    for name in names.accepted.iter().chain(names.primary.iter()) {
+        match SymKey::get_pair_for(name, cache) {
+            Ok(key) => {
+                if ring.as_ref().map_or(true, |ring| key.name == *ring) {
+                    keys.add(key);
+                }
+            }
+            Err(err) => outputln!("Unable to load ring key {}, {}", name, err),
+        }
+    }
+    if let Some(primary) = names.primary {
+        if keys.promote(&primary).is_ok() {
+            outputln!("Encrypting gossip with ring key {}", primary);
+        }
+    }
+    Ok(())
+}
+
 fn obtain_process_lock(fs_cfg: &FsCfg) -> Result<()> {
     match write_process_lock(&fs_cfg.proc_lock_file) {
         Ok(()) => Ok(()),
@@ -1940,6 +2417,7 @@ impl From<service::ServiceBind> for protocol::types::ServiceBind {
         let mut proto = protocol::types::ServiceBind::default();
         proto.name = bind.name;
         proto.service_group = bind.service_group.into();
+        proto.labels = bind.labels.into_iter().collect();
         proto
     }
 }
@@ -1954,6 +2432,8 @@ impl From<ServiceStatus> for protocol::types::ServiceStatus {
             proto.composite = Some(composite);
         }
         proto.desired_state = Some(other.desired_state.into());
+        proto.held_update = other.held_update.map(Into::into);
+        proto.update_hold = Some(other.update_hold);
         proto
     }
 }
@@ -1964,6 +2444,7 @@ impl Into<service::ServiceBind> for protocol::types::ServiceBind {
             name: self.name,
             service_group: self.service_group.into(),
             service_name: self.service_name,
+            labels: self.labels.into_iter().collect(),
         }
     }
 }
@@ -1980,10 +2461,15 @@ impl From<SpecDesiredState> for i32 {
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
 
+    use butterfly::message::RingKeys;
+    use hcore::crypto::SymKey;
     use protocol::STATE_PATH_PREFIX;
+    use tempdir::TempDir;
 
-    use super::ManagerConfig;
+    use super::{restore_ring_keys, write_ring_keys, ManagerConfig};
 
     #[test]
     fn manager_state_path_default() {
@@ -2026,4 +2512,50 @@ mod test {
 
         assert_eq!(PathBuf::from("/tmp/partay"), path);
     }
+
+    #[test]
+    fn ring_keys_are_restored_after_a_restart() {
+        let cache = TempDir::new("cache").unwrap();
+        let data = TempDir::new("data").unwrap();
+        let path = data.path().join("ring_keys.dat");
+        let old = SymKey::generate_pair_for_ring("beyonce").unwrap();
+        old.to_pair_files(cache.path()).unwrap();
+        // Key revisions only have a resolution of one second
+        thread::sleep(Duration::from_millis(1100));
+        let new = SymKey::generate_pair_for_ring("beyonce").unwrap();
+        new.to_pair_files(cache.path()).unwrap();
+        let other = SymKey::generate_pair_for_ring("jayz").unwrap();
+        other.to_pair_files(cache.path()).unwrap();
+
+        let keys = RingKeys::new(Some(new.clone()));
+        keys.add(old.clone());
+        keys.add(other.clone());
+        keys.promote(&old.name_with_rev()).unwrap();
+        write_ring_keys(&path, &keys).unwrap();
+
+        let restarted = RingKeys::new(Some(new.clone()));
+        restore_ring_keys(&path, &restarted, cache.path()).unwrap();
+
+        assert_eq!(
+            restarted.primary().map(|key| key.name_with_rev()),
+            Some(old.name_with_rev())
+        );
+        assert_eq!(restarted.accepted(), vec![new.name_with_rev()]);
+    }
+
+    #[test]
+    fn ring_keys_are_not_restored_without_a_file() {
+        let cache = TempDir::new("cache").unwrap();
+        let data = TempDir::new("data").unwrap();
+        let key = SymKey::generate_pair_for_ring("beyonce").unwrap();
+        let keys = RingKeys::new(Some(key.clone()));
+
+        restore_ring_keys(&data.path().join("ring_keys.dat"), &keys, cache.path()).unwrap();
+
+        assert_eq!(
+            keys.primary().map(|key| key.name_with_rev()),
+            Some(key.name_with_rev())
+        );
+        assert!(keys.accepted().is_empty());
+    }
 }
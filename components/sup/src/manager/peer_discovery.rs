@@ -0,0 +1,596 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Discover the peers to seed the gossip ring with, in environments where they aren't known up
+//! front. Every source of peers implements `PeerDiscovery`; a `PeerDiscoverer` polls all of them
+//! on an interval from its own thread, and keeps the latest peers they found for the Manager to
+//! seed the ring with.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, Builder as ThreadBuilder};
+use std::time::{Duration, Instant};
+
+use butterfly::member::Member;
+use trust_dns_resolver::Resolver as DnsResolver;
+
+use config::GOSSIP_DEFAULT_PORT;
+use error::{Error, Result};
+
+static LOGKEY: &'static str = "PD";
+
+/// How often peers are discovered, unless configured otherwise.
+pub const DEFAULT_INTERVAL_SECS: u64 = 30;
+/// How long an executable peer source may run before it is killed.
+const EXEC_TIMEOUT_SECS: u64 = 10;
+/// How often a running executable peer source is checked for having exited.
+const EXEC_POLL_MILLIS: u64 = 50;
+
+/// Where to discover peers from, as given on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PeerSource {
+    /// The A records of a DNS name; the peers listen on the default gossip port.
+    DnsA(String),
+    /// The SRV records of a DNS name, which give the host and gossip port of each peer.
+    DnsSrv(String),
+    /// A directory of files, each listing one peer per line.
+    Directory(PathBuf),
+    /// An executable which prints one peer per line.
+    Exec(PathBuf),
+}
+
+impl PeerSource {
+    fn discovery(&self) -> Result<Box<PeerDiscovery>> {
+        let discovery: Box<PeerDiscovery> = match *self {
+            PeerSource::DnsA(ref name) => {
+                Box::new(DnsDiscovery::a(name.clone(), SystemResolver::new()?))
+            }
+            PeerSource::DnsSrv(ref name) => {
+                Box::new(DnsDiscovery::srv(name.clone(), SystemResolver::new()?))
+            }
+            PeerSource::Directory(ref path) => Box::new(DirectoryDiscovery::new(path.clone())),
+            PeerSource::Exec(ref path) => Box::new(ExecDiscovery::new(
+                path.clone(),
+                Duration::from_secs(EXEC_TIMEOUT_SECS),
+            )),
+        };
+        Ok(discovery)
+    }
+}
+
+impl fmt::Display for PeerSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PeerSource::DnsA(ref name) => write!(f, "DNS A records of {}", name),
+            PeerSource::DnsSrv(ref name) => write!(f, "DNS SRV records of {}", name),
+            PeerSource::Directory(ref path) => write!(f, "peer files in {}", path.display()),
+            PeerSource::Exec(ref path) => write!(f, "output of {}", path.display()),
+        }
+    }
+}
+
+/// A source of peers.
+pub trait PeerDiscovery: Send {
+    /// The peers currently known to this source.
+    fn discover(&mut self) -> Result<Vec<Member>>;
+}
+
+/// A host and port, as found in a DNS SRV record.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SrvTarget {
+    pub host: String,
+    pub port: u16,
+}
+
+/// The DNS lookups peer discovery needs. Tests can stand in for a real resolver.
+pub trait Resolver: Send {
+    fn lookup_a(&self, name: &str) -> io::Result<Vec<IpAddr>>;
+    fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvTarget>>;
+}
+
+/// Resolves names with the system's DNS configuration.
+pub struct SystemResolver(DnsResolver);
+
+impl SystemResolver {
+    pub fn new() -> Result<Self> {
+        let resolver = DnsResolver::from_system_conf().map_err(|e| sup_error!(Error::Io(e)))?;
+        Ok(SystemResolver(resolver))
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn lookup_a(&self, name: &str) -> io::Result<Vec<IpAddr>> {
+        self.0
+            .lookup_ip(name)
+            .map(|lookup| lookup.iter().collect())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+
+    fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvTarget>> {
+        self.0
+            .lookup_srv(name)
+            .map(|lookup| {
+                lookup
+                    .iter()
+                    .map(|srv| SrvTarget {
+                        host: srv.target().to_utf8().trim_right_matches('.').to_string(),
+                        port: srv.port(),
+                    })
+                    .collect()
+            })
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+}
+
+/// Peers from the A or SRV records of a DNS name.
+pub struct DnsDiscovery<R: Resolver> {
+    name: String,
+    srv: bool,
+    resolver: R,
+}
+
+impl<R: Resolver> DnsDiscovery<R> {
+    pub fn a(name: String, resolver: R) -> Self {
+        DnsDiscovery {
+            name: name,
+            srv: false,
+            resolver: resolver,
+        }
+    }
+
+    pub fn srv(name: String, resolver: R) -> Self {
+        DnsDiscovery {
+            name: name,
+            srv: true,
+            resolver: resolver,
+        }
+    }
+}
+
+impl<R: Resolver> PeerDiscovery for DnsDiscovery<R> {
+    fn discover(&mut self) -> Result<Vec<Member>> {
+        let mut members = vec![];
+        if self.srv {
+            let targets = self
+                .resolver
+                .lookup_srv(&self.name)
+                .map_err(|e| sup_error!(Error::NameLookup(e)))?;
+            for target in targets {
+                // One target which doesn't resolve shouldn't hide the others
+                let ips = match self.resolver.lookup_a(&target.host) {
+                    Ok(ips) => ips,
+                    Err(err) => {
+                        outputln!(
+                            "Skipping SRV target {} of {}, {}",
+                            target.host,
+                            self.name,
+                            err
+                        );
+                        continue;
+                    }
+                };
+                members.extend(
+                    ips.into_iter()
+                        .map(|ip| member_for(SocketAddr::new(ip, target.port))),
+                );
+            }
+        } else {
+            let ips = self
+                .resolver
+                .lookup_a(&self.name)
+                .map_err(|e| sup_error!(Error::NameLookup(e)))?;
+            members.extend(
+                ips.into_iter()
+                    .map(|ip| member_for(SocketAddr::new(ip, GOSSIP_DEFAULT_PORT))),
+            );
+        }
+        Ok(members)
+    }
+}
+
+/// Peers from every file in a directory, in the format of the peer watch file: one `IP[:PORT]`
+/// per line. Hidden files are skipped, so files can be written next to their final name and
+/// moved into place.
+pub struct DirectoryDiscovery {
+    path: PathBuf,
+}
+
+impl DirectoryDiscovery {
+    pub fn new(path: PathBuf) -> Self {
+        DirectoryDiscovery { path: path }
+    }
+}
+
+impl PeerDiscovery for DirectoryDiscovery {
+    fn discover(&mut self) -> Result<Vec<Member>> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .map_or(true, |name| name.to_string_lossy().starts_with('.'));
+            if path.is_file() && !hidden {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        let mut members = vec![];
+        for path in paths {
+            members.extend(members_from_lines(&fs::read_to_string(&path)?));
+        }
+        Ok(members)
+    }
+}
+
+/// Peers printed by an executable, one `IP[:PORT]` per line. The executable is killed if it runs
+/// for longer than its timeout, so a hung one can't stall discovery.
+pub struct ExecDiscovery {
+    path: PathBuf,
+    timeout: Duration,
+}
+
+impl ExecDiscovery {
+    pub fn new(path: PathBuf, timeout: Duration) -> Self {
+        ExecDiscovery {
+            path: path,
+            timeout: timeout,
+        }
+    }
+}
+
+impl PeerDiscovery for ExecDiscovery {
+    fn discover(&mut self) -> Result<Vec<Member>> {
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        // Read the output as it comes, so the executable can't block on a full pipe
+        let mut stdout = child.stdout.take().expect("Child stdout is piped");
+        let reader = thread::spawn(move || {
+            let mut output = vec![];
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+        let started = Instant::now();
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if started.elapsed() >= self.timeout {
+                // The reader thread finishes once the killed executable's stdout is closed
+                child.kill()?;
+                child.wait()?;
+                return Err(sup_error!(Error::PeerDiscoveryTimeout(
+                    self.path.clone(),
+                    self.timeout.as_secs()
+                )));
+            }
+            thread::sleep(Duration::from_millis(EXEC_POLL_MILLIS));
+        };
+        let output = reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::Other, "reader panicked")))?;
+        if !status.success() {
+            return Err(sup_error!(Error::PeerDiscoveryExec(
+                self.path.clone(),
+                status.code()
+            )));
+        }
+        Ok(members_from_lines(&String::from_utf8(output)?))
+    }
+}
+
+/// Polls every peer source on an interval, and keeps the peers found in the last round.
+pub struct PeerDiscoverer {
+    members: Arc<RwLock<Vec<Member>>>,
+}
+
+impl PeerDiscoverer {
+    pub fn run(sources: &[PeerSource], interval: Duration) -> Result<Self> {
+        let mut discoveries = vec![];
+        for source in sources {
+            discoveries.push((source.to_string(), source.discovery()?));
+        }
+        let members = Arc::new(RwLock::new(Vec::new()));
+        let members_for_thread = Arc::clone(&members);
+        ThreadBuilder::new()
+            .name("peer-discovery".to_string())
+            .spawn(move || loop {
+                let found = Self::poll(&mut discoveries);
+                // Keep the peers we had if every source came up empty
+                if !found.is_empty() {
+                    *members_for_thread
+                        .write()
+                        .expect("Discovered peers lock is poisoned") = found;
+                }
+                thread::sleep(interval);
+            })?;
+        Ok(PeerDiscoverer { members: members })
+    }
+
+    fn poll(discoveries: &mut [(String, Box<PeerDiscovery>)]) -> Vec<Member> {
+        let mut found: Vec<Member> = vec![];
+        for &mut (ref name, ref mut discovery) in discoveries.iter_mut() {
+            match discovery.discover() {
+                Ok(members) => {
+                    debug!("Discovered {} peers from {}", members.len(), name);
+                    for member in members {
+                        let known = found.iter().any(|m| {
+                            m.address == member.address && m.gossip_port == member.gossip_port
+                        });
+                        if !known {
+                            found.push(member);
+                        }
+                    }
+                }
+                Err(err) => outputln!("Failed to discover peers from {}, {}", name, err),
+            }
+        }
+        found
+    }
+
+    /// The peers found in the last round in which any were found.
+    pub fn members(&self) -> Vec<Member> {
+        self.members
+            .read()
+            .expect("Discovered peers lock is poisoned")
+            .clone()
+    }
+}
+
+/// Parse peers given one `IP[:PORT]` per line, skipping blank lines and comments. Lines which
+/// don't resolve to an address are skipped as well, so one bad line doesn't lose every peer.
+pub fn members_from_lines(lines: &str) -> Vec<Member> {
+    let mut members = vec![];
+    for line in lines.lines() {
+        let peer = line.trim();
+        if peer.is_empty() || peer.starts_with('#') {
+            continue;
+        }
+        match member_from_peer(peer) {
+            Ok(member) => members.push(member),
+            Err(err) => outputln!("Skipping peer {}, {}", peer, err),
+        }
+    }
+    members
+}
+
+/// The member listening at an `IP[:PORT]` address, on the default gossip port if none is given.
+pub fn member_from_peer(peer: &str) -> Result<Member> {
+    let peer_addr = if peer.find(':').is_some() {
+        peer.to_string()
+    } else {
+        format!("{}:{}", peer, GOSSIP_DEFAULT_PORT)
+    };
+    let addrs: Vec<SocketAddr> = match peer_addr.to_socket_addrs() {
+        Ok(addrs) => addrs.collect(),
+        Err(e) => {
+            outputln!("Failed to resolve peer: {}", peer_addr);
+            return Err(sup_error!(Error::NameLookup(e)));
+        }
+    };
+    match addrs.first() {
+        Some(addr) => Ok(member_for(*addr)),
+        None => Err(sup_error!(Error::NameLookup(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} has no addresses", peer_addr),
+        )))),
+    }
+}
+
+fn member_for(addr: SocketAddr) -> Member {
+    let mut member = Member::default();
+    member.address = format!("{}", addr.ip());
+    member.swim_port = addr.port() as i32;
+    member.gossip_port = addr.port() as i32;
+    member
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::io::{self, Write};
+    use std::net::IpAddr;
+
+    use tempdir::TempDir;
+
+    use super::*;
+    use config::GOSSIP_DEFAULT_PORT;
+
+    /// Answers lookups from a fixed table instead of DNS.
+    #[derive(Default)]
+    struct StaticResolver {
+        a: HashMap<String, Vec<IpAddr>>,
+        srv: HashMap<String, Vec<SrvTarget>>,
+    }
+
+    impl Resolver for StaticResolver {
+        fn lookup_a(&self, name: &str) -> io::Result<Vec<IpAddr>> {
+            self.a
+                .get(name)
+                .cloned()
+                .ok_or(io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+        }
+
+        fn lookup_srv(&self, name: &str) -> io::Result<Vec<SrvTarget>> {
+            self.srv
+                .get(name)
+                .cloned()
+                .ok_or(io::Error::new(io::ErrorKind::NotFound, name.to_string()))
+        }
+    }
+
+    fn addresses(members: &[Member]) -> Vec<(String, i32)> {
+        members
+            .iter()
+            .map(|member| (member.address.clone(), member.gossip_port))
+            .collect()
+    }
+
+    #[test]
+    fn dns_a_records_use_the_default_port() {
+        let mut resolver = StaticResolver::default();
+        resolver.a.insert(
+            "peers.example.com".to_string(),
+            vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+        );
+        let mut discovery = DnsDiscovery::a("peers.example.com".to_string(), resolver);
+        let port = GOSSIP_DEFAULT_PORT as i32;
+        assert_eq!(
+            addresses(&discovery.discover().unwrap()),
+            vec![
+                ("10.0.0.1".to_string(), port),
+                ("10.0.0.2".to_string(), port)
+            ]
+        );
+    }
+
+    #[test]
+    fn dns_srv_records_give_hosts_and_ports() {
+        let mut resolver = StaticResolver::default();
+        resolver.srv.insert(
+            "_gossip._udp.example.com".to_string(),
+            vec![
+                SrvTarget {
+                    host: "sup-0.example.com".to_string(),
+                    port: 9000,
+                },
+                SrvTarget {
+                    host: "sup-1.example.com".to_string(),
+                    port: 9001,
+                },
+            ],
+        );
+        resolver.a.insert(
+            "sup-0.example.com".to_string(),
+            vec!["10.0.0.1".parse().unwrap()],
+        );
+        resolver.a.insert(
+            "sup-1.example.com".to_string(),
+            vec!["10.0.0.2".parse().unwrap()],
+        );
+        let mut discovery = DnsDiscovery::srv("_gossip._udp.example.com".to_string(), resolver);
+        assert_eq!(
+            addresses(&discovery.discover().unwrap()),
+            vec![
+                ("10.0.0.1".to_string(), 9000),
+                ("10.0.0.2".to_string(), 9001),
+            ]
+        );
+    }
+
+    #[test]
+    fn dns_srv_targets_which_do_not_resolve_are_skipped() {
+        let mut resolver = StaticResolver::default();
+        resolver.srv.insert(
+            "_gossip._udp.example.com".to_string(),
+            vec![
+                SrvTarget {
+                    host: "gone.example.com".to_string(),
+                    port: 9000,
+                },
+                SrvTarget {
+                    host: "sup-1.example.com".to_string(),
+                    port: 9001,
+                },
+            ],
+        );
+        resolver.a.insert(
+            "sup-1.example.com".to_string(),
+            vec!["10.0.0.2".parse().unwrap()],
+        );
+        let mut discovery = DnsDiscovery::srv("_gossip._udp.example.com".to_string(), resolver);
+        assert_eq!(
+            addresses(&discovery.discover().unwrap()),
+            vec![("10.0.0.2".to_string(), 9001)]
+        );
+    }
+
+    #[test]
+    fn dns_failures_are_errors() {
+        let mut discovery =
+            DnsDiscovery::a("missing.example.com".to_string(), StaticResolver::default());
+        assert!(discovery.discover().is_err());
+    }
+
+    #[test]
+    fn directory_reads_every_visible_file_and_skips_bad_lines() {
+        let tmpdir = TempDir::new("peerdiscoverytest").unwrap();
+        let mut file = File::create(tmpdir.path().join("a")).unwrap();
+        writeln!(file, "1.2.3.4:5").unwrap();
+        writeln!(file, "# a comment").unwrap();
+        let mut file = File::create(tmpdir.path().join("b")).unwrap();
+        writeln!(file, "4.3.2.1").unwrap();
+        writeln!(file, "4.3.2.1:notaport").unwrap();
+        let mut file = File::create(tmpdir.path().join(".b.tmp")).unwrap();
+        writeln!(file, "9.9.9.9").unwrap();
+
+        let mut discovery = DirectoryDiscovery::new(tmpdir.path().to_path_buf());
+        assert_eq!(
+            addresses(&discovery.discover().unwrap()),
+            vec![
+                ("1.2.3.4".to_string(), 5),
+                ("4.3.2.1".to_string(), GOSSIP_DEFAULT_PORT as i32),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exec_reads_the_output() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmpdir = TempDir::new("peerdiscoverytest").unwrap();
+        let path = tmpdir.path().join("peers.sh");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "#!/bin/sh\necho 1.2.3.4:5\necho 4.3.2.1:6").unwrap();
+        drop(file);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut discovery = ExecDiscovery::new(path, Duration::from_secs(10));
+        assert_eq!(
+            addresses(&discovery.discover().unwrap()),
+            vec![("1.2.3.4".to_string(), 5), ("4.3.2.1".to_string(), 6)]
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn exec_is_killed_when_it_times_out() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmpdir = TempDir::new("peerdiscoverytest").unwrap();
+        let path = tmpdir.path().join("peers.sh");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "#!/bin/sh\necho 1.2.3.4:5\nexec sleep 30").unwrap();
+        drop(file);
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut discovery = ExecDiscovery::new(path, Duration::from_millis(200));
+        let started = Instant::now();
+        match discovery.discover() {
+            Err(e) => match e.err {
+                Error::PeerDiscoveryTimeout(_, _) => (),
+                _ => panic!("Should fail with Error::PeerDiscoveryTimeout"),
+            },
+            Ok(_) => panic!("Should not wait for a hung executable"),
+        }
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -14,16 +14,15 @@
 
 use std::fs::File;
 use std::io::{BufRead, BufReader};
-use std::net::{SocketAddr, ToSocketAddrs};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::Builder as ThreadBuilder;
 
 use butterfly::member::Member;
-use config::GOSSIP_DEFAULT_PORT;
 use error::{Error, Result};
 use manager::file_watcher::{default_file_watcher, Callbacks};
+use manager::peer_discovery::member_from_peer;
 
 static LOGKEY: &'static str = "PW";
 
@@ -134,24 +133,7 @@ impl PeerWatcher {
         let mut members: Vec<Member> = Vec::new();
         for line in reader.lines() {
             if let Ok(peer) = line {
-                let peer_addr = if peer.find(':').is_some() {
-                    peer
-                } else {
-                    format!("{}:{}", peer, GOSSIP_DEFAULT_PORT)
-                };
-                let addrs: Vec<SocketAddr> = match peer_addr.to_socket_addrs() {
-                    Ok(addrs) => addrs.collect(),
-                    Err(e) => {
-                        outputln!("Failed to resolve peer: {}", peer_addr);
-                        return Err(sup_error!(Error::NameLookup(e)));
-                    }
-                };
-                let addr: SocketAddr = addrs[0];
-                let mut member = Member::default();
-                member.address = format!("{}", addr.ip());
-                member.swim_port = addr.port() as i32;
-                member.gossip_port = addr.port() as i32;
-                members.push(member);
+                members.push(member_from_peer(&peer)?);
             }
         }
         self.have_events.store(false, Ordering::Relaxed);
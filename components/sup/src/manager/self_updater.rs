@@ -25,6 +25,7 @@ use common::command::package::install::InstallSource;
 use common::ui::UI;
 use env;
 use hcore::package::{PackageIdent, PackageInstall};
+use manager::UpdateWindow;
 use util;
 
 pub const SUP_PKG_IDENT: &'static str = "core/hab-sup";
@@ -36,19 +37,28 @@ pub struct SelfUpdater {
     current: PackageIdent,
     update_url: String,
     update_channel: String,
+    window: Option<UpdateWindow>,
+    held: Option<PackageInstall>,
 }
 
 // TODO (CM): Want to use the Periodic trait here, but can't due to
 // how things are currently structured (The service updater had a worker)
 
 impl SelfUpdater {
-    pub fn new(current: PackageIdent, update_url: String, update_channel: String) -> Self {
+    pub fn new(
+        current: PackageIdent,
+        update_url: String,
+        update_channel: String,
+        window: Option<UpdateWindow>,
+    ) -> Self {
         let rx = Self::init(current.clone(), update_url.clone(), update_channel.clone());
         SelfUpdater {
             rx: rx,
             current: current,
             update_url: update_url,
             update_channel: update_channel,
+            window: window,
+            held: None,
         }
     }
 
@@ -110,9 +120,26 @@ impl SelfUpdater {
         }
     }
 
+    /// Returns a newer Supervisor package once one has been installed and the update window, if
+    /// any, is open. Packages found outside of the window are held until it opens.
     pub fn updated(&mut self) -> Option<PackageInstall> {
+        if self.held.is_some() {
+            return self.release_held();
+        }
         match self.rx.try_recv() {
-            Ok(package) => Some(package),
+            Ok(package) => {
+                if let Some(ref window) = self.window {
+                    if !window.is_open() {
+                        info!(
+                            "Holding Supervisor update to {} until the update window '{}' opens",
+                            package.ident(),
+                            window
+                        );
+                    }
+                }
+                self.held = Some(package);
+                self.release_held()
+            }
             Err(TryRecvError::Empty) => None,
             Err(TryRecvError::Disconnected) => {
                 debug!("Self updater has died, restarting...");
@@ -125,6 +152,14 @@ impl SelfUpdater {
             }
         }
     }
+
+    fn release_held(&mut self) -> Option<PackageInstall> {
+        if self.window.as_ref().map_or(true, UpdateWindow::is_open) {
+            self.held.take()
+        } else {
+            None
+        }
+    }
 }
 
 fn update_frequency() -> i64 {
@@ -15,7 +15,9 @@
 /// Collect all the configuration data that is exposed to users, and render it.
 use std;
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
@@ -29,15 +31,20 @@ use serde::{Serialize, Serializer};
 use serde_json;
 use serde_transcode;
 use toml;
+use valico::common::error::ValicoError;
+use valico::json_schema;
 
 use super::Pkg;
 use census::CensusGroup;
 use error::{Error, Result};
 use sys::abilities;
 use templating::{RenderContext, TemplateRenderer};
+use util::diff;
 
 static LOGKEY: &'static str = "CF";
 static ENV_VAR_PREFIX: &'static str = "HAB";
+/// The file a package's config schema is read from, next to its `default.toml`.
+static CONFIG_SCHEMA_FILE: &'static str = "config_schema.json";
 /// The maximum TOML table merge depth allowed before failing the operation. The value here is
 /// somewhat arbitrary (stack size cannot be easily computed beforehand and different libc
 /// implementations will impose different size constraints), however a parallel data structure that
@@ -45,6 +52,12 @@ static ENV_VAR_PREFIX: &'static str = "HAB";
 /// for a single service.
 static TOML_MAX_MERGE_DEPTH: u16 = 30;
 pub const CONFIG_PERMISSIONS: u32 = 0o740;
+/// String values starting with this refer to a secret, named by the rest of the value.
+pub const SECRET_PREFIX: &'static str = "secret:";
+/// What secret references are replaced with wherever configuration is shown, rather than rendered.
+pub const REDACTED: &'static str = "<redacted>";
+/// The headers of secret files encrypted for the service's key, rather than stored in the clear.
+const ENCRYPTED_SECRET_HEADERS: &'static [&'static str] = &["BOX-1\n", "ANONYMOUS-BOX-1\n"];
 
 /// Describes the path to user configuration that is used by the
 /// service.
@@ -81,6 +94,8 @@ pub trait PackageConfigPaths {
     fn recommended_user_config_dir(&self) -> PathBuf;
     /// Get deprecated path to directory which holds user.toml.
     fn deprecated_user_config_dir(&self) -> PathBuf;
+    /// Get path to directory which holds the secrets configuration refers to.
+    fn secrets_dir(&self) -> PathBuf;
 }
 
 impl PackageConfigPaths for Pkg {
@@ -96,6 +111,30 @@ impl PackageConfigPaths for Pkg {
     fn deprecated_user_config_dir(&self) -> PathBuf {
         self.svc_path.clone()
     }
+    fn secrets_dir(&self) -> PathBuf {
+        fs::user_secrets_path(&self.name)
+    }
+}
+
+/// A layer of the configuration, from the lowest precedence to the highest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CfgLayer {
+    Default,
+    Environment,
+    User,
+    Gossip,
+}
+
+impl fmt::Display for CfgLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = match *self {
+            CfgLayer::Default => "default",
+            CfgLayer::Environment => "environment",
+            CfgLayer::User => "user",
+            CfgLayer::Gossip => "gossip",
+        };
+        write!(f, "{}", value)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -108,8 +147,13 @@ pub struct Cfg {
     pub gossip: Option<toml::value::Table>,
     /// Environment level configuration loaded by the Supervisor's process environment
     pub environment: Option<toml::value::Table>,
+    /// Schema every merge of the layers above is validated against, loaded by a Package's
+    /// `config_schema.json`
+    schema: Option<ConfigSchema>,
     /// Source of the user configuration
     pub user_config_path: UserConfigPath,
+    /// Source of the secrets referred to by configuration values
+    secrets_path: PathBuf,
     /// The path to an optional dev-time configuration directory that
     /// is being used.
     override_config_dir: Option<PathBuf>,
@@ -123,40 +167,46 @@ impl Cfg {
         P: PackageConfigPaths,
     {
         let override_config_dir = config_from.and_then(|c| Some(c.clone()));
-        let default = {
+        let (default, schema) = {
             let pkg_root = match override_config_dir {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
-            Self::load_default(pkg_root.as_ref())?
+            (
+                Self::load_default(pkg_root.as_ref())?,
+                ConfigSchema::load(pkg_root.as_ref())?,
+            )
         };
         let user_config_path = Self::determine_user_config_path(package);
         let user = Self::load_user(user_config_path.get_path())?;
         let environment = Self::load_environment(&package.name())?;
-        return Ok(Self {
+        let cfg = Self {
             default: default,
             user: user,
             gossip: None,
             environment: environment,
+            schema: schema,
             gossip_incarnation: 0,
             user_config_path: user_config_path,
+            secrets_path: package.secrets_dir(),
             override_config_dir: override_config_dir,
-        });
+        };
+        cfg.check()?;
+        Ok(cfg)
     }
 
-    /// Validates a service configuration against a configuration interface.
+    /// Validates the merged configuration against the package's config schema.
     ///
-    /// Returns `None` if valid and `Some` containing a list of errors if invalid.
-    pub fn validate(
-        interface: &toml::value::Table,
-        cfg: &toml::value::Table,
-    ) -> Option<Vec<String>> {
-        let mut errors = vec![];
-        for (key, _) in cfg {
-            if !interface.contains_key(key) {
-                errors.push(format!("Unknown key: {}", key));
-            }
-        }
+    /// Returns `None` if valid, or if the package has no schema, and `Some` containing a list of
+    /// errors, each starting with the path of the key at fault, if invalid.
+    pub fn validate(&self) -> Option<Vec<String>> {
+        let schema = match self.schema {
+            Some(ref schema) => schema,
+            None => return None,
+        };
+        // Secrets are checked by their values. One which can't be read is checked as the
+        // reference to it instead; rendering the configuration reports why it can't be read.
+        let errors = schema.validate(&self.resolved().unwrap_or_else(|_| self.merged()));
         if errors.is_empty() {
             None
         } else {
@@ -164,18 +214,126 @@ impl Cfg {
         }
     }
 
-    /// A structured interface which describes configuration keys which are configurable and their
-    /// optional default values.
-    pub fn interface(&self) -> Option<&toml::value::Table> {
-        // JW TODO: For now let's use `default.toml` as it is for the interface. In the future,
-        // we will need to be able to describe more than just the key value relationship that
-        // `default.toml` provides. We will need to be able to describe things like:
-        //
-        // * Keys which have no default
-        // * Key with a default value
-        // * Keys which only accept certain values
-        // * Allowed types for a key
-        self.default.as_ref()
+    /// A structured interface which describes the configuration keys which are configurable:
+    /// their types, which of them are required, and the values and ranges they accept.
+    pub fn interface(&self) -> Option<&ConfigSchema> {
+        self.schema.as_ref()
+    }
+
+    /// Every value of the merged configuration by the dotted path of its key, along with the
+    /// layer it came from. Layers are merged the same way as when the configuration is rendered.
+    pub fn layered(&self) -> BTreeMap<String, (toml::Value, CfgLayer)> {
+        let mut keys = BTreeMap::new();
+        let layers = [
+            (CfgLayer::Default, &self.default),
+            (CfgLayer::Environment, &self.environment),
+            (CfgLayer::User, &self.user),
+            (CfgLayer::Gossip, &self.gossip),
+        ];
+        for &(layer, table) in layers.iter() {
+            if let Some(ref table) = *table {
+                layer_keys(&mut keys, "", table, layer);
+            }
+        }
+        keys
+    }
+
+    /// The merged configuration as templates see it, with every secret reference replaced by
+    /// the secret it names. Secrets are read from the service's secrets directory, and decrypted
+    /// with the service's key if they were encrypted for it.
+    ///
+    /// This is the only way to get at secrets; everywhere else the configuration is serialized,
+    /// secret references are redacted.
+    pub fn resolved(&self) -> Result<toml::value::Table> {
+        let mut table = self.merged();
+        map_secrets(&mut table, &mut |name| {
+            self.read_secret(name).map(toml::Value::String)
+        })?;
+        Ok(table)
+    }
+
+    /// The merged configuration with every secret reference replaced by a placeholder naming the
+    /// secret, for rendering files which are shown rather than written. The same secret always
+    /// gets the same placeholder, so templates may transform it without giving the secret away.
+    pub fn with_secret_placeholders(&self) -> toml::value::Table {
+        let mut table = self.merged();
+        map_secrets(&mut table, &mut |name| {
+            Ok(toml::Value::String(format!("<redacted:{}>", name)))
+        })
+        .expect("Replacing secrets with placeholders never fails");
+        table
+    }
+
+    fn read_secret(&self, name: &str) -> Result<String> {
+        let secret_error = |reason: String| sup_error!(Error::Secret(name.to_string(), reason));
+        if name.is_empty() || name.contains('/') || name.contains('\\') || name.starts_with('.') {
+            return Err(secret_error("not a valid secret name".to_string()));
+        }
+        let path = self.secrets_path.join(name);
+        let mut bytes = vec![];
+        File::open(&path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(|e| secret_error(format!("{}, {}", path.display(), e)))?;
+        if ENCRYPTED_SECRET_HEADERS
+            .iter()
+            .any(|header| bytes.starts_with(header.as_bytes()))
+        {
+            bytes = crypto::BoxKeyPair::decrypt_with_path(
+                &bytes,
+                &crypto::default_cache_key_path(None),
+            )
+            .map_err(|e| secret_error(e.to_string()))?;
+        }
+        let secret = String::from_utf8(bytes).map_err(|e| secret_error(e.to_string()))?;
+        Ok(secret
+            .trim_right_matches(|c| c == '\n' || c == '\r')
+            .to_string())
+    }
+
+    /// Merges every layer of the configuration, in order of precedence.
+    fn merged(&self) -> toml::value::Table {
+        let mut table = toml::value::Table::new();
+        if let Some(ref default_cfg) = self.default {
+            if let Err(err) = toml_merge(&mut table, default_cfg) {
+                outputln!("Error merging default-cfg into config, {}", err);
+            }
+        }
+        if let Some(ref env_cfg) = self.environment {
+            if let Err(err) = toml_merge(&mut table, env_cfg) {
+                outputln!("Error merging environment-cfg into config, {}", err);
+            }
+        }
+        if let Some(ref user_cfg) = self.user {
+            if let Err(err) = toml_merge(&mut table, user_cfg) {
+                outputln!("Error merging user-cfg into config, {}", err);
+            }
+        }
+        if let Some(ref gossip_cfg) = self.gossip {
+            if let Err(err) = toml_merge(&mut table, gossip_cfg) {
+                outputln!("Error merging gossip-cfg into config, {}", err);
+            }
+        }
+        table
+    }
+
+    fn check(&self) -> Result<()> {
+        match self.validate() {
+            Some(errors) => Err(sup_error!(Error::InvalidConfig(errors))),
+            None => Ok(()),
+        }
+    }
+
+    /// Applies `change` to a copy of the configuration, and keeps the result only if it is valid
+    /// against the package's config schema.
+    fn apply_checked<F>(&mut self, change: F) -> Result<()>
+    where
+        F: FnOnce(&mut Cfg),
+    {
+        let mut candidate = self.clone();
+        change(&mut candidate);
+        candidate.check()?;
+        *self = candidate;
+        Ok(())
     }
 
     /// Updates the default layer of the configuration when a service
@@ -188,30 +346,39 @@ impl Cfg {
     ///
     /// Note that if you're using `config_from`, then changes in the
     /// incoming packages won't be reflected.
+    ///
+    /// The incoming config schema is updated along with the defaults. If the
+    /// configuration does not match it, both are left as they were.
     pub fn update_defaults_from_package<P>(&mut self, package: &P) -> Result<bool>
     where
         P: PackageConfigPaths,
     {
-        let incoming_defaults = {
+        let (incoming_defaults, incoming_schema) = {
             let pkg_root = match self.override_config_dir {
                 Some(ref path) => Cow::Borrowed(path),
                 None => Cow::Owned(package.default_config_dir()),
             };
-            Self::load_default(pkg_root.as_ref())?
+            (
+                Self::load_default(pkg_root.as_ref())?,
+                ConfigSchema::load(pkg_root.as_ref())?,
+            )
         };
 
-        let mut changed = false;
-        if incoming_defaults != self.default {
-            self.default = incoming_defaults;
-            changed = true;
+        if incoming_defaults == self.default && incoming_schema == self.schema {
+            return Ok(false);
         }
-        Ok(changed)
+        self.apply_checked(|cfg| {
+            cfg.default = incoming_defaults;
+            cfg.schema = incoming_schema;
+        })?;
+        Ok(true)
     }
 
     /// Updates the service configuration with data from a census group if the census group has
     /// newer data than the current configuration.
     ///
-    /// Returns `true` if the configuration was updated.
+    /// Returns `true` if the configuration was updated. Gossiped configuration which does not
+    /// match the package's config schema is ignored.
     pub fn update(&mut self, census_group: &CensusGroup) -> bool {
         match census_group.service_config {
             Some(ref config) => {
@@ -219,8 +386,18 @@ impl Cfg {
                     return false;
                 }
                 self.gossip_incarnation = config.incarnation;
-                self.gossip = Some(config.value.clone());
-                true
+                let value = config.value.clone();
+                match self.apply_checked(|cfg| cfg.gossip = Some(value)) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        outputln!(
+                            "Ignoring gossiped configuration version {}, {}",
+                            config.incarnation,
+                            err
+                        );
+                        false
+                    }
+                }
             }
             None => false,
         }
@@ -338,11 +515,11 @@ impl Cfg {
         Self::load_toml_file(path, USER_CONFIG_FILE)
     }
 
-    /// Reloads the user configuration file.
+    /// Reloads the user configuration file. If the configuration would no longer match the
+    /// package's config schema, the previous user configuration is kept.
     pub fn reload_user(&mut self) -> Result<()> {
         let user = Self::load_user(self.user_config_path.get_path())?;
-        self.user = user;
-        Ok(())
+        self.apply_checked(|cfg| cfg.user = user)
     }
 
     fn load_environment(package_name: &String) -> Result<Option<toml::value::Table>> {
@@ -413,27 +590,13 @@ impl Serialize for Cfg {
     where
         S: Serializer,
     {
-        let mut table = toml::value::Table::new();
-        if let Some(ref default_cfg) = self.default {
-            if let Err(err) = toml_merge(&mut table, default_cfg) {
-                outputln!("Error merging default-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref env_cfg) = self.environment {
-            if let Err(err) = toml_merge(&mut table, env_cfg) {
-                outputln!("Error merging environment-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref user_cfg) = self.user {
-            if let Err(err) = toml_merge(&mut table, user_cfg) {
-                outputln!("Error merging user-cfg into config, {}", err);
-            }
-        }
-        if let Some(ref gossip_cfg) = self.gossip {
-            if let Err(err) = toml_merge(&mut table, gossip_cfg) {
-                outputln!("Error merging gossip-cfg into config, {}", err);
-            }
-        }
+        let mut table = self.merged();
+        // Secrets are only exposed to templates, through `resolved`. Redacting them here keeps
+        // them out of the services state file, the HTTP gateway and exported configuration.
+        map_secrets(&mut table, &mut |_| {
+            Ok(toml::Value::String(REDACTED.to_string()))
+        })
+        .expect("Redacting secrets never fails");
 
         // Be sure to visit non-tables first (and also non
         // array-of-tables) as all keys must be emitted first.
@@ -460,6 +623,155 @@ impl Serialize for Cfg {
     }
 }
 
+/// A JSON Schema a package ships alongside its `default.toml`, describing the types, required
+/// keys, allowed values and ranges of its configuration, including those of nested tables.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSchema(serde_json::Value);
+
+impl ConfigSchema {
+    /// Loads the schema in `dir`, if there is one.
+    fn load<T>(dir: T) -> Result<Option<ConfigSchema>>
+    where
+        T: AsRef<Path>,
+    {
+        let path = dir.as_ref().join(CONFIG_SCHEMA_FILE);
+        let mut file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                debug!("Failed to open '{}', {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        match ConfigSchema::parse(&contents) {
+            Ok(schema) => Ok(Some(schema)),
+            Err(err) => Err(sup_error!(Error::ConfigSchema(path, err))),
+        }
+    }
+
+    fn parse(contents: &str) -> result::Result<ConfigSchema, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(contents).map_err(|err| err.to_string())?;
+        // NOTE: using `false` instead of `true` allows the `$comment` keyword and any others
+        // which document the schema.
+        let mut scope = json_schema::Scope::new();
+        scope
+            .compile_and_return(value.clone(), false)
+            .map_err(|err| format!("{:?}", err))?;
+        Ok(ConfigSchema(value))
+    }
+
+    /// Validates a configuration against the schema, returning every violation found. Each one
+    /// starts with the dotted path of the key at fault.
+    pub fn validate<T>(&self, cfg: &T) -> Vec<String>
+    where
+        T: Serialize,
+    {
+        let mut scope = json_schema::Scope::new();
+        let schema = match scope.compile_and_return(self.0.clone(), false) {
+            Ok(schema) => schema,
+            Err(err) => return vec![format!("Invalid config schema, {:?}", err)],
+        };
+        let value = match serde_json::to_value(cfg) {
+            Ok(value) => value,
+            Err(err) => return vec![format!("Unable to convert configuration, {}", err)],
+        };
+        schema
+            .validate(&value)
+            .errors
+            .iter()
+            .map(|err| {
+                format!(
+                    "{}: {}",
+                    key_path(err.get_path()),
+                    err.get_detail().unwrap_or(err.get_title())
+                )
+            })
+            .collect()
+    }
+}
+
+/// Replaces every secret reference in `table`, including those in nested tables and arrays, with
+/// what `f` returns for the name of the secret.
+fn map_secrets<F>(table: &mut toml::value::Table, f: &mut F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<toml::Value>,
+{
+    for (_, value) in table.iter_mut() {
+        map_secrets_in(value, f)?;
+    }
+    Ok(())
+}
+
+fn map_secrets_in<F>(value: &mut toml::Value, f: &mut F) -> Result<()>
+where
+    F: FnMut(&str) -> Result<toml::Value>,
+{
+    let secret = match *value {
+        toml::Value::String(ref s) if s.starts_with(SECRET_PREFIX) => f(&s[SECRET_PREFIX.len()..])?,
+        toml::Value::Table(ref mut table) => return map_secrets(table, f),
+        toml::Value::Array(ref mut values) => {
+            for value in values.iter_mut() {
+                map_secrets_in(value, f)?;
+            }
+            return Ok(());
+        }
+        _ => return Ok(()),
+    };
+    *value = secret;
+    Ok(())
+}
+
+fn layer_keys(
+    keys: &mut BTreeMap<String, (toml::Value, CfgLayer)>,
+    prefix: &str,
+    table: &toml::value::Table,
+    layer: CfgLayer,
+) {
+    for (key, value) in table.iter() {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match *value {
+            // A table is merged into a table below it, but replaces any other value.
+            toml::Value::Table(ref table) => {
+                keys.remove(&path);
+                layer_keys(keys, &path, table, layer);
+            }
+            // Any other value replaces whatever was below it, tables included.
+            _ => {
+                let nested = format!("{}.", path);
+                let replaced: Vec<String> = keys
+                    .range(nested.clone()..)
+                    .take_while(|&(key, _)| key.starts_with(&nested))
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in replaced {
+                    keys.remove(&key);
+                }
+                keys.insert(path, (value.clone(), layer));
+            }
+        }
+    }
+}
+
+/// Turns the JSON pointer of a validation error into the dotted key path used in TOML.
+fn key_path(pointer: &str) -> String {
+    let keys: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|key| key.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    if keys.is_empty() {
+        String::from("(root)")
+    } else {
+        keys.join(".")
+    }
+}
+
 #[derive(Debug)]
 /// Renders configuration templates into config files.
 pub struct CfgRenderer(TemplateRenderer);
@@ -563,6 +875,26 @@ impl CfgRenderer {
         }
         Ok(changed)
     }
+
+    /// Render every configuration file with both contexts, without writing them, and return the
+    /// name of each one which would change along with a unified diff of its changes.
+    pub fn diff<T>(&self, current_ctx: &T, pending_ctx: &T) -> Result<Vec<(String, String)>>
+    where
+        T: Serialize,
+    {
+        let mut templates: Vec<&String> = self.0.get_templates().keys().collect();
+        templates.sort();
+        let mut diffs = vec![];
+        for template in templates {
+            let current = self.0.render(template, current_ctx)?;
+            let pending = self.0.render(template, pending_ctx)?;
+            let changes = diff::unified(&current, &pending);
+            if !changes.is_empty() {
+                diffs.push((template.clone(), changes));
+            }
+        }
+        Ok(diffs)
+    }
 }
 
 // Recursively merges the `other` TOML table into `me`
@@ -825,6 +1157,7 @@ mod test {
                 .expect("create recommended user config dir");
             fs::create_dir_all(pkg.deprecated_user_config_dir())
                 .expect("create default config dir");
+            fs::create_dir_all(pkg.secrets_dir()).expect("create secrets dir");
             pkg
         }
     }
@@ -842,6 +1175,9 @@ mod test {
         fn deprecated_user_config_dir(&self) -> PathBuf {
             self.base_path.join("svc")
         }
+        fn secrets_dir(&self) -> PathBuf {
+            self.base_path.join("secrets")
+        }
     }
 
     struct CfgTestData {
@@ -976,6 +1312,196 @@ mod test {
         assert_eq!(default_toml, toml::to_string(&cfg).unwrap());
     }
 
+    const TEST_SCHEMA: &'static str = r#"{
+        "type": "object",
+        "required": ["port"],
+        "properties": {
+            "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+            "repl": {
+                "type": "object",
+                "properties": {
+                    "mode": { "enum": ["sync", "async"] }
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn schema_errors_name_the_key_path() {
+        let schema = ConfigSchema::parse(TEST_SCHEMA).expect("parse schema");
+        assert!(schema
+            .validate(&toml_from_str("port = 80\n[repl]\nmode = \"sync\""))
+            .is_empty());
+
+        let errors = schema.validate(&toml_from_str("port = 0\n[repl]\nmode = \"eventual\""));
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().any(|e| e.starts_with("port: ")));
+        assert!(errors.iter().any(|e| e.starts_with("repl.mode: ")));
+
+        let errors = schema.validate(&toml_from_str("[repl]\nmode = \"async\""));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("port: "));
+
+        let errors = schema.validate(&toml_from_str("port = \"80\""));
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("port: "));
+    }
+
+    #[test]
+    fn user_config_must_match_schema() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join(CONFIG_SCHEMA_FILE),
+            TEST_SCHEMA,
+        );
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 80",
+        );
+        write_toml(&cfg_data.rucp, "port = 8080");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        assert!(cfg.interface().is_some());
+        assert!(cfg.validate().is_none());
+
+        write_toml(&cfg_data.rucp, "port = 80800");
+        match cfg.reload_user() {
+            Err(e) => match e.err {
+                Error::InvalidConfig(ref errors) => assert!(errors[0].starts_with("port: ")),
+                _ => panic!("Should fail with Error::InvalidConfig"),
+            },
+            Ok(_) => panic!("Should not reload an invalid user config"),
+        }
+        assert_eq!(cfg.user, Some(toml_from_str("port = 8080")));
+        assert!(Cfg::new(&cfg_data.pkg, None).is_err());
+    }
+
+    #[test]
+    fn layered_keys_name_the_layer_they_came_from() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join("default.toml"),
+            "port = 80\nhost = \"localhost\"\n[repl]\nmode = \"sync\"\nlag = 10",
+        );
+        write_toml(&cfg_data.rucp, "port = 8080\n[repl]\nlag = 20");
+        let mut cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        cfg.gossip = Some(toml_from_str("repl = \"off\""));
+
+        let keys = cfg.layered();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(
+            keys["host"],
+            (
+                toml::Value::String("localhost".to_string()),
+                CfgLayer::Default
+            )
+        );
+        assert_eq!(keys["port"], (toml::Value::Integer(8080), CfgLayer::User));
+        assert_eq!(
+            keys["repl"],
+            (toml::Value::String("off".to_string()), CfgLayer::Gossip)
+        );
+    }
+
+    #[test]
+    fn serialized_cfg_redacts_secrets() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.rucp,
+            "user = \"admin\"\npassword = \"secret:db_password\"\n[tls]\nkeys = [\"secret:tls_key\"]",
+        );
+        write_toml(&cfg_data.pkg.secrets_dir().join("db_password"), "hunter2\n");
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        let serialized = toml::to_string(&cfg).expect("serialize config");
+        assert!(!serialized.contains("hunter2"));
+        let table: toml::value::Table = toml::from_str(&serialized).expect("parse config");
+        assert_eq!(table["user"], toml::Value::String("admin".to_string()));
+        assert_eq!(table["password"], toml::Value::String(REDACTED.to_string()));
+        assert_eq!(
+            table["tls"]["keys"],
+            toml::Value::Array(vec![toml::Value::String(REDACTED.to_string())])
+        );
+    }
+
+    #[test]
+    fn resolved_cfg_reads_secrets() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.rucp,
+            "user = \"admin\"\n[db]\npassword = \"secret:db_password\"",
+        );
+        write_toml(&cfg_data.pkg.secrets_dir().join("db_password"), "hunter2\n");
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        let resolved = cfg.resolved().expect("resolve config");
+        assert_eq!(resolved["user"], toml::Value::String("admin".to_string()));
+        assert_eq!(
+            resolved["db"]["password"],
+            toml::Value::String("hunter2".to_string())
+        );
+    }
+
+    #[test]
+    fn placeholders_name_secrets() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.rucp,
+            "user = \"admin\"\n[db]\npassword = \"secret:db_password\"",
+        );
+        write_toml(&cfg_data.pkg.secrets_dir().join("db_password"), "hunter2\n");
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+
+        let table = cfg.with_secret_placeholders();
+        assert_eq!(table["user"], toml::Value::String("admin".to_string()));
+        assert_eq!(
+            table["db"]["password"],
+            toml::Value::String("<redacted:db_password>".to_string())
+        );
+    }
+
+    #[test]
+    fn schema_checks_the_values_of_secrets() {
+        let cfg_data = CfgTestData::new();
+        write_toml(
+            &cfg_data.pkg.default_config_dir().join(CONFIG_SCHEMA_FILE),
+            r#"{ "properties": { "password": { "type": "string", "minLength": 12 } } }"#,
+        );
+        write_toml(&cfg_data.rucp, "password = \"secret:db_password\"");
+        write_toml(&cfg_data.pkg.secrets_dir().join("db_password"), "hunter2\n");
+        match Cfg::new(&cfg_data.pkg, None) {
+            Err(e) => match e.err {
+                Error::InvalidConfig(ref errors) => assert!(errors[0].starts_with("password: ")),
+                _ => panic!("Should fail with Error::InvalidConfig"),
+            },
+            Ok(_) => panic!("A secret shorter than the schema allows should be invalid"),
+        }
+
+        write_toml(
+            &cfg_data.pkg.secrets_dir().join("db_password"),
+            "correct horse battery staple\n",
+        );
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        assert!(cfg.validate().is_none());
+    }
+
+    #[test]
+    fn resolving_missing_or_invalid_secret_fails() {
+        let cfg_data = CfgTestData::new();
+        write_toml(&cfg_data.rucp, "password = \"secret:missing\"");
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        match cfg.resolved() {
+            Err(e) => match e.err {
+                Error::Secret(ref name, _) => assert_eq!(name, "missing"),
+                _ => panic!("Should fail with Error::Secret"),
+            },
+            Ok(_) => panic!("Should not resolve a missing secret"),
+        }
+
+        write_toml(&cfg_data.rucp, "password = \"secret:../user/user.toml\"");
+        let cfg = Cfg::new(&cfg_data.pkg, None).expect("create config");
+        assert!(cfg.resolved().is_err());
+    }
+
     // env_key: the name of the environment variable the config should
     //     be read from
     // package_name: the name of the package that would read
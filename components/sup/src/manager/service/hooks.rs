@@ -23,9 +23,13 @@ use std::path::{Path, PathBuf};
 #[cfg(not(windows))]
 use std::process::{Child, ExitStatus};
 use std::result;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 use hcore;
 use hcore::crypto;
+use hcore::os::process::{self, Pid, Signal};
 use hcore::service::ServiceGroup;
 use serde::{Serialize, Serializer};
 
@@ -124,6 +128,21 @@ pub trait Hook: fmt::Debug + Sized {
         pkg: &Pkg,
         svc_encrypted_password: Option<T>,
     ) -> Self::ExitValue
+    where
+        T: ToString,
+    {
+        self.run_with_timeout(service_group, pkg, svc_encrypted_password, None)
+    }
+
+    /// Run a compiled hook, killing it if it has not exited before the optional timeout elapses.
+    /// A hook which is killed returns the default exit value.
+    fn run_with_timeout<T>(
+        &self,
+        service_group: &ServiceGroup,
+        pkg: &Pkg,
+        svc_encrypted_password: Option<T>,
+        timeout: Option<Duration>,
+    ) -> Self::ExitValue
     where
         T: ToString,
     {
@@ -135,9 +154,18 @@ pub trait Hook: fmt::Debug + Sized {
                 return Self::ExitValue::default();
             }
         };
+        let watchdog = timeout.map(|t| HookWatchdog::start(child.id() as Pid, t));
         let mut hook_output = HookOutput::new(self.stdout_log_path(), self.stderr_log_path());
         hook_output.stream_output::<Self>(service_group, &mut child);
-        match child.wait() {
+        let result = child.wait();
+        if let Some(watchdog) = watchdog {
+            if watchdog.finish() {
+                outputln!(preamble service_group,
+                    "Hook timed out and was killed, {}", Self::file_name());
+                return Self::ExitValue::default();
+            }
+        }
+        match result {
             Ok(status) => self.handle_exit(service_group, &hook_output, &status),
             Err(err) => {
                 outputln!(preamble service_group,
@@ -944,6 +972,38 @@ impl Serialize for RenderPair {
     }
 }
 
+/// Kills a running hook process if it is not finished before a timeout elapses.
+struct HookWatchdog {
+    tx: mpsc::Sender<()>,
+    handle: thread::JoinHandle<bool>,
+}
+
+impl HookWatchdog {
+    fn start(pid: Pid, timeout: Duration) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let handle = thread::spawn(move || match rx.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                if let Err(err) = process::signal(pid, Signal::KILL) {
+                    debug!("Unable to kill timed out hook process {}, {:?}", pid, err);
+                }
+                true
+            }
+            _ => false,
+        });
+        HookWatchdog {
+            tx: tx,
+            handle: handle,
+        }
+    }
+
+    /// Stop watching the hook process. Returns `true` if the process was killed for exceeding
+    /// its timeout.
+    fn finish(self) -> bool {
+        let _ = self.tx.send(());
+        self.handle.join().unwrap_or(false)
+    }
+}
+
 pub struct HookOutput<'a> {
     stdout_log_file: &'a Path,
     stderr_log_file: &'a Path,
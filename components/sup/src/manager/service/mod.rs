@@ -40,6 +40,7 @@ use hcore::service::ServiceGroup;
 use hcore::util::perm::{set_owner, set_permissions};
 use launcher_client::LauncherCli;
 pub use protocol::types::{BindingMode, ProcessState, Topology, UpdateStrategy};
+use serde_json;
 use time::Timespec;
 
 pub use self::composite_spec::CompositeSpec;
@@ -49,14 +50,18 @@ use self::dir::SvcDir;
 pub use self::health::{HealthCheck, SmokeCheck};
 use self::hooks::{Hook, HookTable, HOOK_PERMISSIONS};
 pub use self::package::{Env, Pkg};
-pub use self::spec::{BindMap, DesiredState, IntoServiceSpec, ServiceBind, ServiceSpec, Spec};
-use self::supervisor::Supervisor;
+pub use self::spec::{
+    BindMap, DesiredState, IntoServiceSpec, MemberCount, ServiceBind, ServiceSpec, Spec,
+};
+use self::supervisor::{CrashPolicy, Supervisor};
 use super::ShutdownReason;
 use super::Sys;
 use census::{CensusGroup, CensusRing, ElectionStatus, ServiceFile};
 use error::{Error, Result, SupError};
 use fs;
 use manager;
+use manager::service_updater::UpdateRequest;
+use manager::UpdateWindow;
 use sys::abilities;
 use templating::RenderContext;
 
@@ -64,10 +69,6 @@ static LOGKEY: &'static str = "SR";
 
 pub const GOSSIP_FILE_PERMISSIONS: u32 = 0o640;
 
-lazy_static! {
-    static ref HEALTH_CHECK_INTERVAL: Duration = { Duration::from_millis(30_000) };
-}
-
 /// When evaluating whether a particular service group can satisfy a
 /// bind of the Service, there are several states it can be
 /// in. Depending on which point in the lifecycle of the Service we
@@ -100,6 +101,24 @@ pub struct Service {
     pub spec_ident: PackageIdent,
     pub topology: Topology,
     pub update_strategy: UpdateStrategy,
+    pub update_canary: MemberCount,
+    pub update_batch_size: u32,
+    #[serde(skip_serializing)]
+    pub update_soak: Duration,
+    #[serde(skip_serializing)]
+    pub update_rollback_window: Option<Duration>,
+    pub update_window: Option<UpdateWindow>,
+    pub update_spread_by: Option<String>,
+    /// A newer release which has been installed but is held until the update window opens.
+    pub held_update: Option<PackageIdent>,
+    /// Whether the service is held at its current release, with its updates paused.
+    pub update_hold: bool,
+    /// An update requested through the control gateway, handled on the next update check.
+    #[serde(skip_serializing)]
+    pub update_request: Option<UpdateRequest>,
+    /// The release this service rolled back from after it failed to become healthy following an
+    /// update.
+    pub rolled_back_from: Option<PackageIdent>,
     pub cfg: Cfg,
     pub pkg: Pkg,
     pub sys: Arc<Sys>,
@@ -109,6 +128,9 @@ pub struct Service {
     #[serde(skip_serializing)]
     config_renderer: CfgRenderer,
     health_check: HealthCheck,
+    /// Whether the health check result changed since the service's rumor was last gossiped.
+    #[serde(skip_serializing)]
+    health_check_updated: bool,
     last_election_status: ElectionStatus,
     needs_reload: bool,
     needs_reconfiguration: bool,
@@ -142,6 +164,26 @@ pub struct Service {
     config_from: Option<PathBuf>,
     #[serde(skip_serializing)]
     last_health_check: Option<Instant>,
+    #[serde(skip_serializing)]
+    health_check_interval: Duration,
+    #[serde(skip_serializing)]
+    health_check_timeout: Option<Duration>,
+    #[serde(skip_serializing)]
+    health_check_threshold: u32,
+    /// The number of critical health check results in a row, counted until a non-critical
+    /// result is returned.
+    consecutive_criticals: u32,
+    #[serde(skip_serializing)]
+    restart_on_critical: Option<u32>,
+    #[serde(skip_serializing)]
+    restart_backoff: Duration,
+    #[serde(skip_serializing)]
+    restart_limit: Option<u32>,
+    /// The number of times the service has been restarted for failing its health check since it
+    /// was last healthy.
+    health_restarts: u32,
+    #[serde(skip_serializing)]
+    next_health_restart: Option<Instant>,
     manager_fs_cfg: Arc<manager::FsCfg>,
     #[serde(rename = "process")]
     supervisor: Supervisor,
@@ -182,6 +224,7 @@ impl Service {
             channel: spec.channel,
             desired_state: spec.desired_state,
             health_check: HealthCheck::default(),
+            health_check_updated: false,
             hooks: HookTable::load(
                 &service_group,
                 &hooks_root,
@@ -193,7 +236,14 @@ impl Service {
             needs_reconfiguration: false,
             user_config_updated: false,
             manager_fs_cfg: manager_fs_cfg,
-            supervisor: Supervisor::new(&service_group),
+            supervisor: Supervisor::new(
+                &service_group,
+                CrashPolicy {
+                    window: spec.crash_window,
+                    limit: spec.crash_limit,
+                    backoff: spec.crash_backoff,
+                },
+            ),
             pkg: pkg,
             service_group: service_group,
             smoke_check: SmokeCheck::default(),
@@ -205,8 +255,27 @@ impl Service {
             spec_file: spec_file,
             topology: spec.topology,
             update_strategy: spec.update_strategy,
+            update_canary: spec.update_canary,
+            update_batch_size: spec.update_batch_size,
+            update_soak: Duration::from_secs(spec.update_soak),
+            update_rollback_window: spec.update_rollback_window.map(Duration::from_secs),
+            update_window: spec.update_window,
+            update_spread_by: spec.update_spread_by,
+            held_update: None,
+            update_hold: spec.update_hold,
+            update_request: None,
+            rolled_back_from: None,
             config_from: spec.config_from,
             last_health_check: None,
+            health_check_interval: Duration::from_secs(spec.health_check_interval),
+            health_check_timeout: spec.health_check_timeout.map(Duration::from_secs),
+            health_check_threshold: spec.health_check_threshold,
+            consecutive_criticals: 0,
+            restart_on_critical: spec.restart_on_critical,
+            restart_backoff: Duration::from_secs(spec.restart_backoff),
+            restart_limit: spec.restart_limit,
+            health_restarts: 0,
+            next_health_restart: None,
             svc_encrypted_password: spec.svc_encrypted_password,
             composite: spec.composite,
             defaults_updated: false,
@@ -298,13 +367,43 @@ impl Service {
         }
     }
 
+    /// Clears a failed process state, allowing the service's process to be started again.
+    ///
+    /// Returns `true` if the service was in the failed state.
+    pub fn clear_failed(&mut self) -> bool {
+        if self.supervisor.clear_failed() {
+            outputln!(preamble self.service_group, "Clearing failed state");
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn health_check(&self) -> HealthCheck {
+        self.health_check
+    }
+
+    /// Replaces a release which failed to become healthy after an update with the given package,
+    /// which the service was running before the update. The failed release is recorded so it can
+    /// be gossiped to the rest of the service group.
+    pub fn rollback(&mut self, package: PackageInstall, launcher: &LauncherCli) -> Result<()> {
+        outputln!(preamble self.service_group,
+                  "Rolling back from {} to {}", self.pkg.ident, package.ident());
+        let failed = self.pkg.ident.clone();
+        self.update_package(package, launcher)?;
+        self.rolled_back_from = Some(failed);
+        self.clear_failed();
+        Ok(())
+    }
+
     pub fn last_state_change(&self) -> Timespec {
         self.supervisor.state_entered
     }
 
     /// Performs updates and executes hooks.
     ///
-    /// Returns `true` if the service was updated.
+    /// Returns `true` if the service was updated or its health changed, in which case a new
+    /// service rumor should be gossiped.
     pub fn tick(&mut self, census_ring: &CensusRing, launcher: &LauncherCli) -> bool {
         // We may need to block the service from starting until all
         // its binds are satisfied
@@ -381,7 +480,9 @@ impl Service {
                 }
             }
         }
-        svc_updated
+        let health_updated = self.health_check_updated;
+        self.health_check_updated = false;
+        svc_updated || health_updated
     }
 
     pub fn to_spec(&self) -> ServiceSpec {
@@ -397,11 +498,27 @@ impl Service {
         spec.channel = self.channel.clone();
         spec.topology = self.topology;
         spec.update_strategy = self.update_strategy;
+        spec.update_canary = self.update_canary;
+        spec.update_batch_size = self.update_batch_size;
+        spec.update_soak = self.update_soak.as_secs();
+        spec.update_rollback_window = self.update_rollback_window.map(|w| w.as_secs());
+        spec.update_window = self.update_window.clone();
+        spec.update_spread_by = self.update_spread_by.clone();
+        spec.update_hold = self.update_hold;
         spec.binds = self.binds.clone();
         spec.config_from = self.config_from.clone();
         if let Some(ref password) = self.svc_encrypted_password {
             spec.svc_encrypted_password = Some(password.clone())
         }
+        spec.health_check_interval = self.health_check_interval.as_secs();
+        spec.health_check_timeout = self.health_check_timeout.map(|t| t.as_secs());
+        spec.health_check_threshold = self.health_check_threshold;
+        spec.restart_on_critical = self.restart_on_critical;
+        spec.restart_backoff = self.restart_backoff.as_secs();
+        spec.restart_limit = self.restart_limit;
+        spec.crash_window = self.supervisor.crash_policy.window;
+        spec.crash_limit = self.supervisor.crash_policy.limit;
+        spec.crash_backoff = self.supervisor.crash_policy.backoff;
         spec
     }
 
@@ -477,7 +594,11 @@ impl Service {
         match census_ring.census_group_for(&service_bind.service_group) {
             None => BindStatus::NotPresent,
             Some(group) => {
-                if group.active_members().is_empty() {
+                if !group
+                    .active_members()
+                    .iter()
+                    .any(|m| m.matches_labels(&service_bind.labels))
+                {
                     BindStatus::Empty
                 } else {
                     match self.unsatisfied_bind_exports(group, &service_bind.name) {
@@ -590,48 +711,43 @@ impl Service {
     }
 
     /// Replace the package of the running service and restart its system process.
-    pub fn update_package(&mut self, package: PackageInstall, launcher: &LauncherCli) {
-        match Pkg::from_install(package) {
-            Ok(pkg) => {
-                outputln!(preamble self.service_group,
-                            "Updating service {} to {}", self.pkg.ident, pkg.ident);
-                match CfgRenderer::new(&Self::config_root(&pkg, self.config_from.as_ref())) {
-                    Ok(renderer) => self.config_renderer = renderer,
-                    Err(e) => {
-                        outputln!(preamble self.service_group,
-                                  "Failed to load config templates after updating package, {}", e);
-                        return;
-                    }
-                }
-                self.hooks = HookTable::load(
-                    &self.service_group,
-                    &Self::hooks_root(&pkg, self.config_from.as_ref()),
-                    fs::svc_hooks_path(self.service_group.service()),
-                );
-                self.pkg = pkg;
-            }
-            Err(err) => {
-                outputln!(preamble self.service_group,
-                          "Unexpected error while updating package, {}", err);
-                return;
-            }
-        }
+    ///
+    /// The new package's config templates are loaded, and the service's configuration is
+    /// validated against its default configuration and config schema, before anything is
+    /// replaced. If any of these fail the service keeps running its current package and the
+    /// error is returned.
+    pub fn update_package(
+        &mut self,
+        package: PackageInstall,
+        launcher: &LauncherCli,
+    ) -> Result<()> {
+        let pkg = Pkg::from_install(package)?;
+        let renderer = CfgRenderer::new(&Self::config_root(&pkg, self.config_from.as_ref()))?;
+        let mut cfg = self.cfg.clone();
+        let defaults_updated = cfg.update_defaults_from_package(&pkg)?;
+
+        outputln!(preamble self.service_group,
+                  "Updating service {} to {}", self.pkg.ident, pkg.ident);
+        self.config_renderer = renderer;
+        self.hooks = HookTable::load(
+            &self.service_group,
+            &Self::hooks_root(&pkg, self.config_from.as_ref()),
+            fs::svc_hooks_path(self.service_group.service()),
+        );
+        self.pkg = pkg;
+        self.cfg = cfg;
+        self.defaults_updated = defaults_updated;
         if let Err(err) = self.supervisor.stop(launcher, ShutdownReason::PkgUpdating) {
             outputln!(preamble self.service_group,
                       "Error stopping process while updating package: {}", err);
         }
 
-        match self.cfg.update_defaults_from_package(&self.pkg) {
-            Ok(maybe_updated) => {
-                self.defaults_updated = maybe_updated;
-            }
-            Err(err) => {
-                outputln!(preamble self.service_group,
-                          "Unexpected error while checking for updated package defaults: {}", err);
-            }
-        }
-
+        // The health of the previous release says nothing about the new one, and members
+        // waiting on this one during a canary update rely on seeing it report healthy again.
+        self.health_check = HealthCheck::default();
+        self.consecutive_criticals = 0;
         self.initialized = false;
+        Ok(())
     }
 
     pub fn to_rumor(&self, incarnation: u64) -> ServiceRumor {
@@ -652,6 +768,8 @@ impl Service {
             exported.as_ref(),
         );
         rumor.incarnation = incarnation;
+        rumor.health = Some(self.health_check as i32);
+        rumor.rolled_back_from = self.rolled_back_from.as_ref().map(|ident| ident.to_string());
         rumor
     }
 
@@ -747,6 +865,25 @@ impl Service {
         }
     }
 
+    /// Compares the configuration files as the current configuration renders them against those
+    /// `cfg` would render, both with the given census, returning the name of each file which
+    /// would change along with a unified diff of its changes. Both sides are rendered with
+    /// placeholders in place of secrets, so the diffs never show a secret.
+    pub fn config_diff(
+        &self,
+        cfg: &Cfg,
+        census_ring: &CensusRing,
+    ) -> Result<Vec<(String, String)>> {
+        let mut current = serde_json::to_value(&self.render_context(census_ring))
+            .map_err(|e| sup_error!(Error::RenderContextSerialization(e)))?;
+        let mut pending = current.clone();
+        current["cfg"] = serde_json::to_value(self.cfg.with_secret_placeholders())
+            .map_err(|e| sup_error!(Error::RenderContextSerialization(e)))?;
+        pending["cfg"] = serde_json::to_value(cfg.with_secret_placeholders())
+            .map_err(|e| sup_error!(Error::RenderContextSerialization(e)))?;
+        self.config_renderer.diff(&current, &pending)
+    }
+
     /// Helper for compiling configuration templates into configuration files.
     ///
     /// Returns `true` if the configuration has changed.
@@ -822,13 +959,23 @@ impl Service {
             self.check_process();
             match self.last_health_check {
                 Some(last_check) => {
-                    if Instant::now().duration_since(last_check) >= *HEALTH_CHECK_INTERVAL {
+                    if Instant::now().duration_since(last_check) >= self.health_check_interval {
                         self.run_health_check_hook();
                     }
                 }
                 None => self.run_health_check_hook(),
             }
 
+            // A process which keeps exiting is restarted after an increasing delay, and not at
+            // all once it has been marked as failed. Configuration files are still rendered
+            // while it is down, but the reconfigure hook waits until it is back.
+            if self.supervisor.failed()
+                || (self.process_down() && !self.supervisor.restart_allowed())
+            {
+                return;
+            }
+            self.apply_restart_policy(launcher);
+
             // NOTE: if you need reconfiguration and you DON'T have a
             // reload script, you're going to restart anyway.
             if self.needs_reload || self.process_down() || self.needs_reconfiguration {
@@ -894,10 +1041,11 @@ impl Service {
 
     fn run_health_check_hook(&mut self) {
         let check_result = if let Some(ref hook) = self.hooks.health_check {
-            hook.run(
+            hook.run_with_timeout(
                 &self.service_group,
                 &self.pkg,
                 self.svc_encrypted_password.as_ref(),
+                self.health_check_timeout,
             )
         } else {
             match self.supervisor.status() {
@@ -906,9 +1054,69 @@ impl Service {
             }
         };
         self.last_health_check = Some(Instant::now());
+        if check_result == HealthCheck::Critical {
+            self.consecutive_criticals += 1;
+            if self.consecutive_criticals < self.health_check_threshold {
+                debug!(
+                    "{}, health check critical ({} of {}), keeping current health",
+                    self.service_group, self.consecutive_criticals, self.health_check_threshold
+                );
+                return;
+            }
+        } else {
+            self.consecutive_criticals = 0;
+            self.health_restarts = 0;
+            self.next_health_restart = None;
+        }
+        if self.health_check != check_result {
+            self.health_check_updated = true;
+        }
+        self.health_check = check_result;
         self.cache_health_check(check_result);
     }
 
+    /// Restarts the service's process if it has failed enough consecutive health checks to
+    /// trigger its restart policy. Consecutive restarts are delayed by an exponentially
+    /// increasing backoff and stop once the restart limit is reached; both are reset once the
+    /// service reports a non-critical health check.
+    fn apply_restart_policy(&mut self, launcher: &LauncherCli) {
+        if !health_restart_due(
+            self.restart_on_critical,
+            self.consecutive_criticals,
+            self.restart_limit,
+            self.health_restarts,
+            self.next_health_restart,
+            Instant::now(),
+        ) {
+            return;
+        }
+        outputln!(preamble self.service_group,
+                  "Restarting after {} consecutive critical health checks",
+                  self.consecutive_criticals);
+        if let Some(err) = self
+            .supervisor
+            .restart(
+                &self.pkg,
+                &self.service_group,
+                launcher,
+                self.svc_encrypted_password.as_ref(),
+            )
+            .err()
+        {
+            outputln!(preamble self.service_group, "Service restart failed: {}", err);
+        }
+        self.consecutive_criticals = 0;
+        self.health_restarts += 1;
+        self.next_health_restart = Some(
+            Instant::now() + health_restart_backoff(self.restart_backoff, self.health_restarts),
+        );
+        if Some(self.health_restarts) == self.restart_limit {
+            outputln!(preamble self.service_group,
+                      "Restart limit of {} reached, the service will not be restarted again \
+                       until it is healthy", self.health_restarts);
+        }
+    }
+
     // Returns `false` if the write fails.
     fn cache_service_file(&mut self, service_file: &ServiceFile) -> bool {
         let file = self.pkg.svc_files_path.join(&service_file.filename);
@@ -980,3 +1188,85 @@ impl fmt::Display for Service {
         write!(f, "{} [{}]", self.service_group, self.pkg.ident)
     }
 }
+
+/// Returns `true` if a service which has failed `criticals` consecutive health checks, and has
+/// been restarted `restarts` times for it since it was last healthy, should be restarted now.
+fn health_restart_due(
+    restart_on_critical: Option<u32>,
+    criticals: u32,
+    restart_limit: Option<u32>,
+    restarts: u32,
+    next_restart: Option<Instant>,
+    now: Instant,
+) -> bool {
+    match restart_on_critical {
+        Some(threshold) if criticals >= threshold => (),
+        _ => return false,
+    }
+    if let Some(limit) = restart_limit {
+        if restarts >= limit {
+            return false;
+        }
+    }
+    match next_restart {
+        Some(next_restart) => now >= next_restart,
+        None => true,
+    }
+}
+
+/// The delay before a service may be restarted again after its `restarts`th consecutive restart
+/// for failing its health checks.
+fn health_restart_backoff(backoff: Duration, restarts: u32) -> Duration {
+    backoff * 2u32.pow(restarts.max(1).min(10) - 1)
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{health_restart_backoff, health_restart_due};
+
+    #[test]
+    fn health_restart_never_without_restart_on_critical() {
+        let now = Instant::now();
+        assert!(!health_restart_due(None, 0, None, 0, None, now));
+        assert!(!health_restart_due(None, 100, None, 0, None, now));
+    }
+
+    #[test]
+    fn health_restart_after_consecutive_criticals() {
+        let now = Instant::now();
+        assert!(!health_restart_due(Some(3), 0, None, 0, None, now));
+        assert!(!health_restart_due(Some(3), 2, None, 0, None, now));
+        assert!(health_restart_due(Some(3), 3, None, 0, None, now));
+        assert!(health_restart_due(Some(1), 1, None, 0, None, now));
+    }
+
+    #[test]
+    fn health_restart_until_the_restart_limit() {
+        let now = Instant::now();
+        assert!(health_restart_due(Some(1), 1, Some(2), 1, None, now));
+        assert!(!health_restart_due(Some(1), 1, Some(2), 2, None, now));
+        assert!(health_restart_due(Some(1), 1, None, 1000, None, now));
+    }
+
+    #[test]
+    fn health_restart_waits_out_the_backoff() {
+        let now = Instant::now();
+        let next = now + Duration::from_secs(10);
+        assert!(!health_restart_due(Some(1), 1, None, 1, Some(next), now));
+        assert!(health_restart_due(Some(1), 1, None, 1, Some(next), next));
+    }
+
+    #[test]
+    fn health_restart_backoff_doubles() {
+        let backoff = Duration::from_secs(10);
+        assert_eq!(health_restart_backoff(backoff, 1), Duration::from_secs(10));
+        assert_eq!(health_restart_backoff(backoff, 2), Duration::from_secs(20));
+        assert_eq!(health_restart_backoff(backoff, 3), Duration::from_secs(40));
+        assert_eq!(
+            health_restart_backoff(backoff, 50),
+            Duration::from_secs(10 * 512)
+        );
+    }
+}
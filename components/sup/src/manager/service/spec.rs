@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
 use std::fs::{self, File};
 use std::io::{BufReader, Read, Write};
@@ -35,9 +35,18 @@ use toml;
 use super::composite_spec::CompositeSpec;
 use super::{BindingMode, Topology, UpdateStrategy};
 use error::{Error, Result, SupError};
+use manager::UpdateWindow;
 
 static LOGKEY: &'static str = "SS";
 static DEFAULT_GROUP: &'static str = "default";
+const DEFAULT_HEALTH_CHECK_INTERVAL: u64 = 30;
+const DEFAULT_HEALTH_CHECK_THRESHOLD: u32 = 1;
+const DEFAULT_RESTART_BACKOFF: u64 = 10;
+const DEFAULT_CRASH_WINDOW: u64 = 300;
+const DEFAULT_CRASH_LIMIT: u32 = 5;
+const DEFAULT_CRASH_BACKOFF: u64 = 2;
+const DEFAULT_UPDATE_BATCH_SIZE: u32 = 1;
+const DEFAULT_UPDATE_SOAK: u64 = 60;
 const SPEC_FILE_EXT: &'static str = "spec";
 
 pub type BindMap = HashMap<PackageIdent, Vec<BindMapping>>;
@@ -76,6 +85,76 @@ impl FromStr for DesiredState {
     }
 }
 
+/// A number of members of a service group, given either as an absolute count or as a percentage
+/// of the group's population.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum MemberCount {
+    Count(u32),
+    Percent(u32),
+}
+
+impl MemberCount {
+    /// Resolve this count against a population of `total` members. Percentages are rounded up so
+    /// that any non-zero percentage of a non-empty population selects at least one member.
+    pub fn of(&self, total: usize) -> usize {
+        let count = match *self {
+            MemberCount::Count(count) => count as usize,
+            MemberCount::Percent(percent) => (total * percent as usize + 99) / 100,
+        };
+        count.min(total)
+    }
+}
+
+impl Default for MemberCount {
+    fn default() -> MemberCount {
+        MemberCount::Count(1)
+    }
+}
+
+impl fmt::Display for MemberCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MemberCount::Count(count) => write!(f, "{}", count),
+            MemberCount::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl FromStr for MemberCount {
+    type Err = SupError;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        let value = value.trim();
+        let count = if value.ends_with('%') {
+            value[..value.len() - 1]
+                .parse::<u32>()
+                .ok()
+                .and_then(|p| if p <= 100 { Some(MemberCount::Percent(p)) } else { None })
+        } else {
+            value.parse::<u32>().ok().map(MemberCount::Count)
+        };
+        count.ok_or_else(|| sup_error!(Error::InvalidUpdateCanary(value.to_string())))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MemberCount {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserialize_using_from_str(deserializer)
+    }
+}
+
+impl serde::Serialize for MemberCount {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
 pub enum Spec {
     Service(ServiceSpec),
     Composite(CompositeSpec, Vec<ServiceSpec>),
@@ -141,6 +220,7 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         if let Some(update_strategy) = self.update_strategy {
             spec.update_strategy = UpdateStrategy::from_i32(update_strategy).unwrap_or_default();
         }
+        set_update_settings(self, spec);
         if let Some(ref list) = self.binds {
             let binds: Vec<ServiceBind> = list.binds.clone().into_iter().map(Into::into).collect();
             let (_, standard) = binds.into_iter().partition(|ref bind| bind.is_composite());
@@ -155,6 +235,7 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         if let Some(ref svc_encrypted_password) = self.svc_encrypted_password {
             spec.svc_encrypted_password = Some(svc_encrypted_password.to_string());
         }
+        set_health_check_settings(self, spec);
         spec.composite = None;
     }
 
@@ -234,6 +315,7 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
         if let Some(update_strategy) = self.update_strategy {
             spec.update_strategy = UpdateStrategy::from_i32(update_strategy).unwrap_or_default();
         }
+        set_update_settings(self, spec);
         if let Some(ref list) = self.binds {
             let binds: Vec<ServiceBind> = list
                 .binds
@@ -245,6 +327,85 @@ impl IntoServiceSpec for protocol::ctl::SvcLoad {
             spec.binds = standard;
             set_composite_binds(spec, bind_map, &composite);
         }
+        set_health_check_settings(self, spec);
+    }
+}
+
+/// Apply any canary, batch, update window, spread, and rollback settings present in the `SvcLoad`
+/// message to the given spec. Invalid canary counts are rejected by the CLI, and invalid update
+/// windows by the Supervisor, before they get here. An empty update window or spread label, or a
+/// rollback window of `0`, removes that setting.
+fn set_update_settings(msg: &protocol::ctl::SvcLoad, spec: &mut ServiceSpec) {
+    if let Some(canary) = msg
+        .update_canary
+        .as_ref()
+        .and_then(|c| MemberCount::from_str(c).ok())
+    {
+        spec.update_canary = canary;
+    }
+    if let Some(batch_size) = msg.update_batch_size {
+        spec.update_batch_size = batch_size.max(1);
+    }
+    if let Some(soak) = msg.update_soak {
+        spec.update_soak = soak as u64;
+    }
+    if let Some(ref window) = msg.update_window {
+        spec.update_window = UpdateWindow::from_str(window).ok();
+    }
+    if let Some(ref label) = msg.update_spread_by {
+        spec.update_spread_by = if label.is_empty() {
+            None
+        } else {
+            Some(label.to_string())
+        };
+    }
+    if let Some(window) = msg.update_rollback_window {
+        spec.update_rollback_window = if window == 0 {
+            None
+        } else {
+            Some(window as u64)
+        };
+    }
+}
+
+/// Apply any health check, restart policy, and crash settings present in the `SvcLoad` message to
+/// the given spec. A value of `0` for the health check timeout, critical restart count, or restart
+/// limit removes that setting.
+fn set_health_check_settings(msg: &protocol::ctl::SvcLoad, spec: &mut ServiceSpec) {
+    if let Some(interval) = msg.health_check_interval {
+        spec.health_check_interval = interval as u64;
+    }
+    if let Some(timeout) = msg.health_check_timeout {
+        spec.health_check_timeout = if timeout == 0 {
+            None
+        } else {
+            Some(timeout as u64)
+        };
+    }
+    if let Some(threshold) = msg.health_check_threshold {
+        spec.health_check_threshold = threshold;
+    }
+    if let Some(criticals) = msg.restart_on_critical {
+        spec.restart_on_critical = if criticals == 0 {
+            None
+        } else {
+            Some(criticals)
+        };
+    }
+    if let Some(backoff) = msg.restart_backoff {
+        spec.restart_backoff = backoff as u64;
+    }
+    if let Some(limit) = msg.restart_limit {
+        spec.restart_limit = if limit == 0 { None } else { Some(limit) };
+    }
+    if let Some(window) = msg.crash_window {
+        spec.crash_window = window as u64;
+    }
+    if let Some(limit) = msg.crash_limit {
+        spec.crash_limit = limit;
+    }
+    if let Some(backoff) = msg.crash_backoff {
+        spec.crash_backoff = backoff as u64;
     }
 }
 
@@ -266,6 +427,27 @@ pub struct ServiceSpec {
     pub channel: String,
     pub topology: Topology,
     pub update_strategy: UpdateStrategy,
+    /// Number of members, counting the update leader, which update first and must report a
+    /// healthy status before the rest of the group updates. Only used by the canary strategy.
+    pub update_canary: MemberCount,
+    /// Number of members updated together in each batch once the canaries are healthy. Only used
+    /// by the canary strategy.
+    pub update_batch_size: u32,
+    /// Number of seconds every member ahead of a batch must have reported a healthy status on the
+    /// new release before the batch updates. Only used by the canary strategy.
+    pub update_soak: u64,
+    /// Number of seconds an updated service has to report a healthy status before it is rolled
+    /// back to the release it was running before the update. Updates are never rolled back if
+    /// this is not set.
+    pub update_rollback_window: Option<u64>,
+    /// Schedule of the windows in which updates may be applied. Updates found outside of a window
+    /// are installed but held until the next window opens.
+    pub update_window: Option<UpdateWindow>,
+    /// Member label whose values rolling and canary updates are spread across, so that
+    /// consecutive members updating run in different zones, racks, etc.
+    pub update_spread_by: Option<String>,
+    /// Hold the service at its current release. Its updates are paused until it is released.
+    pub update_hold: bool,
     pub binds: Vec<ServiceBind>,
     pub binding_mode: BindingMode,
     pub config_from: Option<PathBuf>,
@@ -277,6 +459,33 @@ pub struct ServiceSpec {
     pub svc_encrypted_password: Option<String>,
     // The name of the composite this service is a part of
     pub composite: Option<String>,
+    /// Number of seconds between runs of the service's health check.
+    pub health_check_interval: u64,
+    /// Number of seconds a health check hook may run before it is killed and its result
+    /// considered unknown.
+    pub health_check_timeout: Option<u64>,
+    /// Number of consecutive critical health check results required before the service's health
+    /// changes to critical.
+    pub health_check_threshold: u32,
+    /// Restart the service after this many consecutive critical health check results. Services
+    /// are never restarted for failing health checks if this is not set.
+    pub restart_on_critical: Option<u32>,
+    /// Number of seconds to wait before restarting an unhealthy service. The delay is doubled
+    /// after each consecutive restart.
+    pub restart_backoff: u64,
+    /// Maximum number of consecutive restarts of an unhealthy service, after which the service is
+    /// left running in its unhealthy state.
+    pub restart_limit: Option<u32>,
+    /// Number of seconds within which unexpected process exits are counted towards the crash
+    /// limit.
+    pub crash_window: u64,
+    /// Number of unexpected process exits within the crash window after which the service is
+    /// marked as failed and no longer restarted. The service is restarted indefinitely if this
+    /// is `0`.
+    pub crash_limit: u32,
+    /// Number of seconds to wait before restarting a service whose process exited unexpectedly.
+    /// The delay is doubled for each further exit within the crash window.
+    pub crash_backoff: u64,
 }
 
 impl ServiceSpec {
@@ -395,12 +604,28 @@ impl Default for ServiceSpec {
             channel: STABLE_CHANNEL.to_string(),
             topology: Topology::default(),
             update_strategy: UpdateStrategy::default(),
+            update_canary: MemberCount::default(),
+            update_batch_size: DEFAULT_UPDATE_BATCH_SIZE,
+            update_soak: DEFAULT_UPDATE_SOAK,
+            update_rollback_window: None,
+            update_window: None,
+            update_spread_by: None,
+            update_hold: false,
             binds: Vec::default(),
             binding_mode: BindingMode::Strict,
             config_from: None,
             desired_state: DesiredState::default(),
             svc_encrypted_password: None,
             composite: None,
+            health_check_interval: DEFAULT_HEALTH_CHECK_INTERVAL,
+            health_check_timeout: None,
+            health_check_threshold: DEFAULT_HEALTH_CHECK_THRESHOLD,
+            restart_on_critical: None,
+            restart_backoff: DEFAULT_RESTART_BACKOFF,
+            restart_limit: None,
+            crash_window: DEFAULT_CRASH_WINDOW,
+            crash_limit: DEFAULT_CRASH_LIMIT,
+            crash_backoff: DEFAULT_CRASH_BACKOFF,
         }
     }
 }
@@ -424,6 +649,8 @@ pub struct ServiceBind {
     pub service_group: ServiceGroup,
     /// Only set if this is a bind targeting a composite service
     pub service_name: Option<String>,
+    /// Only members carrying every one of these labels satisfy the bind
+    pub labels: BTreeMap<String, String>,
 }
 
 impl ServiceBind {
@@ -436,21 +663,29 @@ impl FromStr for ServiceBind {
     type Err = SupError;
 
     fn from_str(bind_str: &str) -> result::Result<Self, Self::Err> {
-        let values: Vec<&str> = bind_str.split(':').collect();
+        let mut parts = bind_str.splitn(2, '/');
+        let values: Vec<&str> = parts.next().unwrap_or("").split(':').collect();
         if !(values.len() == 3 || values.len() == 2) {
             return Err(sup_error!(Error::InvalidBinding(bind_str.to_string())));
         }
+        let labels = match parts.next() {
+            Some(selector) => protocol::types::parse_label_selector(selector)
+                .ok_or(sup_error!(Error::InvalidBinding(bind_str.to_string())))?,
+            None => BTreeMap::new(),
+        };
         let bind = if values.len() == 3 {
             ServiceBind {
                 name: values[1].to_string(),
                 service_group: ServiceGroup::from_str(values[2])?,
                 service_name: Some(values[0].to_string()),
+                labels: labels,
             }
         } else {
             ServiceBind {
                 name: values[0].to_string(),
                 service_group: ServiceGroup::from_str(values[1])?,
                 service_name: None,
+                labels: labels,
             }
         };
         Ok(bind)
@@ -465,7 +700,16 @@ impl fmt::Display for ServiceBind {
         //
         // Display is what governs how this is rendered in a spec
         // file, so everything should look the same.
-        write!(f, "{}:{}", self.name, self.service_group)
+        write!(f, "{}:{}", self.name, self.service_group)?;
+        if !self.labels.is_empty() {
+            let labels: Vec<String> = self
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            write!(f, "/{}", labels.join(","))?;
+        }
+        Ok(())
     }
 }
 
@@ -534,6 +778,7 @@ fn set_composite_binds(spec: &mut ServiceSpec, bind_map: &mut BindMap, binds: &V
                 name: bind_mapping.bind_name.clone(),
                 service_group: group,
                 service_name: Some(bind_mapping.bind_name.clone()),
+                labels: BTreeMap::new(),
             };
             final_binds.insert(bind.name.clone(), bind);
         }
@@ -632,6 +877,57 @@ mod test {
         );
     }
 
+    #[test]
+    fn service_spec_from_str_health_check_settings() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            health_check_interval = 5
+            health_check_timeout = 2
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.health_check_interval, 5);
+        assert_eq!(spec.health_check_timeout, Some(2));
+        assert_eq!(
+            spec.health_check_threshold, 1,
+            "A single critical result changes health, if nothing was previously specified."
+        );
+    }
+
+    #[test]
+    fn service_spec_from_str_update_settings() {
+        let toml = r#"
+            ident = "origin/name/1.2.3/20170223130020"
+            update_strategy = "canary"
+            update_canary = "10%"
+            update_batch_size = 3
+            update_spread_by = "zone"
+            "#;
+        let spec = ServiceSpec::from_str(toml).unwrap();
+
+        assert_eq!(spec.update_strategy, UpdateStrategy::Canary);
+        assert_eq!(spec.update_canary, MemberCount::Percent(10));
+        assert_eq!(spec.update_batch_size, 3);
+        assert_eq!(spec.update_spread_by, Some(String::from("zone")));
+    }
+
+    #[test]
+    fn member_count_from_str() {
+        assert_eq!(MemberCount::from_str("2").unwrap(), MemberCount::Count(2));
+        assert_eq!(MemberCount::from_str("25%").unwrap(), MemberCount::Percent(25));
+        assert!(MemberCount::from_str("101%").is_err());
+        assert!(MemberCount::from_str("some").is_err());
+    }
+
+    #[test]
+    fn member_count_of() {
+        assert_eq!(MemberCount::Count(2).of(10), 2);
+        assert_eq!(MemberCount::Count(5).of(3), 3);
+        assert_eq!(MemberCount::Percent(25).of(10), 3);
+        assert_eq!(MemberCount::Percent(10).of(4), 1);
+        assert_eq!(MemberCount::Percent(0).of(4), 0);
+    }
+
     #[test]
     fn service_spec_from_str_missing_ident() {
         let toml = r#""#;
@@ -690,6 +986,13 @@ mod test {
             channel: String::from("unstable"),
             topology: Topology::Leader,
             update_strategy: UpdateStrategy::AtOnce,
+            update_canary: MemberCount::default(),
+            update_batch_size: 2,
+            update_soak: 300,
+            update_rollback_window: Some(120),
+            update_window: Some(UpdateWindow::from_str("Sat-Sun 02:00-04:00 UTC").unwrap()),
+            update_spread_by: Some(String::from("zone")),
+            update_hold: true,
             binds: vec![
                 ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
                 ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
@@ -699,6 +1002,15 @@ mod test {
             desired_state: DesiredState::Down,
             svc_encrypted_password: None,
             composite: None,
+            health_check_interval: 10,
+            health_check_timeout: Some(5),
+            health_check_threshold: 3,
+            restart_on_critical: Some(2),
+            restart_backoff: 30,
+            restart_limit: Some(4),
+            crash_window: 600,
+            crash_limit: 3,
+            crash_backoff: 5,
         };
         let toml = spec.to_toml_string().unwrap();
 
@@ -709,11 +1021,40 @@ mod test {
         assert!(toml.contains(r#"channel = "unstable""#));
         assert!(toml.contains(r#"topology = "leader""#));
         assert!(toml.contains(r#"update_strategy = "at-once""#));
+        assert!(toml.contains(r#"update_batch_size = 2"#));
+        assert!(toml.contains(r#"update_soak = 300"#));
+        assert!(toml.contains(r#"update_rollback_window = 120"#));
+        assert!(toml.contains(r#"update_window = "Sat-Sun 02:00-04:00 UTC""#));
+        assert!(toml.contains(r#"update_spread_by = "zone""#));
+        assert!(toml.contains(r#"update_hold = true"#));
         assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
         assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
         assert!(toml.contains(r#"desired_state = "down""#));
         assert!(toml.contains(r#"config_from = "/only/for/development""#));
         assert!(toml.contains(r#"binding_mode = "relaxed""#));
+        assert!(toml.contains(r#"health_check_interval = 10"#));
+        assert!(toml.contains(r#"health_check_timeout = 5"#));
+        assert!(toml.contains(r#"health_check_threshold = 3"#));
+        assert!(toml.contains(r#"restart_on_critical = 2"#));
+        assert!(toml.contains(r#"restart_backoff = 30"#));
+        assert!(toml.contains(r#"restart_limit = 4"#));
+        assert!(toml.contains(r#"crash_window = 600"#));
+        assert!(toml.contains(r#"crash_limit = 3"#));
+        assert!(toml.contains(r#"crash_backoff = 5"#));
+    }
+
+    #[test]
+    fn service_spec_to_toml_string_canary() {
+        let spec = ServiceSpec {
+            ident: PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+            update_strategy: UpdateStrategy::Canary,
+            update_canary: MemberCount::Percent(25),
+            ..ServiceSpec::default()
+        };
+        let toml = spec.to_toml_string().unwrap();
+
+        assert!(toml.contains(r#"update_strategy = "canary""#));
+        assert!(toml.contains(r#"update_canary = "25%""#));
     }
 
     #[test]
@@ -840,6 +1181,13 @@ mod test {
             channel: String::from("unstable"),
             topology: Topology::Leader,
             update_strategy: UpdateStrategy::AtOnce,
+            update_canary: MemberCount::default(),
+            update_batch_size: 2,
+            update_soak: 300,
+            update_rollback_window: Some(120),
+            update_window: Some(UpdateWindow::from_str("Sat-Sun 02:00-04:00 UTC").unwrap()),
+            update_spread_by: Some(String::from("zone")),
+            update_hold: true,
             binds: vec![
                 ServiceBind::from_str("cache:redis.cache@acmecorp").unwrap(),
                 ServiceBind::from_str("db:postgres.app@acmecorp").unwrap(),
@@ -849,6 +1197,15 @@ mod test {
             desired_state: DesiredState::Down,
             svc_encrypted_password: None,
             composite: None,
+            health_check_interval: 10,
+            health_check_timeout: Some(5),
+            health_check_threshold: 3,
+            restart_on_critical: Some(2),
+            restart_backoff: 30,
+            restart_limit: Some(4),
+            crash_window: 600,
+            crash_limit: 3,
+            crash_backoff: 5,
         };
         spec.to_file(&path).unwrap();
         let toml = string_from_file(path);
@@ -860,11 +1217,43 @@ mod test {
         assert!(toml.contains(r#"channel = "unstable""#));
         assert!(toml.contains(r#"topology = "leader""#));
         assert!(toml.contains(r#"update_strategy = "at-once""#));
+        assert!(toml.contains(r#"update_batch_size = 2"#));
+        assert!(toml.contains(r#"update_soak = 300"#));
+        assert!(toml.contains(r#"update_rollback_window = 120"#));
+        assert!(toml.contains(r#"update_window = "Sat-Sun 02:00-04:00 UTC""#));
+        assert!(toml.contains(r#"update_spread_by = "zone""#));
+        assert!(toml.contains(r#"update_hold = true"#));
         assert!(toml.contains(r#""cache:redis.cache@acmecorp""#));
         assert!(toml.contains(r#""db:postgres.app@acmecorp""#));
         assert!(toml.contains(r#"desired_state = "down""#));
         assert!(toml.contains(r#"config_from = "/only/for/development""#));
         assert!(toml.contains(r#"binding_mode = "relaxed""#));
+        assert!(toml.contains(r#"health_check_interval = 10"#));
+        assert!(toml.contains(r#"health_check_timeout = 5"#));
+        assert!(toml.contains(r#"health_check_threshold = 3"#));
+        assert!(toml.contains(r#"restart_on_critical = 2"#));
+        assert!(toml.contains(r#"restart_backoff = 30"#));
+        assert!(toml.contains(r#"restart_limit = 4"#));
+        assert!(toml.contains(r#"crash_window = 600"#));
+        assert!(toml.contains(r#"crash_limit = 3"#));
+        assert!(toml.contains(r#"crash_backoff = 5"#));
+    }
+
+    #[test]
+    fn service_spec_to_file_canary() {
+        let tmpdir = TempDir::new("specs").unwrap();
+        let path = tmpdir.path().join("name.spec");
+        let spec = ServiceSpec {
+            ident: PackageIdent::from_str("origin/name/1.2.3/20170223130020").unwrap(),
+            update_strategy: UpdateStrategy::Canary,
+            update_canary: MemberCount::Count(2),
+            ..ServiceSpec::default()
+        };
+        spec.to_file(&path).unwrap();
+        let spec = ServiceSpec::from_file(&path).unwrap();
+
+        assert_eq!(spec.update_strategy, UpdateStrategy::Canary);
+        assert_eq!(spec.update_canary, MemberCount::Count(2));
     }
 
     #[test]
@@ -962,11 +1351,44 @@ mod test {
             name: String::from("name"),
             service_group: ServiceGroup::from_str("service.group").unwrap(),
             service_name: None,
+            labels: BTreeMap::new(),
         };
 
         assert_eq!("name:service.group", bind.to_string());
     }
 
+    #[test]
+    fn service_bind_from_str_with_labels() {
+        let bind_str = "name:service.group/zone=us-east-1a,rack=r12";
+        let bind = ServiceBind::from_str(bind_str).unwrap();
+
+        assert_eq!(bind.name, String::from("name"));
+        assert_eq!(
+            bind.service_group,
+            ServiceGroup::from_str("service.group").unwrap()
+        );
+        assert_eq!(bind.labels.get("zone"), Some(&String::from("us-east-1a")));
+        assert_eq!(bind.labels.get("rack"), Some(&String::from("r12")));
+        // Labels are always rendered in order, so the spec file stays stable
+        assert_eq!(
+            "name:service.group/rack=r12,zone=us-east-1a",
+            bind.to_string()
+        );
+    }
+
+    #[test]
+    fn service_bind_from_str_invalid_labels() {
+        let bind_str = "name:service.group/zone";
+
+        match ServiceBind::from_str(bind_str) {
+            Err(e) => match e.err {
+                InvalidBinding(val) => assert_eq!("name:service.group/zone", val),
+                wrong => panic!("Unexpected error returned: {:?}", wrong),
+            },
+            Ok(_) => panic!("String should fail to parse"),
+        }
+    }
+
     #[test]
     fn service_bind_toml_deserialize() {
         #[derive(Deserialize)]
@@ -995,6 +1417,7 @@ mod test {
                 name: String::from("name"),
                 service_group: ServiceGroup::from_str("service.group").unwrap(),
                 service_name: None,
+                labels: BTreeMap::new(),
             },
         };
         let toml = toml::to_string(&data).unwrap();
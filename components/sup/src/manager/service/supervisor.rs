@@ -18,6 +18,7 @@
 /// spawning the new process, watching for failure, and ensuring the service is either up or down.
 /// If the process dies, the Supervisor will restart it.
 use std;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -31,7 +32,7 @@ use hcore::service::ServiceGroup;
 use launcher_client::LauncherCli;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
-use time::{self, Timespec};
+use time::{self, Duration, Timespec};
 
 use super::ProcessState;
 use super::ShutdownReason;
@@ -43,6 +44,19 @@ use sys::abilities;
 
 static LOGKEY: &'static str = "SV";
 
+/// How a process which keeps exiting unexpectedly is restarted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CrashPolicy {
+    /// Process exits older than this many seconds are forgotten when detecting a crash loop.
+    pub window: u64,
+    /// Number of process exits within the crash window after which the process is marked as
+    /// failed. A process is never marked as failed if this is 0.
+    pub limit: u32,
+    /// Delay in seconds before restarting a process that has exited once; doubled for each
+    /// further exit within the crash window.
+    pub backoff: u64,
+}
+
 /// Bundles up information about the user and group that a supervised
 /// service should be run as. If the Supervisor itself is running with
 /// root-like permissions, then these will be for `SVC_USER` and
@@ -65,16 +79,24 @@ pub struct Supervisor {
     pub preamble: String,
     pub state: ProcessState,
     pub state_entered: Timespec,
+    /// Number of times the process has been restarted by this Supervisor.
+    pub restarts: u64,
+    pub crash_policy: CrashPolicy,
+    /// Times at which the process exited unexpectedly, within the crash window.
+    exits: VecDeque<Timespec>,
     pid: Option<Pid>,
     pid_file: PathBuf,
 }
 
 impl Supervisor {
-    pub fn new(service_group: &ServiceGroup) -> Supervisor {
+    pub fn new(service_group: &ServiceGroup, crash_policy: CrashPolicy) -> Supervisor {
         Supervisor {
             preamble: service_group.to_string(),
             state: ProcessState::Down,
             state_entered: time::get_time(),
+            restarts: 0,
+            crash_policy: crash_policy,
+            exits: VecDeque::new(),
             pid: None,
             pid_file: fs::svc_pid_file(service_group.service()),
         }
@@ -100,12 +122,86 @@ impl Supervisor {
             }
         }
         debug!("Could not find a live process with pid {:?}", self.pid);
+        if self.state == ProcessState::Up {
+            self.record_exit(time::get_time());
+        }
+        if self.failed() {
+            self.pid = None;
+            return false;
+        }
         self.change_state(ProcessState::Down);
         self.cleanup_pidfile();
         self.pid = None;
         false
     }
 
+    /// Returns `true` if the process exited too many times in the crash window and will not be
+    /// restarted until `clear_failed` is called.
+    pub fn failed(&self) -> bool {
+        self.state == ProcessState::Failed
+    }
+
+    /// Returns `true` if the process may be restarted now, or `false` if we are still backing
+    /// off after recent unexpected exits.
+    pub fn restart_allowed(&self) -> bool {
+        self.restart_allowed_at(time::get_time())
+    }
+
+    fn restart_allowed_at(&self, now: Timespec) -> bool {
+        if self.failed() {
+            return false;
+        }
+        match self.exits.back() {
+            Some(last_exit) => now >= *last_exit + self.crash_backoff(),
+            None => true,
+        }
+    }
+
+    /// Forget about any previous unexpected exits, moving a failed process back to the down
+    /// state so that it will be started again.
+    ///
+    /// Returns `true` if the process was in the failed state.
+    pub fn clear_failed(&mut self) -> bool {
+        self.exits.clear();
+        if self.failed() {
+            self.change_state(ProcessState::Down);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn crash_backoff(&self) -> Duration {
+        let exponent = self.exits.len().saturating_sub(1).min(10) as u32;
+        let secs = self.crash_policy.backoff.saturating_mul(2u64.pow(exponent));
+        Duration::seconds(secs.min(i64::max_value() as u64 / 1000) as i64)
+    }
+
+    fn record_exit(&mut self, now: Timespec) {
+        let window = Duration::seconds(self.crash_policy.window as i64);
+        while let Some(exit) = self.exits.front().cloned() {
+            if now - exit > window {
+                self.exits.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.exits.push_back(now);
+        let limit = self.crash_policy.limit as usize;
+        if limit > 0 && self.exits.len() >= limit {
+            outputln!(preamble self.preamble,
+                      "Process exited {} times in {} seconds, marking as failed. Run `hab svc \
+                       start` to start it again.", self.exits.len(), self.crash_policy.window);
+            self.cleanup_pidfile();
+            self.pid = None;
+            self.change_state(ProcessState::Failed);
+        } else {
+            outputln!(preamble self.preamble,
+                      "Process exited unexpectedly, restarting in {} seconds",
+                      self.crash_backoff().num_seconds());
+        }
+    }
+
     // NOTE: the &self argument is only used to get access to
     // self.preamble, and even then only for Linux :/
     #[cfg(unix)]
@@ -229,7 +325,7 @@ impl Supervisor {
         );
         let healthy = match self.state {
             ProcessState::Up => true,
-            ProcessState::Down => false,
+            ProcessState::Down | ProcessState::Failed => false,
         };
         (healthy, status)
     }
@@ -264,6 +360,7 @@ impl Supervisor {
             Some(pid) => match launcher.restart(pid) {
                 Ok(pid) => {
                     self.pid = Some(pid);
+                    self.restarts += 1;
                     self.create_pidfile()?;
                     self.change_state(ProcessState::Up);
                     Ok(())
@@ -326,6 +423,7 @@ impl Serialize for Supervisor {
         strukt.serialize_field("pid", &self.pid)?;
         strukt.serialize_field("state", &self.state)?;
         strukt.serialize_field("state_entered", &self.state_entered.sec)?;
+        strukt.serialize_field("restarts", &self.restarts)?;
         strukt.end()
     }
 }
@@ -355,3 +453,77 @@ where
         ))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use time::{Duration, Timespec};
+
+    fn supervisor(limit: u32) -> Supervisor {
+        let sg = ServiceGroup::new(None, "crash-policy-test", "default", None).unwrap();
+        Supervisor::new(
+            &sg,
+            CrashPolicy {
+                window: 300,
+                limit: limit,
+                backoff: 2,
+            },
+        )
+    }
+
+    fn at(secs: i64) -> Timespec {
+        Timespec::new(1_000_000 + secs, 0)
+    }
+
+    #[test]
+    fn exits_outside_the_crash_window_are_forgotten() {
+        let mut sup = supervisor(3);
+        sup.record_exit(at(0));
+        sup.record_exit(at(100));
+        sup.record_exit(at(401));
+        assert_eq!(sup.exits.len(), 2);
+        assert!(!sup.failed());
+        sup.record_exit(at(700));
+        assert_eq!(sup.exits.len(), 2);
+        assert!(!sup.failed());
+    }
+
+    #[test]
+    fn backoff_doubles_with_each_exit_in_the_window() {
+        let mut sup = supervisor(0);
+        assert!(sup.restart_allowed_at(at(0)));
+        sup.record_exit(at(0));
+        assert_eq!(sup.crash_backoff(), Duration::seconds(2));
+        assert!(!sup.restart_allowed_at(at(1)));
+        assert!(sup.restart_allowed_at(at(2)));
+        sup.record_exit(at(10));
+        assert_eq!(sup.crash_backoff(), Duration::seconds(4));
+        sup.record_exit(at(20));
+        assert_eq!(sup.crash_backoff(), Duration::seconds(8));
+        assert!(!sup.restart_allowed_at(at(27)));
+        assert!(sup.restart_allowed_at(at(28)));
+        for i in 0..20 {
+            sup.record_exit(at(30 + i));
+        }
+        assert_eq!(sup.crash_backoff(), Duration::seconds(2 * 1024));
+        assert!(!sup.failed());
+    }
+
+    #[test]
+    fn process_is_failed_at_the_crash_limit() {
+        let mut sup = supervisor(3);
+        sup.record_exit(at(0));
+        sup.record_exit(at(10));
+        assert!(!sup.failed());
+        sup.record_exit(at(20));
+        assert!(sup.failed());
+        assert_eq!(sup.state, ProcessState::Failed);
+        assert!(!sup.restart_allowed_at(at(10_000)));
+
+        assert!(sup.clear_failed());
+        assert_eq!(sup.state, ProcessState::Down);
+        assert!(sup.restart_allowed_at(at(20)));
+        assert!(!sup.clear_failed());
+    }
+}
@@ -12,20 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use butterfly;
 use common::ui::UI;
 use env;
+use hcore::fs::FS_ROOT_PATH;
 use hcore::package::{PackageIdent, PackageInstall, PackageTarget};
 use hcore::service::ServiceGroup;
 use launcher_client::LauncherCli;
+use time::SteadyTime;
 
-use census::CensusRing;
+use census::{CensusGroup, CensusMember, CensusRing, ElectionStatus, MemberId};
 use manager::periodic::Periodic;
-use manager::service::{Service, Topology, UpdateStrategy};
+use manager::service::{HealthCheck, Service, Topology, UpdateStrategy};
 use util;
 
 static LOGKEY: &'static str = "SU";
@@ -35,6 +40,9 @@ const MIN_ALLOWED_FREQUENCY: i64 = 60_000;
 const DEFAULT_FREQUENCY: i64 = MIN_ALLOWED_FREQUENCY;
 
 type UpdaterStateList = HashMap<ServiceGroup, UpdaterState>;
+/// Releases of a service group's package which failed to become healthy after an update and
+/// were rolled back. These are shared with update workers, which never install them again.
+type BlockedReleases = Arc<RwLock<HashSet<PackageIdent>>>;
 
 enum UpdaterState {
     AtOnce(Receiver<PackageInstall>),
@@ -44,6 +52,9 @@ enum UpdaterState {
 enum RollingState {
     AwaitingElection,
     InElection,
+    /// Waiting for the update election of the given term, which we started in order to lead an
+    /// update requested through the control gateway
+    Requested(u64),
     Leader(LeaderState),
     Follower(FollowerState),
 }
@@ -60,6 +71,77 @@ enum FollowerState {
     Waiting,
     /// Currently updating
     Updating(Receiver<PackageInstall>),
+    /// A canary update to the given package was halted because an updated member reported a
+    /// critical health status
+    Halted(Option<PackageIdent>),
+}
+
+/// Where a follower stands in an update to the update leader's package
+#[derive(Debug, PartialEq)]
+enum UpdateTurn {
+    /// It's our turn to update
+    Ready,
+    /// Members ahead of us have not all updated and reported a healthy status yet
+    Waiting,
+    /// The given member reported a critical health status after updating
+    Halted(MemberId),
+}
+
+/// An update requested through the control gateway
+#[derive(Clone, Debug, PartialEq)]
+pub enum UpdateRequest {
+    /// Check for a newer release now
+    Latest,
+    /// Install the given fully-qualified release now
+    Release(PackageIdent),
+}
+
+/// Wakes a polling update worker to handle an update request right away, rather than at its next
+/// poll.
+#[derive(Clone, Default)]
+struct UpdateTrigger(Arc<(Mutex<Option<UpdateRequest>>, Condvar)>);
+
+impl UpdateTrigger {
+    fn new(request: Option<UpdateRequest>) -> Self {
+        UpdateTrigger(Arc::new((Mutex::new(request), Condvar::new())))
+    }
+
+    fn request(&self, request: UpdateRequest) {
+        let &(ref lock, ref cvar) = &*self.0;
+        *lock.lock().expect("Update trigger lock poisoned") = Some(request);
+        cvar.notify_all();
+    }
+
+    fn take(&self) -> Option<UpdateRequest> {
+        let &(ref lock, _) = &*self.0;
+        lock.lock().expect("Update trigger lock poisoned").take()
+    }
+
+    /// Wait until the given time, or until an update is requested. Returns the request, if any.
+    fn wait_until(&self, next_time: SteadyTime) -> Option<UpdateRequest> {
+        let &(ref lock, ref cvar) = &*self.0;
+        let mut request = lock.lock().expect("Update trigger lock poisoned");
+        loop {
+            if request.is_some() {
+                return request.take();
+            }
+            let time_to_wait = (next_time - SteadyTime::now()).num_milliseconds();
+            if time_to_wait <= 0 {
+                return None;
+            }
+            request = cvar
+                .wait_timeout(request, Duration::from_millis(time_to_wait as u64))
+                .expect("Update trigger lock poisoned")
+                .0;
+        }
+    }
+}
+
+/// An update which must report a healthy status before its deadline, or be rolled back to the
+/// release it replaced
+struct PendingRollback {
+    previous: PackageIdent,
+    deadline: Instant,
 }
 
 /// The ServiceUpdater is in charge of updating a Service when a more recent version of a package
@@ -67,6 +149,15 @@ enum FollowerState {
 /// To use an update strategy, the supervisor must be configured to watch a depot for new versions.
 pub struct ServiceUpdater {
     states: UpdaterStateList,
+    blocked: HashMap<ServiceGroup, BlockedReleases>,
+    rollbacks: HashMap<ServiceGroup, PendingRollback>,
+    held: HashMap<ServiceGroup, PackageInstall>,
+    /// When each member of a service group in a canary update first reported a healthy status on
+    /// the update leader's package
+    healthy_since: HashMap<ServiceGroup, HashMap<MemberId, Instant>>,
+    /// Update requests waiting for a worker to poll for them
+    requests: HashMap<ServiceGroup, UpdateRequest>,
+    triggers: HashMap<ServiceGroup, UpdateTrigger>,
     butterfly: butterfly::Server,
 }
 
@@ -74,6 +165,12 @@ impl ServiceUpdater {
     pub fn new(butterfly: butterfly::Server) -> Self {
         ServiceUpdater {
             states: UpdaterStateList::default(),
+            blocked: HashMap::default(),
+            rollbacks: HashMap::default(),
+            held: HashMap::default(),
+            healthy_since: HashMap::default(),
+            requests: HashMap::default(),
+            triggers: HashMap::default(),
             butterfly: butterfly,
         }
     }
@@ -85,15 +182,18 @@ impl ServiceUpdater {
         match service.update_strategy {
             UpdateStrategy::None => false,
             UpdateStrategy::AtOnce => {
+                let blocked = self.blocked_releases(&service.service_group);
+                let requests = &mut self.requests;
+                let triggers = &mut self.triggers;
                 self.states
                     .entry(service.service_group.clone())
                     .or_insert_with(|| {
-                        let rx = Worker::new(service).start(&service.service_group, None);
+                        let rx = start_polling(service, &blocked, requests, triggers);
                         UpdaterState::AtOnce(rx)
                     });
                 true
             }
-            UpdateStrategy::Rolling => {
+            UpdateStrategy::Rolling | UpdateStrategy::Canary => {
                 self.states
                     .entry(service.service_group.clone())
                     .or_insert(UpdaterState::Rolling(RollingState::AwaitingElection));
@@ -108,23 +208,241 @@ impl ServiceUpdater {
     /// See if the given service has an update. Returns `true` if a
     /// new version was installed, thus signalling that the service
     /// should be restarted
+    ///
+    /// Also rolls back an update which failed to report a healthy status within the service's
+    /// rollback window, in which case `true` is returned as well.
+    ///
+    /// Updates found while the service's update window is closed are held, and applied on the
+    /// first check after the window opens. Nothing is checked while the service itself is held at
+    /// its current release, or while a suspected network partition affects its service group.
     pub fn check_for_updated_package(
         &mut self,
         service: &mut Service,
         census_ring: &CensusRing,
         launcher: &LauncherCli,
     ) -> bool {
-        let mut updated = false;
+        if service.update_hold || !self.states.contains_key(&service.service_group) {
+            return false;
+        }
+        if census_ring.is_partitioned(&service.service_group) {
+            return false;
+        }
+        // A follower updating to its leader's release finishes that first; the request is kept
+        // on the service until then.
+        if !self.follower_updating(&service.service_group) {
+            if let Some(request) = service.update_request.take() {
+                self.request_update(service, request);
+            }
+        }
+        if self.update_leadership(service, census_ring) {
+            return false;
+        }
+        let blocked = self.blocked_releases(&service.service_group);
+        if let Some(census_group) = census_ring.census_group_for(&service.service_group) {
+            let mut releases = blocked.write().expect("Blocked releases lock poisoned");
+            for ident in census_group
+                .members()
+                .iter()
+                .filter_map(|cm| cm.rolled_back_from.as_ref())
+            {
+                if releases.insert(ident.clone()) {
+                    outputln!(preamble service.service_group,
+                              "No longer updating to {}; it was rolled back", ident);
+                }
+            }
+        }
+        if self.rollback_failed_update(service, launcher) {
+            return true;
+        }
+        let package = match self.held.remove(&service.service_group) {
+            Some(package) => package,
+            None => match self.update_from_state(service, census_ring, &blocked) {
+                Some(package) => package,
+                None => return false,
+            },
+        };
+        if let Some(ref window) = service.update_window {
+            if !window.is_open() {
+                if service.held_update.as_ref() != Some(package.ident()) {
+                    outputln!(preamble service.service_group,
+                              "Holding update to {} until the update window '{}' opens",
+                              package.ident(), window);
+                    service.held_update = Some(package.ident().clone());
+                }
+                self.held.insert(service.service_group.clone(), package);
+                return false;
+            }
+        }
+        service.held_update = None;
+        let previous = service.pkg.ident.clone();
+        let ident = package.ident().clone();
+        // The release isn't blocked: the error may be passing, such as failing to read the
+        // package, or go away once the user config is fixed. It is tried again on the next poll.
+        if let Err(err) = service.update_package(package, launcher) {
+            outputln!(preamble service.service_group,
+                      "Unable to update to {}, {}; trying again on the next poll", ident, err);
+            return false;
+        }
+        if let Some(pending) = pending_rollback(
+            previous,
+            &service.pkg.ident,
+            service.update_rollback_window,
+            Instant::now(),
+        ) {
+            self.rollbacks
+                .insert(service.service_group.clone(), pending);
+        }
+        true
+    }
+
+    /// Whether we are a follower in a rolling group, updating to the update leader's release.
+    fn follower_updating(&self, service_group: &ServiceGroup) -> bool {
+        match self.states.get(service_group) {
+            Some(&UpdaterState::Rolling(RollingState::Follower(FollowerState::Updating(_)))) => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle an update requested through the control gateway. A worker which is polling for
+    /// updates checks right away. Otherwise the request waits for the next worker to poll, and a
+    /// member of a rolling group which isn't leading its updates starts a new update election to
+    /// lead the requested update itself.
+    fn request_update(&mut self, service: &Service, request: UpdateRequest) {
+        let sg = &service.service_group;
+        let (polling, take_lead) = match self.states.get(sg) {
+            Some(&UpdaterState::AtOnce(_))
+            | Some(&UpdaterState::Rolling(RollingState::Leader(LeaderState::Polling(_)))) => {
+                (true, false)
+            }
+            Some(&UpdaterState::Rolling(RollingState::AwaitingElection))
+            | Some(&UpdaterState::Rolling(RollingState::InElection))
+            | Some(&UpdaterState::Rolling(RollingState::Follower(_))) => (false, true),
+            _ => (false, false),
+        };
+        if polling {
+            if let Some(trigger) = self.triggers.get(sg) {
+                trigger.request(request);
+                return;
+            }
+        }
+        self.requests.insert(sg.clone(), request);
+        if take_lead {
+            outputln!(preamble service.service_group,
+                      "Starting an update election to lead the requested update");
+            let term = self
+                .butterfly
+                .restart_update_election(sg.clone(), u64::max_value());
+            self.triggers.remove(sg);
+            self.states.insert(
+                sg.clone(),
+                UpdaterState::Rolling(RollingState::Requested(term)),
+            );
+        }
+    }
+
+    /// Settle who leads the updates of a rolling group after a new update election finishes,
+    /// either one we started to lead a requested update, or one started by another member for the
+    /// same reason. Returns `true` if our update state changed.
+    fn update_leadership(&mut self, service: &Service, census_ring: &CensusRing) -> bool {
+        let sg = &service.service_group;
+        let census_group = match census_ring.census_group_for(sg) {
+            Some(census_group) => census_group,
+            None => return false,
+        };
+        if census_group.update_election_status != ElectionStatus::ElectionFinished {
+            return false;
+        }
+        let leading = match (census_group.me(), census_group.update_leader()) {
+            (Some(me), Some(leader)) => me.member_id == leader.member_id,
+            _ => return false,
+        };
+        let next = match self.states.get(sg) {
+            Some(&UpdaterState::Rolling(RollingState::Requested(term))) => {
+                if census_group.update_election_term < term {
+                    return false;
+                }
+                if leading {
+                    RollingState::Leader(LeaderState::Waiting)
+                } else {
+                    outputln!(preamble service.service_group,
+                              "Another member is leading updates; dropping the requested update");
+                    self.requests.remove(sg);
+                    RollingState::Follower(FollowerState::Waiting)
+                }
+            }
+            Some(&UpdaterState::Rolling(RollingState::Leader(_))) if !leading => {
+                debug!("Another member is leading updates now; following it");
+                self.triggers.remove(sg);
+                RollingState::Follower(FollowerState::Waiting)
+            }
+            _ => return false,
+        };
+        self.states.insert(sg.clone(), UpdaterState::Rolling(next));
+        true
+    }
+
+    fn blocked_releases(&mut self, service_group: &ServiceGroup) -> BlockedReleases {
+        self.blocked
+            .entry(service_group.clone())
+            .or_insert_with(BlockedReleases::default)
+            .clone()
+    }
+
+    /// Roll the service back to the release it was running before its last update if that update
+    /// has not reported a healthy status within the service's rollback window. The failed release
+    /// is blocked from being installed again. Returns `true` if the service was rolled back.
+    fn rollback_failed_update(&mut self, service: &mut Service, launcher: &LauncherCli) -> bool {
+        let previous = match due_rollback(
+            &mut self.rollbacks,
+            &service.service_group,
+            service.health_check(),
+            Instant::now(),
+        ) {
+            Some(previous) => previous,
+            None => return false,
+        };
+        outputln!(preamble service.service_group,
+                  "{} did not become healthy after updating", service.pkg.ident);
+        self.blocked_releases(&service.service_group)
+            .write()
+            .expect("Blocked releases lock poisoned")
+            .insert(service.pkg.ident.clone());
+        match PackageInstall::load(&previous, Some(Path::new(&*FS_ROOT_PATH))) {
+            Ok(package) => match service.rollback(package, launcher) {
+                Ok(()) => true,
+                Err(err) => {
+                    outputln!(preamble service.service_group,
+                              "Unable to roll back to {}: {}", previous, err);
+                    false
+                }
+            },
+            Err(err) => {
+                outputln!(preamble service.service_group,
+                          "Unable to load {} to roll back to: {}", previous, err);
+                false
+            }
+        }
+    }
+
+    /// Advance the service's update state, returning a newly installed package which the service
+    /// should be updated to, if any.
+    fn update_from_state(
+        &mut self,
+        service: &Service,
+        census_ring: &CensusRing,
+        blocked: &BlockedReleases,
+    ) -> Option<PackageInstall> {
+        let mut updated = None;
+        let mut resumed = false;
         match self.states.get_mut(&service.service_group) {
             Some(&mut UpdaterState::AtOnce(ref mut rx)) => match rx.try_recv() {
-                Ok(package) => {
-                    service.update_package(package, launcher);
-                    return true;
-                }
-                Err(TryRecvError::Empty) => return false,
+                Ok(package) => return Some(package),
+                Err(TryRecvError::Empty) => return None,
                 Err(TryRecvError::Disconnected) => {
                     debug!("Service Updater worker has died; restarting...");
-                    *rx = Worker::new(service).start(&service.service_group, None);
+                    *rx = start_polling(service, blocked, &mut self.requests, &mut self.triggers);
                 }
             },
 
@@ -149,7 +467,7 @@ impl ServiceUpdater {
                                 );
                                 *st = RollingState::InElection
                             }
-                            _ => return false,
+                            _ => return None,
                         }
                     } else {
                         debug!("Rolling update, using default suitability");
@@ -173,8 +491,8 @@ impl ServiceUpdater {
                                 *st = RollingState::Follower(FollowerState::Waiting);
                             }
                         }
-                        (Some(_), None) => return false,
-                        _ => return false,
+                        (Some(_), None) => return None,
+                        _ => return None,
                     }
                 }
             }
@@ -183,13 +501,17 @@ impl ServiceUpdater {
                     LeaderState::Polling(ref mut rx) => match rx.try_recv() {
                         Ok(package) => {
                             debug!("Rolling Update, polling found a new package");
-                            service.update_package(package, launcher);
-                            updated = true;
+                            updated = Some(package);
                         }
-                        Err(TryRecvError::Empty) => return false,
+                        Err(TryRecvError::Empty) => return None,
                         Err(TryRecvError::Disconnected) => {
                             debug!("Service Updater worker has died; restarting...");
-                            *rx = Worker::new(service).start(&service.service_group, None);
+                            *rx = start_polling(
+                                service,
+                                blocked,
+                                &mut self.requests,
+                                &mut self.triggers,
+                            );
                         }
                     },
                     LeaderState::Waiting => {
@@ -200,9 +522,14 @@ impl ServiceUpdater {
                                         != census_group.me().unwrap().pkg.as_ref().unwrap()
                                 }) {
                                     debug!("Update leader still waiting for followers...");
-                                    return false;
+                                    return None;
                                 }
-                                let rx = Worker::new(service).start(&service.service_group, None);
+                                let rx = start_polling(
+                                    service,
+                                    blocked,
+                                    &mut self.requests,
+                                    &mut self.triggers,
+                                );
                                 *state = LeaderState::Polling(rx);
                             }
                             None => panic!(
@@ -212,7 +539,7 @@ impl ServiceUpdater {
                         }
                     }
                 }
-                if updated {
+                if updated.is_some() {
                     *state = LeaderState::Waiting;
                 }
             }
@@ -220,26 +547,64 @@ impl ServiceUpdater {
                 match *state {
                     FollowerState::Waiting => {
                         match census_ring.census_group_for(&service.service_group) {
-                            Some(census_group) => match (
-                                census_group.update_leader(),
-                                census_group.previous_peer(),
-                                census_group.me(),
-                            ) {
-                                (Some(leader), Some(peer), Some(me)) => {
-                                    if leader.pkg == me.pkg {
-                                        debug!("We're not in an update");
-                                        return false;
-                                    }
-                                    if leader.pkg != peer.pkg {
-                                        debug!("We're in an update but it's not our turn");
-                                        return false;
+                            Some(census_group) => {
+                                match (census_group.update_leader(), census_group.me()) {
+                                    (Some(leader), Some(me)) => {
+                                        if leader.pkg == me.pkg {
+                                            debug!("We're not in an update");
+                                            return None;
+                                        }
+                                        let healthy_since = self
+                                            .healthy_since
+                                            .entry(service.service_group.clone())
+                                            .or_insert_with(HashMap::new);
+                                        match follower_turn(
+                                            service,
+                                            census_group,
+                                            leader,
+                                            me,
+                                            blocked,
+                                            healthy_since,
+                                        ) {
+                                            UpdateTurn::Ready => (),
+                                            UpdateTurn::Waiting => {
+                                                debug!("We're in an update but it's not our turn");
+                                                return None;
+                                            }
+                                            UpdateTurn::Halted(member_id) => {
+                                                outputln!(preamble service.service_group,
+                                                          "Halting canary update; member {} is \
+                                                           critical after updating",
+                                                          member_id);
+                                                *state = FollowerState::Halted(leader.pkg.clone());
+                                                return None;
+                                            }
+                                        }
+                                        debug!("We're in an update and it's our turn");
+                                        let rx = Worker::new(service, blocked)
+                                            .start(&service.service_group, leader.pkg.clone());
+                                        *state = FollowerState::Updating(rx);
                                     }
-                                    debug!("We're in an update and it's our turn");
-                                    let rx = Worker::new(service)
-                                        .start(&service.service_group, leader.pkg.clone());
-                                    *state = FollowerState::Updating(rx);
+                                    _ => return None,
+                                }
+                            }
+                            None => panic!(
+                                "Expected census list to have service group '{}'!",
+                                &*service.service_group
+                            ),
+                        }
+                    }
+                    FollowerState::Updating(ref mut rx) => {
+                        match census_ring.census_group_for(&service.service_group) {
+                            Some(census_group) => match rx.try_recv() {
+                                Ok(package) => updated = Some(package),
+                                Err(TryRecvError::Empty) => return None,
+                                Err(TryRecvError::Disconnected) => {
+                                    debug!("Service Updater worker has died; restarting...");
+                                    let package = census_group.update_leader().unwrap().pkg.clone();
+                                    *rx = Worker::new(service, blocked)
+                                        .start(&service.service_group, package);
                                 }
-                                _ => return false,
                             },
                             None => panic!(
                                 "Expected census list to have service group '{}'!",
@@ -247,39 +612,201 @@ impl ServiceUpdater {
                             ),
                         }
                     }
-                    FollowerState::Updating(ref mut rx) => match census_ring
-                        .census_group_for(&service.service_group)
-                    {
-                        Some(census_group) => match rx.try_recv() {
-                            Ok(package) => {
-                                service.update_package(package, launcher);
-                                updated = true
-                            }
-                            Err(TryRecvError::Empty) => return false,
-                            Err(TryRecvError::Disconnected) => {
-                                debug!("Service Updater worker has died; restarting...");
-                                let package = census_group.update_leader().unwrap().pkg.clone();
-                                *rx = Worker::new(service).start(&service.service_group, package);
+                    FollowerState::Halted(ref halted) => {
+                        // Wait for the update leader to move on to another package, either a
+                        // fixed release or the one we are already running.
+                        match census_ring
+                            .census_group_for(&service.service_group)
+                            .and_then(|census_group| census_group.update_leader())
+                        {
+                            Some(leader) if leader.pkg != *halted => {
+                                debug!("Update leader has moved on from the halted update");
+                                resumed = true;
                             }
-                        },
-                        None => panic!(
-                            "Expected census list to have service group '{}'!",
-                            &*service.service_group
-                        ),
-                    },
+                            _ => return None,
+                        }
+                    }
                 }
-                if updated {
+                if updated.is_some() || resumed {
                     *state = FollowerState::Waiting;
                 }
             }
-            None => {}
+            Some(&mut UpdaterState::Rolling(RollingState::Requested(_))) | None => {}
         }
         updated
     }
 }
 
+/// The rollback to wait for after a service updated from `previous` to `updated`, if it has a
+/// rollback window and there is a previous release to roll back to.
+fn pending_rollback(
+    previous: PackageIdent,
+    updated: &PackageIdent,
+    window: Option<Duration>,
+    now: Instant,
+) -> Option<PendingRollback> {
+    match window {
+        Some(window) if previous != *updated => Some(PendingRollback {
+            previous: previous,
+            deadline: now + window,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the release to roll the service group back to if its last update has not reported a
+/// healthy status before the rollback deadline. The pending rollback is forgotten once the
+/// service is healthy or the rollback is due.
+fn due_rollback(
+    rollbacks: &mut HashMap<ServiceGroup, PendingRollback>,
+    service_group: &ServiceGroup,
+    health: HealthCheck,
+    now: Instant,
+) -> Option<PackageIdent> {
+    let expired = match rollbacks.get(service_group) {
+        Some(pending) => now >= pending.deadline,
+        None => return None,
+    };
+    if health == HealthCheck::Ok {
+        debug!("{} is healthy after updating", service_group);
+        rollbacks.remove(service_group);
+        return None;
+    }
+    if !expired {
+        return None;
+    }
+    rollbacks
+        .remove(service_group)
+        .map(|pending| pending.previous)
+}
+
+/// Start a worker polling for updates to the service, handing it any update requested while no
+/// worker was polling.
+fn start_polling(
+    service: &Service,
+    blocked: &BlockedReleases,
+    requests: &mut HashMap<ServiceGroup, UpdateRequest>,
+    triggers: &mut HashMap<ServiceGroup, UpdateTrigger>,
+) -> Receiver<PackageInstall> {
+    let trigger = UpdateTrigger::new(requests.remove(&service.service_group));
+    triggers.insert(service.service_group.clone(), trigger.clone());
+    Worker::new(service, blocked)
+        .with_trigger(trigger)
+        .start(&service.service_group, None)
+}
+
+/// Determine whether it's our turn to update to the update leader's package.
+///
+/// Members never update to a release which was rolled back. With the rolling strategy, members
+/// update one at a time, each waiting for the previous alive member to be running the leader's
+/// package. When the service spreads its updates across a member label, the previous member is
+/// the one ahead of it in the spread update order instead.
+fn follower_turn(
+    service: &Service,
+    census_group: &CensusGroup,
+    leader: &CensusMember,
+    me: &CensusMember,
+    blocked: &BlockedReleases,
+    healthy_since: &mut HashMap<MemberId, Instant>,
+) -> UpdateTurn {
+    if let Some(ref ident) = leader.pkg {
+        if blocked
+            .read()
+            .expect("Blocked releases lock poisoned")
+            .contains(ident)
+        {
+            return UpdateTurn::Waiting;
+        }
+    }
+    let spread_by = service.update_spread_by.as_ref().map(String::as_str);
+    if service.update_strategy == UpdateStrategy::Canary {
+        let order = census_group.update_order(spread_by);
+        canary_turn(
+            &order,
+            &me.member_id,
+            service.update_canary.of(order.len()),
+            service.update_batch_size as usize,
+            service.update_soak,
+            healthy_since,
+            Instant::now(),
+        )
+    } else if spread_by.is_some() {
+        let order = census_group.update_order(spread_by);
+        match order.iter().position(|cm| cm.member_id == me.member_id) {
+            Some(idx) if idx > 0 && order[idx - 1].pkg == leader.pkg => UpdateTurn::Ready,
+            _ => UpdateTurn::Waiting,
+        }
+    } else {
+        match census_group.previous_peer() {
+            Some(peer) if peer.pkg == leader.pkg => UpdateTurn::Ready,
+            _ => UpdateTurn::Waiting,
+        }
+    }
+}
+
+/// Determine whether the member `me` may update to the update leader's package during a canary
+/// update. `order` lists the members in update order, starting with the update leader.
+///
+/// The first `canaries` members, including the leader, update straight away. The remaining
+/// members update in batches of `batch_size`, each batch waiting until every member ahead of it
+/// runs the leader's package and has reported a healthy status for at least `soak`. The time each
+/// member first reported healthy is tracked in `healthy_since`, and forgotten as soon as it stops
+/// doing so. The update halts as soon as any member running the leader's package reports a
+/// critical status.
+fn canary_turn(
+    order: &[&CensusMember],
+    me: &str,
+    canaries: usize,
+    batch_size: usize,
+    soak: Duration,
+    healthy_since: &mut HashMap<MemberId, Instant>,
+    now: Instant,
+) -> UpdateTurn {
+    let target = match order.first() {
+        Some(leader) => &leader.pkg,
+        None => return UpdateTurn::Waiting,
+    };
+    if let Some(critical) = order
+        .iter()
+        .find(|cm| cm.pkg == *target && cm.health_check == Some(HealthCheck::Critical))
+    {
+        return UpdateTurn::Halted(critical.member_id.clone());
+    }
+    let healthy: Vec<&CensusMember> = order
+        .iter()
+        .filter(|cm| cm.pkg == *target && cm.health_check == Some(HealthCheck::Ok))
+        .map(|cm| *cm)
+        .collect();
+    healthy_since.retain(|id, _| healthy.iter().any(|cm| cm.member_id == *id));
+    for cm in healthy {
+        healthy_since.entry(cm.member_id.clone()).or_insert(now);
+    }
+    let position = match order.iter().position(|cm| cm.member_id == me) {
+        Some(position) => position,
+        None => return UpdateTurn::Waiting,
+    };
+    // The update leader is always the first canary
+    let canaries = canaries.max(1);
+    if position < canaries {
+        return UpdateTurn::Ready;
+    }
+    let batch_size = batch_size.max(1);
+    let batch_start = canaries + (position - canaries) / batch_size * batch_size;
+    if order[..batch_start].iter().all(|cm| {
+        healthy_since
+            .get(&cm.member_id)
+            .map_or(false, |since| now.duration_since(*since) >= soak)
+    }) {
+        UpdateTurn::Ready
+    } else {
+        UpdateTurn::Waiting
+    }
+}
+
 struct Worker {
     current: PackageIdent,
+    blocked: BlockedReleases,
+    trigger: UpdateTrigger,
     spec_ident: PackageIdent,
     builder_url: String,
     channel: String,
@@ -336,15 +863,24 @@ impl Periodic for Worker {
 }
 
 impl Worker {
-    fn new(service: &Service) -> Self {
+    fn new(service: &Service, blocked: &BlockedReleases) -> Self {
         Worker {
             current: service.pkg.ident.clone(),
+            blocked: blocked.clone(),
+            trigger: UpdateTrigger::default(),
             spec_ident: service.spec_ident.clone(),
             builder_url: service.bldr_url.clone(),
             channel: service.channel.clone(),
         }
     }
 
+    /// Wake the worker with the given trigger when an update is requested, instead of waiting
+    /// for its next poll.
+    fn with_trigger(mut self, trigger: UpdateTrigger) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
     /// Start a new update worker.
     ///
     /// Passing an optional package identifier will make the worker perform a run-once update to
@@ -412,12 +948,31 @@ impl Worker {
         }
     }
 
+    fn is_blocked(&self, ident: &PackageIdent) -> bool {
+        self.blocked
+            .read()
+            .expect("Blocked releases lock poisoned")
+            .contains(ident)
+    }
+
     /// Continually poll for a new version of a package, installing it
     /// when found.
+    ///
+    /// An update request wakes the worker up early. Requesting a specific release installs that
+    /// release instead of the latest one, even if it is older than the current one.
     fn run_poll(&mut self, sender: SyncSender<PackageInstall>) {
-        let install_source = (self.spec_ident.clone(), *PackageTarget::active_target()).into();
+        let mut request = self.trigger.take();
         loop {
             let next_time = self.next_period_start();
+            let release = match request {
+                Some(UpdateRequest::Release(ref ident)) => Some(ident.clone()),
+                _ => None,
+            };
+            let install_source = (
+                release.clone().unwrap_or_else(|| self.spec_ident.clone()),
+                *PackageTarget::active_target(),
+            )
+                .into();
 
             match util::pkg::install(
                 // We don't want anything in here to print
@@ -427,16 +982,25 @@ impl Worker {
                 &self.channel,
             ) {
                 Ok(maybe_newer_package) => {
-                    if self.current < *maybe_newer_package.ident() {
+                    let wanted = match release {
+                        Some(_) => self.current != *maybe_newer_package.ident(),
+                        None => self.current < *maybe_newer_package.ident(),
+                    };
+                    if self.is_blocked(maybe_newer_package.ident()) {
+                        debug!(
+                            "Package found was rolled back, not updating to {}",
+                            maybe_newer_package.ident()
+                        );
+                    } else if wanted {
                         outputln!(
                             "Updating from {} to {}",
                             self.current,
                             maybe_newer_package.ident()
                         );
                         self.current = maybe_newer_package.ident().clone();
-                        sender
-                            .send(maybe_newer_package)
-                            .expect("Main thread has gone away!");
+                        if sender.send(maybe_newer_package).is_err() {
+                            debug!("Service Updater no longer needs this worker's update");
+                        }
                         break;
                     } else {
                         debug!("Package found is not newer than ours");
@@ -445,7 +1009,244 @@ impl Worker {
                 Err(e) => warn!("Updater failed to get latest package: {:?}", e),
             }
 
-            self.sleep_until(next_time);
+            request = self.trigger.wait_until(next_time);
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn member(id: &str, pkg: &str, health: Option<HealthCheck>) -> CensusMember {
+        let mut member = CensusMember::default();
+        member.member_id = id.to_string();
+        member.pkg = Some(PackageIdent::from_str(pkg).unwrap());
+        member.health_check = health;
+        member
+    }
+
+    fn turn(order: &[&CensusMember], me: &str, canaries: usize, batch_size: usize) -> UpdateTurn {
+        canary_turn(
+            order,
+            me,
+            canaries,
+            batch_size,
+            Duration::from_secs(0),
+            &mut HashMap::new(),
+            Instant::now(),
+        )
+    }
+
+    #[test]
+    fn canary_turn_updates_canaries_first() {
+        let leader = member("a", "core/redis/1.0.1/2", Some(HealthCheck::Unknown));
+        let b = member("b", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let c = member("c", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let d = member("d", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let order = vec![&leader, &b, &c, &d];
+
+        assert_eq!(turn(&order, "b", 2, 1), UpdateTurn::Ready);
+        assert_eq!(turn(&order, "c", 2, 1), UpdateTurn::Waiting);
+        assert_eq!(turn(&order, "d", 2, 1), UpdateTurn::Waiting);
+    }
+
+    #[test]
+    fn canary_turn_updates_batches_after_healthy_canaries() {
+        let leader = member("a", "core/redis/1.0.1/2", Some(HealthCheck::Ok));
+        let b = member("b", "core/redis/1.0.1/2", Some(HealthCheck::Ok));
+        let c = member("c", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let d = member("d", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let e = member("e", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let order = vec![&leader, &b, &c, &d, &e];
+
+        assert_eq!(turn(&order, "c", 2, 2), UpdateTurn::Ready);
+        assert_eq!(turn(&order, "d", 2, 2), UpdateTurn::Ready);
+        assert_eq!(turn(&order, "e", 2, 2), UpdateTurn::Waiting);
+    }
+
+    #[test]
+    fn canary_turn_holds_batches_during_soak() {
+        let leader = member("a", "core/redis/1.0.1/2", Some(HealthCheck::Ok));
+        let b = member("b", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let order = vec![&leader, &b];
+        let soak = Duration::from_secs(60);
+        let mut healthy_since = HashMap::new();
+        let start = Instant::now();
+
+        assert_eq!(
+            canary_turn(&order, "b", 1, 1, soak, &mut healthy_since, start),
+            UpdateTurn::Waiting
+        );
+        assert_eq!(
+            canary_turn(
+                &order,
+                "b",
+                1,
+                1,
+                soak,
+                &mut healthy_since,
+                start + Duration::from_secs(59)
+            ),
+            UpdateTurn::Waiting
+        );
+        assert_eq!(
+            canary_turn(
+                &order,
+                "b",
+                1,
+                1,
+                soak,
+                &mut healthy_since,
+                start + Duration::from_secs(60)
+            ),
+            UpdateTurn::Ready
+        );
+    }
+
+    #[test]
+    fn canary_turn_restarts_soak_when_canary_becomes_unhealthy() {
+        let mut leader = member("a", "core/redis/1.0.1/2", Some(HealthCheck::Ok));
+        let b = member("b", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let soak = Duration::from_secs(60);
+        let mut healthy_since = HashMap::new();
+        let start = Instant::now();
+
+        canary_turn(&[&leader, &b], "b", 1, 1, soak, &mut healthy_since, start);
+        leader.health_check = Some(HealthCheck::Warning);
+        let later = start + Duration::from_secs(30);
+        canary_turn(&[&leader, &b], "b", 1, 1, soak, &mut healthy_since, later);
+        leader.health_check = Some(HealthCheck::Ok);
+        let later = start + Duration::from_secs(40);
+        canary_turn(&[&leader, &b], "b", 1, 1, soak, &mut healthy_since, later);
+
+        assert_eq!(
+            canary_turn(
+                &[&leader, &b],
+                "b",
+                1,
+                1,
+                soak,
+                &mut healthy_since,
+                start + Duration::from_secs(90)
+            ),
+            UpdateTurn::Waiting
+        );
+        assert_eq!(
+            canary_turn(
+                &[&leader, &b],
+                "b",
+                1,
+                1,
+                soak,
+                &mut healthy_since,
+                start + Duration::from_secs(100)
+            ),
+            UpdateTurn::Ready
+        );
+    }
+
+    #[test]
+    fn canary_turn_halts_on_critical_member() {
+        let leader = member("a", "core/redis/1.0.1/2", Some(HealthCheck::Critical));
+        let b = member("b", "core/redis/1.0.0/1", Some(HealthCheck::Ok));
+        let order = vec![&leader, &b];
+
+        assert_eq!(turn(&order, "b", 1, 1), UpdateTurn::Halted("a".to_string()));
+    }
+
+    #[test]
+    fn rollback_to_previous_release_after_failed_health_check() {
+        let sg = ServiceGroup::new(None, "redis", "default", None).unwrap();
+        let previous = PackageIdent::from_str("core/redis/1.0.0/1").unwrap();
+        let updated = PackageIdent::from_str("core/redis/1.0.1/2").unwrap();
+        let now = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut rollbacks = HashMap::new();
+        rollbacks.insert(
+            sg.clone(),
+            pending_rollback(previous.clone(), &updated, Some(window), now).unwrap(),
+        );
+
+        assert_eq!(
+            due_rollback(&mut rollbacks, &sg, HealthCheck::Critical, now),
+            None
+        );
+        assert_eq!(
+            due_rollback(&mut rollbacks, &sg, HealthCheck::Critical, now + window),
+            Some(previous)
+        );
+        assert!(rollbacks.is_empty());
+    }
+
+    #[test]
+    fn no_rollback_once_healthy() {
+        let sg = ServiceGroup::new(None, "redis", "default", None).unwrap();
+        let previous = PackageIdent::from_str("core/redis/1.0.0/1").unwrap();
+        let updated = PackageIdent::from_str("core/redis/1.0.1/2").unwrap();
+        let now = Instant::now();
+        let window = Duration::from_secs(60);
+        let mut rollbacks = HashMap::new();
+        rollbacks.insert(
+            sg.clone(),
+            pending_rollback(previous, &updated, Some(window), now).unwrap(),
+        );
+
+        assert_eq!(
+            due_rollback(&mut rollbacks, &sg, HealthCheck::Ok, now),
+            None
+        );
+        assert!(rollbacks.is_empty());
+        assert_eq!(
+            due_rollback(&mut rollbacks, &sg, HealthCheck::Critical, now + window),
+            None
+        );
+    }
+
+    #[test]
+    fn no_rollback_without_previous_release() {
+        let sg = ServiceGroup::new(None, "redis", "default", None).unwrap();
+        let ident = PackageIdent::from_str("core/redis/1.0.0/1").unwrap();
+        let now = Instant::now();
+        let window = Duration::from_secs(60);
+
+        assert!(pending_rollback(ident.clone(), &ident, Some(window), now).is_none());
+        assert!(pending_rollback(
+            ident,
+            &PackageIdent::from_str("core/redis/1.0.1/2").unwrap(),
+            None,
+            now
+        )
+        .is_none());
+        assert_eq!(
+            due_rollback(
+                &mut HashMap::new(),
+                &sg,
+                HealthCheck::Critical,
+                now + window
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn update_trigger_wakes_waiting_worker() {
+        let trigger = UpdateTrigger::default();
+        let waiting = trigger.clone();
+        let worker = thread::spawn(move || {
+            waiting.wait_until(SteadyTime::now() + ::time::Duration::seconds(60))
+        });
+        trigger.request(UpdateRequest::Latest);
+
+        assert_eq!(worker.join().unwrap(), Some(UpdateRequest::Latest));
+    }
+
+    #[test]
+    fn update_trigger_times_out_without_request() {
+        let trigger = UpdateTrigger::new(None);
+
+        assert_eq!(trigger.wait_until(SteadyTime::now()), None);
+    }
+}
@@ -0,0 +1,432 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Maintenance windows during which automatic updates may be applied.
+//!
+//! A window is a list of entries separated by `;`, and is open whenever any one of its entries
+//! is. Each entry is either a range of days and times, or a cron-like expression matching every
+//! minute during which the window is open:
+//!
+//! * `Sat-Sun 02:00-04:00 Europe/Berlin`
+//! * `daily 22:00-02:00`, where a range ending before it starts continues into the next day
+//! * `* 1-3 * * Mon-Fri America/New_York`, open from 01:00 until 03:59 on weekdays
+//!
+//! Times are in UTC unless an entry ends with the name of a timezone from the tz database.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::result;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono_tz::{Tz, UTC};
+use serde;
+
+use error::{Error, Result, SupError};
+
+static LOGKEY: &'static str = "UW";
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const DAY_NAMES: [&'static str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Clone, Debug)]
+pub struct UpdateWindow {
+    spec: String,
+    entries: Vec<Entry>,
+}
+
+impl UpdateWindow {
+    /// Returns `true` if the window is currently open.
+    pub fn is_open(&self) -> bool {
+        self.is_open_at(Utc::now())
+    }
+
+    /// Returns `true` if the window is open at the given time.
+    pub fn is_open_at(&self, time: DateTime<Utc>) -> bool {
+        self.entries.iter().any(|entry| entry.is_open_at(time))
+    }
+}
+
+// Windows are compared by the schedule they were parsed from
+impl PartialEq for UpdateWindow {
+    fn eq(&self, other: &UpdateWindow) -> bool {
+        self.spec == other.spec
+    }
+}
+
+impl Eq for UpdateWindow {}
+
+impl Hash for UpdateWindow {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.spec.hash(state);
+    }
+}
+
+impl fmt::Display for UpdateWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.spec)
+    }
+}
+
+impl FromStr for UpdateWindow {
+    type Err = SupError;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        let entries = value
+            .split(';')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(Entry::from_str)
+            .collect::<result::Result<Vec<Entry>, String>>()
+            .map_err(|e| sup_error!(Error::InvalidUpdateWindow(value.to_string(), e)))?;
+        if entries.is_empty() {
+            return Err(sup_error!(Error::InvalidUpdateWindow(
+                value.to_string(),
+                "no schedule given".to_string(),
+            )));
+        }
+        Ok(UpdateWindow {
+            spec: value.trim().to_string(),
+            entries: entries,
+        })
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for UpdateWindow {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        UpdateWindow::from_str(&value).map_err(serde::de::Error::custom)
+    }
+}
+
+impl serde::Serialize for UpdateWindow {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.spec)
+    }
+}
+
+/// Parse an update window given on the command line or in a control gateway message, where an
+/// empty value means no window.
+pub fn parse_optional(value: &str) -> Result<Option<UpdateWindow>> {
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        UpdateWindow::from_str(value).map(Some)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    tz: Tz,
+    schedule: Schedule,
+}
+
+#[derive(Clone, Debug)]
+enum Schedule {
+    /// Open on the given days from `start` until `end`, both in minutes since midnight. A range
+    /// ending before it starts continues into the following day.
+    Range {
+        days: Field,
+        start: u32,
+        end: u32,
+    },
+    Cron(Cron),
+}
+
+/// The values matched by one field of a cron expression, as a bit set.
+#[derive(Clone, Copy, Debug)]
+struct Field(u64);
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.0 & (1 << value) != 0
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Cron {
+    minutes: Field,
+    hours: Field,
+    days: Field,
+    months: Field,
+    weekdays: Field,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Entry {
+    fn is_open_at(&self, time: DateTime<Utc>) -> bool {
+        let local = time.with_timezone(&self.tz);
+        let weekday = local.weekday().num_days_from_monday();
+        let minute = local.hour() * 60 + local.minute();
+        match self.schedule {
+            Schedule::Range { days, start, end } => {
+                if start < end {
+                    days.contains(weekday) && minute >= start && minute < end
+                } else {
+                    (days.contains(weekday) && minute >= start)
+                        || (days.contains((weekday + 6) % 7) && minute < end)
+                }
+            }
+            Schedule::Cron(ref cron) => {
+                // As with cron, a day matches either the day of the month or the day of the week
+                // when both are restricted.
+                let day = cron.days.contains(local.day());
+                let weekday = cron.weekdays.contains(weekday);
+                let day_matches = match (cron.any_day, cron.any_weekday) {
+                    (false, false) => day || weekday,
+                    _ => day && weekday,
+                };
+                day_matches
+                    && cron.minutes.contains(local.minute())
+                    && cron.hours.contains(local.hour())
+                    && cron.months.contains(local.month())
+            }
+        }
+    }
+}
+
+impl FromStr for Entry {
+    type Err = String;
+
+    fn from_str(value: &str) -> result::Result<Self, Self::Err> {
+        let mut fields: Vec<&str> = value.split_whitespace().collect();
+        let tz = match fields.last().map(|f| f.parse::<Tz>()) {
+            Some(Ok(tz)) if fields.len() == 3 || fields.len() == 6 => {
+                fields.pop();
+                tz
+            }
+            _ => UTC,
+        };
+        let schedule = match fields.len() {
+            2 => parse_range(fields[0], fields[1])?,
+            5 => Schedule::Cron(Cron {
+                minutes: parse_field(fields[0], 0, 59, &[])?,
+                hours: parse_field(fields[1], 0, 23, &[])?,
+                days: parse_field(fields[2], 1, 31, &[])?,
+                months: parse_field(fields[3], 1, 12, &[])?,
+                weekdays: parse_cron_weekdays(fields[4])?,
+                any_day: fields[2] == "*",
+                any_weekday: fields[4] == "*",
+            }),
+            _ => {
+                return Err(format!(
+                    "'{}' is neither a day and time range nor a cron expression",
+                    value
+                ))
+            }
+        };
+        Ok(Entry {
+            tz: tz,
+            schedule: schedule,
+        })
+    }
+}
+
+fn parse_range(days: &str, times: &str) -> result::Result<Schedule, String> {
+    let days = match days.to_lowercase().as_ref() {
+        "*" | "daily" => Field((1 << 7) - 1),
+        days => parse_field(days, 0, 6, &DAY_NAMES)?,
+    };
+    let mut parts = times.splitn(2, '-');
+    let start = parse_time(parts.next().unwrap_or(""))?;
+    let end = match parts.next() {
+        Some(end) => parse_time(end)?,
+        None => return Err(format!("'{}' is not a time range, like 02:00-04:00", times)),
+    };
+    Ok(Schedule::Range {
+        days: days,
+        start: start,
+        end: end,
+    })
+}
+
+/// Parse a time of day, like `02:30`, into minutes since midnight. `24:00` is accepted as the
+/// end of the day.
+fn parse_time(value: &str) -> result::Result<u32, String> {
+    let invalid = || format!("'{}' is not a time of day, like 02:30", value);
+    let mut parts = value.splitn(2, ':');
+    let hour = parts
+        .next()
+        .and_then(|h| h.parse::<u32>().ok())
+        .ok_or_else(&invalid)?;
+    let minute = parts
+        .next()
+        .and_then(|m| m.parse::<u32>().ok())
+        .ok_or_else(&invalid)?;
+    let time = hour * 60 + minute;
+    if minute >= 60 || time > MINUTES_PER_DAY {
+        return Err(invalid());
+    }
+    Ok(time)
+}
+
+/// Cron allows Sunday to be written as either `0` or `7`, while days are otherwise numbered from
+/// Monday. Normalize to Monday being `0`.
+fn parse_cron_weekdays(value: &str) -> result::Result<Field, String> {
+    let lower = value.to_lowercase();
+    if lower.chars().any(|c| c.is_alphabetic()) {
+        return parse_field(&lower, 0, 6, &DAY_NAMES);
+    }
+    let sunday_first = parse_field(value, 0, 7, &[])?;
+    let mut days = 0;
+    for day in 0..8 {
+        if sunday_first.contains(day) {
+            days |= 1 << ((day + 6) % 7);
+        }
+    }
+    Ok(Field(days))
+}
+
+/// Parse a comma separated list of values, ranges (`a-b`), and steps (`*/n` or `a-b/n`) between
+/// `min` and `max`. Values may also be given by name, where `names[0]` is `min`.
+fn parse_field(value: &str, min: u32, max: u32, names: &[&str]) -> result::Result<Field, String> {
+    let parse_value = |v: &str| -> result::Result<u32, String> {
+        let v = v.to_lowercase();
+        if let Some(position) = names.iter().position(|n| v.starts_with(n)) {
+            return Ok(min + position as u32);
+        }
+        match v.parse::<u32>() {
+            Ok(n) if n >= min && n <= max => Ok(n),
+            _ => Err(format!("'{}' is not between {} and {}", v, min, max)),
+        }
+    };
+    let mut field = 0;
+    for part in value.split(',') {
+        let mut step_parts = part.splitn(2, '/');
+        let range = step_parts.next().unwrap_or("");
+        let step = match step_parts.next() {
+            Some(step) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => step,
+                _ => return Err(format!("'{}' is not a valid step", step)),
+            },
+            None => 1,
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else {
+            let mut bounds = range.splitn(2, '-');
+            let start = parse_value(bounds.next().unwrap_or(""))?;
+            let end = match bounds.next() {
+                Some(end) => parse_value(end)?,
+                None => start,
+            };
+            (start, end)
+        };
+        // Ranges such as `Fri-Mon` wrap around to the start of the field
+        let mut current = start;
+        let mut count = 0;
+        loop {
+            if count % step == 0 {
+                field |= 1 << current;
+            }
+            if current == end {
+                break;
+            }
+            current = if current == max { min } else { current + 1 };
+            count += 1;
+        }
+    }
+    Ok(Field(field))
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    #[test]
+    fn range_window() {
+        let window = UpdateWindow::from_str("Sat-Sun 02:00-04:00").unwrap();
+
+        // 2018-06-02 is a Saturday
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 2).and_hms(2, 0, 0)));
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 3).and_hms(3, 59, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 3).and_hms(4, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 4).and_hms(2, 30, 0)));
+    }
+
+    #[test]
+    fn range_window_over_midnight() {
+        let window = UpdateWindow::from_str("Fri 22:00-02:00").unwrap();
+
+        // 2018-06-01 is a Friday
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 1).and_hms(23, 0, 0)));
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 2).and_hms(1, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 1).and_hms(1, 0, 0)));
+    }
+
+    #[test]
+    fn range_window_with_timezone() {
+        let window = UpdateWindow::from_str("daily 02:00-04:00 Europe/Berlin").unwrap();
+
+        // Berlin is two hours ahead of UTC in summer
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 2).and_hms(0, 30, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 2).and_hms(2, 30, 0)));
+    }
+
+    #[test]
+    fn cron_window() {
+        let window = UpdateWindow::from_str("* 1-3 * * Mon-Fri").unwrap();
+
+        // 2018-06-01 is a Friday
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 1).and_hms(3, 59, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 1).and_hms(4, 0, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 2).and_hms(2, 0, 0)));
+    }
+
+    #[test]
+    fn cron_window_sunday() {
+        let window = UpdateWindow::from_str("*/15 0 * * 0").unwrap();
+
+        // 2018-06-03 is a Sunday
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 3).and_hms(0, 15, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 3).and_hms(0, 16, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 4).and_hms(0, 15, 0)));
+    }
+
+    #[test]
+    fn multiple_entries() {
+        let window = UpdateWindow::from_str("Mon 01:00-02:00; Thu 01:00-02:00").unwrap();
+
+        // 2018-06-04 is a Monday
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 4).and_hms(1, 30, 0)));
+        assert!(window.is_open_at(Utc.ymd(2018, 6, 7).and_hms(1, 30, 0)));
+        assert!(!window.is_open_at(Utc.ymd(2018, 6, 5).and_hms(1, 30, 0)));
+    }
+
+    #[test]
+    fn invalid_windows() {
+        assert!(UpdateWindow::from_str("").is_err());
+        assert!(UpdateWindow::from_str("Sat 02:00").is_err());
+        assert!(UpdateWindow::from_str("Sat 02:00-25:00").is_err());
+        assert!(UpdateWindow::from_str("Someday 02:00-04:00").is_err());
+        assert!(UpdateWindow::from_str("* 24 * * *").is_err());
+        assert!(UpdateWindow::from_str("daily 02:00-04:00 Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn parse_optional_empty() {
+        assert!(parse_optional("").unwrap().is_none());
+        assert!(parse_optional("daily 02:00-04:00").unwrap().is_some());
+    }
+}
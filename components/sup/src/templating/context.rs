@@ -48,12 +48,12 @@
 //! anything else, and so, they _can't_ be used for anything else.
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::net::IpAddr;
 use std::path::PathBuf;
 use std::result;
 
-use serde::ser::SerializeMap;
+use serde::ser::{self, SerializeMap};
 use serde::{Serialize, Serializer};
 use toml;
 
@@ -81,6 +81,7 @@ use manager::Sys;
 pub struct RenderContext<'a> {
     sys: SystemInfo<'a>,
     pkg: Package<'a>,
+    #[serde(serialize_with = "serialize_resolved_cfg")]
     cfg: Cow<'a, Cfg>,
     svc: Svc<'a>,
     bind: Binds<'a>,
@@ -92,10 +93,10 @@ impl<'a> RenderContext<'a> {
     /// in their templates.
     ///
     /// Note that we wrap everything except the `Cfg`, to which we
-    /// maintain a direct reference. The serialization logic for this
-    /// is already complex, and exactly what we need. Because of the
-    /// nature of `Cfg`s behavior, we should be safe relying on that
-    /// implementation for the foreseeable future.
+    /// maintain a direct reference. Rather than its own `Serialize`
+    /// implementation, which redacts secrets, we serialize its
+    /// resolved configuration; templates are the one place secrets
+    /// are exposed.
     pub fn new<T>(
         service_group: &ServiceGroup,
         sys: &'a Sys,
@@ -395,7 +396,7 @@ impl<'a> Binds<'a> {
         let mut map = HashMap::default();
         for bind in bindings {
             if let Some(group) = census.census_group_for(&bind.service_group) {
-                map.insert(bind.name.to_string(), BindGroup::new(group));
+                map.insert(bind.name.to_string(), BindGroup::new(group, &bind.labels));
             }
         }
         Binds(map)
@@ -410,15 +411,23 @@ struct BindGroup<'a> {
 }
 
 impl<'a> BindGroup<'a> {
-    fn new(group: &'a CensusGroup) -> Self {
+    /// Only members carrying every one of the bind's `labels` are listed. The leader is the
+    /// leader of the whole group, wherever it runs.
+    fn new(group: &'a CensusGroup, labels: &BTreeMap<String, String>) -> Self {
+        let members: Vec<SvcMember<'a>> = group
+            .active_members()
+            .iter()
+            .filter(|m| m.matches_labels(labels))
+            .map(|m| SvcMember::from_census_member(m))
+            .collect();
         BindGroup {
-            first: select_first(group),
+            first: if labels.is_empty() {
+                select_first(group)
+            } else {
+                members.first().cloned()
+            },
             leader: group.leader().map(|m| SvcMember::from_census_member(m)),
-            members: group
-                .active_members()
-                .iter()
-                .map(|m| SvcMember::from_census_member(m))
-                .collect(),
+            members: members,
         }
     }
 }
@@ -454,6 +463,7 @@ struct SvcMember<'a> {
     suspect: Cow<'a, bool>,
     confirmed: Cow<'a, bool>,
     departed: Cow<'a, bool>,
+    labels: Cow<'a, BTreeMap<String, String>>,
     cfg: Cow<'a, toml::value::Table>,
 }
 
@@ -489,6 +499,7 @@ impl<'a> SvcMember<'a> {
             confirmed: Cow::Owned(c.confirmed()),
             departed: Cow::Owned(c.departed()),
 
+            labels: Cow::Borrowed(&c.labels),
             cfg: Cow::Borrowed(&c.cfg),
         }
     }
@@ -562,6 +573,7 @@ impl<'a> Serialize for SvcMember<'a> {
         map.serialize_entry("confirmed", &self.confirmed)?;
         map.serialize_entry("departed", &self.departed)?;
 
+        map.serialize_entry("labels", &self.labels)?;
         map.serialize_entry("cfg", &self.cfg)?;
 
         map.end()
@@ -591,6 +603,18 @@ fn select_first(census_group: &CensusGroup) -> Option<SvcMember> {
     }
 }
 
+/// Serializes the `cfg` of a rendering context with its secret
+/// references resolved.
+fn serialize_resolved_cfg<S>(cfg: &Cow<Cfg>, serializer: S) -> result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let resolved = cfg
+        .resolved()
+        .map_err(|e| ser::Error::custom(e.to_string()))?;
+    resolved.serialize(serializer)
+}
+
 ////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -709,6 +733,9 @@ JSON:
         fn deprecated_user_config_dir(&self) -> PathBuf {
             self.base_path.join("svc")
         }
+        fn secrets_dir(&self) -> PathBuf {
+            self.base_path.join("secrets")
+        }
     }
 
     fn new_test_pkg() -> (TempDir, TestPkg) {
@@ -761,6 +788,7 @@ two = 2
             suspect: Cow::Owned(false),
             confirmed: Cow::Owned(false),
             departed: Cow::Owned(false),
+            labels: Cow::Owned(BTreeMap::new()),
             cfg: Cow::Owned(BTreeMap::new() as toml::value::Table),
         }
     }
@@ -975,6 +1003,29 @@ two = 2
         assert_eq!(output, "deadbeefdeadbeefdeadbeefdeadbeef");
     }
 
+    #[test]
+    fn member_labels_render_correctly() {
+        let mut ctx = default_render_context();
+
+        let mut svc_member = default_svc_member();
+        let mut labels = BTreeMap::new();
+        labels.insert("zone".to_string(), "us-east-1a".to_string());
+        svc_member.labels = Cow::Owned(labels);
+
+        let mut bind_map = HashMap::new();
+        let bind_group = BindGroup {
+            first: Some(svc_member.clone()),
+            leader: None,
+            members: vec![svc_member.clone()],
+        };
+        bind_map.insert("foo".into(), bind_group);
+        ctx.bind = Binds(bind_map);
+
+        let output = render("{{#each bind.foo.members}}{{labels.zone}}{{/each}}", &ctx);
+
+        assert_eq!(output, "us-east-1a");
+    }
+
     // Technically, `bind.<SERVICE>.first` could be None, according to
     // the typing of the code.  This was always been technically
     // possible, even though for practical purposes, it will be
@@ -0,0 +1,148 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::write_value;
+
+#[derive(Clone, Copy)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+}
+
+impl Operator {
+    fn name(&self) -> &'static str {
+        match *self {
+            Operator::Add => "add",
+            Operator::Subtract => "subtract",
+            Operator::Multiply => "multiply",
+            Operator::Divide => "divide",
+            Operator::Modulo => "mod",
+        }
+    }
+
+    /// Applies the operator to two numbers. Integers stay integers, so dividing them discards
+    /// the remainder; if either number is a float, so is the result.
+    fn apply(&self, lhs: Number, rhs: Number) -> RenderResult<Number> {
+        match (lhs, rhs) {
+            (Number::Integer(lhs), Number::Integer(rhs)) => {
+                let result = match *self {
+                    Operator::Add => lhs.checked_add(rhs),
+                    Operator::Subtract => lhs.checked_sub(rhs),
+                    Operator::Multiply => lhs.checked_mul(rhs),
+                    Operator::Divide => lhs.checked_div(rhs),
+                    Operator::Modulo => lhs.checked_rem(rhs),
+                };
+                result.map(Number::Integer).ok_or_else(|| {
+                    RenderError::new(format!(
+                        "Can't {} {} and {}: overflow or division by zero",
+                        self.name(),
+                        lhs,
+                        rhs
+                    ))
+                })
+            }
+            (lhs, rhs) => {
+                let (lhs, rhs) = (lhs.as_f64(), rhs.as_f64());
+                let result = match *self {
+                    Operator::Add => lhs + rhs,
+                    Operator::Subtract => lhs - rhs,
+                    Operator::Multiply => lhs * rhs,
+                    Operator::Divide => lhs / rhs,
+                    Operator::Modulo => lhs % rhs,
+                };
+                if result.is_finite() {
+                    Ok(Number::Float(result))
+                } else {
+                    Err(RenderError::new(format!(
+                        "Can't {} {} and {}: the result is not a number",
+                        self.name(),
+                        lhs,
+                        rhs
+                    )))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn from_json(value: &Json, helper: &str) -> RenderResult<Number> {
+        match value.as_i64() {
+            Some(n) => Ok(Number::Integer(n)),
+            None => value.as_f64().map(Number::Float).ok_or_else(|| {
+                RenderError::new(format!(
+                    "Expected number parameters for \"{}\", got {}",
+                    helper, value
+                ))
+            }),
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Integer(n) => n as f64,
+            Number::Float(n) => n,
+        }
+    }
+
+    fn to_json(self) -> Json {
+        match self {
+            Number::Integer(n) => Json::from(n),
+            Number::Float(n) => Json::from(n),
+        }
+    }
+}
+
+/// Folds the arithmetic operator over all of its parameters, from left to right:
+/// `{{subtract 10 2 3}}` is `5`.
+#[derive(Clone, Copy)]
+pub struct ArithmeticHelper(Operator);
+
+impl HelperDef for ArithmeticHelper {
+    fn call(&self, h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let name = self.0.name();
+        let params = h.params();
+        if params.len() < 2 {
+            return Err(RenderError::new(format!(
+                "Expected at least 2 number parameters for \"{}\"",
+                name
+            )));
+        }
+        let mut result = Number::from_json(params[0].value(), name)?;
+        for param in params[1..].iter() {
+            let operand = Number::from_json(param.value(), name)?;
+            result = self.0.apply(result, operand)?;
+        }
+        write_value(rc, &result.to_json())
+    }
+}
+
+pub static ADD: ArithmeticHelper = ArithmeticHelper(Operator::Add);
+pub static SUBTRACT: ArithmeticHelper = ArithmeticHelper(Operator::Subtract);
+pub static MULTIPLY: ArithmeticHelper = ArithmeticHelper(Operator::Multiply);
+pub static DIVIDE: ArithmeticHelper = ArithmeticHelper(Operator::Divide);
+pub static MODULO: ArithmeticHelper = ArithmeticHelper(Operator::Modulo);
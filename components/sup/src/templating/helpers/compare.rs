@@ -0,0 +1,111 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::render_condition;
+
+#[derive(Clone, Copy)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl Comparison {
+    fn name(&self) -> &'static str {
+        match *self {
+            Comparison::Eq => "eq",
+            Comparison::Ne => "ne",
+            Comparison::Gt => "gt",
+            Comparison::Gte => "gte",
+            Comparison::Lt => "lt",
+            Comparison::Lte => "lte",
+        }
+    }
+
+    fn holds(&self, lhs: &Json, rhs: &Json) -> RenderResult<bool> {
+        match *self {
+            Comparison::Eq => Ok(equal(lhs, rhs)),
+            Comparison::Ne => Ok(!equal(lhs, rhs)),
+            Comparison::Gt => self.order(lhs, rhs).map(|o| o == Ordering::Greater),
+            Comparison::Gte => self.order(lhs, rhs).map(|o| o != Ordering::Less),
+            Comparison::Lt => self.order(lhs, rhs).map(|o| o == Ordering::Less),
+            Comparison::Lte => self.order(lhs, rhs).map(|o| o != Ordering::Greater),
+        }
+    }
+
+    /// Orders two numbers, or two strings.
+    fn order(&self, lhs: &Json, rhs: &Json) -> RenderResult<Ordering> {
+        let ordering = match (lhs, rhs) {
+            (&Json::Number(ref lhs), &Json::Number(ref rhs)) => lhs
+                .as_f64()
+                .and_then(|lhs| rhs.as_f64().and_then(|rhs| lhs.partial_cmp(&rhs))),
+            (&Json::String(ref lhs), &Json::String(ref rhs)) => Some(lhs.cmp(rhs)),
+            _ => None,
+        };
+        ordering.ok_or_else(|| {
+            RenderError::new(format!(
+                "Expected 2 numbers or 2 strings for \"{}\", got {} and {}",
+                self.name(),
+                lhs,
+                rhs
+            ))
+        })
+    }
+}
+
+/// Values are equal if they are the same JSON value, or the same number, even if one of them is
+/// an integer and the other a float.
+fn equal(lhs: &Json, rhs: &Json) -> bool {
+    match (lhs, rhs) {
+        (&Json::Number(ref lhs), &Json::Number(ref rhs)) => lhs.as_f64() == rhs.as_f64(),
+        (lhs, rhs) => lhs == rhs,
+    }
+}
+
+/// Compares its 2 parameters. As a block, renders the block if the comparison holds and the
+/// `else` block otherwise; inline, writes `true` if it holds and nothing if it does not.
+#[derive(Clone, Copy)]
+pub struct CompareHelper(Comparison);
+
+impl HelperDef for CompareHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let (lhs, rhs) = match (h.param(0), h.param(1)) {
+            (Some(lhs), Some(rhs)) => (lhs.value(), rhs.value()),
+            _ => {
+                return Err(RenderError::new(format!(
+                    "Expected 2 parameters for \"{}\"",
+                    self.0.name()
+                )))
+            }
+        };
+        let holds = self.0.holds(lhs, rhs)?;
+        render_condition(holds, h, r, rc)
+    }
+}
+
+pub static EQ: CompareHelper = CompareHelper(Comparison::Eq);
+pub static NE: CompareHelper = CompareHelper(Comparison::Ne);
+pub static GT: CompareHelper = CompareHelper(Comparison::Gt);
+pub static GTE: CompareHelper = CompareHelper(Comparison::Gte);
+pub static LT: CompareHelper = CompareHelper(Comparison::Lt);
+pub static LTE: CompareHelper = CompareHelper(Comparison::Lte);
@@ -0,0 +1,45 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::write_value;
+
+/// Writes the first of its parameters which is set, meaning it is neither missing nor an empty
+/// string: `{{default cfg.bind_address "0.0.0.0"}}`.
+#[derive(Clone, Copy)]
+pub struct DefaultHelper;
+
+impl HelperDef for DefaultHelper {
+    fn call(&self, h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        if h.params().len() < 2 {
+            return Err(RenderError::new(
+                "Expected at least 2 parameters for \"default\"",
+            ));
+        }
+        let value = h.params().iter().map(|p| p.value()).find(|v| match **v {
+            Json::Null => false,
+            Json::String(ref s) => !s.is_empty(),
+            _ => true,
+        });
+        match value {
+            Some(value) => write_value(rc, value),
+            None => Ok(()),
+        }
+    }
+}
+
+pub static DEFAULT: DefaultHelper = DefaultHelper;
@@ -0,0 +1,49 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::render_each;
+
+/// Iterates over the alive members of a list, like `eachAlive`, but starting with the leader.
+/// The other members keep their order. Useful for connection strings which should try the leader
+/// before its followers.
+#[derive(Clone, Copy)]
+pub struct EachLeaderFirstHelper;
+
+impl HelperDef for EachLeaderFirstHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let members = h
+            .param(0)
+            .and_then(|v| v.value().as_array())
+            .ok_or_else(|| {
+                RenderError::new("Expected a list of members for \"eachLeaderFirst\"")
+            })?;
+        let (leaders, followers): (Vec<Json>, Vec<Json>) = members
+            .iter()
+            .filter(|m| is_set(m, "alive"))
+            .cloned()
+            .partition(|m| is_set(m, "leader"));
+        let ordered: Vec<Json> = leaders.into_iter().chain(followers.into_iter()).collect();
+        render_each(&ordered, h, r, rc)
+    }
+}
+
+fn is_set(member: &Json, key: &str) -> bool {
+    member.get(key).and_then(Json::as_bool) == Some(true)
+}
+
+pub static EACH_LEADER_FIRST: EachLeaderFirstHelper = EachLeaderFirstHelper;
@@ -0,0 +1,37 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::super::RenderResult;
+use super::{list_param, write_value};
+
+/// Writes the last item of a list, or nothing if it is empty.
+#[derive(Clone, Copy)]
+pub struct LastHelper;
+
+impl HelperDef for LastHelper {
+    fn call(&self, h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let list = h
+            .param(0)
+            .and_then(|v| list_param(v.value()))
+            .ok_or_else(|| RenderError::new("Expected a list parameter for \"last\""))?;
+        match list.last() {
+            Some(item) => write_value(rc, item),
+            None => Ok(()),
+        }
+    }
+}
+
+pub static LAST: LastHelper = LastHelper;
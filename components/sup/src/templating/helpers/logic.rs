@@ -0,0 +1,55 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+
+use super::super::RenderResult;
+use super::{render_condition, JsonTruthy};
+
+#[derive(Clone, Copy)]
+enum Operator {
+    And,
+    Or,
+    Not,
+}
+
+/// Combines the truthiness of its parameters: `and` holds if all of them are truthy, `or` if any
+/// of them is, and `not` if its only parameter is not. As a block, renders the block if the
+/// result holds and the `else` block otherwise; inline, writes `true` or nothing.
+#[derive(Clone, Copy)]
+pub struct LogicHelper(Operator);
+
+impl HelperDef for LogicHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let params = h.params();
+        let holds = match self.0 {
+            Operator::And | Operator::Or if params.len() < 2 => {
+                return Err(RenderError::new(
+                    "Expected at least 2 parameters for \"and\" and \"or\"",
+                ))
+            }
+            Operator::And => params.iter().all(|p| p.value().is_truthy()),
+            Operator::Or => params.iter().any(|p| p.value().is_truthy()),
+            Operator::Not => match h.param(0) {
+                Some(param) if params.len() == 1 => !param.value().is_truthy(),
+                _ => return Err(RenderError::new("Expected 1 parameter for \"not\"")),
+            },
+        };
+        render_condition(holds, h, r, rc)
+    }
+}
+
+pub static AND: LogicHelper = LogicHelper(Operator::And);
+pub static OR: LogicHelper = LogicHelper(Operator::Or);
+pub static NOT: LogicHelper = LogicHelper(Operator::Not);
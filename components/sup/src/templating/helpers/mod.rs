@@ -12,25 +12,54 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod arithmetic;
+mod base64;
+mod compare;
+mod default;
 mod each_alive;
+mod each_leader_first;
+mod first;
+mod last;
+mod logic;
 mod pkg_path_for;
+mod sha256;
+mod sort;
+mod split_lines;
 mod str_concat;
 mod str_join;
 mod str_replace;
+mod to_integer;
 mod to_json;
 mod to_lowercase;
 mod to_toml;
 mod to_uppercase;
 mod to_yaml;
 
+use std::collections::BTreeMap;
+
+use handlebars::{Handlebars, Helper, RenderContext, Renderable, Template};
 use serde::Serialize;
 use serde_json::{self, Value as Json};
 
+use super::RenderResult;
+
+pub use self::arithmetic::{ADD, DIVIDE, MODULO, MULTIPLY, SUBTRACT};
+pub use self::base64::{BASE64, BASE64_DECODE};
+pub use self::compare::{EQ, GT, GTE, LT, LTE, NE};
+pub use self::default::DEFAULT;
 pub use self::each_alive::EACH_ALIVE;
+pub use self::each_leader_first::EACH_LEADER_FIRST;
+pub use self::first::FIRST;
+pub use self::last::LAST;
+pub use self::logic::{AND, NOT, OR};
 pub use self::pkg_path_for::PKG_PATH_FOR;
+pub use self::sha256::SHA256;
+pub use self::sort::SORT;
+pub use self::split_lines::SPLIT_LINES;
 pub use self::str_concat::STR_CONCAT;
 pub use self::str_join::STR_JOIN;
 pub use self::str_replace::STR_REPLACE;
+pub use self::to_integer::TO_INTEGER;
 pub use self::to_json::TO_JSON;
 pub use self::to_lowercase::TO_LOWERCASE;
 pub use self::to_toml::TO_TOML;
@@ -63,3 +92,101 @@ where
 {
     serde_json::to_value(src).unwrap_or(Json::Null)
 }
+
+/// Helper which writes a value the way a template would show it: strings as they are, nothing for
+/// `null`, and anything else as JSON.
+fn write_value(rc: &mut RenderContext, value: &Json) -> RenderResult<()> {
+    match *value {
+        Json::String(ref s) => rc.writer.write_all(s.as_bytes())?,
+        Json::Null => (),
+        ref value => rc.writer.write_all(value.to_string().as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Helper which reads a list parameter. A list written by a helper used as a subexpression, like
+/// `sort` or `splitLines`, reaches the next helper as JSON text, so that is read back as a list.
+fn list_param(value: &Json) -> Option<Vec<Json>> {
+    match *value {
+        Json::Array(ref list) => Some(list.clone()),
+        Json::String(ref s) => serde_json::from_str(s).ok(),
+        _ => None,
+    }
+}
+
+/// Helper for helpers which test a condition. Used as a block, the block is rendered if the
+/// condition holds and its `else` block otherwise. Used inline or as a subexpression, `true` is
+/// written if the condition holds and nothing otherwise. Handlebars passes a subexpression on as
+/// the text it rendered, so only an empty string is false to the `if` it is the condition of.
+fn render_condition(
+    condition: bool,
+    h: &Helper,
+    r: &Handlebars,
+    rc: &mut RenderContext,
+) -> RenderResult<()> {
+    if h.template().is_none() {
+        if condition {
+            rc.writer.write_all(b"true")?;
+        }
+        return Ok(());
+    }
+    let template = if condition { h.template() } else { h.inverse() };
+    if let Some(template) = template {
+        template.render(r, rc)?;
+    }
+    Ok(())
+}
+
+/// Helper for block helpers which iterate over a list, rendering the block once for every item
+/// with `@index`, `@first` and `@last` set, and the item bound to the block parameter. If there
+/// are no items, the `else` block is rendered instead.
+fn render_each(
+    items: &[Json],
+    h: &Helper,
+    r: &Handlebars,
+    rc: &mut RenderContext,
+) -> RenderResult<()> {
+    let template = match h.template() {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+    if items.is_empty() {
+        if let Some(else_template) = h.inverse() {
+            else_template.render(r, rc)?;
+        }
+        return Ok(());
+    }
+    rc.promote_local_vars();
+    let rendered = render_items(items, template, h, r, rc);
+    rc.demote_local_vars();
+    rendered
+}
+
+fn render_items(
+    items: &[Json],
+    template: &Template,
+    h: &Helper,
+    r: &Handlebars,
+    rc: &mut RenderContext,
+) -> RenderResult<()> {
+    let len = items.len();
+    for (i, item) in items.iter().enumerate() {
+        let mut local_rc = rc.derive();
+        local_rc.set_local_var("@first".to_string(), to_json(&(i == 0usize)));
+        local_rc.set_local_var("@last".to_string(), to_json(&(i == len - 1)));
+        local_rc.set_local_var("@index".to_string(), to_json(&i));
+
+        if let Some(block_param) = h.block_param() {
+            let mut map = BTreeMap::new();
+            map.insert(block_param.to_string(), item.clone());
+            local_rc.push_block_context(&map)?;
+        }
+
+        template.render(r, &mut local_rc)?;
+
+        if h.block_param().is_some() {
+            local_rc.pop_block_context();
+        }
+    }
+    Ok(())
+}
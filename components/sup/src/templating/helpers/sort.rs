@@ -0,0 +1,58 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::{list_param, render_each, write_value};
+
+/// Sorts a list of numbers, or of strings. As a block, renders the block for every item in
+/// order; inline, writes the sorted list as a JSON array, so it can be passed on to another
+/// helper as a subexpression.
+#[derive(Clone, Copy)]
+pub struct SortHelper;
+
+impl HelperDef for SortHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let mut list = h
+            .param(0)
+            .and_then(|v| list_param(v.value()))
+            .ok_or_else(|| RenderError::new("Expected a list parameter for \"sort\""))?;
+        let all_numbers = list.iter().all(|v| v.is_number());
+        if !all_numbers && !list.iter().all(|v| v.is_string()) {
+            return Err(RenderError::new(
+                "Expected a list of numbers or of strings for \"sort\"",
+            ));
+        }
+        if all_numbers {
+            list.sort_by(|a, b| {
+                a.as_f64()
+                    .partial_cmp(&b.as_f64())
+                    .unwrap_or(Ordering::Equal)
+            });
+        } else {
+            list.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+        }
+        if h.template().is_some() {
+            render_each(&list, h, r, rc)
+        } else {
+            write_value(rc, &Json::Array(list))
+        }
+    }
+}
+
+pub static SORT: SortHelper = SortHelper;
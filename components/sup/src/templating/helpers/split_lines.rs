@@ -0,0 +1,41 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+use super::{render_each, write_value};
+
+/// Splits a string into its lines. As a block, renders the block for every line; inline, writes
+/// the lines as a JSON array, so it can be passed on to another helper as a subexpression.
+#[derive(Clone, Copy)]
+pub struct SplitLinesHelper;
+
+impl HelperDef for SplitLinesHelper {
+    fn call(&self, h: &Helper, r: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .and_then(|v| v.value().as_str())
+            .ok_or_else(|| RenderError::new("Expected a string parameter for \"splitLines\""))?;
+        let lines: Vec<Json> = param.lines().map(|l| Json::String(l.to_string())).collect();
+        if h.template().is_some() {
+            render_each(&lines, h, r, rc)
+        } else {
+            write_value(rc, &Json::Array(lines))
+        }
+    }
+}
+
+pub static SPLIT_LINES: SplitLinesHelper = SplitLinesHelper;
@@ -0,0 +1,60 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::i64;
+
+use handlebars::{Handlebars, Helper, HelperDef, RenderContext, RenderError};
+use serde_json::Value as Json;
+
+use super::super::RenderResult;
+
+/// Writes its parameter as an integer. Floats are truncated, strings are parsed, and booleans
+/// are `1` or `0`. Floats which aren't finite or don't fit in an integer are an error.
+#[derive(Clone, Copy)]
+pub struct ToIntegerHelper;
+
+impl HelperDef for ToIntegerHelper {
+    fn call(&self, h: &Helper, _: &Handlebars, rc: &mut RenderContext) -> RenderResult<()> {
+        let param = h
+            .param(0)
+            .ok_or_else(|| RenderError::new("Expected 1 parameter for \"toInteger\""))?
+            .value();
+        let integer = match *param {
+            Json::Number(ref n) => n.as_i64().or_else(|| n.as_f64().and_then(truncate)),
+            Json::String(ref s) => s
+                .trim()
+                .parse::<i64>()
+                .ok()
+                .or_else(|| s.trim().parse::<f64>().ok().and_then(truncate)),
+            Json::Bool(b) => Some(if b { 1 } else { 0 }),
+            _ => None,
+        };
+        let integer = integer
+            .ok_or_else(|| RenderError::new(format!("Can't convert {} to an integer", param)))?;
+        rc.writer.write_all(integer.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Truncates a float to an integer, if it is finite and in range. `i64::MAX as f64` is 2^63, one
+/// past the largest integer, hence the strict comparison.
+fn truncate(f: f64) -> Option<i64> {
+    if f.is_finite() && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Some(f as i64)
+    } else {
+        None
+    }
+}
+
+pub static TO_INTEGER: ToIntegerHelper = ToIntegerHelper;
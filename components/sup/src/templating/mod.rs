@@ -36,15 +36,39 @@ impl TemplateRenderer {
     pub fn new() -> Self {
         let mut handlebars = Handlebars::new();
         handlebars.register_helper("eachAlive", Box::new(helpers::EACH_ALIVE));
+        handlebars.register_helper("eachLeaderFirst", Box::new(helpers::EACH_LEADER_FIRST));
         handlebars.register_helper("pkgPathFor", Box::new(helpers::PKG_PATH_FOR));
         handlebars.register_helper("strConcat", Box::new(helpers::STR_CONCAT));
         handlebars.register_helper("strJoin", Box::new(helpers::STR_JOIN));
         handlebars.register_helper("strReplace", Box::new(helpers::STR_REPLACE));
+        handlebars.register_helper("splitLines", Box::new(helpers::SPLIT_LINES));
         handlebars.register_helper("toUppercase", Box::new(helpers::TO_UPPERCASE));
         handlebars.register_helper("toLowercase", Box::new(helpers::TO_LOWERCASE));
+        handlebars.register_helper("toInteger", Box::new(helpers::TO_INTEGER));
         handlebars.register_helper("toJson", Box::new(helpers::TO_JSON));
         handlebars.register_helper("toToml", Box::new(helpers::TO_TOML));
         handlebars.register_helper("toYaml", Box::new(helpers::TO_YAML));
+        handlebars.register_helper("base64", Box::new(helpers::BASE64));
+        handlebars.register_helper("base64Decode", Box::new(helpers::BASE64_DECODE));
+        handlebars.register_helper("sha256", Box::new(helpers::SHA256));
+        handlebars.register_helper("default", Box::new(helpers::DEFAULT));
+        handlebars.register_helper("add", Box::new(helpers::ADD));
+        handlebars.register_helper("subtract", Box::new(helpers::SUBTRACT));
+        handlebars.register_helper("multiply", Box::new(helpers::MULTIPLY));
+        handlebars.register_helper("divide", Box::new(helpers::DIVIDE));
+        handlebars.register_helper("mod", Box::new(helpers::MODULO));
+        handlebars.register_helper("eq", Box::new(helpers::EQ));
+        handlebars.register_helper("ne", Box::new(helpers::NE));
+        handlebars.register_helper("gt", Box::new(helpers::GT));
+        handlebars.register_helper("gte", Box::new(helpers::GTE));
+        handlebars.register_helper("lt", Box::new(helpers::LT));
+        handlebars.register_helper("lte", Box::new(helpers::LTE));
+        handlebars.register_helper("and", Box::new(helpers::AND));
+        handlebars.register_helper("or", Box::new(helpers::OR));
+        handlebars.register_helper("not", Box::new(helpers::NOT));
+        handlebars.register_helper("sort", Box::new(helpers::SORT));
+        handlebars.register_helper("first", Box::new(helpers::FIRST));
+        handlebars.register_helper("last", Box::new(helpers::LAST));
 
         handlebars.register_escape_fn(never_escape);
         TemplateRenderer(handlebars)
@@ -228,6 +252,154 @@ test: something"#.to_string()
         assert_eq!(rendered, "this is new".to_string());
     }
 
+    #[test]
+    fn arithmetic_helpers() {
+        let content = "{{add 1 2}} {{subtract 10 2 3}} {{multiply 2 2.5}} {{divide 7 2}} \
+                       {{mod 7 2}} {{add 1 (multiply 2 3)}}"
+            .to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer.render("t", &json!({})).unwrap();
+        assert_eq!(rendered, "3 5 5.0 3 1 7");
+    }
+
+    #[test]
+    fn arithmetic_helpers_fail_on_division_by_zero() {
+        let mut renderer = TemplateRenderer::new();
+        renderer
+            .register_template_string("t", "{{divide 1 0}}".to_string())
+            .unwrap();
+        assert!(renderer.render("t", &json!({})).is_err());
+    }
+
+    #[test]
+    fn default_helper() {
+        let content =
+            "{{default port 8080}} {{default missing 8080}} {{default empty \"none\"}}".to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer
+            .render("t", &json!({ "port": 80, "empty": "" }))
+            .unwrap();
+        assert_eq!(rendered, "80 8080 none");
+    }
+
+    #[test]
+    fn comparison_helpers() {
+        let content = "{{#if (eq a 1)}}one{{/if}} {{#if (eq a b)}}same{{else}}other{{/if}} \
+                       {{#gt a b}}bigger{{else}}smaller{{/gt}} \
+                       {{lte a 1.0}} [{{ne name \"web\"}}] {{lt \"a\" \"b\"}}"
+            .to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer
+            .render("t", &json!({ "a": 1, "b": 2, "name": "web" }))
+            .unwrap();
+        assert_eq!(rendered, "one other smaller true [] true");
+    }
+
+    #[test]
+    fn logic_helpers() {
+        let content = "[{{and yes no}}] {{or yes no}} {{not no}} \
+                       {{#if (and yes (not no))}}both{{/if}} \
+                       {{#if (and yes (eq yes no))}}both{{else}}one{{/if}} \
+                       {{#if (not (and yes no))}}neither{{/if}}"
+            .to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer
+            .render("t", &json!({ "yes": true, "no": false }))
+            .unwrap();
+        assert_eq!(rendered, "[] true true both one neither");
+    }
+
+    #[test]
+    fn encoding_helpers() {
+        let content = "{{base64 var}} {{base64Decode \"aGVsbG8=\"}} {{sha256 var}}".to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer.render("t", &json!({ "var": "hello" })).unwrap();
+        assert_eq!(
+            rendered,
+            "aGVsbG8= hello \
+             2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+    }
+
+    #[test]
+    fn split_lines_helper() {
+        let content =
+            "{{#splitLines hosts as |host|}}[{{@index}}:{{host}}]{{/splitLines}}".to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer
+            .render("t", &json!({ "hosts": "a.example\nb.example\n" }))
+            .unwrap();
+        assert_eq!(rendered, "[0:a.example][1:b.example]");
+    }
+
+    #[test]
+    fn to_integer_helper() {
+        let content = "{{toInteger port}} {{toInteger \"42\"}} {{toInteger 3.9}}".to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer.render("t", &json!({ "port": "8080" })).unwrap();
+        assert_eq!(rendered, "8080 42 3");
+
+        for bad in &["NaN", "inf", "-inf", "1e30"] {
+            assert!(renderer.render("t", &json!({ "port": bad })).is_err());
+        }
+    }
+
+    #[test]
+    fn list_helpers() {
+        let content = "{{#sort ports as |port|}}{{port}}{{#unless @last}},{{/unless}}{{/sort}} \
+                       {{first names}} {{last names}} {{first (sort names)}} \
+                       {{last (sort ports)}} {{first (splitLines hosts)}}"
+            .to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let rendered = renderer
+            .render(
+                "t",
+                &json!({
+                    "ports": [8080, 80, 443],
+                    "names": ["one", "two", "three"],
+                    "hosts": "a.example\nb.example\n"
+                }),
+            )
+            .unwrap();
+        assert_eq!(rendered, "80,443,8080 one three one 8080 a.example");
+    }
+
+    #[test]
+    fn each_leader_first_helper() {
+        let content =
+            "{{#eachLeaderFirst members as |member|}}{{member.member_id}},{{/eachLeaderFirst}}"
+                .to_string();
+        let mut renderer = TemplateRenderer::new();
+        renderer.register_template_string("t", content).unwrap();
+
+        let data = json!({
+            "members": [
+                { "member_id": "a", "alive": true, "leader": false },
+                { "member_id": "b", "alive": false, "leader": false },
+                { "member_id": "c", "alive": true, "leader": false },
+                { "member_id": "d", "alive": true, "leader": true }
+            ]
+        });
+        let rendered = renderer.render("t", &data).unwrap();
+        assert_eq!(rendered, "d,a,c,");
+    }
+
     #[test]
     fn bind_variable() {
         let content = "{{bind.foo.members[0].sys.ip}}";
@@ -0,0 +1,283 @@
+// Copyright (c) 2018 Chef Software Inc. and/or applicable contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Line based diffs of rendered files, in the unified format.
+
+use std::cmp;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT_LINES: usize = 3;
+/// The most lines added and removed that are paired up line by line. Files which differ by more
+/// are shown as all of their differing lines removed, then added.
+const MAX_EDITS: isize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Returns a unified diff of `old` against `new`, or an empty string if they have the same lines.
+pub fn unified(old: &str, new: &str) -> String {
+    let lines = diff_lines(old, new);
+    let changes: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|&(_, line)| match *line {
+            Line::Same(_) => false,
+            _ => true,
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    // The line numbers in `old` and `new` each entry starts at.
+    let mut positions = Vec::with_capacity(lines.len());
+    let (mut old_line, mut new_line) = (0, 0);
+    for line in lines.iter() {
+        positions.push((old_line, new_line));
+        match *line {
+            Line::Same(_) => {
+                old_line += 1;
+                new_line += 1;
+            }
+            Line::Removed(_) => old_line += 1,
+            Line::Added(_) => new_line += 1,
+        }
+    }
+
+    let mut output = String::new();
+    let mut next = 0;
+    while next < changes.len() {
+        let start = changes[next].saturating_sub(CONTEXT_LINES);
+        let mut last = changes[next];
+        next += 1;
+        // Changes close enough to share their context go in the same hunk.
+        while next < changes.len() && changes[next] - last <= 2 * CONTEXT_LINES + 1 {
+            last = changes[next];
+            next += 1;
+        }
+        let end = cmp::min(last + CONTEXT_LINES + 1, lines.len());
+        let hunk = &lines[start..end];
+        let old_len = hunk
+            .iter()
+            .filter(|line| match **line {
+                Line::Added(_) => false,
+                _ => true,
+            })
+            .count();
+        let new_len = hunk
+            .iter()
+            .filter(|line| match **line {
+                Line::Removed(_) => false,
+                _ => true,
+            })
+            .count();
+        let (old_start, new_start) = positions[start];
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            range(old_start, old_len),
+            range(new_start, new_len)
+        ));
+        for line in hunk {
+            match *line {
+                Line::Same(text) => output.push_str(&format!(" {}\n", text)),
+                Line::Removed(text) => output.push_str(&format!("-{}\n", text)),
+                Line::Added(text) => output.push_str(&format!("+{}\n", text)),
+            }
+        }
+    }
+    output
+}
+
+/// A hunk's range of lines, as shown in its header. An empty range refers to the line before it.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Pairs up the lines of `old` and `new` along their longest common subsequence, found with
+/// Myers' algorithm after setting aside the lines they start and end with in common.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let old_changed = &old[prefix..old.len() - suffix];
+    let new_changed = &new[prefix..new.len() - suffix];
+
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    lines.extend(old[..prefix].iter().map(|line| Line::Same(*line)));
+    match shortest_edit(old_changed, new_changed) {
+        Some(edit) => lines.extend(edit),
+        None => {
+            lines.extend(old_changed.iter().map(|line| Line::Removed(*line)));
+            lines.extend(new_changed.iter().map(|line| Line::Added(*line)));
+        }
+    }
+    let suffix_start = old.len() - suffix;
+    lines.extend(old[suffix_start..].iter().map(|line| Line::Same(*line)));
+    lines
+}
+
+/// Returns the shortest edit script turning `old` into `new`, or `None` if it takes more than
+/// `MAX_EDITS` lines to be added or removed. The time and memory this takes grow with the square
+/// of the number of edits, rather than with the product of the files' lengths.
+fn shortest_edit<'a>(old: &[&'a str], new: &[&'a str]) -> Option<Vec<Line<'a>>> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = cmp::min(n + m, MAX_EDITS);
+    let offset = max + 1;
+    let at = |k: isize| (offset + k) as usize;
+    // The furthest `x` in `old` reached on each diagonal `k = x - y`, indexed by `at(k)`.
+    let mut v = vec![0isize; (2 * offset + 1) as usize];
+    // The diagonals `-d - 1..=d + 1` of `v` before each step `d`, for walking the path back.
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..max + 1 {
+        trace.push(v[at(-d - 1)..at(d + 2)].to_vec());
+        let mut k = -d;
+        while k <= d {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(old, new, &trace));
+            }
+            k += 2;
+        }
+    }
+    None
+}
+
+/// Walks the path found by `shortest_edit` back from the ends of `old` and `new`.
+fn backtrack<'a>(old: &[&'a str], new: &[&'a str], trace: &[Vec<isize>]) -> Vec<Line<'a>> {
+    let (mut x, mut y) = (old.len() as isize, new.len() as isize);
+    let mut lines = Vec::with_capacity(old.len() + new.len());
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d + 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            lines.push(Line::Same(old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            if x == prev_x {
+                lines.push(Line::Added(new[prev_y as usize]));
+            } else {
+                lines.push(Line::Removed(old[prev_x as usize]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    lines.reverse();
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unified;
+
+    #[test]
+    fn same_lines_have_no_diff() {
+        assert_eq!(unified("a\nb\n", "a\nb"), "");
+    }
+
+    #[test]
+    fn changed_line_is_shown_with_context() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+        let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+        assert_eq!(
+            unified(old, new),
+            "@@ -2,7 +2,7 @@\n 2\n 3\n 4\n-5\n+five\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_their_own_hunks() {
+        let old = "a\n1\n2\n3\n4\n5\n6\n7\nb\n";
+        let new = "A\n1\n2\n3\n4\n5\n6\n7\n";
+        assert_eq!(
+            unified(old, new),
+            "@@ -1,4 +1,4 @@\n-a\n+A\n 1\n 2\n 3\n@@ -6,4 +6,3 @@\n 5\n 6\n 7\n-b\n"
+        );
+    }
+
+    #[test]
+    fn new_file_is_all_added() {
+        assert_eq!(unified("", "a\nb\n"), "@@ -0,0 +1,2 @@\n+a\n+b\n");
+    }
+
+    #[test]
+    fn interleaved_changes_take_the_fewest_edits() {
+        let old = "a\nb\nc\na\nb\nb\na\n";
+        let new = "c\nb\na\nb\na\nc\n";
+        let diff = unified(old, new);
+        let edits = diff
+            .lines()
+            .filter(|line| line.starts_with('-') || line.starts_with('+'))
+            .count();
+        assert_eq!(edits, 5);
+        assert!(diff.starts_with("@@ -1,7 +1,6 @@\n"));
+    }
+
+    #[test]
+    fn one_change_in_a_long_file() {
+        let old: String = (0..20000).map(|i| format!("line {}\n", i)).collect();
+        let new = old.replace("line 10000\n", "line ten thousand\n");
+        assert_eq!(
+            unified(&old, &new),
+            "@@ -9998,7 +9998,7 @@\n line 9997\n line 9998\n line 9999\n-line 10000\n\
+             +line ten thousand\n line 10001\n line 10002\n line 10003\n"
+        );
+    }
+
+    #[test]
+    fn too_many_changes_are_removed_then_added() {
+        let old: String = (0..1000).map(|i| format!("old {}\n", i)).collect();
+        let new: String = (0..1000).map(|i| format!("new {}\n", i)).collect();
+        let diff = unified(&old, &new);
+        assert!(diff.starts_with("@@ -1,1000 +1,1000 @@\n-old 0\n"));
+        assert!(diff.contains("-old 999\n+new 0\n"));
+        assert!(diff.ends_with("+new 999\n"));
+    }
+}
@@ -12,27 +12,64 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::cmp::Ordering;
+use std::fs as stdfs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use common;
 use common::command::package::install::{InstallMode, InstallSource, LocalPackageUsage};
 use common::ui::UIWriter;
+use hcore::crypto::{artifact, SigKeyPair};
 use hcore::env as henv;
 use hcore::fs::{self, FS_ROOT_PATH};
-use hcore::package::{PackageIdent, PackageInstall};
+use hcore::package::{PackageArchive, PackageIdent, PackageInstall, PackageTarget};
 use hcore::AUTH_TOKEN_ENVVAR;
+use protocol;
+use url::Url;
 
-use error::{Result, SupError};
+use error::{Error, Result, SupError};
 use {PRODUCT, VERSION};
 
+static LOGKEY: &'static str = "PK";
+
 /// Helper function for use in the Supervisor to handle lower-level
 /// arguments needed for installing a package.
+///
+/// A `file://` URL, or an absolute path, is a local package source rather than a Builder URL;
+/// see `local_source`.
 pub fn install<T>(
     ui: &mut T,
     url: &str,
     install_source: &InstallSource,
     channel: &str,
 ) -> Result<PackageInstall>
+where
+    T: UIWriter,
+{
+    match local_source(url) {
+        Some(dir) => match *install_source {
+            InstallSource::Ident(ref ident, _) => install_from_dir(ui, &dir, ident, channel),
+            InstallSource::Archive(_) => start_install(
+                ui,
+                &protocol::DEFAULT_BLDR_URL,
+                install_source,
+                channel,
+                &InstallMode::Offline,
+            ),
+        },
+        // TODO fn: pass through and enable offline install mode
+        None => start_install(ui, url, install_source, channel, &InstallMode::default()),
+    }
+}
+
+fn start_install<T>(
+    ui: &mut T,
+    url: &str,
+    install_source: &InstallSource,
+    channel: &str,
+    install_mode: &InstallMode,
+) -> Result<PackageInstall>
 where
     T: UIWriter,
 {
@@ -57,8 +94,7 @@ where
         fs_root_path,
         &fs::cache_artifact_path(None::<String>),
         auth_token.as_ref().map(String::as_str),
-        // TODO fn: pass through and enable offline install mode
-        &InstallMode::default(),
+        install_mode,
         // TODO (CM): pass through and enable ignore-local mode
         &LocalPackageUsage::default(),
     ).map_err(SupError::from)
@@ -93,3 +129,259 @@ where
     let fs_root_path = Path::new(&*FS_ROOT_PATH);
     PackageInstall::load(ident.as_ref(), Some(fs_root_path)).ok()
 }
+
+/// Returns the directory of a local package source, if the given URL is a `file://` URL or an
+/// absolute path. Air-gapped Supervisors use a directory of `.hart` files, along with the public
+/// keys of their origins, in place of Builder.
+pub fn local_source(url: &str) -> Option<PathBuf> {
+    if Path::new(url).is_absolute() {
+        return Some(PathBuf::from(url));
+    }
+    match Url::parse(url) {
+        Ok(ref url) if url.scheme() == "file" => url.to_file_path().ok(),
+        _ => None,
+    }
+}
+
+/// Install the latest package in a local package source which satisfies the given identifier
+/// and verifies against the origin keys in the source directory. Packages which don't verify
+/// are skipped. Dependencies which aren't installed yet are taken from the same directory.
+fn install_from_dir<T>(
+    ui: &mut T,
+    dir: &Path,
+    ident: &PackageIdent,
+    channel: &str,
+) -> Result<PackageInstall>
+where
+    T: UIWriter,
+{
+    let archives = local_archives(dir)?;
+    for &&(ref candidate, ref path) in newest_first(&archives, ident).iter() {
+        if let Some(package) = installed(candidate) {
+            return Ok(package);
+        }
+        match cache_with_dependencies(dir, path, &archives) {
+            Ok(()) => {
+                let source = InstallSource::from_str(&path.to_string_lossy())?;
+                return start_install(
+                    ui,
+                    &protocol::DEFAULT_BLDR_URL,
+                    &source,
+                    channel,
+                    &InstallMode::Offline,
+                );
+            }
+            Err(err) => warn!("Skipping {} from {}: {}", candidate, path.display(), err),
+        }
+    }
+    Err(sup_error!(Error::PackageNotFound(ident.clone())))
+}
+
+/// The packages which satisfy the given identifier, newest first.
+fn newest_first<'a>(
+    archives: &'a [(PackageIdent, PathBuf)],
+    ident: &PackageIdent,
+) -> Vec<&'a (PackageIdent, PathBuf)> {
+    let mut candidates: Vec<&(PackageIdent, PathBuf)> = archives
+        .iter()
+        .filter(|&&(ref candidate, _)| candidate.satisfies(ident))
+        .collect();
+    candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(Ordering::Equal));
+    candidates
+}
+
+/// All packages for the active target in a local package source, with their identifiers.
+fn local_archives(dir: &Path) -> Result<Vec<(PackageIdent, PathBuf)>> {
+    let mut archives = vec![];
+    for entry in stdfs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "hart") {
+            continue;
+        }
+        let mut archive = PackageArchive::new(&path);
+        let ident = match archive.ident() {
+            Ok(ident) => ident,
+            Err(err) => {
+                debug!("Ignoring {}: {}", path.display(), err);
+                continue;
+            }
+        };
+        match archive.target() {
+            Ok(ref target) if target == PackageTarget::active_target() => {
+                archives.push((ident, path))
+            }
+            Ok(_) => debug!("Ignoring {} for another target", path.display()),
+            Err(err) => debug!("Ignoring {}: {}", path.display(), err),
+        }
+    }
+    Ok(archives)
+}
+
+/// Verify a package, and any of its dependencies which aren't installed, against the origin keys
+/// in a local package source. The origin keys are imported into the key cache, and the
+/// dependencies copied into the artifact cache, so installing the package needs neither Builder
+/// nor any other network access.
+fn cache_with_dependencies(
+    dir: &Path,
+    path: &Path,
+    archives: &[(PackageIdent, PathBuf)],
+) -> Result<()> {
+    import_verified(dir, path)?;
+    let artifact_cache_path = fs::cache_artifact_path(None::<String>);
+    for dependency in PackageArchive::new(path).tdeps()? {
+        if installed(&dependency).is_some() {
+            continue;
+        }
+        let cached = artifact_cache_path.join(dependency.archive_name()?);
+        if cached.is_file() {
+            continue;
+        }
+        match archives
+            .iter()
+            .find(|&&(ref ident, _)| *ident == dependency)
+        {
+            Some(&(_, ref dependency_path)) => {
+                import_verified(dir, dependency_path)?;
+                stdfs::create_dir_all(&artifact_cache_path)?;
+                stdfs::copy(dependency_path, &cached)?;
+            }
+            None => debug!(
+                "{} is not in {}; it must be installed or cached already",
+                dependency,
+                dir.display()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Verify a package against the origin keys in a local package source, and import the public
+/// key it is signed with into the key cache.
+fn import_verified(dir: &Path, path: &Path) -> Result<()> {
+    PackageArchive::new(path).verify(&dir)?;
+    let signer = artifact::artifact_signer(&path)?;
+    let key_cache_path = fs::cache_key_path(Some(&*FS_ROOT_PATH));
+    if SigKeyPair::get_public_key_path(&signer, &key_cache_path).is_err() {
+        let key = SigKeyPair::get_public_key_path(&signer, dir)?;
+        stdfs::create_dir_all(&key_cache_path)?;
+        stdfs::copy(
+            &key,
+            key_cache_path.join(key.file_name().expect("Key has a file name")),
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+    use std::io::Write;
+
+    use common::ui::UI;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn archive(ident: &str) -> (PackageIdent, PathBuf) {
+        let ident = PackageIdent::from_str(ident).unwrap();
+        let path = PathBuf::from(format!(
+            "/srv/pkgs/{}-{}.hart",
+            ident.name,
+            ident.release.as_ref().unwrap()
+        ));
+        (ident, path)
+    }
+
+    #[test]
+    fn local_source_from_path_or_file_url() {
+        assert_eq!(local_source("/srv/pkgs"), Some(PathBuf::from("/srv/pkgs")));
+        assert_eq!(
+            local_source("file:///srv/pkgs"),
+            Some(PathBuf::from("/srv/pkgs"))
+        );
+    }
+
+    #[test]
+    fn local_source_not_from_builder_url() {
+        assert_eq!(local_source("https://bldr.habitat.sh"), None);
+        assert_eq!(local_source("http://localhost:9636/v1"), None);
+        assert_eq!(local_source("srv/pkgs"), None);
+    }
+
+    #[test]
+    fn newest_first_picks_the_latest_matching_archive() {
+        let archives = vec![
+            archive("core/redis/3.2.1/20170101000000"),
+            archive("core/redis/4.0.1/20180101000000"),
+            archive("core/nginx/9.9.9/20190101000000"),
+            archive("core/redis/4.0.1/20180201000000"),
+        ];
+
+        let candidates = newest_first(&archives, &PackageIdent::from_str("core/redis").unwrap());
+        let idents: Vec<String> = candidates.iter().map(|c| c.0.to_string()).collect();
+        assert_eq!(
+            idents,
+            vec![
+                "core/redis/4.0.1/20180201000000",
+                "core/redis/4.0.1/20180101000000",
+                "core/redis/3.2.1/20170101000000",
+            ]
+        );
+
+        let candidates = newest_first(
+            &archives,
+            &PackageIdent::from_str("core/redis/3.2.1").unwrap(),
+        );
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(
+            candidates[0].0.to_string(),
+            "core/redis/3.2.1/20170101000000"
+        );
+
+        assert!(newest_first(&archives, &PackageIdent::from_str("core/etcd").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn local_archives_of_a_missing_dir() {
+        let tmp = TempDir::new("local_archives").unwrap();
+        assert!(local_archives(&tmp.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn local_archives_skips_other_files() {
+        let tmp = TempDir::new("local_archives").unwrap();
+        assert!(local_archives(tmp.path()).unwrap().is_empty());
+
+        File::create(tmp.path().join("README")).unwrap();
+        File::create(tmp.path().join("core-20170101000000.pub")).unwrap();
+        let mut bogus = File::create(tmp.path().join("bogus.hart")).unwrap();
+        bogus.write_all(b"not a package").unwrap();
+        assert!(local_archives(tmp.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn install_from_an_empty_dir() {
+        let tmp = TempDir::new("install_from_dir").unwrap();
+        let ident = PackageIdent::from_str("core/redis").unwrap();
+        match install_from_dir(&mut UI::with_sinks(), tmp.path(), &ident, "stable") {
+            Err(e) => match e.err {
+                Error::PackageNotFound(ref missing) => assert_eq!(*missing, ident),
+                e => panic!("Unexpected error returned: {:?}", e),
+            },
+            Ok(_) => panic!("Nothing should be installed from an empty directory"),
+        }
+    }
+
+    #[test]
+    fn install_from_a_missing_dir() {
+        let tmp = TempDir::new("install_from_dir").unwrap();
+        let ident = PackageIdent::from_str("core/redis").unwrap();
+        assert!(install_from_dir(
+            &mut UI::with_sinks(),
+            &tmp.path().join("missing"),
+            &ident,
+            "stable"
+        )
+        .is_err());
+    }
+}